@@ -5,59 +5,381 @@ pub mod terraform;
 use anyhow::{Context, Result};
 use colored::*;
 use dialoguer::MultiSelect;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
 
+use crate::cli::{BumpFormat, BumpSort};
+use crate::executor;
+use crate::log;
+use crate::path_filter::PathFilter;
 use crate::techno::{self, Technology};
+use registry::HttpFetcher;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dependency {
     pub name: String,
     pub current_version: String,
     pub latest_version: String,
     pub latest_app_version: Option<String>,
+    /// `latest_version` was capped by `--max-major-jump`, a newer major exists
+    #[serde(default)]
+    pub capped: bool,
     pub file_path: String,
     pub line_number: usize,
     pub dep_type: DependencyType,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DependencyType {
     TerraformModule { source: String, constraint: String },
     HelmChart { repository: String },
 }
 
-impl Dependency {
-    pub fn display_name(&self) -> String {
-        format!(
-            "{} ({}:{}) {} → {}",
-            self.name.bright_cyan(),
-            self.file_path.purple(),
-            self.line_number,
-            self.current_version.yellow(),
-            self.latest_version.green()
+impl DependencyType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DependencyType::TerraformModule { .. } => "terraform-module",
+            DependencyType::HelmChart { .. } => "helm-chart",
+        }
+    }
+}
+
+/// Whether a version line opts out of bumping with a trailing `# mk:ignore` comment
+pub(crate) fn has_ignore_comment(line: &str) -> bool {
+    line.split_once('#')
+        .is_some_and(|(_, comment)| comment.trim() == "mk:ignore")
+}
+
+/// Options for `mk bump`
+pub struct BumpOptions {
+    pub verbose: bool,
+    pub include_prereleases: bool,
+    pub recursive: bool,
+    pub no_ignore: bool,
+    /// Never look for the technology in child directories of the project path
+    pub strict_path: bool,
+    pub format: BumpFormat,
+    /// Order of the available updates, discovery order when unset
+    pub sort: Option<BumpSort>,
+    pub fail_on_error: bool,
+    /// Globs restricting recursive scans to matching project paths
+    pub path_filters: Vec<String>,
+    /// Markdown file receiving the list of applied updates
+    pub changelog_file: Option<String>,
+    /// List available updates and exit non-zero instead of prompting
+    pub check: bool,
+    /// Only list the dependencies whose version could not be fetched
+    pub only_errors: bool,
+    /// Refresh the terraform lock file of the projects with bumped modules
+    pub update_lock: bool,
+    /// Record scanned projects and skip them when a recursive bump is re-run
+    pub resume: bool,
+    /// Discard the recorded progress before a resumable recursive bump
+    pub fresh: bool,
+    /// Propose at most this many major versions above the current one
+    pub max_major_jump: Option<u64>,
+    /// Commit each updated file on its own
+    pub git_commit: bool,
+    /// Message of those commits, with `{name}` and `{version}` placeholders
+    pub commit_message_template: String,
+}
+
+pub fn run_bump(project_path: &str, options: BumpOptions) -> Result<()> {
+    if options.recursive {
+        let path_filter = PathFilter::new(&options.path_filters)?;
+        run_bump_recursive(project_path, &options, &path_filter)
+    } else {
+        run_bump_single(project_path, &options)
+    }
+}
+
+/// Markdown list of applied updates, grouped by file or, with `by_project`, by project directory
+fn render_changelog(updated: &[&Dependency], by_project: bool) -> String {
+    use std::collections::BTreeMap;
+
+    let mut groups: BTreeMap<&str, Vec<&Dependency>> = BTreeMap::new();
+    for dep in updated {
+        let group = if by_project {
+            std::path::Path::new(&dep.file_path)
+                .parent()
+                .and_then(|p| p.to_str())
+                .unwrap_or(&dep.file_path)
+        } else {
+            &dep.file_path
+        };
+        groups.entry(group).or_default().push(dep);
+    }
+
+    let mut markdown = String::from("## Dependency updates\n");
+    for (group, deps) in groups {
+        markdown.push_str(&format!("\n### `{}`\n\n", group));
+        for dep in deps {
+            markdown.push_str(&format!(
+                "- {}: {} → {}\n",
+                dep.name, dep.current_version, dep.latest_version
+            ));
+        }
+    }
+    markdown
+}
+
+/// Terraform lock file recording the provider versions and hashes of a root module
+const TERRAFORM_LOCK_FILE: &str = ".terraform.lock.hcl";
+
+/// Commands refreshing the lock file, run in each directory of bumped terraform modules
+/// `terraform get` installs the bumped modules so `providers lock` sees the providers they
+/// require; providers already locked keep their version
+fn lock_update_commands() -> Vec<String> {
+    vec![
+        "terraform get".to_string(),
+        "terraform providers lock".to_string(),
+    ]
+}
+
+/// Directories of the bumped terraform modules that have a lock file, without duplicates
+fn lock_update_dirs(updated: &[&Dependency]) -> Vec<std::path::PathBuf> {
+    let mut dirs = Vec::new();
+    for dep in updated {
+        if !matches!(dep.dep_type, DependencyType::TerraformModule { .. }) {
+            continue;
+        }
+        let Some(dir) = std::path::Path::new(&dep.file_path).parent() else {
+            continue;
+        };
+        if dir.join(TERRAFORM_LOCK_FILE).is_file() && !dirs.iter().any(|d| d == dir) {
+            dirs.push(dir.to_path_buf());
+        }
+    }
+    dirs
+}
+
+/// `bump --update-lock`: refresh the lock file of every project with bumped modules
+fn update_lock_files(updated: &[&Dependency], verbose: bool) -> Result<()> {
+    for dir in lock_update_dirs(updated) {
+        log::info!("Updating {} in {}", TERRAFORM_LOCK_FILE, dir.display());
+        executor::execute_commands_sequential(
+            &lock_update_commands(),
+            &dir.to_string_lossy(),
+            verbose,
+            0,
         )
+        .with_context(|| {
+            format!(
+                "Failed to update {} in {}",
+                TERRAFORM_LOCK_FILE,
+                dir.display()
+            )
+        })?;
     }
+    Ok(())
 }
 
-pub fn run_bump(
-    project_path: &str,
-    verbose: bool,
-    include_prereleases: bool,
-    recursive: bool,
-    no_ignore: bool,
-) -> Result<()> {
-    if recursive {
-        run_bump_recursive(project_path, verbose, include_prereleases, no_ignore)
+/// Default `bump --commit-message-template`
+pub const DEFAULT_COMMIT_MESSAGE_TEMPLATE: &str = "bump {name} to {version}";
+
+/// Commit message for the updates of one file: `template` filled in for each dependency,
+/// joined with ", " when the file had several
+fn commit_message(deps: &[&Dependency], template: &str) -> String {
+    deps.iter()
+        .map(|dep| {
+            template
+                .replace("{name}", &dep.name)
+                .replace("{version}", &dep.latest_version)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `bump --git-commit`: one `git add <file> && git commit` per updated file, run with
+/// `run(dir, command)` in the file's directory
+/// Files outside a git work tree are skipped; returns the number of commits made
+fn commit_updates<F>(updated: &[&Dependency], template: &str, mut run: F) -> Result<usize>
+where
+    F: FnMut(&std::path::Path, &str) -> Result<()>,
+{
+    // Files in first-update order, each with its updated dependencies
+    let mut files: Vec<(&str, Vec<&Dependency>)> = Vec::new();
+    for dep in updated {
+        match files.iter_mut().find(|(file, _)| *file == dep.file_path) {
+            Some((_, deps)) => deps.push(dep),
+            None => files.push((&dep.file_path, vec![dep])),
+        }
+    }
+
+    let mut commits = 0;
+    for (file, deps) in files {
+        let path = std::path::Path::new(file);
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(std::path::Path::new("."));
+        let file_name = path
+            .file_name()
+            .map_or(file.into(), |name| name.to_string_lossy());
+
+        if run(dir, "git rev-parse --is-inside-work-tree").is_err() {
+            log::warning!("{} is not in a git repository, not committed", file);
+            continue;
+        }
+        // Commit only this file, leaving anything else already staged out of it
+        let message = commit_message(&deps, template);
+        let file_name = executor::shell_quote(&file_name);
+        run(
+            dir,
+            &format!(
+                "git add {} && git commit -m {} -- {}",
+                file_name,
+                executor::shell_quote(&message),
+                file_name
+            ),
+        )
+        .with_context(|| format!("Failed to commit {}", file))?;
+        log::info!("Committed {}: {}", file, message);
+        commits += 1;
+    }
+    Ok(commits)
+}
+
+/// Commit the updated files when `--git-commit` is given
+fn commit_updated_files(updated: &[&Dependency], options: &BumpOptions) -> Result<()> {
+    if !options.git_commit || updated.is_empty() {
+        return Ok(());
+    }
+    let commits = commit_updates(updated, &options.commit_message_template, |dir, cmd| {
+        executor::execute_command_output(cmd, &dir.to_string_lossy(), options.verbose).map(|_| ())
+    })?;
+    if commits > 0 {
+        log::success!("{} commit(s) created", commits);
+    }
+    Ok(())
+}
+
+/// Write the changelog of applied updates, skipped when nothing was updated
+fn write_changelog(path: &str, updated: &[&Dependency], by_project: bool) -> Result<()> {
+    if updated.is_empty() {
+        log::info!("No dependencies updated, {} not written", path);
+        return Ok(());
+    }
+    std::fs::write(path, render_changelog(updated, by_project))
+        .with_context(|| format!("Failed to write changelog {}", path))?;
+    log::info!("Changelog written to {}", path);
+    Ok(())
+}
+
+/// Name, `(file:line)` and current version of each dependency, padded to the widest entry
+fn aligned_columns(deps: &[&Dependency]) -> Vec<[String; 3]> {
+    let rows: Vec<[String; 3]> = deps
+        .iter()
+        .map(|dep| {
+            [
+                dep.name.clone(),
+                format!("({}:{})", dep.file_path, dep.line_number),
+                dep.current_version.clone(),
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 3];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    rows.into_iter()
+        .map(|row| {
+            let mut cells = row.into_iter().zip(widths);
+            std::array::from_fn(|_| {
+                let (cell, width) = cells.next().unwrap();
+                format!("{:<width$}", cell, width = width)
+            })
+        })
+        .collect()
+}
+
+/// ` (capped)` when a newer major than `latest_version` was left out by `--max-major-jump`
+fn capped_marker(dep: &Dependency) -> &'static str {
+    if dep.capped {
+        " (capped)"
     } else {
-        run_bump_single(project_path, verbose, include_prereleases)
+        ""
     }
 }
 
-fn run_bump_single(project_path: &str, verbose: bool, include_prereleases: bool) -> Result<()> {
-    eprintln!(
-        "{} Scanning for dependencies in: {}",
-        "INFO:".cyan(),
-        project_path
-    );
+/// One color-coded line per dependency with aligned name, location and version columns
+fn display_names(deps: &[&Dependency]) -> Vec<String> {
+    aligned_columns(deps)
+        .iter()
+        .zip(deps)
+        .map(|([name, location, current], dep)| {
+            format!(
+                "{} {} {} → {}{}",
+                name.bright_cyan(),
+                location.purple(),
+                current.yellow(),
+                dep.latest_version.green(),
+                capped_marker(dep).dimmed()
+            )
+        })
+        .collect()
+}
+
+/// Render dependencies as an aligned table (name, file:line, current, latest, type)
+fn render_table(deps: &[&Dependency]) -> String {
+    let headers = ["NAME", "FILE", "CURRENT", "LATEST", "TYPE"];
+    let rows: Vec<[String; 5]> = deps
+        .iter()
+        .map(|dep| {
+            [
+                dep.name.clone(),
+                format!("{}:{}", dep.file_path, dep.line_number),
+                dep.current_version.clone(),
+                format!("{}{}", dep.latest_version, capped_marker(dep)),
+                dep.dep_type.as_str().to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = headers.map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: &[String]| -> String {
+        cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut out = format_row(&headers.map(String::from));
+    out.push('\n');
+    for row in &rows {
+        out.push_str(&format_row(row));
+        out.push('\n');
+    }
+    out
+}
+
+fn run_bump_single(project_path: &str, options: &BumpOptions) -> Result<()> {
+    let BumpOptions {
+        verbose,
+        include_prereleases,
+        max_major_jump,
+        format,
+        fail_on_error,
+        ..
+    } = *options;
+    log::info!("Scanning for dependencies in: {}", project_path);
 
     // Try direct detection first, fallback to hierarchical detection if needed
     let (techno, actual_path) = if let Some(tech) = techno::detect_technology_direct(project_path) {
@@ -65,8 +387,16 @@ fn run_bump_single(project_path: &str, verbose: bool, include_prereleases: bool)
         (tech, project_path.to_string())
     } else {
         // Fallback to hierarchical detection with silent mode to avoid info messages
-        techno::detect_technology(project_path, None, true)
-            .context("Failed to detect technology")?
+        techno::detect_technology(
+            project_path,
+            None,
+            true,
+            techno::DetectFlags {
+                strict_path: options.strict_path,
+                ..Default::default()
+            },
+        )
+        .context("Failed to detect technology")?
     };
 
     let mut all_dependencies = Vec::new();
@@ -74,16 +404,27 @@ fn run_bump_single(project_path: &str, verbose: bool, include_prereleases: bool)
     // Scan for dependencies based on technology
     match techno {
         Technology::Terraform => {
-            eprintln!("{} Detected Terraform project", "INFO:".cyan());
-            let deps =
-                terraform::scan_terraform_modules(&actual_path, verbose, include_prereleases)
-                    .context("Failed to scan Terraform modules")?;
+            log::info!("Detected Terraform project");
+            let deps = terraform::scan_terraform_modules(
+                &actual_path,
+                verbose,
+                include_prereleases,
+                max_major_jump,
+                &HttpFetcher { verbose },
+            )
+            .context("Failed to scan Terraform modules")?;
             all_dependencies.extend(deps);
         }
         Technology::Helm => {
-            eprintln!("{} Detected Helm project", "INFO:".cyan());
-            let deps = helm::scan_helm_charts(&actual_path, verbose, include_prereleases)
-                .context("Failed to scan Helm charts")?;
+            log::info!("Detected Helm project");
+            let deps = helm::scan_helm_charts(
+                &actual_path,
+                verbose,
+                include_prereleases,
+                max_major_jump,
+                &HttpFetcher { verbose },
+            )
+            .context("Failed to scan Helm charts")?;
             all_dependencies.extend(deps);
         }
         _ => {
@@ -91,153 +432,360 @@ fn run_bump_single(project_path: &str, verbose: bool, include_prereleases: bool)
         }
     }
 
-    // Separate successful dependencies from errors
-    let (successful_deps, error_deps): (Vec<_>, Vec<_>) = all_dependencies
-        .iter()
-        .partition(|dep| !dep.latest_version.starts_with("ERROR:"));
-
     if all_dependencies.is_empty() {
-        eprintln!("{} No dependencies found", "INFO:".cyan());
+        log::info!("No dependencies found");
         return Ok(());
     }
 
     // Report errors if any
-    if !error_deps.is_empty() {
-        eprintln!(
-            "{} {} dependencies found, {} with fetch errors",
-            "WARNING:".yellow(),
+    let error_count = all_dependencies
+        .iter()
+        .filter(|dep| dep.latest_version.starts_with("ERROR:"))
+        .count();
+    if error_count > 0 {
+        log::warning!(
+            "{} dependencies found, {} with fetch errors",
             all_dependencies.len(),
-            error_deps.len()
+            error_count
         );
     }
 
-    // Filter dependencies with updates available (excluding errors)
-    let updates_available: Vec<_> = successful_deps
-        .iter()
-        .filter(|dep| dep.current_version != dep.latest_version)
-        .copied()
-        .collect();
-
-    // Check if all dependencies failed
-    if !error_deps.is_empty() && successful_deps.is_empty() {
-        eprintln!(
-            "{} Could not verify any dependencies due to fetch errors",
-            "WARNING:".yellow()
-        );
-        eprintln!(
-            "  {} found but all failed to fetch",
-            if all_dependencies.len() == 1 {
-                "1 dependency".to_string()
-            } else {
-                format!("{} dependencies", all_dependencies.len())
-            }
-        );
-        eprintln!(
-            "  {}",
-            "Tip: Configure OCI authentication in ~/.config/mk/config.toml or use --verbose for details"
-                .dimmed()
-        );
+    if options.only_errors {
+        report_errors(&all_dependencies);
         return Ok(());
     }
-
-    if updates_available.is_empty() {
-        eprintln!("{} All dependencies are up to date!", "SUCCESS:".green());
+    if options.check {
+        check_updates(&all_dependencies, format, options.sort, fail_on_error);
         return Ok(());
     }
 
-    eprintln!(
-        "{} Found {} dependencies with updates available\n",
-        "INFO:".cyan(),
-        updates_available.len()
-    );
+    let updated = select_and_update(&all_dependencies, verbose, format, options.sort)?;
+    if !updated.is_empty() {
+        log::success!("\n{} dependencies updated", updated.len());
+    }
+    if options.update_lock {
+        update_lock_files(&updated, verbose)?;
+    }
+    commit_updated_files(&updated, options)?;
+    if let Some(path) = &options.changelog_file {
+        write_changelog(path, &updated, false)?;
+    }
 
-    // Create multi-select prompt
-    let items: Vec<String> = updates_available
-        .iter()
-        .map(|dep| dep.display_name())
-        .collect();
+    report_summary(&all_dependencies, updated.len(), fail_on_error);
+    Ok(())
+}
 
-    // Pre-select if only one dependency is available
-    let defaults = if updates_available.len() == 1 {
-        vec![true]
-    } else {
-        vec![false; updates_available.len()]
-    };
+/// Upper bound on concurrent project scans (each one is mostly network-bound)
+const MAX_SCAN_WORKERS: usize = 8;
 
-    let selections = MultiSelect::new()
-        .with_prompt("Select dependencies to update (Space to select, Enter to confirm)")
-        .items(&items)
-        .defaults(&defaults)
-        .interact()
-        .context("Failed to get user selection")?;
+/// Run `scan` for every project on a pool of worker threads
+/// Results are returned in the same order as `projects`
+fn scan_projects_parallel<F>(
+    projects: &[(Technology, String)],
+    scan: F,
+) -> Vec<Result<Vec<Dependency>>>
+where
+    F: Fn(Technology, &str) -> Result<Vec<Dependency>> + Sync,
+{
+    executor::run_bounded(projects, MAX_SCAN_WORKERS, |(techno, path)| {
+        scan(*techno, path)
+    })
+}
 
-    if selections.is_empty() {
-        eprintln!("{} No dependencies selected", "INFO:".cyan());
-        return Ok(());
-    }
+/// Progress of `bump --recursive --resume`, relative to the scanned base directory
+const BUMP_PROGRESS_FILE: &str = ".mk/bump-progress.json";
 
-    // Apply updates
-    eprintln!("\n{} Updating selected dependencies...", "INFO:".cyan());
-    let selected_deps: Vec<_> = selections.iter().map(|&i| updates_available[i]).collect();
+/// Age after which the fetched versions of a recorded progress are discarded
+const BUMP_PROGRESS_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
 
-    for dep in &selected_deps {
-        match &dep.dep_type {
-            DependencyType::TerraformModule { source, constraint } => {
-                terraform::update_terraform_module(
-                    &dep.file_path,
-                    source,
-                    constraint,
-                    &dep.latest_version,
+/// Options changing the fetched versions, recorded projects are reused only under the same ones
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct ScanSettings {
+    include_prereleases: bool,
+    max_major_jump: Option<u64>,
+}
+
+/// Dependencies, with their fetched versions, of the projects already scanned
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProgressState {
+    #[serde(default)]
+    settings: ScanSettings,
+    /// Seconds since the epoch when the progress was started
+    #[serde(default)]
+    created: u64,
+    projects: BTreeMap<String, ProjectProgress>,
+}
+
+/// A scanned project, valid while its files are unchanged
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectProgress {
+    /// Latest modification time of the project's files when it was scanned
+    modified: Option<u64>,
+    dependencies: Vec<Dependency>,
+}
+
+/// Latest modification time (nanoseconds since the epoch) of the files directly in `project`
+fn project_modified(project: &str) -> Option<u64> {
+    std::fs::read_dir(project)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .filter_map(|metadata| metadata.modified().ok())
+        .filter_map(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_nanos() as u64)
+        .max()
+}
+
+/// Progress of a resumable recursive bump, saved after every scanned project
+struct BumpProgress {
+    path: PathBuf,
+    state: Mutex<ProgressState>,
+}
+
+impl BumpProgress {
+    /// Load the progress recorded under `root_path`, starting empty with `fresh`
+    /// Progress recorded with other `settings` or older than `BUMP_PROGRESS_MAX_AGE` is discarded
+    fn open(root_path: &str, fresh: bool, settings: ScanSettings) -> Result<Self> {
+        let path = Path::new(root_path).join(BUMP_PROGRESS_FILE);
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
+        let empty = ProgressState {
+            settings,
+            created: now,
+            projects: BTreeMap::new(),
+        };
+
+        let state: ProgressState = if fresh || !path.exists() {
+            empty
+        } else {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let recorded: ProgressState = serde_json::from_str(&content).with_context(|| {
+                format!(
+                    "Invalid bump progress {}, re-run with --fresh",
+                    path.display()
                 )
-                .context(format!("Failed to update {}", dep.name))?;
-                eprintln!(
-                    "  {} Updated {} in {}",
-                    "✓".green(),
-                    dep.name.cyan(),
-                    dep.file_path.purple()
+            })?;
+            if recorded.settings != settings {
+                log::info!(
+                    "Ignoring {}: recorded with different --include-prereleases/--max-major-jump",
+                    path.display()
                 );
+                empty
+            } else if now.saturating_sub(recorded.created) >= BUMP_PROGRESS_MAX_AGE.as_secs() {
+                log::info!(
+                    "Ignoring {}: fetched versions are older than {} hours",
+                    path.display(),
+                    BUMP_PROGRESS_MAX_AGE.as_secs() / 3600
+                );
+                empty
+            } else {
+                recorded
             }
-            DependencyType::HelmChart { repository } => {
-                if verbose {
-                    eprintln!("  Updating {} from repository: {}", dep.name, repository);
-                }
-                helm::update_helm_chart(
-                    &actual_path,
-                    &dep.name,
-                    &dep.current_version,
-                    &dep.latest_version,
-                    dep.latest_app_version.as_deref(),
-                )
-                .context(format!("Failed to update {}", dep.name))?;
-                eprintln!("  {} Updated {} in Chart.yaml", "✓".green(), dep.name);
-            }
+        };
+        if !state.projects.is_empty() {
+            log::info!(
+                "Resuming from {}: {} project(s) already scanned",
+                path.display(),
+                state.projects.len()
+            );
         }
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
     }
 
-    eprintln!(
-        "\n{} {} dependencies updated",
-        "SUCCESS:".green(),
-        selected_deps.len()
-    );
+    /// Dependencies recorded for `project` by a previous run, unless its files changed since
+    fn scanned(&self, project: &str) -> Option<Vec<Dependency>> {
+        let state = self.state.lock().unwrap();
+        let recorded = state.projects.get(project)?;
+        (recorded.modified == project_modified(project)).then(|| recorded.dependencies.clone())
+    }
 
-    Ok(())
+    /// Record the dependencies of a scanned project and save the progress
+    fn record(&self, project: &str, deps: &[Dependency]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.projects.insert(
+            project.to_string(),
+            ProjectProgress {
+                modified: project_modified(project),
+                dependencies: deps.to_vec(),
+            },
+        );
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string(&*state)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Remove the progress once every project is recorded, otherwise keep it for the next run
+    fn finish(self, projects: &[(Technology, String)]) -> Result<()> {
+        let BumpProgress { path, state } = self;
+        let state = state.into_inner().unwrap();
+        let pending = projects
+            .iter()
+            .filter(|(_, project)| !state.projects.contains_key(project))
+            .count();
+
+        if pending > 0 {
+            log::warning!(
+                "{} project(s) could not be scanned, re-run with --resume to retry only them",
+                pending
+            );
+        } else if path.exists() {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// `scan_projects_parallel` reusing the projects recorded in `progress`
+/// Projects scanned without fetch errors are recorded as they complete
+fn scan_projects_resumable<F>(
+    projects: &[(Technology, String)],
+    progress: Option<&BumpProgress>,
+    scan: F,
+) -> Vec<Result<Vec<Dependency>>>
+where
+    F: Fn(Technology, &str) -> Result<Vec<Dependency>> + Sync,
+{
+    let Some(progress) = progress else {
+        return scan_projects_parallel(projects, scan);
+    };
+
+    scan_projects_parallel(projects, |techno, path| {
+        if let Some(deps) = progress.scanned(path) {
+            return Ok(deps);
+        }
+        let deps = scan(techno, path)?;
+        if !deps
+            .iter()
+            .any(|dep| dep.latest_version.starts_with("ERROR:"))
+        {
+            if let Err(e) = progress.record(path, &deps) {
+                log::warning!("Could not record bump progress: {:#}", e);
+            }
+        }
+        Ok(deps)
+    })
+}
+
+/// Latest version and `capped` flag already seen per module/chart and current major
+type VersionCache = HashMap<String, (String, bool)>;
+
+/// Reuse the version already seen for the same module/chart and log the dependency status
+///
+/// The current major is part of the key: `--max-major-jump` bounds the proposed
+/// version relative to it, and `prefer_stable_major` does not apply on `0.x`.
+fn apply_version_cache(mut dep: Dependency, version_cache: &mut VersionCache) -> Dependency {
+    let current_major = dep
+        .current_version
+        .trim_start_matches('v')
+        .split('.')
+        .next()
+        .unwrap_or_default();
+    let cache_key = match &dep.dep_type {
+        // Submodules share the version of their registry module
+        DependencyType::TerraformModule { source, .. } => format!(
+            "tf:{}@{}",
+            terraform::registry_source(source),
+            current_major
+        ),
+        DependencyType::HelmChart { repository } => {
+            format!("helm:{}:{}@{}", repository, dep.name, current_major)
+        }
+    };
+
+    let used_cache = match version_cache.get(&cache_key) {
+        Some((cached_version, capped)) => {
+            dep.latest_version = cached_version.clone();
+            dep.capped = *capped;
+            true
+        }
+        None => {
+            version_cache.insert(cache_key, (dep.latest_version.clone(), dep.capped));
+            false
+        }
+    };
+    let cached_marker = if used_cache {
+        " [cached]".dimmed().to_string()
+    } else {
+        "".to_string()
+    };
+
+    // Log dependency status
+    if dep.latest_version.starts_with("ERROR:") {
+        // Show error inline
+        let error_msg = dep
+            .latest_version
+            .strip_prefix("ERROR: ")
+            .unwrap_or(&dep.latest_version);
+        // Truncate long error messages
+        let short_error = if error_msg.len() > 60 {
+            format!("{}...", &error_msg[..57])
+        } else {
+            error_msg.to_string()
+        };
+        eprintln!(
+            "  {} {} ({}:{}){} - {}",
+            "⚠".yellow(),
+            dep.name.cyan(),
+            dep.file_path.purple(),
+            dep.line_number,
+            cached_marker,
+            short_error.dimmed()
+        );
+    } else if dep.current_version == dep.latest_version {
+        eprintln!(
+            "  {} {} {} ({}:{}){} - already up to date",
+            "✓".green(),
+            dep.name.cyan(),
+            dep.current_version.yellow(),
+            dep.file_path.purple(),
+            dep.line_number,
+            cached_marker
+        );
+    } else {
+        eprintln!(
+            "  {} {} {} → {} ({}:{}){} - update available",
+            "↑".yellow(),
+            dep.name.cyan(),
+            dep.current_version.yellow(),
+            dep.latest_version.green(),
+            dep.file_path.purple(),
+            dep.line_number,
+            cached_marker
+        );
+    }
+
+    dep
 }
 
 fn run_bump_recursive(
     root_path: &str,
-    verbose: bool,
-    include_prereleases: bool,
-    no_ignore: bool,
+    options: &BumpOptions,
+    path_filter: &PathFilter,
 ) -> Result<()> {
-    use std::collections::HashMap;
+    let BumpOptions {
+        verbose,
+        include_prereleases,
+        no_ignore,
+        max_major_jump,
+        format,
+        fail_on_error,
+        ..
+    } = *options;
 
     // Load config to get max_depth
-    let config = crate::config::Config::load().unwrap_or_default();
+    let config = crate::config::Config::load_merged(root_path)?;
     let max_depth = config.bump.max_depth;
 
-    eprintln!(
-        "{} Scanning recursively (max depth: {}): {}",
-        "INFO:".cyan(),
+    log::info!(
+        "Scanning recursively (max depth: {}): {}",
         max_depth,
         root_path
     );
@@ -262,6 +810,11 @@ fn run_bump_recursive(
             continue;
         }
 
+        // Skip projects outside the --path-filter globs
+        if !path_filter.matches(std::path::Path::new(root_path), path) {
+            continue;
+        }
+
         let path_str = path.to_str().unwrap_or("");
 
         // Direct file-based detection to avoid interactive prompts
@@ -295,10 +848,13 @@ fn run_bump_recursive(
     }
 
     if projects.is_empty() {
-        eprintln!("{} No Terraform or Helm projects found", "INFO:".cyan());
+        log::info!("No Terraform or Helm projects found");
         return Ok(());
     }
 
+    // Walk order is filesystem-dependent, keep the scan output stable
+    projects.sort_by(|a, b| a.1.cmp(&b.1));
+
     let terraform_count = projects
         .iter()
         .filter(|(t, _)| matches!(t, Technology::Terraform))
@@ -308,240 +864,265 @@ fn run_bump_recursive(
         .filter(|(t, _)| matches!(t, Technology::Helm))
         .count();
 
-    eprintln!(
-        "{} Found {} Terraform project(s), {} Helm project(s)",
-        "INFO:".cyan(),
+    log::info!(
+        "Found {} Terraform project(s), {} Helm project(s)",
         terraform_count,
         helm_count
     );
 
-    // Scan all projects and aggregate dependencies
-    // Use a cache to avoid querying the same module/chart version twice
-    let mut version_cache: HashMap<String, String> = HashMap::new();
-    let mut all_dependencies = Vec::new();
+    // Projects scanned by an interrupted run are reused with --resume
+    let progress = if options.resume {
+        let settings = ScanSettings {
+            include_prereleases: options.include_prereleases,
+            max_major_jump: options.max_major_jump,
+        };
+        Some(BumpProgress::open(root_path, options.fresh, settings)?)
+    } else {
+        None
+    };
+
+    // Scan projects on a worker pool; results are aggregated afterwards in project
+    // order so the version cache and the printed output do not depend on scheduling
     let total_projects = projects.len();
+    let fetcher = HttpFetcher { verbose };
+    let scan_results =
+        scan_projects_resumable(&projects, progress.as_ref(), |techno, actual_path| {
+            if verbose {
+                eprintln!("  Scanning: {}", actual_path);
+            }
+            match techno {
+                Technology::Terraform => terraform::scan_terraform_modules(
+                    actual_path,
+                    verbose,
+                    include_prereleases,
+                    max_major_jump,
+                    &fetcher,
+                ),
+                Technology::Helm => helm::scan_helm_charts(
+                    actual_path,
+                    verbose,
+                    include_prereleases,
+                    max_major_jump,
+                    &fetcher,
+                ),
+                _ => Ok(Vec::new()),
+            }
+        });
 
-    for (techno, actual_path) in &projects {
-        if verbose {
-            eprintln!("  Scanning: {}", actual_path);
-        }
+    // Use a cache to avoid reporting different versions for the same module/chart
+    let mut version_cache = VersionCache::new();
+    let mut all_dependencies = Vec::new();
 
-        match *techno {
-            Technology::Terraform => {
-                match terraform::scan_terraform_modules(actual_path, verbose, include_prereleases) {
-                    Ok(deps) => {
-                        for dep in deps {
-                            // Check cache for this source
-                            let cache_key =
-                                if let DependencyType::TerraformModule { ref source, .. } =
-                                    dep.dep_type
-                                {
-                                    format!("tf:{}", source)
-                                } else {
-                                    continue;
-                                };
-
-                            let (latest_version, used_cache) =
-                                if let Some(cached_version) = version_cache.get(&cache_key) {
-                                    (cached_version.clone(), true)
-                                } else {
-                                    let version = dep.latest_version.clone();
-                                    version_cache.insert(cache_key.clone(), version.clone());
-                                    (version, false)
-                                };
-
-                            // Log dependency status
-                            if latest_version.starts_with("ERROR:") {
-                                // Show error inline
-                                let error_msg = latest_version
-                                    .strip_prefix("ERROR: ")
-                                    .unwrap_or(&latest_version);
-                                // Truncate long error messages
-                                let short_error = if error_msg.len() > 60 {
-                                    format!("{}...", &error_msg[..57])
-                                } else {
-                                    error_msg.to_string()
-                                };
-                                eprintln!(
-                                    "  {} {} ({}:{}){} - {}",
-                                    "⚠".yellow(),
-                                    dep.name.cyan(),
-                                    dep.file_path.purple(),
-                                    dep.line_number,
-                                    if used_cache {
-                                        " [cached]".dimmed().to_string()
-                                    } else {
-                                        "".to_string()
-                                    },
-                                    short_error.dimmed()
-                                );
-                            } else if dep.current_version == latest_version {
-                                eprintln!(
-                                    "  {} {} {} ({}:{}){} - already up to date",
-                                    "✓".green(),
-                                    dep.name.cyan(),
-                                    dep.current_version.yellow(),
-                                    dep.file_path.purple(),
-                                    dep.line_number,
-                                    if used_cache {
-                                        " [cached]".dimmed().to_string()
-                                    } else {
-                                        "".to_string()
-                                    }
-                                );
-                            } else {
-                                eprintln!(
-                                    "  {} {} {} → {} ({}:{}){} - update available",
-                                    "↑".yellow(),
-                                    dep.name.cyan(),
-                                    dep.current_version.yellow(),
-                                    latest_version.green(),
-                                    dep.file_path.purple(),
-                                    dep.line_number,
-                                    if used_cache {
-                                        " [cached]".dimmed().to_string()
-                                    } else {
-                                        "".to_string()
-                                    }
-                                );
-                            }
-
-                            let mut final_dep = dep.clone();
-                            final_dep.latest_version = latest_version;
-                            all_dependencies.push(final_dep);
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "  {} Failed to scan Terraform modules in {}: {}",
-                            "✗".red(),
-                            actual_path,
-                            e
-                        );
-                    }
+    for ((techno, actual_path), result) in projects.iter().zip(scan_results) {
+        match result {
+            Ok(deps) => {
+                for dep in deps {
+                    all_dependencies.push(apply_version_cache(dep, &mut version_cache));
                 }
             }
-            Technology::Helm => {
-                match helm::scan_helm_charts(actual_path, verbose, include_prereleases) {
-                    Ok(deps) => {
-                        for dep in deps {
-                            // Check cache for this chart
-                            let cache_key = if let DependencyType::HelmChart { ref repository } =
-                                dep.dep_type
-                            {
-                                format!("helm:{}:{}", repository, dep.name)
-                            } else {
-                                continue;
-                            };
-
-                            let (latest_version, used_cache) =
-                                if let Some(cached_version) = version_cache.get(&cache_key) {
-                                    (cached_version.clone(), true)
-                                } else {
-                                    let version = dep.latest_version.clone();
-                                    version_cache.insert(cache_key.clone(), version.clone());
-                                    (version, false)
-                                };
-
-                            // Log dependency status
-                            if latest_version.starts_with("ERROR:") {
-                                // Show error inline
-                                let error_msg = latest_version
-                                    .strip_prefix("ERROR: ")
-                                    .unwrap_or(&latest_version);
-                                // Truncate long error messages
-                                let short_error = if error_msg.len() > 60 {
-                                    format!("{}...", &error_msg[..57])
-                                } else {
-                                    error_msg.to_string()
-                                };
-                                eprintln!(
-                                    "  {} {} ({}:{}){} - {}",
-                                    "⚠".yellow(),
-                                    dep.name.cyan(),
-                                    dep.file_path.purple(),
-                                    dep.line_number,
-                                    if used_cache {
-                                        " [cached]".dimmed().to_string()
-                                    } else {
-                                        "".to_string()
-                                    },
-                                    short_error.dimmed()
-                                );
-                            } else if dep.current_version == latest_version {
-                                eprintln!(
-                                    "  {} {} {} ({}:{}){} - already up to date",
-                                    "✓".green(),
-                                    dep.name.cyan(),
-                                    dep.current_version.yellow(),
-                                    dep.file_path.purple(),
-                                    dep.line_number,
-                                    if used_cache {
-                                        " [cached]".dimmed().to_string()
-                                    } else {
-                                        "".to_string()
-                                    }
-                                );
-                            } else {
-                                eprintln!(
-                                    "  {} {} {} → {} ({}:{}){} - update available",
-                                    "↑".yellow(),
-                                    dep.name.cyan(),
-                                    dep.current_version.yellow(),
-                                    latest_version.green(),
-                                    dep.file_path.purple(),
-                                    dep.line_number,
-                                    if used_cache {
-                                        " [cached]".dimmed().to_string()
-                                    } else {
-                                        "".to_string()
-                                    }
-                                );
-                            }
-
-                            let mut final_dep = dep.clone();
-                            final_dep.latest_version = latest_version;
-                            all_dependencies.push(final_dep);
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "  {} Failed to scan Helm charts in {}: {}",
-                            "✗".red(),
-                            actual_path,
-                            e
-                        );
-                    }
-                }
+            Err(e) => {
+                let kind = if *techno == Technology::Helm {
+                    "Helm charts"
+                } else {
+                    "Terraform modules"
+                };
+                eprintln!(
+                    "  {} Failed to scan {} in {}: {}",
+                    "✗".red(),
+                    kind,
+                    actual_path,
+                    e
+                );
             }
-            _ => {}
         }
     }
 
+    // Stable order for the selection prompt
+    all_dependencies.sort_by(|a, b| {
+        a.file_path
+            .cmp(&b.file_path)
+            .then(a.line_number.cmp(&b.line_number))
+    });
+
+    // Keep the progress only while some projects still have to be scanned
+    if let Some(progress) = progress {
+        progress.finish(&projects)?;
+    }
+
     if all_dependencies.is_empty() {
-        eprintln!("{} No dependencies found", "INFO:".cyan());
+        log::info!("No dependencies found");
         return Ok(());
     }
 
+    if options.only_errors {
+        report_errors(&all_dependencies);
+        return Ok(());
+    }
+    if options.check {
+        check_updates(&all_dependencies, format, options.sort, fail_on_error);
+        return Ok(());
+    }
+
+    let updated = select_and_update(&all_dependencies, verbose, format, options.sort)?;
+    if !updated.is_empty() {
+        log::success!(
+            "\n{} dependencies updated across {} project(s)",
+            updated.len(),
+            total_projects
+        );
+    }
+    if options.update_lock {
+        update_lock_files(&updated, verbose)?;
+    }
+    commit_updated_files(&updated, options)?;
+    if let Some(path) = &options.changelog_file {
+        write_changelog(path, &updated, true)?;
+    }
+
+    report_summary(&all_dependencies, updated.len(), fail_on_error);
+    Ok(())
+}
+
+/// Exit code of `bump --check` when updates are available
+const UPDATES_AVAILABLE_EXIT_CODE: i32 = 3;
+
+/// Exit code of `bump --fail-on-error` and `bump --only-errors` when some versions could not be fetched
+const UNVERIFIED_EXIT_CODE: i32 = 2;
+
+/// Dependencies whose latest version could not be fetched, with the fetch error
+fn fetch_errors(all_dependencies: &[Dependency]) -> Vec<(&Dependency, &str)> {
+    all_dependencies
+        .iter()
+        .filter_map(|dep| {
+            let message = dep.latest_version.strip_prefix("ERROR:")?;
+            Some((dep, message.trim_start()))
+        })
+        .collect()
+}
+
+/// `bump --only-errors`: print the dependencies that could not be verified on stdout
+/// Exits with `UNVERIFIED_EXIT_CODE` when there are any
+fn report_errors(all_dependencies: &[Dependency]) {
+    let errors = fetch_errors(all_dependencies);
+    if errors.is_empty() {
+        log::success!("All {} dependencies verified", all_dependencies.len());
+        return;
+    }
+
+    for (dep, message) in &errors {
+        println!(
+            "{} ({}:{}): {}",
+            dep.name, dep.file_path, dep.line_number, message
+        );
+    }
+    log::error!("{} dependencies could not be verified", errors.len());
+    std::process::exit(UNVERIFIED_EXIT_CODE);
+}
+
+/// Dependencies with a newer version available (fetch errors excluded)
+fn available_updates<'a>(deps: &[&'a Dependency]) -> Vec<&'a Dependency> {
+    deps.iter()
+        .filter(|dep| !dep.latest_version.starts_with("ERROR:"))
+        .filter(|dep| dep.current_version != dep.latest_version)
+        .copied()
+        .collect()
+}
+
+/// `(major, minor, patch)` of a version such as `v7.0` or `1.2.3-rc.1`, missing parts are 0
+fn version_parts(version: &str) -> Option<[u64; 3]> {
+    let core = version.trim_start_matches('v').split(['-', '+']).next()?;
+    let mut parts = [0; 3];
+    for (part, number) in parts.iter_mut().zip(core.split('.')) {
+        *part = number.parse().ok()?;
+    }
+    Some(parts)
+}
+
+/// How far `latest` is from `current`: the major, then minor, then patch difference
+/// `None` when either version is not numeric
+fn version_jump(dep: &Dependency) -> Option<[u64; 3]> {
+    let current = version_parts(&dep.current_version)?;
+    let latest = version_parts(&dep.latest_version)?;
+    let first_change = (0..3).find(|&i| latest[i] != current[i]).unwrap_or(3);
+    let mut jump = [0; 3];
+    if first_change < 3 {
+        jump[first_change] = latest[first_change].saturating_sub(current[first_change]);
+        jump[first_change + 1..].copy_from_slice(&latest[first_change + 1..]);
+    }
+    Some(jump)
+}
+
+/// Order `updates` for `--sort`, keeping discovery order between equal entries
+fn sort_updates(updates: &mut [&Dependency], sort: Option<BumpSort>) {
+    match sort {
+        None => {}
+        Some(BumpSort::Name) => updates.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(BumpSort::File) => updates.sort_by(|a, b| {
+            a.file_path
+                .cmp(&b.file_path)
+                .then(a.line_number.cmp(&b.line_number))
+        }),
+        // Biggest jump first, unparseable versions last
+        Some(BumpSort::Jump) => updates.sort_by_key(|dep| std::cmp::Reverse(version_jump(dep))),
+    }
+}
+
+/// `bump --check`: list available updates without prompting or changing files
+/// Exits with `UPDATES_AVAILABLE_EXIT_CODE` when there are any
+fn check_updates(
+    all_dependencies: &[Dependency],
+    format: BumpFormat,
+    sort: Option<BumpSort>,
+    fail_on_error: bool,
+) {
+    let deps: Vec<&Dependency> = all_dependencies.iter().collect();
+    let mut updates = available_updates(&deps);
+    sort_updates(&mut updates, sort);
+
+    if !updates.is_empty() {
+        log::info!(
+            "Found {} dependencies with updates available\n",
+            updates.len()
+        );
+        if format == BumpFormat::Table {
+            eprintln!("{}", render_table(&updates));
+        } else {
+            for name in display_names(&updates) {
+                eprintln!("  {}", name);
+            }
+        }
+    }
+
+    report_summary(all_dependencies, 0, fail_on_error);
+
+    if !updates.is_empty() {
+        log::error!("{} dependencies are out of date", updates.len());
+        std::process::exit(UPDATES_AVAILABLE_EXIT_CODE);
+    }
+    if BumpSummary::new(all_dependencies, 0).errored == 0 {
+        log::success!("All dependencies are up to date!");
+    }
+}
+
+/// Prompt for the dependencies to update and apply them
+/// Returns the updated dependencies
+fn select_and_update(
+    all_dependencies: &[Dependency],
+    verbose: bool,
+    format: BumpFormat,
+    sort: Option<BumpSort>,
+) -> Result<Vec<&Dependency>> {
     // Separate successful dependencies from errors
     let (successful_deps, error_deps): (Vec<&Dependency>, Vec<&Dependency>) = all_dependencies
         .iter()
         .partition(|dep| !dep.latest_version.starts_with("ERROR:"));
 
-    // Filter dependencies with updates available (excluding errors)
-    let updates_available: Vec<_> = all_dependencies
-        .iter()
-        .filter(|dep| {
-            !dep.latest_version.starts_with("ERROR:") && dep.current_version != dep.latest_version
-        })
-        .collect();
+    let mut updates_available = available_updates(&successful_deps);
+    sort_updates(&mut updates_available, sort);
 
     // Check if all dependencies failed
     if !error_deps.is_empty() && successful_deps.is_empty() {
-        eprintln!(
-            "{} Could not verify any dependencies due to fetch errors",
-            "WARNING:".yellow()
-        );
+        log::warning!("Could not verify any dependencies due to fetch errors");
         eprintln!(
             "  {} found but all failed to fetch",
             if all_dependencies.len() == 1 {
@@ -555,25 +1136,25 @@ fn run_bump_recursive(
             "Tip: Configure OCI authentication in ~/.config/mk/config.toml or use --verbose for details"
                 .dimmed()
         );
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     if updates_available.is_empty() {
-        eprintln!("{} All dependencies are up to date!", "SUCCESS:".green());
-        return Ok(());
+        log::success!("All dependencies are up to date!");
+        return Ok(Vec::new());
     }
 
-    eprintln!(
-        "{} Found {} dependencies with updates available\n",
-        "INFO:".cyan(),
+    log::info!(
+        "Found {} dependencies with updates available\n",
         updates_available.len()
     );
 
+    if format == BumpFormat::Table {
+        eprintln!("{}", render_table(&updates_available));
+    }
+
     // Create multi-select prompt with project path info
-    let items: Vec<String> = updates_available
-        .iter()
-        .map(|dep| dep.display_name())
-        .collect();
+    let items = display_names(&updates_available);
 
     // Pre-select if only one dependency is available
     let defaults = if updates_available.len() == 1 {
@@ -590,12 +1171,12 @@ fn run_bump_recursive(
         .context("Failed to get user selection")?;
 
     if selections.is_empty() {
-        eprintln!("{} No dependencies selected", "INFO:".cyan());
-        return Ok(());
+        log::info!("No dependencies selected");
+        return Ok(Vec::new());
     }
 
     // Apply updates
-    eprintln!("\n{} Updating selected dependencies...", "INFO:".cyan());
+    log::info!("\nUpdating selected dependencies...");
     let selected_deps: Vec<_> = selections.iter().map(|&i| updates_available[i]).collect();
 
     for dep in &selected_deps {
@@ -637,12 +1218,619 @@ fn run_bump_recursive(
         }
     }
 
-    eprintln!(
-        "\n{} {} dependencies updated across {} project(s)",
-        "SUCCESS:".green(),
-        selected_deps.len(),
-        total_projects
-    );
+    Ok(selected_deps)
+}
 
-    Ok(())
+/// Counts reported at the end of a bump run
+#[derive(Debug, PartialEq, Eq)]
+struct BumpSummary {
+    up_to_date: usize,
+    updated: usize,
+    errored: usize,
+}
+
+impl BumpSummary {
+    fn new(all_dependencies: &[Dependency], updated: usize) -> Self {
+        let errored = all_dependencies
+            .iter()
+            .filter(|dep| dep.latest_version.starts_with("ERROR:"))
+            .count();
+        let up_to_date = all_dependencies
+            .iter()
+            .filter(|dep| dep.current_version == dep.latest_version)
+            .count();
+
+        Self {
+            up_to_date,
+            updated,
+            errored,
+        }
+    }
+}
+
+impl std::fmt::Display for BumpSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} up to date, {} updated, {} errored",
+            self.up_to_date, self.updated, self.errored
+        )
+    }
+}
+
+/// Print the closing summary, exiting with code 2 on fetch errors when `fail_on_error` is set
+fn report_summary(all_dependencies: &[Dependency], updated: usize, fail_on_error: bool) {
+    let summary = BumpSummary::new(all_dependencies, updated);
+    log::info!("\nSummary: {}", summary);
+
+    if fail_on_error && summary.errored > 0 {
+        log::error!("{} dependencies could not be verified", summary.errored);
+        std::process::exit(UNVERIFIED_EXIT_CODE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::fs;
+
+    fn dependency(name: &str, file_path: &str, current: &str, latest: &str) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            current_version: current.to_string(),
+            latest_version: latest.to_string(),
+            latest_app_version: None,
+            capped: false,
+            file_path: file_path.to_string(),
+            line_number: 3,
+            dep_type: DependencyType::TerraformModule {
+                source: "terraform-google-modules/network/google".to_string(),
+                constraint: format!("~> {}", current),
+            },
+        }
+    }
+
+    #[test]
+    fn test_scan_projects_parallel_aggregates_all_projects() {
+        let projects: Vec<(Technology, String)> = (0..40)
+            .map(|i| {
+                let techno = if i % 3 == 0 {
+                    Technology::Helm
+                } else {
+                    Technology::Terraform
+                };
+                (techno, format!("project-{:02}", i))
+            })
+            .collect();
+
+        let results = scan_projects_parallel(&projects, |_, path| {
+            let index: u64 = path.trim_start_matches("project-").parse().unwrap();
+            // Finish out of order
+            std::thread::sleep(std::time::Duration::from_millis((40 - index) % 7));
+            if index == 13 {
+                anyhow::bail!("scan failed");
+            }
+            Ok(vec![
+                dependency("a", &format!("{}/main.tf", path), "1.0.0", "2.0.0"),
+                dependency("b", &format!("{}/main.tf", path), "1.0.0", "1.0.0"),
+            ])
+        });
+
+        assert_eq!(results.len(), projects.len());
+        for (i, result) in results.iter().enumerate() {
+            if i == 13 {
+                assert!(result.is_err());
+                continue;
+            }
+            let deps = result.as_ref().unwrap();
+            assert_eq!(deps.len(), 2);
+            assert_eq!(deps[0].file_path, format!("project-{:02}/main.tf", i));
+        }
+    }
+
+    #[test]
+    fn test_apply_version_cache_reuses_first_version() {
+        let mut cache = VersionCache::new();
+        let first = apply_version_cache(dependency("vpc", "a/main.tf", "7.0", "9.1.0"), &mut cache);
+        let second =
+            apply_version_cache(dependency("vpc", "b/main.tf", "7.0", "9.2.0"), &mut cache);
+
+        assert_eq!(first.latest_version, "9.1.0");
+        assert_eq!(second.latest_version, "9.1.0");
+    }
+
+    #[test]
+    fn test_apply_version_cache_keeps_capped_version_per_major() {
+        let mut cache = VersionCache::new();
+        let mut old_vpc = dependency("vpc", "a/main.tf", "3.0", "4.2.0");
+        old_vpc.capped = true;
+        let mut other_old_vpc = dependency("vpc", "c/main.tf", "3.1", "4.3.0");
+        other_old_vpc.capped = false;
+
+        apply_version_cache(old_vpc, &mut cache);
+        let new_vpc =
+            apply_version_cache(dependency("vpc", "b/main.tf", "7.0", "9.2.0"), &mut cache);
+        let other_old_vpc = apply_version_cache(other_old_vpc, &mut cache);
+
+        assert_eq!(new_vpc.latest_version, "9.2.0");
+        assert!(!new_vpc.capped);
+        assert_eq!(other_old_vpc.latest_version, "4.2.0");
+        assert!(other_old_vpc.capped);
+    }
+
+    #[test]
+    fn test_apply_version_cache_shares_submodule_version() {
+        let mut cache = VersionCache::new();
+        let mut subnets = dependency("subnets", "b/main.tf", "7.0", "9.2.0");
+        subnets.dep_type = DependencyType::TerraformModule {
+            source: "terraform-google-modules/network/google//modules/subnets".to_string(),
+            constraint: "~> 7.0".to_string(),
+        };
+
+        apply_version_cache(dependency("vpc", "a/main.tf", "7.0", "9.1.0"), &mut cache);
+        let subnets = apply_version_cache(subnets, &mut cache);
+
+        assert_eq!(subnets.latest_version, "9.1.0");
+    }
+
+    #[test]
+    fn test_available_updates_skips_current_and_errored() {
+        let stale = dependency("vpc", "a/main.tf", "7.0", "9.1.0");
+        let current = dependency("nat", "a/nat.tf", "5.0", "5.0");
+        let errored = dependency("sql", "a/sql.tf", "4.0", "ERROR: HTTP 500");
+
+        let updates = available_updates(&[&stale, &current, &errored]);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].name, "vpc");
+    }
+
+    #[test]
+    fn test_fetch_errors_keeps_only_errored_dependencies() {
+        let deps = vec![
+            dependency("vpc", "a/main.tf", "1.0", "2.0"),
+            dependency("sql", "a/sql.tf", "4.0", "ERROR: HTTP 500"),
+            dependency("dns", "b/main.tf", "3.0", "3.0"),
+        ];
+
+        let errors = fetch_errors(&deps);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0.name, "sql");
+        assert_eq!(errors[0].1, "HTTP 500");
+    }
+
+    #[test]
+    fn test_bump_summary_counts_errored_dependencies() {
+        let deps = vec![
+            dependency("vpc", "network.tf", "9.1.0", "9.1.0"),
+            dependency("gke", "cluster.tf", "30.0.0", "33.0.0"),
+            dependency("nat", "nat.tf", "4.0.0", "5.3.0"),
+            dependency(
+                "private",
+                "private.tf",
+                "1.0.0",
+                "ERROR: Failed to reach registry",
+            ),
+        ];
+
+        let summary = BumpSummary::new(&deps, 1);
+
+        assert_eq!(
+            summary,
+            BumpSummary {
+                up_to_date: 1,
+                updated: 1,
+                errored: 1,
+            }
+        );
+        assert_eq!(summary.to_string(), "1 up to date, 1 updated, 1 errored");
+    }
+
+    #[test]
+    fn test_write_changelog_lists_applied_bumps() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let changelog = temp_dir.path().join("CHANGELOG.md");
+        let changelog = changelog.to_str().unwrap();
+        let network = dependency("network", "infra/vpc/main.tf", "1.0.0", "2.0.0");
+        let nat = dependency("nat", "infra/vpc/nat.tf", "3.1.0", "3.2.0");
+        let sql = dependency("sql", "infra/db/main.tf", "5.0.0", "5.1.0");
+        let updated = vec![&network, &nat, &sql];
+
+        write_changelog(changelog, &updated, false).unwrap();
+        assert_eq!(
+            fs::read_to_string(changelog).unwrap(),
+            "## Dependency updates\n\
+             \n### `infra/db/main.tf`\n\n\
+             - sql: 5.0.0 → 5.1.0\n\
+             \n### `infra/vpc/main.tf`\n\n\
+             - network: 1.0.0 → 2.0.0\n\
+             \n### `infra/vpc/nat.tf`\n\n\
+             - nat: 3.1.0 → 3.2.0\n"
+        );
+
+        // Recursive runs group by project directory
+        write_changelog(changelog, &updated, true).unwrap();
+        assert_eq!(
+            fs::read_to_string(changelog).unwrap(),
+            "## Dependency updates\n\
+             \n### `infra/db`\n\n\
+             - sql: 5.0.0 → 5.1.0\n\
+             \n### `infra/vpc`\n\n\
+             - network: 1.0.0 → 2.0.0\n\
+             - nat: 3.1.0 → 3.2.0\n"
+        );
+    }
+
+    #[test]
+    fn test_write_changelog_skipped_without_updates() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let changelog = temp_dir.path().join("CHANGELOG.md");
+
+        write_changelog(changelog.to_str().unwrap(), &[], false).unwrap();
+        assert!(!changelog.exists());
+    }
+
+    #[test]
+    fn test_render_table_headers_and_rows() {
+        let vpc = dependency("vpc", "network.tf", "7.0", "9.1.0");
+        let nat = dependency("cloud_nat", "modules/nat/main.tf", "4.0.0", "5.3.0");
+
+        let table = render_table(&[&vpc, &nat]);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("NAME"));
+        for header in ["FILE", "CURRENT", "LATEST", "TYPE"] {
+            assert!(lines[0].contains(header));
+        }
+        assert_eq!(
+            lines[1],
+            "vpc        network.tf:3           7.0      9.1.0   terraform-module"
+        );
+    }
+
+    #[test]
+    fn test_render_table_marks_capped_latest() {
+        let mut vpc = dependency("vpc", "network.tf", "7.0", "8.4.0");
+        vpc.capped = true;
+
+        let table = render_table(&[&vpc]);
+        assert_eq!(
+            table.lines().nth(1).unwrap(),
+            "vpc   network.tf:3  7.0      8.4.0 (capped)  terraform-module"
+        );
+    }
+
+    /// Updates in discovery order: different files, names and jump sizes
+    fn sort_fixture() -> Vec<Dependency> {
+        vec![
+            dependency("vpc", "network.tf", "7.0", "9.1.0"),
+            dependency("cloud_nat", "modules/nat/main.tf", "4.0.0", "4.2.0"),
+            dependency("gke", "cluster.tf", "30.1.0", "30.1.4"),
+            dependency("sql", "database.tf", "v1.2.0", "v2.0.0"),
+        ]
+    }
+
+    fn sorted_names(deps: &[Dependency], sort: Option<BumpSort>) -> Vec<&str> {
+        let mut updates: Vec<&Dependency> = deps.iter().collect();
+        sort_updates(&mut updates, sort);
+        updates.iter().map(|dep| dep.name.as_str()).collect()
+    }
+
+    #[rstest]
+    #[case(None, ["vpc", "cloud_nat", "gke", "sql"])]
+    #[case(Some(BumpSort::Name), ["cloud_nat", "gke", "sql", "vpc"])]
+    #[case(Some(BumpSort::File), ["gke", "sql", "cloud_nat", "vpc"])]
+    #[case(Some(BumpSort::Jump), ["vpc", "sql", "cloud_nat", "gke"])]
+    fn test_sort_updates(#[case] sort: Option<BumpSort>, #[case] expected: [&str; 4]) {
+        assert_eq!(sorted_names(&sort_fixture(), sort), expected);
+    }
+
+    #[test]
+    fn test_version_jump() {
+        let dep = |current, latest| dependency("vpc", "network.tf", current, latest);
+        assert_eq!(version_jump(&dep("7.0", "9.1.0")), Some([2, 1, 0]));
+        assert_eq!(version_jump(&dep("4.0.0", "4.2.0")), Some([0, 2, 0]));
+        assert_eq!(version_jump(&dep("v1.2.3", "v1.2.5-rc.1")), Some([0, 0, 2]));
+        assert_eq!(version_jump(&dep("main", "1.0.0")), None);
+    }
+
+    #[test]
+    fn test_resume_skips_projects_scanned_by_interrupted_run() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        let projects = vec![
+            (Technology::Terraform, format!("{}/network", root)),
+            (Technology::Terraform, format!("{}/dns", root)),
+        ];
+        let progress_file = temp_dir.path().join(BUMP_PROGRESS_FILE);
+        let scanned = Mutex::new(Vec::new());
+        let scan = |network_down: bool| {
+            let scanned = &scanned;
+            move |_: Technology, path: &str| {
+                scanned.lock().unwrap().push(path.to_string());
+                if network_down && path.ends_with("dns") {
+                    anyhow::bail!("connection reset by peer");
+                }
+                Ok(vec![dependency(
+                    "vpc",
+                    &format!("{}/main.tf", path),
+                    "7.0",
+                    "9.1.0",
+                )])
+            }
+        };
+
+        // Interrupted run: the network fails while scanning dns
+        let progress = BumpProgress::open(root, false, ScanSettings::default()).unwrap();
+        let results = scan_projects_resumable(&projects, Some(&progress), scan(true));
+        assert!(results[0].is_ok() && results[1].is_err());
+        progress.finish(&projects).unwrap();
+        assert!(progress_file.exists());
+
+        // Re-run: the network project comes from the progress file, only dns is scanned
+        scanned.lock().unwrap().clear();
+        let progress = BumpProgress::open(root, false, ScanSettings::default()).unwrap();
+        let results = scan_projects_resumable(&projects, Some(&progress), scan(false));
+        assert_eq!(*scanned.lock().unwrap(), [projects[1].1.clone()]);
+        assert_eq!(results[0].as_ref().unwrap()[0].latest_version, "9.1.0");
+        progress.finish(&projects).unwrap();
+        assert!(!progress_file.exists());
+    }
+
+    #[test]
+    fn test_progress_rescans_changed_project() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        let project = temp_dir.path().join("network");
+        fs::create_dir(&project).unwrap();
+        fs::write(project.join("main.tf"), "# v1\n").unwrap();
+        let project = project.to_str().unwrap();
+
+        BumpProgress::open(root, false, ScanSettings::default())
+            .unwrap()
+            .record(project, &[])
+            .unwrap();
+        assert!(BumpProgress::open(root, false, ScanSettings::default())
+            .unwrap()
+            .scanned(project)
+            .is_some());
+
+        // Edited after the scan: the recorded dependencies are stale
+        let main_tf = fs::File::options()
+            .write(true)
+            .open(format!("{}/main.tf", project))
+            .unwrap();
+        main_tf
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+            .unwrap();
+        assert!(BumpProgress::open(root, false, ScanSettings::default())
+            .unwrap()
+            .scanned(project)
+            .is_none());
+    }
+
+    #[test]
+    fn test_fresh_ignores_recorded_progress() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        let project = format!("{}/network", root);
+        BumpProgress::open(root, false, ScanSettings::default())
+            .unwrap()
+            .record(&project, &[])
+            .unwrap();
+
+        assert!(BumpProgress::open(root, false, ScanSettings::default())
+            .unwrap()
+            .scanned(&project)
+            .is_some());
+        assert!(BumpProgress::open(root, true, ScanSettings::default())
+            .unwrap()
+            .scanned(&project)
+            .is_none());
+    }
+
+    #[test]
+    fn test_progress_discarded_on_other_settings_or_age() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        let project = format!("{}/network", root);
+        let settings = ScanSettings {
+            include_prereleases: false,
+            max_major_jump: Some(1),
+        };
+        let record = || {
+            BumpProgress::open(root, true, settings)
+                .unwrap()
+                .record(&project, &[])
+                .unwrap()
+        };
+
+        record();
+        assert!(BumpProgress::open(root, false, settings)
+            .unwrap()
+            .scanned(&project)
+            .is_some());
+        let prereleases = ScanSettings {
+            include_prereleases: true,
+            ..settings
+        };
+        assert!(BumpProgress::open(root, false, prereleases)
+            .unwrap()
+            .scanned(&project)
+            .is_none());
+
+        // Recorded more than BUMP_PROGRESS_MAX_AGE ago
+        record();
+        let progress_file = temp_dir.path().join(BUMP_PROGRESS_FILE);
+        let mut state: ProgressState =
+            serde_json::from_str(&fs::read_to_string(&progress_file).unwrap()).unwrap();
+        state.created -= BUMP_PROGRESS_MAX_AGE.as_secs();
+        fs::write(&progress_file, serde_json::to_string(&state).unwrap()).unwrap();
+        assert!(BumpProgress::open(root, false, settings)
+            .unwrap()
+            .scanned(&project)
+            .is_none());
+    }
+
+    #[test]
+    fn test_lock_update_only_where_lock_file_exists() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let locked = temp_dir.path().join("network");
+        let unlocked = temp_dir.path().join("dns");
+        fs::create_dir_all(&locked).unwrap();
+        fs::create_dir_all(&unlocked).unwrap();
+        fs::write(locked.join(TERRAFORM_LOCK_FILE), "").unwrap();
+
+        let path = |dir: &std::path::Path, file: &str| dir.join(file).to_string_lossy().to_string();
+        let vpc = dependency("vpc", &path(&locked, "main.tf"), "7.0", "9.1.0");
+        let nat = dependency("nat", &path(&locked, "nat.tf"), "4.0", "5.0.0");
+        let zone = dependency("zone", &path(&unlocked, "main.tf"), "1.0", "2.0.0");
+
+        assert_eq!(lock_update_dirs(&[&vpc, &nat, &zone]), [locked]);
+        assert_eq!(
+            lock_update_commands(),
+            ["terraform get", "terraform providers lock"]
+        );
+    }
+
+    #[test]
+    fn test_commit_updates_one_commit_per_file() {
+        let vpc = dependency("vpc", "infra/network/main.tf", "7.0", "9.1.0");
+        let zone = dependency("zone", "infra/dns/main.tf", "1.0", "2.0.0");
+        let nat = dependency("nat", "infra/network/main.tf", "4.0", "5.0.0");
+
+        // Fake git runner recording every command instead of running it
+        let mut runs = Vec::new();
+        let commits = commit_updates(
+            &[&vpc, &zone, &nat],
+            "bump {name} to {version}",
+            |dir, cmd| {
+                runs.push((dir.to_string_lossy().to_string(), cmd.to_string()));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(commits, 2);
+        let commits: Vec<_> = runs
+            .iter()
+            .filter(|(_, cmd)| cmd.starts_with("git add"))
+            .collect();
+        assert_eq!(
+            commits,
+            [
+                &(
+                    "infra/network".to_string(),
+                    "git add main.tf && git commit -m 'bump vpc to 9.1.0, bump nat to 5.0.0' -- main.tf"
+                        .to_string()
+                ),
+                &(
+                    "infra/dns".to_string(),
+                    "git add main.tf && git commit -m 'bump zone to 2.0.0' -- main.tf".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commit_updates_leaves_other_staged_files_out() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(["-c", "user.name=mk", "-c", "user.email=mk@example.com"])
+                .args(args)
+                .current_dir(temp_dir.path())
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{:?}", output);
+            String::from_utf8_lossy(&output.stdout).to_string()
+        };
+        git(&["init", "-q"]);
+        fs::write(temp_dir.path().join("main.tf"), "version = \"7.0\"\n").unwrap();
+        git(&["add", "main.tf"]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        // Bumped file, plus an unrelated change the user already staged
+        fs::write(temp_dir.path().join("main.tf"), "version = \"9.1.0\"\n").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "wip\n").unwrap();
+        git(&["add", "notes.txt"]);
+
+        let main_tf = temp_dir.path().join("main.tf");
+        let vpc = dependency("vpc", main_tf.to_str().unwrap(), "7.0", "9.1.0");
+        let commits = commit_updates(&[&vpc], "bump {name} to {version}", |dir, cmd| {
+            let status = std::process::Command::new("sh")
+                .args(["-c", cmd])
+                .current_dir(dir)
+                .env("GIT_AUTHOR_NAME", "mk")
+                .env("GIT_AUTHOR_EMAIL", "mk@example.com")
+                .env("GIT_COMMITTER_NAME", "mk")
+                .env("GIT_COMMITTER_EMAIL", "mk@example.com")
+                .output()?
+                .status;
+            anyhow::ensure!(status.success(), "{} failed", cmd);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(commits, 1);
+        assert_eq!(
+            git(&["show", "--name-only", "--format=%s"]),
+            "bump vpc to 9.1.0\n\nmain.tf\n"
+        );
+        assert_eq!(git(&["diff", "--cached", "--name-only"]), "notes.txt\n");
+    }
+
+    #[test]
+    fn test_commit_updates_skips_files_outside_git() {
+        let vpc = dependency("vpc", "network/main.tf", "7.0", "9.1.0");
+
+        let mut runs = Vec::new();
+        let commits = commit_updates(&[&vpc], "chore: {name} {version}", |_, cmd| {
+            runs.push(cmd.to_string());
+            if cmd.starts_with("git rev-parse") {
+                anyhow::bail!("not a git repository");
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(commits, 0);
+        assert_eq!(runs, ["git rev-parse --is-inside-work-tree"]);
+    }
+
+    #[test]
+    fn test_aligned_columns() {
+        let vpc = dependency("vpc", "network.tf", "7.0", "9.1.0");
+        let nat = dependency("cloud_nat", "modules/nat/main.tf", "4.0.0", "5.3.0");
+
+        let rows: Vec<String> = aligned_columns(&[&vpc, &nat])
+            .iter()
+            .map(|row| row.join(" "))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "vpc       (network.tf:3)          7.0  ",
+                "cloud_nat (modules/nat/main.tf:3) 4.0.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_table_columns_aligned() {
+        let vpc = dependency("vpc", "network.tf", "7.0", "9.1.0");
+        let nat = dependency("cloud_nat", "modules/nat/main.tf", "4.0.0", "5.3.0");
+
+        let table = render_table(&[&vpc, &nat]);
+        let columns: Vec<usize> = table
+            .lines()
+            .map(|line| {
+                line.find("terraform-module")
+                    .unwrap_or_else(|| line.find("TYPE").unwrap())
+            })
+            .collect();
+
+        assert!(columns.windows(2).all(|w| w[0] == w[1]));
+    }
 }
@@ -4,17 +4,19 @@ use std::fs;
 use std::path::Path;
 use yaml_rust2::{Yaml, YamlEmitter, YamlLoader};
 
-use super::registry;
-use super::{Dependency, DependencyType};
+use super::registry::{self, VersionFetcher};
+use super::{has_ignore_comment, Dependency, DependencyType};
 
 /// Scan Helm Chart.yaml for chart dependencies
 pub fn scan_helm_charts(
     project_path: &str,
     verbose: bool,
     include_prereleases: bool,
+    max_major_jump: Option<u64>,
+    fetcher: &dyn VersionFetcher,
 ) -> Result<Vec<Dependency>> {
     // Load config to get OCI registry authentication
-    let config = crate::config::Config::load().unwrap_or_default();
+    let config = crate::config::Config::load_merged(project_path)?;
 
     let mut dependencies = Vec::new();
 
@@ -79,24 +81,26 @@ pub fn scan_helm_charts(
                 );
             }
 
+            // Find line number (approximate)
+            let line_number = content
+                .lines()
+                .enumerate()
+                .find(|(_, line)| line.contains(&format!("name: {}", name)))
+                .map(|(i, _)| i + 1)
+                .unwrap_or(1);
+
             // Skip local file:// dependencies - they don't need remote fetching
             if repository.starts_with("file://") {
                 if verbose {
                     eprintln!("  Skipping local file dependency: {}", repository);
                 }
                 // Add as dependency with current version (no update available)
-                let line_number = content
-                    .lines()
-                    .enumerate()
-                    .find(|(_, line)| line.contains(&format!("name: {}", name)))
-                    .map(|(i, _)| i + 1)
-                    .unwrap_or(1);
-
                 dependencies.push(Dependency {
                     name: name.to_string(),
                     current_version: version.to_string(),
                     latest_version: version.to_string(), // Same as current for local
                     latest_app_version: None,            // No appVersion for local files
+                    capped: false,
                     file_path: chart_yaml_path.to_string_lossy().to_string(),
                     line_number,
                     dep_type: DependencyType::HelmChart {
@@ -106,40 +110,49 @@ pub fn scan_helm_charts(
                 continue;
             }
 
+            if is_version_pinned(&content, line_number) || config.bump.is_ignored(&[name]) {
+                if verbose {
+                    eprintln!("  Pinned (ignored): {} from {}", name, repository);
+                }
+                continue;
+            }
+
             // Fetch latest version - handle both OCI and HTTP registries
+            let bounds = registry::VersionBounds::for_current(&config, version, max_major_jump);
             let fetch_result = if repository.starts_with("oci://") {
                 if verbose {
                     eprintln!("  Fetching from OCI registry: {}", repository);
                 }
                 // OCI registries only return version, no appVersion available
                 registry::fetch_helm_chart_version_oci(
+                    fetcher,
                     repository,
                     name,
                     &config,
                     verbose,
                     include_prereleases,
+                    bounds,
                 )
-                .map(|version| (version, None))
             } else {
-                // HTTP registries return (version, appVersion)
-                registry::fetch_helm_chart_version(repository, name, verbose, include_prereleases)
+                // HTTP registries also return the appVersion
+                registry::fetch_helm_chart_version(
+                    fetcher,
+                    repository,
+                    name,
+                    &config,
+                    include_prereleases,
+                    bounds,
+                )
             };
 
-            // Find line number (approximate)
-            let line_number = content
-                .lines()
-                .enumerate()
-                .find(|(_, line)| line.contains(&format!("name: {}", name)))
-                .map(|(i, _)| i + 1)
-                .unwrap_or(1);
-
             match fetch_result {
-                Ok((latest_version, latest_app_version)) => {
+                Ok(latest) => {
                     dependencies.push(Dependency {
                         name: name.to_string(),
                         current_version: version.to_string(),
-                        latest_version,
-                        latest_app_version,
+                        latest_version: latest.version,
+                        latest_app_version: latest.app_version,
+                        capped: latest.capped,
                         file_path: chart_yaml_path.to_string_lossy().to_string(),
                         line_number,
                         dep_type: DependencyType::HelmChart {
@@ -164,6 +177,7 @@ pub fn scan_helm_charts(
                         current_version: version.to_string(),
                         latest_version: format!("ERROR: {}", e),
                         latest_app_version: None,
+                        capped: false,
                         file_path: chart_yaml_path.to_string_lossy().to_string(),
                         line_number,
                         dep_type: DependencyType::HelmChart {
@@ -178,6 +192,38 @@ pub fn scan_helm_charts(
     Ok(dependencies)
 }
 
+/// Whether the `version:` line of the dependency item around `name_line` (1-based)
+/// ends with `# mk:ignore`
+fn is_version_pinned(content: &str, name_line: usize) -> bool {
+    let lines: Vec<&str> = content.lines().collect();
+    let Some(name_index) = name_line.checked_sub(1).filter(|&i| i < lines.len()) else {
+        return false;
+    };
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let is_item_start = |line: &str| line.trim_start().starts_with("- ");
+
+    // The item starts at the closest `- ` line and ends at the next one or a dedent
+    let start = (0..=name_index)
+        .rev()
+        .find(|&i| is_item_start(lines[i]))
+        .unwrap_or(name_index);
+    let item_indent = indent(lines[start]);
+    lines[start..]
+        .iter()
+        .enumerate()
+        .take_while(|(i, line)| {
+            *i == 0
+                || line.trim().is_empty()
+                || (!is_item_start(line) && indent(line) > item_indent)
+        })
+        .any(|(_, line)| {
+            line.trim_start()
+                .trim_start_matches("- ")
+                .starts_with("version:")
+                && has_ignore_comment(line)
+        })
+}
+
 /// Update a Helm chart version in Chart.yaml
 /// Also updates the Chart.yaml's version and appVersion fields if they match the old dependency version
 pub fn update_helm_chart(
@@ -268,3 +314,106 @@ pub fn update_helm_chart(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bump::registry::{ChartVersion, StubFetcher};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_helm_charts_with_stub_fetcher() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Chart.yaml"),
+            r#"apiVersion: v2
+name: app
+version: 1.0.0
+dependencies:
+  - name: redis
+    version: "17.0.0"
+    repository: "https://charts.example.com"
+  - name: common
+    version: "1.0.0"
+    repository: "file://../common"
+"#,
+        )
+        .unwrap();
+
+        let fetcher = StubFetcher {
+            charts: vec![
+                ChartVersion {
+                    version: "17.0.0".to_string(),
+                    app_version: Some("7.0.0".to_string()),
+                },
+                ChartVersion {
+                    version: "18.1.0".to_string(),
+                    app_version: Some("7.2.0".to_string()),
+                },
+            ],
+            ..Default::default()
+        };
+        let deps = scan_helm_charts(
+            temp_dir.path().to_str().unwrap(),
+            false,
+            false,
+            None,
+            &fetcher,
+        )
+        .unwrap();
+
+        assert_eq!(deps.len(), 2);
+        assert_eq!(deps[0].name, "redis");
+        assert_eq!(deps[0].latest_version, "18.1.0");
+        assert_eq!(deps[0].latest_app_version.as_deref(), Some("7.2.0"));
+        // Local dependencies are never looked up
+        assert_eq!(deps[1].name, "common");
+        assert_eq!(deps[1].latest_version, "1.0.0");
+    }
+
+    #[test]
+    fn test_scan_helm_charts_skips_ignored() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Chart.yaml"),
+            r#"apiVersion: v2
+name: app
+version: 1.0.0
+dependencies:
+  - name: redis
+    version: "17.0.0" # mk:ignore
+    repository: "https://charts.example.com"
+  - name: postgresql
+    version: "12.0.0"
+    repository: "https://charts.example.com"
+  - version: "2.0.0"
+    name: nginx
+    repository: "https://charts.example.com"
+"#,
+        )
+        .unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        let fetcher = StubFetcher {
+            charts: vec![ChartVersion {
+                version: "18.1.0".to_string(),
+                app_version: None,
+            }],
+            ..Default::default()
+        };
+
+        // The comment pins redis only
+        let deps = scan_helm_charts(project_path, false, false, None, &fetcher).unwrap();
+        let names: Vec<&str> = deps.iter().map(|dep| dep.name.as_str()).collect();
+        assert_eq!(names, ["postgresql", "nginx"]);
+
+        // The config pins postgresql by chart name
+        fs::write(
+            temp_dir.path().join(".mk.toml"),
+            "[bump]\nignore = [\"postgresql\"]\n",
+        )
+        .unwrap();
+        let deps = scan_helm_charts(project_path, false, false, None, &fetcher).unwrap();
+        let names: Vec<&str> = deps.iter().map(|dep| dep.name.as_str()).collect();
+        assert_eq!(names, ["nginx"]);
+    }
+}
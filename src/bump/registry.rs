@@ -1,9 +1,15 @@
 use anyhow::{Context, Result};
+use attohttpc::{ErrorKind, ProxySettings, RequestBuilder, Response};
+use globset::Glob;
 use semver::Version;
 use serde::Deserialize;
+use std::fs;
 use std::process::Command;
 use std::time::Duration;
 
+use crate::config::Config;
+use crate::log;
+
 #[derive(Debug, Deserialize)]
 struct TerraformModule {
     versions: Vec<String>,
@@ -25,196 +31,414 @@ fn is_prerelease(version: &Version) -> bool {
     !version.pre.is_empty()
 }
 
-/// Fetch the latest version of a Terraform module from the Terraform Registry
-pub fn fetch_terraform_module_version(
-    namespace: &str,
-    name: &str,
-    provider: &str,
-    verbose: bool,
-    include_prereleases: bool,
-) -> Result<String> {
-    let url = format!(
-        "https://registry.terraform.io/v1/modules/{}/{}/{}",
-        namespace, name, provider
-    );
-
-    if verbose {
-        eprintln!("  Fetching versions from: {}", url);
-    }
-
-    let response = attohttpc::get(&url)
+/// Build a GET request that honors proxy environment variables and the configured CA bundle
+fn http_get(url: &str, config: &Config) -> Result<RequestBuilder> {
+    let mut request = attohttpc::get(url)
         .timeout(Duration::from_secs(10))
-        .send()
-        .context(format!("Failed to fetch module info from {}", url))?;
+        .proxy_settings(ProxySettings::from_env());
 
-    if !response.status().is_success() {
-        anyhow::bail!("Failed to fetch module: HTTP {}", response.status());
+    if let Some(ca_bundle) = &config.bump.ca_bundle {
+        request =
+            request.add_root_certificate(load_ca_certificate(&config.expand_env(ca_bundle)?)?);
     }
 
-    let module: TerraformModule = response
-        .json()
-        .context("Failed to parse Terraform Registry response")?;
+    Ok(request)
+}
 
-    // Parse versions and find the latest
-    let mut versions: Vec<Version> = module
-        .versions
-        .iter()
-        .filter_map(|v| Version::parse(v).ok())
-        .collect();
+/// Load a PEM-encoded CA certificate from disk
+fn load_ca_certificate(path: &str) -> Result<native_tls::Certificate> {
+    let pem = fs::read(path).context(format!("Failed to read CA bundle at {}", path))?;
+    native_tls::Certificate::from_pem(&pem)
+        .context(format!("Failed to parse CA bundle at {} as PEM", path))
+}
 
-    // Filter out pre-releases unless explicitly requested
-    if !include_prereleases {
-        versions.retain(|v| !is_prerelease(v));
-    }
+/// Send a request, turning proxy and TLS failures into descriptive errors
+fn send(request: RequestBuilder, url: &str) -> Result<Response> {
+    request.send().map_err(|e| {
+        let proxy = url::Url::parse(url)
+            .ok()
+            .and_then(|u| ProxySettings::from_env().for_url(&u).cloned());
+
+        match (e.kind(), proxy) {
+            (ErrorKind::ConnectError { status_code, .. }, Some(proxy)) => anyhow::anyhow!(
+                "Proxy {} refused to connect to {}: HTTP {}",
+                proxy,
+                url,
+                status_code
+            ),
+            (ErrorKind::Tls(_), _) => anyhow::anyhow!(
+                "TLS verification failed for {}: {} (set `ca_bundle` under [bump] to trust a private CA)",
+                url,
+                e
+            ),
+            (_, Some(proxy)) => {
+                anyhow::anyhow!("Failed to reach {} via proxy {}: {}", url, proxy, e)
+            }
+            _ => anyhow::Error::new(e).context(format!("Failed to fetch {}", url)),
+        }
+    })
+}
 
-    versions.sort();
+/// One chart entry of a Helm repository index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartVersion {
+    pub version: String,
+    pub app_version: Option<String>,
+}
 
-    versions
-        .last()
-        .map(|v| v.to_string())
-        .ok_or_else(|| anyhow::anyhow!("No valid versions found"))
+/// Source of the published versions of modules and charts
+/// The registry lookups are behind this trait so version selection can be tested offline
+pub trait VersionFetcher: Sync {
+    /// Versions of a Terraform Registry module
+    fn terraform_module_versions(
+        &self,
+        namespace: &str,
+        name: &str,
+        provider: &str,
+        config: &Config,
+    ) -> Result<Vec<String>>;
+
+    /// Entries of a chart in a Helm repository index
+    fn helm_chart_versions(
+        &self,
+        repo_url: &str,
+        chart_name: &str,
+        config: &Config,
+    ) -> Result<Vec<ChartVersion>>;
+
+    /// Tags of a chart in an OCI registry
+    fn oci_chart_tags(
+        &self,
+        oci_url: &str,
+        chart_name: &str,
+        config: &Config,
+    ) -> Result<Vec<String>>;
 }
 
-/// Fetch the latest version of a Helm chart from a Helm repository
-/// Returns (version, appVersion)
-pub fn fetch_helm_chart_version(
-    repo_url: &str,
-    chart_name: &str,
-    verbose: bool,
-    include_prereleases: bool,
-) -> Result<(String, Option<String>)> {
-    // Ensure repo_url ends with /index.yaml
-    let index_url = if repo_url.ends_with("/index.yaml") {
-        repo_url.to_string()
-    } else if repo_url.ends_with('/') {
-        format!("{}index.yaml", repo_url)
-    } else {
-        format!("{}/index.yaml", repo_url)
-    };
+/// Fetcher querying the registries over HTTP
+pub struct HttpFetcher {
+    pub verbose: bool,
+}
 
-    if verbose {
-        eprintln!("  Fetching versions from: {}", index_url);
-    }
+impl VersionFetcher for HttpFetcher {
+    fn terraform_module_versions(
+        &self,
+        namespace: &str,
+        name: &str,
+        provider: &str,
+        config: &Config,
+    ) -> Result<Vec<String>> {
+        let url = format!(
+            "https://registry.terraform.io/v1/modules/{}/{}/{}",
+            namespace, name, provider
+        );
 
-    let response = attohttpc::get(&index_url)
-        .timeout(Duration::from_secs(10))
-        .send()
-        .context(format!("Failed to fetch Helm index from {}", index_url))?;
+        if self.verbose {
+            eprintln!("  Fetching versions from: {}", url);
+        }
+
+        let response = send(http_get(&url, config)?, &url)
+            .context(format!("Failed to fetch module info from {}", url))?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to fetch module: HTTP {}", response.status());
+        }
 
-    if !response.status().is_success() {
-        anyhow::bail!("Failed to fetch Helm index: HTTP {}", response.status());
+        let module: TerraformModule = response
+            .json()
+            .context("Failed to parse Terraform Registry response")?;
+        Ok(module.versions)
     }
 
-    let text = response
-        .text()
-        .context("Failed to read Helm index response")?;
+    fn helm_chart_versions(
+        &self,
+        repo_url: &str,
+        chart_name: &str,
+        config: &Config,
+    ) -> Result<Vec<ChartVersion>> {
+        // Ensure repo_url ends with /index.yaml
+        let index_url = if repo_url.ends_with("/index.yaml") {
+            repo_url.to_string()
+        } else if repo_url.ends_with('/') {
+            format!("{}index.yaml", repo_url)
+        } else {
+            format!("{}/index.yaml", repo_url)
+        };
 
-    // Parse YAML
-    use yaml_rust2::{Yaml, YamlLoader};
-    let docs = YamlLoader::load_from_str(&text).context("Failed to parse Helm index YAML")?;
+        if self.verbose {
+            eprintln!("  Fetching versions from: {}", index_url);
+        }
 
-    let doc = docs
-        .first()
-        .ok_or_else(|| anyhow::anyhow!("Empty YAML document"))?;
+        let response = send(http_get(&index_url, config)?, &index_url)
+            .context(format!("Failed to fetch Helm index from {}", index_url))?;
 
-    // Navigate to entries[chart_name]
-    if verbose {
-        eprintln!("  Parsing index.yaml for chart '{}'", chart_name);
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to fetch Helm index: HTTP {}", response.status());
+        }
+
+        let text = response
+            .text()
+            .context("Failed to read Helm index response")?;
+        parse_helm_index(&text, chart_name, self.verbose)
     }
 
-    let entries = if let Some(hash) = doc.as_hash() {
-        hash.get(&Yaml::String("entries".to_string()))
-    } else {
-        None
-    };
+    fn oci_chart_tags(
+        &self,
+        oci_url: &str,
+        chart_name: &str,
+        config: &Config,
+    ) -> Result<Vec<String>> {
+        let verbose = self.verbose;
+        let (registry, repository) = parse_oci_url(oci_url)?;
 
-    if verbose {
-        eprintln!("  Entries field found: {}", entries.is_some());
-    }
+        if verbose {
+            eprintln!(
+                "  Fetching OCI chart '{}' from {}/{}",
+                chart_name, registry, repository
+            );
+        }
 
-    let chart_entries = entries.and_then(|e| {
-        if let Some(entries_hash) = e.as_hash() {
-            entries_hash.get(&Yaml::String(chart_name.to_string()))
+        // All OCI registries follow the same pattern: the chart name is part of the repository path
+        // Examples:
+        //   - Docker Hub: bitnamicharts/mariadb
+        //   - ghcr.io: grafana/helm-charts/grafana-operator
+        //   - Any OCI: repo/path/chart-name
+        let full_repository = if !repository.ends_with(&format!("/{}", chart_name)) {
+            // Append chart name if not already present in the path
+            format!("{}/{}", repository, chart_name)
         } else {
-            None
-        }
-    });
+            repository.clone()
+        };
 
-    if chart_entries.is_none() {
-        anyhow::bail!("Chart '{}' not found in repository", chart_name);
-    }
+        // Get authentication token (use original repository for token scope)
+        let token = get_oci_token(&registry, &full_repository, config, verbose)?;
 
-    let chart_entries = chart_entries.unwrap();
+        // Build the tags list URL
+        let tags_url = format!("https://{}/v2/{}/tags/list", registry, full_repository);
 
-    // Get all versions
-    let versions_array = chart_entries
-        .as_vec()
-        .ok_or_else(|| anyhow::anyhow!("Invalid chart entries format"))?;
+        if verbose {
+            eprintln!("  Fetching tags from: {}", tags_url);
+        }
 
-    if verbose {
-        eprintln!("  Found {} chart entries", versions_array.len());
-    }
+        // Every page carries the same optional authentication
+        collect_paginated_tags(&tags_url, |page_url| {
+            let mut request = http_get(page_url, config)?;
 
-    let version_strings: Vec<String> = versions_array
-        .iter()
-        .filter_map(|entry| {
-            if let Some(hash) = entry.as_hash() {
-                hash.get(&Yaml::String("version".to_string()))
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-            } else {
-                None
+            if let Some(token) = &token {
+                request = request.header("Authorization", format!("Bearer {}", token));
             }
+
+            let response = send(request, page_url).context(format!(
+                "Failed to fetch tags from OCI registry: {}",
+                page_url
+            ))?;
+
+            if !response.status().is_success() {
+                anyhow::bail!(
+                    "Failed to fetch OCI tags: HTTP {} for {}",
+                    response.status(),
+                    page_url
+                );
+            }
+
+            let link = response
+                .headers()
+                .get("link")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let tags_response: OciTagsResponse = response
+                .json()
+                .context("Failed to parse OCI tags response")?;
+
+            Ok(TagsPage {
+                tags: tags_response.tags,
+                link,
+            })
         })
-        .collect();
+    }
+}
 
-    if verbose {
-        eprintln!("  Found {} version strings", version_strings.len());
-        if !version_strings.is_empty() {
-            eprintln!(
-                "  Sample versions: {:?}",
-                &version_strings[..version_strings.len().min(3)]
-            );
+/// Upper bound on `tags/list` pages followed for one chart
+const MAX_TAG_PAGES: usize = 50;
+
+/// One `tags/list` response: its tags and `Link` header
+struct TagsPage {
+    tags: Vec<String>,
+    link: Option<String>,
+}
+
+/// Accumulate tags across pages, following `Link: <...>; rel="next"` up to `MAX_TAG_PAGES`
+fn collect_paginated_tags(
+    first_url: &str,
+    mut fetch_page: impl FnMut(&str) -> Result<TagsPage>,
+) -> Result<Vec<String>> {
+    let mut tags = Vec::new();
+    let mut url = first_url.to_string();
+
+    for _ in 0..MAX_TAG_PAGES {
+        let page = fetch_page(&url)?;
+        tags.extend(page.tags);
+        match page.link.and_then(|link| next_page_url(&link, &url)) {
+            Some(next) => url = next,
+            None => return Ok(tags),
         }
     }
 
-    // Build a map of version string to chart entry for lookup
-    let version_to_entry: std::collections::HashMap<String, &Yaml> = versions_array
+    log::warning!(
+        "Stopped after {} pages of tags from {}, newer versions may be missing",
+        MAX_TAG_PAGES,
+        first_url
+    );
+    Ok(tags)
+}
+
+/// Target of the `rel="next"` entry of a `Link` header, resolved against the current page URL
+/// e.g. `</v2/org/chart/tags/list?last=1.0.0&n=100>; rel="next"`
+/// Pages on another scheme, host or port are not followed: they would receive the token
+fn next_page_url(link: &str, current_url: &str) -> Option<String> {
+    let target = link.split(',').find_map(|entry| {
+        let (target, params) = entry.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|param| param.trim().replace(' ', "") == "rel=\"next\"");
+        let target = target.trim().strip_prefix('<')?.strip_suffix('>')?;
+        is_next.then_some(target)
+    })?;
+
+    let current = url::Url::parse(current_url).ok()?;
+    let next = current.join(target).ok()?;
+    if next.origin() != current.origin() {
+        log::warning!("Not following tag list page on another origin: {}", next);
+        return None;
+    }
+    Some(next.into())
+}
+
+/// Highest semver among `versions` (a leading `v` is allowed), pre-releases only when requested
+fn select_latest(versions: &[String], include_prereleases: bool) -> Option<Version> {
+    versions
         .iter()
-        .filter_map(|entry| {
-            if let Some(hash) = entry.as_hash() {
-                hash.get(&Yaml::String("version".to_string()))
-                    .and_then(|v| v.as_str())
-                    .map(|version_str| (version_str.to_string(), entry))
-            } else {
-                None
-            }
-        })
-        .collect();
+        .filter_map(|v| Version::parse(v.trim_start_matches('v')).ok())
+        // Filter out pre-releases unless explicitly requested
+        .filter(|v| include_prereleases || !is_prerelease(v))
+        .max()
+}
+
+/// Highest major version proposed for a dependency at `current` (`7.0`, `v1.2.3`, ...),
+/// `max_jump` majors above it; `None` when `current` has no leading major number
+fn max_major_within(current: &str, max_jump: u64) -> Option<u64> {
+    let major = current.trim_start_matches('v').split('.').next()?;
+    Some(major.parse::<u64>().ok()? + max_jump)
+}
 
-    let mut versions: Vec<Version> = version_strings
+/// Whether `[bump] prefer_stable_major` applies to a dependency at `current`,
+/// which is not the case when it is itself on a `0.x` version
+pub fn prefers_stable_major(config: &Config, current: &str) -> bool {
+    config.bump.prefer_stable_major
+        && current.trim_start_matches('v').split('.').next() != Some("0")
+}
+
+/// `latest` unless its major line has no stable release while a lower one does: the newest
+/// stable release of the highest major having one (up to `max_major`) is proposed then
+fn prefer_stable_major(versions: &[String], latest: Version, max_major: Option<u64>) -> Version {
+    let stable: Vec<Version> = versions
         .iter()
-        .filter_map(|v| {
-            // Try with and without 'v' prefix
-            let clean_v = v.trim_start_matches('v');
-            Version::parse(clean_v).ok()
-        })
+        .filter_map(|v| Version::parse(v.trim_start_matches('v')).ok())
+        .filter(|v| !is_prerelease(v) && max_major.is_none_or(|max| v.major <= max))
         .collect();
+    if stable.iter().any(|v| v.major == latest.major) {
+        latest
+    } else {
+        stable.into_iter().max().unwrap_or(latest)
+    }
+}
+
+/// Limits on the versions proposed for a dependency
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VersionBounds {
+    /// Highest major version proposed, from `--max-major-jump`
+    pub max_major: Option<u64>,
+    /// Skip major lines with only prereleases when another one has a stable release
+    pub stable_major: bool,
+}
 
-    // Filter out pre-releases unless explicitly requested
-    if !include_prereleases {
-        versions.retain(|v| !is_prerelease(v));
+impl VersionBounds {
+    /// Bounds for a dependency currently at `current`, with `--max-major-jump` if given
+    pub fn for_current(config: &Config, current: &str, max_major_jump: Option<u64>) -> Self {
+        Self {
+            max_major: max_major_jump.and_then(|max_jump| max_major_within(current, max_jump)),
+            stable_major: prefers_stable_major(config, current),
+        }
     }
+}
 
-    if versions.is_empty() {
-        anyhow::bail!("No valid versions found for chart '{}'", chart_name);
+/// `select_latest` limited to versions up to `bounds.max_major`, and whether a newer version
+/// was left out
+/// With `bounds.stable_major`, a prerelease-only major line is skipped for a stable one
+fn select_latest_capped(
+    versions: &[String],
+    include_prereleases: bool,
+    bounds: VersionBounds,
+) -> Option<(Version, bool)> {
+    let latest = select_latest(versions, include_prereleases)?;
+    let (latest, capped) = match bounds.max_major {
+        Some(max_major) if latest.major > max_major => {
+            let within: Vec<String> = versions
+                .iter()
+                .filter(|v| {
+                    Version::parse(v.trim_start_matches('v')).is_ok_and(|v| v.major <= max_major)
+                })
+                .cloned()
+                .collect();
+            (select_latest(&within, include_prereleases)?, true)
+        }
+        _ => (latest, false),
+    };
+    if bounds.stable_major {
+        Some((
+            prefer_stable_major(versions, latest, bounds.max_major),
+            capped,
+        ))
+    } else {
+        Some((latest, capped))
     }
+}
 
-    versions.sort();
+/// Version proposed for a dependency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Latest {
+    pub version: String,
+    /// appVersion of the chart, only known for Helm repositories
+    pub app_version: Option<String>,
+    /// A newer version exists beyond `--max-major-jump`
+    pub capped: bool,
+}
 
-    // Return with 'v' prefix if original had it
-    let latest = versions.last().unwrap();
-    let latest_str = if version_strings
+/// Whether pre-releases are considered for `repository`: always with `--include-prereleases`,
+/// otherwise only when it matches a `[bump] prerelease_repositories` glob
+fn includes_prereleases(
+    config: &Config,
+    repository: &str,
+    include_prereleases: bool,
+) -> Result<bool> {
+    if include_prereleases {
+        return Ok(true);
+    }
+
+    let repository = repository.trim_end_matches('/');
+    for pattern in &config.bump.prerelease_repositories {
+        let glob = Glob::new(pattern)
+            .with_context(|| format!("Invalid prerelease_repositories pattern {}", pattern))?;
+        if glob.compile_matcher().is_match(repository) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// `latest` with a `v` prefix when the registry's versions use one
+fn with_registry_prefix(latest: &Version, versions: &[String]) -> String {
+    if versions
         .first()
         .map(|s| s.starts_with('v'))
         .unwrap_or(false)
@@ -222,26 +446,110 @@ pub fn fetch_helm_chart_version(
         format!("v{}", latest)
     } else {
         latest.to_string()
+    }
+}
+
+/// Fetch the latest version of a Terraform module from the Terraform Registry
+pub fn fetch_terraform_module_version(
+    fetcher: &dyn VersionFetcher,
+    namespace: &str,
+    name: &str,
+    provider: &str,
+    config: &Config,
+    include_prereleases: bool,
+    bounds: VersionBounds,
+) -> Result<Latest> {
+    let versions = fetcher.terraform_module_versions(namespace, name, provider, config)?;
+    let source = format!("{}/{}/{}", namespace, name, provider);
+    let include_prereleases = includes_prereleases(config, &source, include_prereleases)?;
+
+    select_latest_capped(&versions, include_prereleases, bounds)
+        .map(|(version, capped)| Latest {
+            version: version.to_string(),
+            app_version: None,
+            capped,
+        })
+        .ok_or_else(|| anyhow::anyhow!("No valid versions found"))
+}
+
+/// Fetch the latest version of a Helm chart, with its appVersion, from a Helm repository
+pub fn fetch_helm_chart_version(
+    fetcher: &dyn VersionFetcher,
+    repo_url: &str,
+    chart_name: &str,
+    config: &Config,
+    include_prereleases: bool,
+    bounds: VersionBounds,
+) -> Result<Latest> {
+    let entries = fetcher.helm_chart_versions(repo_url, chart_name, config)?;
+    let version_strings: Vec<String> = entries.iter().map(|e| e.version.clone()).collect();
+    let include_prereleases = includes_prereleases(config, repo_url, include_prereleases)?;
+
+    let (latest, capped) = select_latest_capped(&version_strings, include_prereleases, bounds)
+        .ok_or_else(|| anyhow::anyhow!("No valid versions found for chart '{}'", chart_name))?;
+
+    // Return with 'v' prefix if original had it
+    let latest_str = with_registry_prefix(&latest, &version_strings);
+
+    // Find the appVersion for the latest version, with or without 'v' prefix
+    let app_version = entries
+        .iter()
+        .find(|e| e.version == latest_str)
+        .or_else(|| entries.iter().find(|e| e.version == latest.to_string()))
+        .and_then(|e| e.app_version.clone());
+
+    Ok(Latest {
+        version: latest_str,
+        app_version,
+        capped,
+    })
+}
+
+/// Chart entries of `chart_name` in a Helm repository `index.yaml`
+fn parse_helm_index(text: &str, chart_name: &str, verbose: bool) -> Result<Vec<ChartVersion>> {
+    // Parse YAML
+    use yaml_rust2::YamlLoader;
+    let docs = YamlLoader::load_from_str(text).context("Failed to parse Helm index YAML")?;
+
+    let doc = docs
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("Empty YAML document"))?;
+
+    // Navigate to entries[chart_name]
+    if verbose {
+        eprintln!("  Parsing index.yaml for chart '{}'", chart_name);
+    }
+
+    let chart_entries = doc["entries"][chart_name].as_vec();
+    let versions_array = match chart_entries {
+        Some(entries) => entries,
+        None if doc["entries"][chart_name].is_badvalue() => {
+            anyhow::bail!("Chart '{}' not found in repository", chart_name)
+        }
+        None => anyhow::bail!("Invalid chart entries format"),
     };
 
-    // Find the appVersion for the latest version
-    let app_version = version_to_entry
-        .get(&latest_str)
-        .or_else(|| {
-            // Try without 'v' prefix
-            version_to_entry.get(&latest.to_string())
+    if verbose {
+        eprintln!("  Found {} chart entries", versions_array.len());
+    }
+
+    let entries: Vec<ChartVersion> = versions_array
+        .iter()
+        .filter_map(|entry| {
+            let version = entry["version"].as_str()?;
+            Some(ChartVersion {
+                version: version.to_string(),
+                app_version: entry["appVersion"].as_str().map(str::to_string),
+            })
         })
-        .and_then(|entry| {
-            if let Some(hash) = entry.as_hash() {
-                hash.get(&Yaml::String("appVersion".to_string()))
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-            } else {
-                None
-            }
-        });
+        .collect();
+
+    if verbose && !entries.is_empty() {
+        let sample: Vec<&str> = entries.iter().take(3).map(|e| e.version.as_str()).collect();
+        eprintln!("  Sample versions: {:?}", sample);
+    }
 
-    Ok((latest_str, app_version))
+    Ok(entries)
 }
 
 /// Parse OCI registry URL to extract registry hostname and repository path
@@ -272,7 +580,7 @@ fn parse_oci_url(oci_url: &str) -> Result<(String, String)> {
 fn get_oci_token(
     registry: &str,
     repository: &str,
-    config: &crate::config::Config,
+    config: &Config,
     verbose: bool,
 ) -> Result<Option<String>> {
     // Check if there's a configured auth for this registry
@@ -281,7 +589,7 @@ fn get_oci_token(
             if verbose {
                 eprintln!("  Using configured token for registry '{}'", registry);
             }
-            return Ok(Some(token.clone()));
+            return Ok(Some(config.expand_env(token)?));
         }
 
         if let Some(command) = &auth.command {
@@ -318,7 +626,7 @@ fn get_oci_token(
         eprintln!("  Attempting to get anonymous token for '{}'", registry);
     }
 
-    fetch_anonymous_oci_token(registry, repository, verbose)
+    fetch_anonymous_oci_token(registry, repository, config, verbose)
 }
 
 /// Attempt to fetch an anonymous token from the OCI registry
@@ -326,6 +634,7 @@ fn get_oci_token(
 fn fetch_anonymous_oci_token(
     registry: &str,
     repository: &str,
+    config: &Config,
     verbose: bool,
 ) -> Result<Option<String>> {
     // Docker Hub uses a different authentication endpoint
@@ -353,10 +662,7 @@ fn fetch_anonymous_oci_token(
             eprintln!("  Trying token endpoint: {}", token_url);
         }
 
-        match attohttpc::get(&token_url)
-            .timeout(Duration::from_secs(10))
-            .send()
-        {
+        match send(http_get(&token_url, config)?, &token_url) {
             Ok(response) if response.status().is_success() => {
                 if let Ok(token_response) = response.json::<OciTokenResponse>() {
                     let token = token_response.token.or(token_response.access_token);
@@ -381,114 +687,609 @@ fn fetch_anonymous_oci_token(
 
 /// Fetch the latest version of a Helm chart from an OCI registry
 pub fn fetch_helm_chart_version_oci(
+    fetcher: &dyn VersionFetcher,
     oci_url: &str,
     chart_name: &str,
-    config: &crate::config::Config,
+    config: &Config,
     verbose: bool,
     include_prereleases: bool,
-) -> Result<String> {
-    let (registry, repository) = parse_oci_url(oci_url)?;
+    bounds: VersionBounds,
+) -> Result<Latest> {
+    let tags = fetcher.oci_chart_tags(oci_url, chart_name, config)?;
 
     if verbose {
-        eprintln!(
-            "  Fetching OCI chart '{}' from {}/{}",
-            chart_name, registry, repository
+        eprintln!("  Found {} tags", tags.len());
+        if !tags.is_empty() {
+            eprintln!("  Sample tags: {:?}", &tags[..tags.len().min(3)]);
+        }
+    }
+
+    let include_prereleases = includes_prereleases(config, oci_url, include_prereleases)?;
+    let (latest, capped) = select_latest_capped(&tags, include_prereleases, bounds)
+        .ok_or_else(|| anyhow::anyhow!("No valid versions found for OCI chart '{}'", chart_name))?;
+
+    // Return with 'v' prefix if original had it
+    let latest_str = with_registry_prefix(&latest, &tags);
+
+    if verbose {
+        eprintln!("  Latest version: {}", latest_str);
+    }
+
+    Ok(Latest {
+        version: latest_str,
+        app_version: None,
+        capped,
+    })
+}
+
+/// Fetcher returning canned version lists, or `error` for every lookup
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct StubFetcher {
+    pub terraform: Vec<String>,
+    pub charts: Vec<ChartVersion>,
+    pub tags: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+impl StubFetcher {
+    fn canned<T: Clone>(&self, values: &[T]) -> Result<Vec<T>> {
+        match &self.error {
+            Some(error) => Err(anyhow::anyhow!("{}", error)),
+            None => Ok(values.to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl VersionFetcher for StubFetcher {
+    fn terraform_module_versions(
+        &self,
+        _: &str,
+        _: &str,
+        _: &str,
+        _: &Config,
+    ) -> Result<Vec<String>> {
+        self.canned(&self.terraform)
+    }
+
+    fn helm_chart_versions(&self, _: &str, _: &str, _: &Config) -> Result<Vec<ChartVersion>> {
+        self.canned(&self.charts)
+    }
+
+    fn oci_chart_tags(&self, _: &str, _: &str, _: &Config) -> Result<Vec<String>> {
+        self.canned(&self.tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn chart(version: &str, app_version: Option<&str>) -> ChartVersion {
+        ChartVersion {
+            version: version.to_string(),
+            app_version: app_version.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_terraform_latest_uses_semver_order() {
+        let fetcher = StubFetcher {
+            terraform: strings(&["1.9.0", "1.10.0", "1.2.3", "not-a-version"]),
+            ..Default::default()
+        };
+        let config = Config::default();
+
+        let latest = fetch_terraform_module_version(
+            &fetcher,
+            "ns",
+            "vpc",
+            "aws",
+            &config,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        assert_eq!(latest.version, "1.10.0");
+    }
+
+    #[rstest::rstest]
+    #[case::within_bound(Some(4), "4.0.0", false)]
+    #[case::capped_one_major(Some(2), "2.1.0", true)]
+    #[case::capped_to_current_major(Some(1), "1.9.0", true)]
+    #[case::no_cap(None, "4.0.0", false)]
+    fn test_select_latest_capped(
+        #[case] max_major: Option<u64>,
+        #[case] expected: &str,
+        #[case] capped: bool,
+    ) {
+        let versions = strings(&[
+            "1.2.0",
+            "1.9.0",
+            "2.0.0",
+            "2.1.0",
+            "3.4.0",
+            "4.0.0",
+            "5.0.0-rc.1",
+        ]);
+        let (latest, was_capped) = select_latest_capped(
+            &versions,
+            false,
+            VersionBounds {
+                max_major,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(latest.to_string(), expected);
+        assert_eq!(was_capped, capped);
+    }
+
+    #[rstest]
+    #[case::prerelease_only_major(&["0.11.0", "0.12.0", "1.0.0-rc.1"], "0.12.0")]
+    #[case::stable_major_keeps_its_prerelease(&["1.2.0", "1.3.0-rc.1"], "1.3.0-rc.1")]
+    #[case::newest_stable_of_highest_major(&["1.9.0", "2.0.0", "2.1.0", "3.0.0-beta.1"], "2.1.0")]
+    #[case::only_prereleases(&["1.0.0-rc.1", "1.0.0-rc.2"], "1.0.0-rc.2")]
+    #[case::v_prefixed(&["v0.30.0", "v1.0.0-alpha.1"], "0.30.0")]
+    fn test_select_latest_prefers_stable_major(#[case] versions: &[&str], #[case] expected: &str) {
+        let bounds = VersionBounds {
+            max_major: None,
+            stable_major: true,
+        };
+        let (latest, _) = select_latest_capped(&strings(versions), true, bounds).unwrap();
+        assert_eq!(latest.to_string(), expected);
+
+        // Without the option the newest prerelease wins
+        let (latest, _) =
+            select_latest_capped(&strings(versions), true, VersionBounds::default()).unwrap();
+        assert_eq!(latest, select_latest(&strings(versions), true).unwrap());
+    }
+
+    #[test]
+    fn test_prefer_stable_major_within_max_major() {
+        let versions = strings(&["1.4.0", "2.0.0-rc.1", "3.0.0"]);
+        let bounds = VersionBounds {
+            max_major: Some(2),
+            stable_major: true,
+        };
+        let (latest, capped) = select_latest_capped(&versions, true, bounds).unwrap();
+        assert_eq!((latest.to_string(), capped), ("1.4.0".to_string(), true));
+    }
+
+    #[test]
+    fn test_prefers_stable_major() {
+        let mut config = Config::default();
+        assert!(!prefers_stable_major(&config, "1.2.0"));
+
+        config.bump.prefer_stable_major = true;
+        assert!(prefers_stable_major(&config, "1.2.0"));
+        assert!(prefers_stable_major(&config, "~> 2.0"));
+        // Dependencies already on 0.x keep following it
+        assert!(!prefers_stable_major(&config, "0.12.0"));
+        assert!(!prefers_stable_major(&config, "v0.3"));
+    }
+
+    #[test]
+    fn test_max_major_within() {
+        assert_eq!(max_major_within("1.2.0", 1), Some(2));
+        assert_eq!(max_major_within("v3", 0), Some(3));
+        // Constraint-style current versions from terraform modules
+        assert_eq!(max_major_within("7.0", 2), Some(9));
+        assert_eq!(max_major_within("latest", 1), None);
+    }
+
+    #[test]
+    fn test_version_bounds_for_current() {
+        let config = Config::default();
+        assert_eq!(
+            VersionBounds::for_current(&config, "1.2.0", None),
+            VersionBounds::default()
+        );
+        assert_eq!(
+            VersionBounds::for_current(&config, "latest", Some(1)).max_major,
+            None
+        );
+        assert_eq!(
+            VersionBounds::for_current(&config, "5.1", Some(1)).max_major,
+            Some(6)
         );
     }
 
-    // All OCI registries follow the same pattern: the chart name is part of the repository path
-    // Examples:
-    //   - Docker Hub: bitnamicharts/mariadb
-    //   - ghcr.io: grafana/helm-charts/grafana-operator
-    //   - Any OCI: repo/path/chart-name
-    let full_repository = if !repository.ends_with(&format!("/{}", chart_name)) {
-        // Append chart name if not already present in the path
-        format!("{}/{}", repository, chart_name)
-    } else {
-        repository.clone()
-    };
+    #[test]
+    fn test_terraform_latest_capped_by_max_major() {
+        let fetcher = StubFetcher {
+            terraform: strings(&["5.1.0", "5.8.2", "6.0.0", "7.3.0"]),
+            ..Default::default()
+        };
+        let latest = fetch_terraform_module_version(
+            &fetcher,
+            "ns",
+            "vpc",
+            "aws",
+            &Config::default(),
+            false,
+            VersionBounds {
+                max_major: max_major_within("5.1", 0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(latest.version, "5.8.2");
+        assert!(latest.capped);
+    }
 
-    // Get authentication token (use original repository for token scope)
-    let token = get_oci_token(&registry, &full_repository, config, verbose)?;
+    #[test]
+    fn test_terraform_latest_prereleases_only_when_requested() {
+        let fetcher = StubFetcher {
+            terraform: strings(&["2.0.0", "2.1.0-rc.1"]),
+            ..Default::default()
+        };
+        let config = Config::default();
+
+        let stable = fetch_terraform_module_version(
+            &fetcher,
+            "ns",
+            "vpc",
+            "aws",
+            &config,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        let prerelease = fetch_terraform_module_version(
+            &fetcher,
+            "ns",
+            "vpc",
+            "aws",
+            &config,
+            true,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        assert_eq!(stable.version, "2.0.0");
+        assert_eq!(prerelease.version, "2.1.0-rc.1");
+    }
+
+    #[test]
+    fn test_prerelease_repositories_config() {
+        let fetcher = StubFetcher {
+            terraform: strings(&["2.0.0", "2.1.0-rc.1"]),
+            tags: strings(&["1.4.0", "1.5.0-beta.2"]),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        config.bump.prerelease_repositories = strings(&["oci://ghcr.io/acme/*", "acme/*"]);
+
+        let internal = fetch_helm_chart_version_oci(
+            &fetcher,
+            "oci://ghcr.io/acme/charts",
+            "api",
+            &config,
+            false,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        let public = fetch_helm_chart_version_oci(
+            &fetcher,
+            "oci://ghcr.io/bitnami",
+            "redis",
+            &config,
+            false,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        assert_eq!(internal.version, "1.5.0-beta.2");
+        assert_eq!(public.version, "1.4.0");
+
+        let internal = fetch_terraform_module_version(
+            &fetcher,
+            "acme",
+            "vpc",
+            "aws",
+            &config,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        let public = fetch_terraform_module_version(
+            &fetcher,
+            "terraform-aws-modules",
+            "vpc",
+            "aws",
+            &config,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        assert_eq!(internal.version, "2.1.0-rc.1");
+        assert_eq!(public.version, "2.0.0");
+    }
 
-    // Build the tags list URL
-    let tags_url = format!("https://{}/v2/{}/tags/list", registry, full_repository);
+    #[test]
+    fn test_prerelease_repositories_invalid_pattern() {
+        let mut config = Config::default();
+        config.bump.prerelease_repositories = strings(&["oci://ghcr.io/[acme"]);
 
-    if verbose {
-        eprintln!("  Fetching tags from: {}", tags_url);
+        let err = includes_prereleases(&config, "oci://ghcr.io/acme", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid prerelease_repositories pattern oci://ghcr.io/[acme"
+        );
+        // The global flag short-circuits the patterns
+        assert!(includes_prereleases(&config, "oci://ghcr.io/acme", true).unwrap());
     }
 
-    // Build request with optional authentication
-    let mut request = attohttpc::get(&tags_url).timeout(Duration::from_secs(10));
+    #[test]
+    fn test_terraform_no_valid_versions() {
+        let fetcher = StubFetcher {
+            terraform: strings(&["3.0.0-beta"]),
+            ..Default::default()
+        };
+        let err = fetch_terraform_module_version(
+            &fetcher,
+            "ns",
+            "vpc",
+            "aws",
+            &Config::default(),
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "No valid versions found");
+    }
 
-    if let Some(token) = token {
-        request = request.header("Authorization", format!("Bearer {}", token));
+    #[test]
+    fn test_helm_latest_keeps_prefix_and_app_version() {
+        let fetcher = StubFetcher {
+            charts: vec![
+                chart("v1.2.0", Some("2.0")),
+                chart("v1.10.0", Some("2.4")),
+                chart("v1.9.0", None),
+            ],
+            ..Default::default()
+        };
+
+        let latest = fetch_helm_chart_version(
+            &fetcher,
+            "https://charts",
+            "app",
+            &Config::default(),
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        assert_eq!(latest.version, "v1.10.0");
+        assert_eq!(latest.app_version.as_deref(), Some("2.4"));
     }
 
-    let response = request.send().context(format!(
-        "Failed to fetch tags from OCI registry: {}",
-        tags_url
-    ))?;
+    #[test]
+    fn test_oci_latest_and_errors() {
+        let config = Config::default();
+        let fetcher = StubFetcher {
+            tags: strings(&["0.9.1", "latest", "0.10.0"]),
+            ..Default::default()
+        };
+        let latest = fetch_helm_chart_version_oci(
+            &fetcher,
+            "oci://ghcr.io/org",
+            "app",
+            &config,
+            false,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        assert_eq!(latest.version, "0.10.0");
+
+        let fetcher = StubFetcher {
+            tags: strings(&["latest"]),
+            ..Default::default()
+        };
+        let err = fetch_helm_chart_version_oci(
+            &fetcher,
+            "oci://ghcr.io/org",
+            "app",
+            &config,
+            false,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No valid versions found for OCI chart 'app'"
+        );
+
+        let fetcher = StubFetcher {
+            error: Some("HTTP 401".to_string()),
+            ..Default::default()
+        };
+        let err = fetch_helm_chart_version_oci(
+            &fetcher,
+            "oci://ghcr.io/org",
+            "app",
+            &config,
+            false,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "HTTP 401");
+    }
 
-    if !response.status().is_success() {
-        anyhow::bail!(
-            "Failed to fetch OCI tags: HTTP {} for {}",
-            response.status(),
-            tags_url
+    #[test]
+    fn test_next_page_url() {
+        let current = "https://ghcr.io/v2/org/app/tags/list";
+        assert_eq!(
+            next_page_url(
+                r#"</v2/org/app/tags/list?last=1.9.0&n=100>; rel="next""#,
+                current
+            )
+            .as_deref(),
+            Some("https://ghcr.io/v2/org/app/tags/list?last=1.9.0&n=100")
+        );
+        assert_eq!(
+            next_page_url(
+                r#"<https://ghcr.io/v2/org/app/tags/list?last=a>; rel="prev", <https://ghcr.io/v2/org/app/tags/list?last=b>; rel="next""#,
+                current
+            )
+            .as_deref(),
+            Some("https://ghcr.io/v2/org/app/tags/list?last=b")
+        );
+        assert_eq!(
+            next_page_url(r#"</v2/org/app/tags/list?last=a>; rel="prev""#, current),
+            None
         );
     }
 
-    let tags_response: OciTagsResponse = response
-        .json()
-        .context("Failed to parse OCI tags response")?;
+    #[rstest]
+    #[case("https://evil.example/v2/org/app/tags/list?last=b")]
+    #[case("http://ghcr.io/v2/org/app/tags/list?last=b")]
+    #[case("https://ghcr.io:8443/v2/org/app/tags/list?last=b")]
+    fn test_next_page_url_stays_on_registry_origin(#[case] target: &str) {
+        let link = format!(r#"<{}>; rel="next""#, target);
+        assert_eq!(
+            next_page_url(&link, "https://ghcr.io/v2/org/app/tags/list"),
+            None
+        );
+    }
 
-    if verbose {
-        eprintln!("  Found {} tags", tags_response.tags.len());
-        if !tags_response.tags.is_empty() {
-            eprintln!(
-                "  Sample tags: {:?}",
-                &tags_response.tags[..tags_response.tags.len().min(3)]
-            );
-        }
+    #[test]
+    fn test_collect_paginated_tags_picks_latest_from_page_two() {
+        let first = "https://ghcr.io/v2/org/app/tags/list";
+        let mut requested = Vec::new();
+
+        let tags = collect_paginated_tags(first, |url| {
+            requested.push(url.to_string());
+            Ok(match requested.len() {
+                1 => TagsPage {
+                    tags: vec!["1.0.0".to_string(), "1.9.0".to_string()],
+                    link: Some(r#"</v2/org/app/tags/list?last=1.9.0>; rel="next""#.to_string()),
+                },
+                _ => TagsPage {
+                    tags: vec!["1.10.0".to_string(), "2.0.0-rc.1".to_string()],
+                    link: None,
+                },
+            })
+        })
+        .unwrap();
+
+        assert_eq!(
+            requested,
+            vec![
+                first.to_string(),
+                "https://ghcr.io/v2/org/app/tags/list?last=1.9.0".to_string()
+            ]
+        );
+        let fetcher = StubFetcher {
+            tags,
+            ..Default::default()
+        };
+        let latest = fetch_helm_chart_version_oci(
+            &fetcher,
+            "oci://ghcr.io/org",
+            "app",
+            &Config::default(),
+            false,
+            false,
+            VersionBounds::default(),
+        )
+        .unwrap();
+        assert_eq!(latest.version, "1.10.0");
     }
 
-    // Parse versions
-    let mut versions: Vec<Version> = tags_response
-        .tags
-        .iter()
-        .filter_map(|tag| {
-            let clean_tag = tag.trim_start_matches('v');
-            Version::parse(clean_tag).ok()
+    #[test]
+    fn test_collect_paginated_tags_stops_at_page_cap() {
+        let mut pages = 0;
+        let tags = collect_paginated_tags("https://ghcr.io/v2/org/app/tags/list", |_| {
+            pages += 1;
+            Ok(TagsPage {
+                tags: vec![format!("0.{}.0", pages)],
+                link: Some(r#"</v2/org/app/tags/list?n=1>; rel="next""#.to_string()),
+            })
         })
-        .collect();
+        .unwrap();
 
-    // Filter out pre-releases unless explicitly requested
-    if !include_prereleases {
-        versions.retain(|v| !is_prerelease(v));
+        assert_eq!(pages, MAX_TAG_PAGES);
+        assert_eq!(tags.len(), MAX_TAG_PAGES);
     }
 
-    if versions.is_empty() {
-        anyhow::bail!("No valid versions found for OCI chart '{}'", chart_name);
+    #[test]
+    fn test_parse_helm_index() {
+        let index = "apiVersion: v1\nentries:\n  redis:\n    - version: 18.1.0\n      appVersion: 7.2.0\n    - version: 17.0.0\n";
+        assert_eq!(
+            parse_helm_index(index, "redis", false).unwrap(),
+            vec![chart("18.1.0", Some("7.2.0")), chart("17.0.0", None)]
+        );
+
+        let err = parse_helm_index(index, "nginx", false).unwrap_err();
+        assert_eq!(err.to_string(), "Chart 'nginx' not found in repository");
     }
 
-    versions.sort();
+    const TEST_CA_PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIBgjCCASegAwIBAgIUKw3DD4tU3v9jMRIhkZkzvZYBd+4wCgYIKoZIzj0EAwIw
+FTETMBEGA1UEAwwKbWstdGVzdC1jYTAgFw0yNjEwMTcwMTMxMjFaGA8yMTI2MDky
+MzAxMzEyMVowFTETMBEGA1UEAwwKbWstdGVzdC1jYTBZMBMGByqGSM49AgEGCCqG
+SM49AwEHA0IABDsQ0OF6EpwuQqmguQHRXNlrI9vJJGaUQQDUihE0ms4wzOQZvVI4
+amXYZ6l+qOk/WHsW1ulMihTXhLfDq1EQBbqjUzBRMB0GA1UdDgQWBBSKvAzTupvg
+ztOgQ8aGFO3rVuI+4zAfBgNVHSMEGDAWgBSKvAzTupvgztOgQ8aGFO3rVuI+4zAP
+BgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0kAMEYCIQCDnh6YCoMziqRVcQI8
+i+yJGNH3OgrRXsXX7I1l8KGzzQIhAK0xgC/R90rEZEXsVJeLHLpVNkDFZKQkFOQO
+2QuoEdBL
+-----END CERTIFICATE-----
+";
+
+    fn config_with_ca_bundle(path: &str) -> Config {
+        let mut config = Config::default();
+        config.bump.ca_bundle = Some(path.to_string());
+        config
+    }
 
-    // Return with 'v' prefix if original had it
-    let latest = versions.last().unwrap();
-    let latest_str = if tags_response
-        .tags
-        .first()
-        .map(|s| s.starts_with('v'))
-        .unwrap_or(false)
-    {
-        format!("v{}", latest)
-    } else {
-        latest.to_string()
-    };
+    #[test]
+    fn test_http_get_without_ca_bundle() {
+        let config = Config::default();
+        assert!(http_get("https://registry.terraform.io", &config).is_ok());
+    }
 
-    if verbose {
-        eprintln!("  Latest version: {}", latest_str);
+    #[test]
+    fn test_http_get_loads_configured_ca_bundle() {
+        let temp_dir = TempDir::new().unwrap();
+        let ca_path = temp_dir.path().join("ca.pem");
+        fs::write(&ca_path, TEST_CA_PEM).unwrap();
+
+        let config = config_with_ca_bundle(ca_path.to_str().unwrap());
+        assert!(http_get("https://registry.terraform.io", &config).is_ok());
+    }
+
+    #[test]
+    fn test_http_get_reports_missing_ca_bundle() {
+        let config = config_with_ca_bundle("/nonexistent/ca.pem");
+
+        let err = http_get("https://registry.terraform.io", &config).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Failed to read CA bundle at /nonexistent/ca.pem"));
     }
 
-    Ok(latest_str)
+    #[test]
+    fn test_http_get_reports_invalid_ca_bundle() {
+        let temp_dir = TempDir::new().unwrap();
+        let ca_path = temp_dir.path().join("ca.pem");
+        fs::write(&ca_path, "not a certificate").unwrap();
+
+        let config = config_with_ca_bundle(ca_path.to_str().unwrap());
+        let err = http_get("https://registry.terraform.io", &config).unwrap_err();
+        assert!(err.to_string().contains("as PEM"));
+    }
 }
@@ -3,15 +3,20 @@ use ignore::WalkBuilder;
 use regex::Regex;
 use std::fs;
 
-use super::registry;
-use super::{Dependency, DependencyType};
+use super::registry::{self, VersionFetcher};
+use super::{has_ignore_comment, Dependency, DependencyType};
 
 /// Scan Terraform files for module dependencies
 pub fn scan_terraform_modules(
     project_path: &str,
     verbose: bool,
     include_prereleases: bool,
+    max_major_jump: Option<u64>,
+    fetcher: &dyn VersionFetcher,
 ) -> Result<Vec<Dependency>> {
+    // Load config to get registry proxy/CA settings
+    let config = crate::config::Config::load_merged(project_path)?;
+
     let mut dependencies = Vec::new();
 
     // Walk through .tf files in the project, respecting .gitignore
@@ -42,22 +47,45 @@ pub fn scan_terraform_modules(
             fs::read_to_string(file_path).context(format!("Failed to read {}", relative_path))?;
 
         // Parse modules from the file - pass both full path and relative path
-        let file_deps =
-            parse_terraform_modules(&content, &full_path, verbose, include_prereleases)?;
+        let file_deps = parse_terraform_modules(
+            &content,
+            &full_path,
+            &config,
+            verbose,
+            include_prereleases,
+            max_major_jump,
+            fetcher,
+        )?;
         dependencies.extend(file_deps);
     }
 
     Ok(dependencies)
 }
 
-/// Parse Terraform module blocks and fetch latest versions
-fn parse_terraform_modules(
-    content: &str,
-    full_path: &str,
-    verbose: bool,
-    include_prereleases: bool,
-) -> Result<Vec<Dependency>> {
-    let mut dependencies = Vec::new();
+/// A module block sourced from the Terraform Registry
+#[derive(Debug, PartialEq, Eq)]
+struct RegistryModule<'a> {
+    name: &'a str,
+    /// Full `source`, including any `//submodule` path
+    source: &'a str,
+    namespace: &'a str,
+    module: &'a str,
+    provider: &'a str,
+    constraint: &'a str,
+    line_number: usize,
+    /// The version line ends with `# mk:ignore`
+    pinned: bool,
+}
+
+/// Registry address of a module source, without the `//submodule` path
+/// e.g. `terraform-google-modules/network/google//modules/subnets` -> `terraform-google-modules/network/google`
+pub(crate) fn registry_source(source: &str) -> &str {
+    source.split("//").next().unwrap_or(source)
+}
+
+/// Module blocks with a versioned `namespace/name/provider[//submodule]` source
+fn find_registry_modules(content: &str) -> Vec<RegistryModule<'_>> {
+    let mut modules = Vec::new();
 
     // Regex to match module blocks with registry sources
     // Example: source = "terraform-google-modules/cloud-nat/google"
@@ -69,7 +97,7 @@ fn parse_terraform_modules(
     let version_regex = Regex::new(r#"version\s*=\s*"([^"]+)""#).unwrap();
 
     for cap in module_regex.captures_iter(content) {
-        let module_name = cap.get(1).unwrap().as_str();
+        let name = cap.get(1).unwrap().as_str();
         let module_block = cap.get(2).unwrap().as_str();
 
         let source = match source_regex.captures(module_block) {
@@ -77,70 +105,127 @@ fn parse_terraform_modules(
             None => continue, // Skip if no source found
         };
 
-        let version_constraint = match version_regex.captures(module_block) {
+        let constraint = match version_regex.captures(module_block) {
             Some(cap) => cap.get(1).unwrap().as_str(),
             None => continue, // Skip if no version found
         };
 
         // Only handle Terraform Registry modules (format: namespace/name/provider)
-        if source.contains('/') && !source.starts_with("git::") && !source.starts_with("./") {
-            // Handle submodules: split on '//' and use only the first part for registry lookup
-            let registry_source = source.split("//").next().unwrap_or(source);
+        if !source.contains('/') || source.starts_with("git::") || source.starts_with("./") {
+            continue;
+        }
 
-            let parts: Vec<&str> = registry_source.split('/').collect();
-            if parts.len() == 3 {
-                let namespace = parts[0];
-                let name = parts[1];
-                let provider = parts[2];
+        // Submodules are versioned with their parent module
+        let parts: Vec<&str> = registry_source(source).split('/').collect();
+        if let [namespace, module, provider] = parts[..] {
+            let line_number = content
+                .lines()
+                .position(|line| line.contains(&format!(r#"module "{}""#, name)))
+                .map(|i| i + 1)
+                .unwrap_or(1);
+
+            let pinned = module_block
+                .lines()
+                .find(|line| version_regex.is_match(line))
+                .is_some_and(has_ignore_comment);
+
+            modules.push(RegistryModule {
+                name,
+                source,
+                namespace,
+                module,
+                provider,
+                constraint,
+                line_number,
+                pinned,
+            });
+        }
+    }
 
-                // Extract current version from constraint
-                let current_version = extract_version_from_constraint(version_constraint);
+    modules
+}
+
+/// Parse Terraform module blocks and fetch latest versions
+fn parse_terraform_modules(
+    content: &str,
+    full_path: &str,
+    config: &crate::config::Config,
+    verbose: bool,
+    include_prereleases: bool,
+    max_major_jump: Option<u64>,
+    fetcher: &dyn VersionFetcher,
+) -> Result<Vec<Dependency>> {
+    let mut dependencies = Vec::new();
+
+    for found in find_registry_modules(content) {
+        if found.pinned
+            || config
+                .bump
+                .is_ignored(&[found.source, registry_source(found.source)])
+        {
+            if verbose {
+                eprintln!("  Pinned (ignored): {} ({})", found.name, found.source);
+            }
+            continue;
+        }
+
+        // Extract current version from constraint
+        let current_version = extract_version_from_constraint(found.constraint);
+
+        if verbose {
+            eprintln!(
+                "  Found module: {} ({}), current: {}",
+                found.name, found.source, current_version
+            );
+        }
 
+        // Fetch latest version
+        match registry::fetch_terraform_module_version(
+            fetcher,
+            found.namespace,
+            found.module,
+            found.provider,
+            config,
+            include_prereleases,
+            registry::VersionBounds::for_current(config, &current_version, max_major_jump),
+        ) {
+            Ok(latest) => {
+                dependencies.push(Dependency {
+                    name: found.name.to_string(),
+                    current_version,
+                    latest_version: latest.version,
+                    latest_app_version: None, // Terraform modules don't have appVersion
+                    capped: latest.capped,
+                    file_path: full_path.to_string(),
+                    line_number: found.line_number,
+                    dep_type: DependencyType::TerraformModule {
+                        source: found.source.to_string(),
+                        constraint: found.constraint.to_string(),
+                    },
+                });
+            }
+            Err(e) => {
                 if verbose {
                     eprintln!(
-                        "  Found module: {} ({}), current: {}",
-                        module_name, source, current_version
+                        "  Warning: Failed to fetch version for {}: {}",
+                        found.source, e
                     );
                 }
 
-                // Fetch latest version
-                match registry::fetch_terraform_module_version(
-                    namespace,
-                    name,
-                    provider,
-                    verbose,
-                    include_prereleases,
-                ) {
-                    Ok(latest_version) => {
-                        // Find line number
-                        let line_number = content
-                            .lines()
-                            .enumerate()
-                            .find(|(_, line)| {
-                                line.contains(&format!(r#"module "{}""#, module_name))
-                            })
-                            .map(|(i, _)| i + 1)
-                            .unwrap_or(1);
-
-                        dependencies.push(Dependency {
-                            name: module_name.to_string(),
-                            current_version: current_version.clone(),
-                            latest_version,
-                            latest_app_version: None, // Terraform modules don't have appVersion
-                            file_path: full_path.to_string(),
-                            line_number,
-                            dep_type: DependencyType::TerraformModule {
-                                source: source.to_string(),
-                                constraint: version_constraint.to_string(),
-                            },
-                        });
-                    }
-                    Err(e) => {
-                        if verbose {
-                            eprintln!("  Warning: Failed to fetch version for {}: {}", source, e);
-                        }
-                    }
-                }
+                // Add dependency with ERROR marker so it can be filtered out later
+                dependencies.push(Dependency {
+                    name: found.name.to_string(),
+                    current_version,
+                    latest_version: format!("ERROR: {}", e),
+                    latest_app_version: None,
+                    capped: false,
+                    file_path: full_path.to_string(),
+                    line_number: found.line_number,
+                    dep_type: DependencyType::TerraformModule {
+                        source: found.source.to_string(),
+                        constraint: found.constraint.to_string(),
+                    },
+                });
             }
         }
     }
@@ -283,6 +368,185 @@ module "nat" {
         assert!(updated_content.contains(r#"version = ">= 5.0.0""#));
     }
 
+    #[test]
+    fn test_find_registry_modules_submodule_source() {
+        let content = r#"
+module "network" {
+  source  = "terraform-google-modules/network/google"
+  version = "~> 7.0"
+}
+
+module "subnets" {
+  source  = "terraform-google-modules/network/google//modules/subnets"
+  version = "~> 7.0"
+  project_id = "test"
+}
+
+module "local" {
+  source  = "./modules/local"
+  version = "1.0.0"
+}
+"#;
+
+        let modules = find_registry_modules(content);
+        assert_eq!(modules.len(), 2);
+        assert_eq!(
+            modules[1],
+            RegistryModule {
+                name: "subnets",
+                source: "terraform-google-modules/network/google//modules/subnets",
+                namespace: "terraform-google-modules",
+                module: "network",
+                provider: "google",
+                constraint: "~> 7.0",
+                line_number: 7,
+                pinned: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_scan_terraform_modules_with_stub_fetcher() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("main.tf"),
+            r#"
+module "vpc" {
+  source  = "terraform-google-modules/network/google"
+  version = "~> 7.0"
+}
+"#,
+        )
+        .unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+
+        let fetcher = registry::StubFetcher {
+            terraform: vec!["7.0.0".to_string(), "9.1.0".to_string()],
+            ..Default::default()
+        };
+        let deps = scan_terraform_modules(project_path, false, false, None, &fetcher).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].name, "vpc");
+        assert_eq!(deps[0].current_version, "7.0");
+        assert_eq!(deps[0].latest_version, "9.1.0");
+        assert_eq!(deps[0].line_number, 2);
+
+        // Modules whose lookup fails are reported with the error
+        let fetcher = registry::StubFetcher {
+            error: Some("HTTP 503".to_string()),
+            ..Default::default()
+        };
+        let deps = scan_terraform_modules(project_path, false, false, None, &fetcher).unwrap();
+        assert_eq!(deps.len(), 1);
+        assert_eq!(deps[0].current_version, "7.0");
+        assert_eq!(deps[0].latest_version, "ERROR: HTTP 503");
+    }
+
+    #[test]
+    fn test_scan_terraform_modules_skips_ignored() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("main.tf"),
+            r#"
+module "vpc" {
+  source  = "terraform-google-modules/network/google"
+  version = "~> 7.0"
+}
+
+module "nat" {
+  source  = "terraform-google-modules/cloud-nat/google"
+  version = "~> 4.0" # mk:ignore
+}
+
+module "gke" {
+  source  = "terraform-google-modules/kubernetes-engine/google"
+  version = "~> 30.0"
+}
+"#,
+        )
+        .unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        let fetcher = registry::StubFetcher {
+            terraform: vec!["9.1.0".to_string()],
+            ..Default::default()
+        };
+
+        // The comment pins the nat module
+        let deps = scan_terraform_modules(project_path, false, false, None, &fetcher).unwrap();
+        let names: Vec<&str> = deps.iter().map(|dep| dep.name.as_str()).collect();
+        assert_eq!(names, ["vpc", "gke"]);
+
+        // The config pins the network module by source
+        fs::write(
+            temp_dir.path().join(".mk.toml"),
+            "[bump]\nignore = [\"terraform-google-modules/network/google\"]\n",
+        )
+        .unwrap();
+        let deps = scan_terraform_modules(project_path, false, false, None, &fetcher).unwrap();
+        let names: Vec<&str> = deps.iter().map(|dep| dep.name.as_str()).collect();
+        assert_eq!(names, ["gke"]);
+    }
+
+    #[test]
+    fn test_registry_source_strips_submodule() {
+        assert_eq!(
+            registry_source("hashicorp/consul/aws//modules/consul-cluster"),
+            "hashicorp/consul/aws"
+        );
+        assert_eq!(
+            registry_source("hashicorp/consul/aws"),
+            "hashicorp/consul/aws"
+        );
+    }
+
+    #[test]
+    fn test_update_terraform_module_keeps_submodule_source() {
+        let temp_dir = TempDir::new().unwrap();
+        let tf_file = temp_dir.path().join("test.tf");
+
+        fs::write(
+            &tf_file,
+            r#"
+module "network" {
+  source  = "terraform-google-modules/network/google"
+  version = "~> 7.0"
+}
+
+module "subnets" {
+  source  = "terraform-google-modules/network/google//modules/subnets"
+  version = "~> 7.0"
+  project_id = "test"
+}
+"#,
+        )
+        .unwrap();
+
+        update_terraform_module(
+            tf_file.to_str().unwrap(),
+            "terraform-google-modules/network/google//modules/subnets",
+            "~> 7.0",
+            "9.1.0",
+        )
+        .unwrap();
+
+        let updated_content = fs::read_to_string(&tf_file).unwrap();
+        assert_eq!(
+            updated_content,
+            r#"
+module "network" {
+  source  = "terraform-google-modules/network/google"
+  version = "~> 7.0"
+}
+
+module "subnets" {
+  source  = "terraform-google-modules/network/google//modules/subnets"
+  version = "~> 9.1.0"
+  project_id = "test"
+}
+"#
+        );
+    }
+
     #[test]
     fn test_update_terraform_module_exact_version() {
         let temp_dir = TempDir::new().unwrap();
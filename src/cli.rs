@@ -1,3 +1,5 @@
+use crate::commands::ansible::PlaybookFilters;
+use crate::output::OutputFormat;
 use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
@@ -15,6 +17,69 @@ pub struct Cli {
     /// Disable gitignore filtering during directory walk
     #[arg(long, global = true)]
     pub no_ignore: bool,
+
+    /// Format of INFO/DEBUG/WARNING/ERROR messages on stderr, defaults to $MK_LOG_FORMAT or text
+    #[arg(long, global = true, value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Skip checking that the required binaries are on PATH before running
+    #[arg(long, global = true)]
+    pub no_preflight: bool,
+
+    /// Deploy without taking the per-environment lock (`.mk/locks/<env>.lock`)
+    #[arg(long, global = true)]
+    pub no_lock: bool,
+
+    /// Don't log the "Detected <technology>" messages (detection still runs)
+    #[arg(long, global = true)]
+    pub no_detect_log: bool,
+
+    /// Require the technology directly at the given path, without looking in child directories
+    #[arg(long, global = true)]
+    pub strict_path: bool,
+
+    /// Skip `tfswitch` and `terraform init -reconfigure`, running only the terraform operation
+    /// (for tight plan/apply loops; init still runs when `.terraform` has another backend)
+    #[arg(long, global = true)]
+    pub no_init: bool,
+
+    /// Print the resolved commands as one copy-pasteable line to stdout before running them
+    #[arg(long, global = true)]
+    pub print_command: bool,
+
+    /// Answer yes to confirmations: terraform apply/destroy get -auto-approve and
+    /// mk's own prompts are skipped (helmfile is already non-interactive)
+    #[arg(
+        short = 'y',
+        long = "yes",
+        visible_alias = "auto-approve",
+        global = true
+    )]
+    pub yes: bool,
+
+    /// Print a final `mk: <action> <tech> <env> exit=<code>` line to stdout
+    #[arg(long, global = true)]
+    pub report_exit: bool,
+
+    /// Run tool commands in this directory instead of the detected project path
+    #[arg(long, global = true, value_name = "DIR")]
+    pub workdir: Option<String>,
+
+    /// Switch to this kubectl context and skip context mapping validation
+    #[arg(long, global = true, value_name = "NAME")]
+    pub context: Option<String>,
+
+    /// Use this kubeconfig file for kubectl, helm and kustomize commands
+    #[arg(long, global = true, value_name = "PATH")]
+    pub kubeconfig: Option<String>,
+
+    /// Use this project config file instead of discovering `.mk.toml`
+    #[arg(long, global = true, value_name = "PATH")]
+    pub project_config: Option<String>,
+
+    /// Print the effective configuration of the command's project as JSON and exit
+    #[arg(long, global = true)]
+    pub dump_config: bool,
 }
 
 #[derive(Subcommand)]
@@ -25,6 +90,22 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Skip the helm dependency update check
+        #[arg(long)]
+        skip_deps: bool,
+        /// Extra helm values file layered after the environment values (repeatable,
+        /// rejected for other technologies)
+        #[arg(long = "values", value_name = "PATH")]
+        values: Vec<String>,
+        /// Limit the Ansible play to hosts matching this pattern
+        #[arg(long, value_name = "PATTERN")]
+        limit: Option<String>,
+        /// Only run Ansible tasks with these comma-separated tags
+        #[arg(long, value_name = "CSV")]
+        tags: Option<String>,
+        /// Ansible verbosity, repeat for more (-v up to -vvvv)
+        #[arg(long, action = clap::ArgAction::Count)]
+        ansible_verbose: u8,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -35,6 +116,25 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Skip the helm dependency update check
+        #[arg(long)]
+        skip_deps: bool,
+        /// Extra helm values file layered after the environment values (repeatable,
+        /// rejected for other technologies)
+        #[arg(long = "values", value_name = "PATH")]
+        values: Vec<String>,
+        /// Only refresh the terraform state, without planning changes (`-refresh-only`)
+        #[arg(long)]
+        refresh_only: bool,
+        /// Limit the Ansible play to hosts matching this pattern
+        #[arg(long, value_name = "PATTERN")]
+        limit: Option<String>,
+        /// Only run Ansible tasks with these comma-separated tags
+        #[arg(long, value_name = "CSV")]
+        tags: Option<String>,
+        /// Ansible verbosity, repeat for more (-v up to -vvvv)
+        #[arg(long, action = clap::ArgAction::Count)]
+        ansible_verbose: u8,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -45,6 +145,22 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Skip the helm dependency update check
+        #[arg(long)]
+        skip_deps: bool,
+        /// Extra helm values file layered after the environment values (repeatable,
+        /// rejected for other technologies)
+        #[arg(long = "values", value_name = "PATH")]
+        values: Vec<String>,
+        /// Limit the Ansible play to hosts matching this pattern
+        #[arg(long, value_name = "PATTERN")]
+        limit: Option<String>,
+        /// Only run Ansible tasks with these comma-separated tags
+        #[arg(long, value_name = "CSV")]
+        tags: Option<String>,
+        /// Ansible verbosity, repeat for more (-v up to -vvvv)
+        #[arg(long, action = clap::ArgAction::Count)]
+        ansible_verbose: u8,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -55,6 +171,13 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Only refresh the terraform state, without planning changes (`-refresh-only`)
+        #[arg(long)]
+        refresh_only: bool,
+        /// Also save the plan output, and the `-out` plan file if any, to a
+        /// timestamped subdirectory of DIR for review
+        #[arg(long, value_name = "DIR")]
+        save_plan_dir: Option<String>,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -65,6 +188,9 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Skip the helm dependency update check
+        #[arg(long)]
+        skip_deps: bool,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -75,6 +201,9 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Skip the helm dependency update check
+        #[arg(long)]
+        skip_deps: bool,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -85,6 +214,9 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Skip the helm dependency update check
+        #[arg(long)]
+        skip_deps: bool,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -105,6 +237,16 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Skip the helm dependency update check
+        #[arg(long)]
+        skip_deps: bool,
+        /// Extra helm values file layered after the environment values (repeatable,
+        /// rejected for other technologies)
+        #[arg(long = "values", value_name = "PATH")]
+        values: Vec<String>,
+        /// Write the rendered manifests to this file instead of stdout
+        #[arg(long, value_name = "PATH")]
+        output_file: Option<String>,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -120,6 +262,12 @@ pub enum Commands {
         /// Show all outputs
         #[arg(short, long)]
         all: bool,
+        /// Query up to N outputs at once with --all (init still runs first)
+        #[arg(long, value_name = "N", requires = "all", value_parser = clap::value_parser!(usize))]
+        parallel: Option<usize>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
     /// List ansible inventory
     List {
@@ -127,6 +275,9 @@ pub enum Commands {
         project_path: String,
         /// Environment name
         environment: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
         /// Additional options to pass to the underlying command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         options: Vec<String>,
@@ -148,8 +299,40 @@ pub enum Commands {
         environment: String,
         /// Lock ID
         lock_id: String,
+        /// Unlock without asking for confirmation
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import an existing resource into the terraform state
+    Import {
+        /// Project path
+        project_path: String,
+        /// Environment name
+        environment: String,
+        /// Resource address, e.g. aws_instance.web["blue"]
+        address: String,
+        /// Provider ID of the existing resource
+        id: String,
+        /// Additional options to pass to the underlying command
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        options: Vec<String>,
+    },
+    /// Force recreation of a terraform resource with `-replace` on apply (or plan)
+    Replace {
+        /// Project path
+        project_path: String,
+        /// Environment name
+        environment: String,
+        /// Resource address, e.g. module.app.aws_instance.web[0]
+        address: String,
+        /// Only plan the replacement
+        #[arg(long)]
+        plan: bool,
+        /// Additional options to pass to the underlying command
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        options: Vec<String>,
     },
-    /// Show the current terraform state
+    /// Show the current terraform state or helm release status
     Show {
         /// Project path
         project_path: String,
@@ -171,11 +354,46 @@ pub enum Commands {
         #[arg(short, long)]
         force: bool,
     },
+    /// Add or remove environments of a project
+    Env {
+        #[command(subcommand)]
+        action: EnvAction,
+    },
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Show the detected technology, project path and environments
+    Detect {
+        /// Project path
+        project_path: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Report versions of terraform, helm, helmfile, kustomize, ansible and kubectl
+    Doctor,
+    /// List the actions each technology supports
+    #[command(visible_alias = "actions")]
+    Technologies {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Inspect Kubernetes context validation
+    Context {
+        #[command(subcommand)]
+        action: ContextAction,
+    },
     /// Hidden command for shell completion: list available environments
     #[command(hide = true)]
     CompleteEnv {
         /// Project path to detect environments from
         project_path: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
     /// Hidden command for shell completion: list available terraform output keys
     #[command(hide = true)]
@@ -193,6 +411,57 @@ pub enum Commands {
         /// Recursively scan subdirectories for projects
         #[arg(short, long)]
         recursive: bool,
+        /// How to list available updates before the selection prompt
+        #[arg(long, value_enum, default_value_t = BumpFormat::Inline)]
+        format: BumpFormat,
+        /// Order of the available updates (default: discovery order)
+        #[arg(long, value_enum)]
+        sort: Option<BumpSort>,
+        /// Exit with code 2 when some dependency versions could not be verified
+        #[arg(long)]
+        fail_on_error: bool,
+        /// With --recursive, only scan projects whose path relative to the base
+        /// matches this glob (can be repeated)
+        #[arg(long = "path-filter", value_name = "GLOB", requires = "recursive")]
+        path_filters: Vec<String>,
+        /// Write a markdown list of the applied updates to this file
+        #[arg(long, value_name = "PATH")]
+        changelog_file: Option<String>,
+        /// Only list available updates and exit with code 3 if there are any
+        #[arg(long, conflicts_with = "changelog_file")]
+        check: bool,
+        /// Only list the dependencies whose version could not be fetched, exiting
+        /// with code 2 if there are any
+        #[arg(long, conflicts_with_all = ["changelog_file", "check"])]
+        only_errors: bool,
+        /// Refresh `.terraform.lock.hcl` with `terraform providers lock` in the
+        /// projects whose modules were bumped (skipped when there is no lock file)
+        #[arg(long, conflicts_with_all = ["check", "only_errors"])]
+        update_lock: bool,
+        /// With --recursive, record scanned projects in `.mk/bump-progress.json` and
+        /// skip them when re-run after an interruption
+        #[arg(long, requires = "recursive")]
+        resume: bool,
+        /// With --resume, ignore the recorded progress and scan every project again
+        #[arg(long, requires = "resume")]
+        fresh: bool,
+        /// Propose at most this many major versions above the current one, the
+        /// highest version within the bound is marked "capped" when newer exist
+        #[arg(long, value_name = "N")]
+        max_major_jump: Option<u64>,
+        /// Commit each updated file on its own (`git add <file> && git commit`),
+        /// files outside a git repository are left uncommitted
+        #[arg(long, conflicts_with_all = ["check", "only_errors"])]
+        git_commit: bool,
+        /// Message of the --git-commit commits, `{name}` and `{version}` are replaced
+        /// by the dependency and its new version
+        #[arg(
+            long,
+            value_name = "TEMPLATE",
+            requires = "git_commit",
+            default_value = crate::bump::DEFAULT_COMMIT_MESSAGE_TEMPLATE
+        )]
+        commit_message_template: String,
     },
     /// Check for IaC drift across multiple stacks
     Drift {
@@ -202,17 +471,88 @@ pub enum Commands {
         #[arg(short, long)]
         verbose: bool,
         /// Filter to only check specific technology (terraform or helm)
-        #[arg(short = 't', long)]
-        tech: Option<String>,
+        #[arg(short = 't', long, value_enum, ignore_case = true)]
+        tech: Option<DriftTech>,
         /// Check only specific environment(s) (can be repeated)
         #[arg(short = 'e', long = "env")]
         environments: Vec<String>,
+        /// Also check the environments listed in this file, one per line
+        /// (blank lines and # comments are ignored)
+        #[arg(long, value_name = "PATH")]
+        env_file: Option<String>,
+        /// Also check the environments whose name matches this regex (e.g. `-prod$`)
+        #[arg(long, value_name = "PATTERN", allow_hyphen_values = true)]
+        env_regex: Option<String>,
         /// Capture full output to log files in .drift-logs/ directory
         #[arg(short, long)]
         capture: bool,
+        /// Capture output for every result (OK, drift and error) to .drift-logs/
+        #[arg(long)]
+        capture_all: bool,
+        /// Stop at the first drift or error, skipping the remaining checks
+        #[arg(long)]
+        fail_fast: bool,
+        /// Plan terraform projects with `-refresh-only`, reporting drift of the
+        /// state from the real infrastructure rather than pending changes
+        #[arg(long)]
+        refresh_only: bool,
         /// Maximum depth for recursive scanning (default: 5)
         #[arg(short = 'd', long, default_value = "5")]
         max_depth: usize,
+        /// Only check projects whose path relative to the base matches this glob
+        /// (can be repeated)
+        #[arg(long = "path-filter", value_name = "GLOB")]
+        path_filters: Vec<String>,
+        /// Only check projects containing files changed since this git ref
+        #[arg(long, value_name = "GIT_REF")]
+        changed_since: Option<String>,
+        /// Exit with an error when no IaC project is found (likely a wrong base path)
+        #[arg(long)]
+        require_projects: bool,
+        /// Show absolute project paths instead of paths relative to the base path
+        #[arg(long)]
+        absolute_paths: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Open the config file in $EDITOR (created from the template if missing)
+    Edit,
+}
+
+#[derive(Subcommand)]
+pub enum EnvAction {
+    /// Scaffold the files of a new environment for the detected technology
+    Add {
+        /// Project path
+        project_path: String,
+        /// Environment name
+        environment: String,
+    },
+    /// Delete the files of an environment
+    Remove {
+        /// Project path
+        project_path: String,
+        /// Environment name
+        environment: String,
+        /// Delete without asking for confirmation
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ContextAction {
+    /// Explain how the kubectl context is validated for a project and environment
+    Doctor {
+        /// Project path
+        project_path: String,
+        /// Environment name
+        environment: String,
     },
 }
 
@@ -232,3 +572,142 @@ impl std::fmt::Display for Shell {
         }
     }
 }
+
+impl Commands {
+    /// `--limit`, `--tags` and `--ansible-verbose` given to an ansible-capable command
+    pub fn playbook_filters(&self) -> PlaybookFilters {
+        match self {
+            Commands::Apply {
+                limit,
+                tags,
+                ansible_verbose,
+                ..
+            }
+            | Commands::Check {
+                limit,
+                tags,
+                ansible_verbose,
+                ..
+            }
+            | Commands::Diff {
+                limit,
+                tags,
+                ansible_verbose,
+                ..
+            } => PlaybookFilters {
+                limit: limit.clone(),
+                tags: tags.clone(),
+                verbosity: *ansible_verbose,
+            },
+            _ => PlaybookFilters::default(),
+        }
+    }
+
+    /// Whether `--skip-deps` was given to a helm-capable command
+    pub fn skip_deps(&self) -> bool {
+        match self {
+            Commands::Apply { skip_deps, .. }
+            | Commands::Check { skip_deps, .. }
+            | Commands::Diff { skip_deps, .. }
+            | Commands::Delete { skip_deps, .. }
+            | Commands::Destroy { skip_deps, .. }
+            | Commands::Uninstall { skip_deps, .. }
+            | Commands::Template { skip_deps, .. } => *skip_deps,
+            _ => false,
+        }
+    }
+
+    /// Project (or drift base) path the command runs on, if it takes one
+    pub fn project_path(&self) -> Option<&str> {
+        match self {
+            Commands::Apply { project_path, .. }
+            | Commands::Check { project_path, .. }
+            | Commands::Diff { project_path, .. }
+            | Commands::Plan { project_path, .. }
+            | Commands::Delete { project_path, .. }
+            | Commands::Destroy { project_path, .. }
+            | Commands::Uninstall { project_path, .. }
+            | Commands::Deps { project_path, .. }
+            | Commands::Template { project_path, .. }
+            | Commands::Output { project_path, .. }
+            | Commands::List { project_path, .. }
+            | Commands::Duplicate { project_path, .. }
+            | Commands::Unlock { project_path, .. }
+            | Commands::Import { project_path, .. }
+            | Commands::Replace { project_path, .. }
+            | Commands::Show { project_path, .. }
+            | Commands::Detect { project_path, .. }
+            | Commands::Bump { project_path, .. } => Some(project_path),
+            Commands::Drift { base_path, .. } => Some(base_path),
+            _ => None,
+        }
+    }
+
+    /// Values files given with `--values` to a helm-capable command
+    pub fn values_files(&self) -> Vec<String> {
+        match self {
+            Commands::Apply { values, .. }
+            | Commands::Check { values, .. }
+            | Commands::Diff { values, .. }
+            | Commands::Template { values, .. } => values.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether `--refresh-only` was given to a terraform plan-capable command
+    pub fn refresh_only(&self) -> bool {
+        match self {
+            Commands::Plan { refresh_only, .. }
+            | Commands::Check { refresh_only, .. }
+            | Commands::Drift { refresh_only, .. } => *refresh_only,
+            _ => false,
+        }
+    }
+
+    /// `--format` of a command supporting JSON output, text for the others
+    pub fn format(&self) -> OutputFormat {
+        match self {
+            Commands::Output { format, .. }
+            | Commands::List { format, .. }
+            | Commands::Detect { format, .. }
+            | Commands::Technologies { format }
+            | Commands::CompleteEnv { format, .. }
+            | Commands::Drift { format, .. } => *format,
+            _ => OutputFormat::Text,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BumpFormat {
+    /// Only show updates inside the selection prompt
+    Inline,
+    /// Print an aligned table of updates before the selection prompt
+    Table,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BumpSort {
+    /// By dependency name
+    Name,
+    /// By file and line
+    File,
+    /// Biggest semver jump from current to latest first
+    Jump,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DriftTech {
+    /// Only terraform projects
+    Terraform,
+    /// Only helm charts
+    Helm,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Colored human-readable messages
+    Text,
+    /// One JSON object per line: {level, message, ts}
+    Json,
+}
@@ -1,12 +1,52 @@
 use super::Action;
+use crate::config::Config;
+use crate::executor::shell_quote;
 use anyhow::Result;
+use std::fs;
+use std::path::Path;
 use std::process::Command;
 
+/// Highest `ansible-playbook` verbosity (`-vvvv`)
+const MAX_VERBOSITY: u8 = 4;
+
+/// `--limit`, `--tags` and `--ansible-verbose` rendered on the `ansible-playbook` line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaybookFilters {
+    pub limit: Option<String>,
+    pub tags: Option<String>,
+    /// Number of `v` in ansible's `-v` flag, capped at `MAX_VERBOSITY`
+    pub verbosity: u8,
+}
+
+impl PlaybookFilters {
+    /// Whether any of `--limit`/`--tags`/`--ansible-verbose` was given
+    pub fn is_set(&self) -> bool {
+        *self != PlaybookFilters::default()
+    }
+
+    /// Flags with a leading space, e.g. ` --limit web --tags nginx,tls -vv`
+    fn render(&self) -> String {
+        let mut flags = String::new();
+        if let Some(limit) = &self.limit {
+            flags.push_str(&format!(" --limit {}", shell_quote(limit)));
+        }
+        if let Some(tags) = &self.tags {
+            flags.push_str(&format!(" --tags {}", shell_quote(tags)));
+        }
+        if self.verbosity > 0 {
+            let count = self.verbosity.min(MAX_VERBOSITY) as usize;
+            flags.push_str(&format!(" -{}", "v".repeat(count)));
+        }
+        flags
+    }
+}
+
 pub fn get_command(
     action: &Action,
     project_path: &str,
     environment: &str,
     options: &[String],
+    filters: &PlaybookFilters,
 ) -> Result<String> {
     let options_str = options.join(" ");
     let opts = if options_str.is_empty() {
@@ -15,21 +55,20 @@ pub fn get_command(
         format!(" {}", options_str)
     };
 
-    // Find the inventory file (could be .yml or .yaml)
-    let inventory_pattern = format!("inventories/{}.*yml", environment);
+    let inventory_pattern = inventory_path(project_path, environment);
+    // Filters go before pass-through options
 
     let cmd = match action {
-        Action::Apply => {
-            format!(
-                "ansible-playbook -i {} playbook.yml -D{}",
-                inventory_pattern, opts
-            )
-        }
-        Action::Check | Action::Diff => {
-            format!(
-                "ansible-playbook -i {} playbook.yml -DC{}",
-                inventory_pattern, opts
-            )
+        Action::Apply | Action::Check | Action::Diff => {
+            let config = Config::load_merged(project_path)?;
+            let configured = config
+                .ansible
+                .playbook
+                .as_deref()
+                .map(|playbook| config.expand_env(playbook))
+                .transpose()?;
+            let playbook = find_playbook(project_path, configured.as_deref())?;
+            playbook_command(action, &inventory_pattern, &playbook, filters, &opts)
         }
         Action::Deps => {
             format!(
@@ -50,12 +89,151 @@ pub fn get_command(
     Ok(cmd)
 }
 
+/// Playbooks tried in order when `[ansible] playbook` is not set
+const PLAYBOOK_CANDIDATES: &[&str] = &["site.yml", "playbook.yml", "main.yml"];
+
+/// Root YAML files of ansible projects that are never playbooks (extension omitted)
+const NON_PLAYBOOK_FILES: &[&str] = &["requirements", "galaxy", "molecule", "docker-compose"];
+
+/// Playbook to run, relative to the project
+/// The configured one if set, else the first of `PLAYBOOK_CANDIDATES`, else the only `*.yml`/`*.yaml` at the root
+/// that is neither hidden nor one of `NON_PLAYBOOK_FILES`
+fn find_playbook(project_path: &str, configured: Option<&str>) -> Result<String> {
+    let path = Path::new(project_path);
+
+    if let Some(playbook) = configured {
+        if !path.join(playbook).is_file() {
+            anyhow::bail!(
+                "Configured playbook {} not found in {}",
+                playbook,
+                project_path
+            );
+        }
+        return Ok(playbook.to_string());
+    }
+
+    if let Some(playbook) = PLAYBOOK_CANDIDATES
+        .iter()
+        .find(|candidate| path.join(candidate).is_file())
+    {
+        return Ok(playbook.to_string());
+    }
+
+    let mut yaml_files: Vec<String> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| !name.starts_with('.'))
+        .filter_map(|name| {
+            let stem = name
+                .strip_suffix(".yml")
+                .or_else(|| name.strip_suffix(".yaml"))?;
+            (!NON_PLAYBOOK_FILES.contains(&stem)).then_some(name)
+        })
+        .collect();
+    yaml_files.sort();
+
+    match yaml_files.as_slice() {
+        [playbook] => Ok(playbook.clone()),
+        [] => anyhow::bail!(
+            "No playbook found in {}: expected {} or a single *.yml file (or set [ansible] playbook)",
+            project_path,
+            PLAYBOOK_CANDIDATES.join(", ")
+        ),
+        candidates => anyhow::bail!(
+            "Multiple playbook candidates in {}: {} (set [ansible] playbook to choose one)",
+            project_path,
+            candidates.join(", ")
+        ),
+    }
+}
+
+/// `ansible-playbook` line for apply (`-D`) or check/diff (`-DC`)
+fn playbook_command(
+    action: &Action,
+    inventory: &str,
+    playbook: &str,
+    filters: &PlaybookFilters,
+    opts: &str,
+) -> String {
+    let mode = if matches!(action, Action::Apply) {
+        "-D"
+    } else {
+        "-DC"
+    };
+    format!(
+        "ansible-playbook -i {} {} {}{}{}",
+        inventory,
+        shell_quote(playbook),
+        mode,
+        filters.render(),
+        opts
+    )
+}
+
+/// Inventory passed to `-i`, relative to the project
+/// A directory `inventories/<env>` wins over a file `inventories/<env>.<ext>`;
+/// falls back to the `inventories/<env>.*yml` glob when neither is found
+fn inventory_path(project_path: &str, environment: &str) -> String {
+    let inventories_dir = Path::new(project_path).join("inventories");
+    if inventories_dir.join(environment).is_dir() {
+        return format!("inventories/{}", environment);
+    }
+
+    let mut files: Vec<String> = fs::read_dir(&inventories_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| name.split('.').next() == Some(environment))
+        .collect();
+    files.sort();
+
+    match files.first() {
+        Some(file) => format!("inventories/{}", file),
+        None => format!("inventories/{}.*yml", environment),
+    }
+}
+
 /// List ansible inventory with pretty-printed JSON output
 fn list_ansible_inventory(
     project_path: &str,
     inventory_pattern: &str,
     options: &[String],
 ) -> Result<()> {
+    let json_str = run_inventory(project_path, inventory_pattern, options)?;
+
+    // Parse JSON and pretty-print it
+    if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&json_str) {
+        let pretty = serde_json::to_string_pretty(&parsed)?;
+        println!("{}", pretty);
+    } else {
+        // If parsing fails, just print the raw output
+        print!("{}", json_str);
+    }
+
+    Ok(())
+}
+
+/// Parsed `ansible-inventory --list` of an environment
+pub fn inventory(
+    project_path: &str,
+    environment: &str,
+    options: &[String],
+) -> Result<serde_json::Value> {
+    let inventory_pattern = inventory_path(project_path, environment);
+    let json_str = run_inventory(project_path, &inventory_pattern, options)?;
+    serde_json::from_str(&json_str)
+        .map_err(|e| anyhow::anyhow!("Invalid ansible-inventory output: {}", e))
+}
+
+/// Stdout of `ansible-inventory --list`, failing with its stderr
+fn run_inventory(
+    project_path: &str,
+    inventory_pattern: &str,
+    options: &[String],
+) -> Result<String> {
     let options_str = options.join(" ");
     let opts = if options_str.is_empty() {
         String::new()
@@ -73,20 +251,269 @@ fn list_ansible_inventory(
         .current_dir(project_path)
         .output()?;
 
-    if output.status.success() {
-        // Parse JSON and pretty-print it
-        let json_str = String::from_utf8_lossy(&output.stdout);
-        if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&json_str) {
-            let pretty = serde_json::to_string_pretty(&parsed)?;
-            println!("{}", pretty);
-        } else {
-            // If parsing fails, just print the raw output
-            print!("{}", json_str);
-        }
-    } else {
+    if !output.status.success() {
         let error = String::from_utf8_lossy(&output.stderr);
         anyhow::bail!("ansible-inventory failed: {}", error);
     }
 
-    Ok(())
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn inventories(temp_dir: &TempDir) -> std::path::PathBuf {
+        fs::write(temp_dir.path().join("playbook.yml"), "- hosts: all\n").unwrap();
+        let inventories_dir = temp_dir.path().join("inventories");
+        fs::create_dir_all(&inventories_dir).unwrap();
+        inventories_dir
+    }
+
+    #[test]
+    fn test_inventory_path_file_layout() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(inventories(&temp_dir).join("dev.yaml"), "all:\n").unwrap();
+
+        let cmd = get_command(
+            &Action::Apply,
+            temp_dir.path().to_str().unwrap(),
+            "dev",
+            &[],
+            &PlaybookFilters::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            cmd,
+            "ansible-playbook -i inventories/dev.yaml playbook.yml -D"
+        );
+    }
+
+    #[test]
+    fn test_inventory_path_directory_layout() {
+        let temp_dir = TempDir::new().unwrap();
+        let prod = inventories(&temp_dir).join("prod");
+        fs::create_dir(&prod).unwrap();
+        fs::write(prod.join("hosts"), "[web]\n").unwrap();
+
+        let cmd = get_command(
+            &Action::Diff,
+            temp_dir.path().to_str().unwrap(),
+            "prod",
+            &[],
+            &PlaybookFilters::default(),
+        )
+        .unwrap();
+        assert_eq!(cmd, "ansible-playbook -i inventories/prod playbook.yml -DC");
+    }
+
+    /// Project directory holding the given root files
+    fn project_with(files: &[&str]) -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        for file in files {
+            fs::write(temp_dir.path().join(file), "- hosts: all\n").unwrap();
+        }
+        temp_dir
+    }
+
+    #[test]
+    fn test_find_playbook_precedence() {
+        let cases: &[(&[&str], &str)] = &[
+            (&["site.yml", "playbook.yml", "main.yml"], "site.yml"),
+            (
+                &["playbook.yml", "main.yml", "requirements.yml"],
+                "playbook.yml",
+            ),
+            (&["main.yml", "deploy.yml"], "main.yml"),
+            (&["deploy.yaml"], "deploy.yaml"),
+            // Hidden files and known non-playbook YAML do not make it ambiguous
+            (
+                &[
+                    "deploy.yml",
+                    "requirements.yml",
+                    "galaxy.yaml",
+                    ".yamllint.yml",
+                ],
+                "deploy.yml",
+            ),
+        ];
+        for (files, expected) in cases {
+            let temp_dir = project_with(files);
+            let playbook = find_playbook(temp_dir.path().to_str().unwrap(), None).unwrap();
+            assert_eq!(playbook, *expected, "files: {:?}", files);
+        }
+    }
+
+    #[test]
+    fn test_find_playbook_configured_override() {
+        let temp_dir = project_with(&["site.yml", "deploy.yml"]);
+        let project_path = temp_dir.path().to_str().unwrap();
+
+        assert_eq!(
+            find_playbook(project_path, Some("deploy.yml")).unwrap(),
+            "deploy.yml"
+        );
+        let err = find_playbook(project_path, Some("missing.yml")).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Configured playbook missing.yml not found"));
+    }
+
+    #[test]
+    fn test_find_playbook_ambiguous_or_missing() {
+        let temp_dir = project_with(&["deploy.yml", "upgrade.yml"]);
+        let err = find_playbook(temp_dir.path().to_str().unwrap(), None).unwrap_err();
+        assert!(
+            err.to_string().contains("Multiple playbook candidates in"),
+            "{}",
+            err
+        );
+        assert!(err.to_string().contains("deploy.yml, upgrade.yml"));
+
+        let temp_dir = project_with(&[]);
+        let err = find_playbook(temp_dir.path().to_str().unwrap(), None).unwrap_err();
+        assert!(err.to_string().contains("No playbook found in"));
+    }
+
+    #[test]
+    fn test_get_command_uses_configured_playbook() {
+        let temp_dir = project_with(&["site.yml", "deploy.yml"]);
+        fs::write(
+            temp_dir.path().join(".mk.toml"),
+            "[ansible]\nplaybook = \"deploy.yml\"\n",
+        )
+        .unwrap();
+
+        let cmd = get_command(
+            &Action::Apply,
+            temp_dir.path().to_str().unwrap(),
+            "dev",
+            &[],
+            &PlaybookFilters::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            cmd,
+            "ansible-playbook -i inventories/dev.*yml deploy.yml -D"
+        );
+    }
+
+    #[test]
+    fn test_playbook_command_limit() {
+        let filters = PlaybookFilters {
+            limit: Some("web".to_string()),
+            tags: None,
+            ..Default::default()
+        };
+        assert_eq!(
+            playbook_command(
+                &Action::Apply,
+                "inventories/dev.yml",
+                "playbook.yml",
+                &filters,
+                ""
+            ),
+            "ansible-playbook -i inventories/dev.yml playbook.yml -D --limit web"
+        );
+    }
+
+    #[test]
+    fn test_playbook_command_tags() {
+        let filters = PlaybookFilters {
+            limit: None,
+            tags: Some("nginx,tls".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            playbook_command(
+                &Action::Diff,
+                "inventories/dev.yml",
+                "playbook.yml",
+                &filters,
+                ""
+            ),
+            "ansible-playbook -i inventories/dev.yml playbook.yml -DC --tags nginx,tls"
+        );
+    }
+
+    #[test]
+    fn test_playbook_command_verbosity_levels() {
+        for (level, flag) in [(1, "-v"), (2, "-vv"), (3, "-vvv"), (4, "-vvvv")] {
+            let filters = PlaybookFilters {
+                verbosity: level,
+                ..Default::default()
+            };
+            assert_eq!(
+                playbook_command(
+                    &Action::Apply,
+                    "inventories/dev.yml",
+                    "playbook.yml",
+                    &filters,
+                    ""
+                ),
+                format!(
+                    "ansible-playbook -i inventories/dev.yml playbook.yml -D {}",
+                    flag
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_playbook_verbosity_capped() {
+        let filters = PlaybookFilters {
+            verbosity: 7,
+            ..Default::default()
+        };
+        assert_eq!(filters.render(), " -vvvv");
+        assert_eq!(PlaybookFilters::default().render(), "");
+    }
+
+    #[test]
+    fn test_playbook_command_limit_and_tags_before_options() {
+        let filters = PlaybookFilters {
+            limit: Some("db*".to_string()),
+            tags: Some("backup".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            playbook_command(
+                &Action::Check,
+                "inventories/prod",
+                "playbook.yml",
+                &filters,
+                " -v"
+            ),
+            "ansible-playbook -i inventories/prod playbook.yml -DC --limit 'db*' --tags backup -v"
+        );
+        assert_eq!(
+            playbook_command(
+                &Action::Apply,
+                "inventories/prod",
+                "site.yml",
+                &PlaybookFilters::default(),
+                " -v"
+            ),
+            "ansible-playbook -i inventories/prod site.yml -D -v"
+        );
+    }
+
+    #[test]
+    fn test_inventory_path_mixed_layout() {
+        let temp_dir = TempDir::new().unwrap();
+        let inventories_dir = inventories(&temp_dir);
+        fs::create_dir(inventories_dir.join("prod")).unwrap();
+        fs::write(inventories_dir.join("dev.yml"), "all:\n").unwrap();
+        // Same env as a file and a directory: the directory wins
+        fs::write(inventories_dir.join("prod.yml"), "all:\n").unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+
+        assert_eq!(inventory_path(project_path, "dev"), "inventories/dev.yml");
+        assert_eq!(inventory_path(project_path, "prod"), "inventories/prod");
+        assert_eq!(
+            inventory_path(project_path, "staging"),
+            "inventories/staging.*yml"
+        );
+    }
 }
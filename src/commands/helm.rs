@@ -1,21 +1,30 @@
 use anyhow::Result;
-use colored::*;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
 
-use super::Action;
+use super::{Action, ActionOptions};
+use crate::config::{Config, HelmConfig, HelmMode, HelmRegistry};
+use crate::env;
 use crate::executor::execute_command;
 use crate::executor::execute_command_output;
+use crate::executor::retry_with_backoff;
+use crate::executor::shell_quote;
+use crate::log;
+use crate::techno::Technology;
+use crate::tools;
 
 /// Check if helm dependencies need updating and update if needed
 pub fn helm_deps_update(
     project_path: &str,
     environment: &str,
-    verbose: bool,
     force: bool,
     silent: bool,
+    action_options: &ActionOptions,
 ) -> Result<()> {
+    let verbose = action_options.verbose;
     let chart_yaml = Path::new(project_path).join("Chart.yaml");
     if !chart_yaml.exists() {
         return Ok(());
@@ -32,28 +41,44 @@ pub fn helm_deps_update(
 
     if needs_update {
         if !silent {
-            eprintln!("{} Helm dependencies need updating", "INFO:".cyan());
+            log::info!("Helm dependencies need updating");
         }
 
-        // Authenticate to helm registries if needed
+        let helm_config = Config::load_merged(project_path)?.helm;
+
+        // Authenticate to helm registries if needed, one after the other since
+        // the logins share helm's registry credentials file
         if let Ok(registries) = extract_helm_registries(project_path) {
-            for registry in registries {
+            for registry in &registries {
+                let Some(auth_cmd) = registry_login_command(&helm_config.registries, registry)
+                else {
+                    if verbose {
+                        log::debug!("No login command configured for registry {}", registry);
+                    }
+                    continue;
+                };
                 if verbose {
-                    eprintln!(
-                        "{} Authenticating to Helm registry {}",
-                        "INFO:".cyan(),
-                        registry
+                    log::info!("Authenticating to Helm registry {}", registry);
+                }
+                let auth_retries = helm_config.auth_retries;
+                let result = retry_with_backoff(auth_retries, AUTH_RETRY_DELAY, || {
+                    execute_command_output(&auth_cmd, project_path, false).map(|_| ())
+                });
+                if let Err(e) = result {
+                    log::warning!(
+                        "Helm registry login to {} failed after {} attempt(s), `helmfile deps` may fail: {}",
+                        registry,
+                        auth_retries + 1,
+                        e
                     );
                 }
-                let auth_cmd = format!(
-                    "gcloud auth print-access-token | helm registry login -u oauth2accesstoken --password-stdin https://{}",
-                    registry
-                );
-                let _ = execute_command_output(&auth_cmd, project_path, false);
             }
         }
 
-        let deps_cmd = format!("helmfile deps -e {}", environment);
+        let (deps_binary, deps_cmd) = deps_command(&helm_config, environment);
+        if !action_options.no_preflight {
+            tools::preflight(&[deps_binary], std::slice::from_ref(&deps_cmd))?;
+        }
         if verbose {
             // Stream output when verbose
             execute_command(&deps_cmd, project_path, verbose)?;
@@ -66,8 +91,71 @@ pub fn helm_deps_update(
     Ok(())
 }
 
+/// Binary and command downloading the chart dependencies, with at most
+/// `deps_jobs` downloads at once when set
+fn deps_command(helm_config: &HelmConfig, environment: &str) -> (String, String) {
+    match helm_config.mode {
+        HelmMode::Helmfile => {
+            let mut cmd = helmfile_command(helm_config, "deps", environment);
+            if let Some(jobs) = helm_config.deps_jobs {
+                cmd.push_str(&format!(" --concurrency {}", jobs));
+            }
+            (helm_config.binary.clone(), cmd)
+        }
+        HelmMode::Helm => ("helm".to_string(), "helm dependency update".to_string()),
+    }
+}
+
+/// Delay before the first registry login retry, doubled after each failure
+const AUTH_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Modification times of Chart.yaml, Chart.lock and charts/ the outdated check depends on
+type DepsStamp = [Option<SystemTime>; 3];
+
+/// Outdated check results per project, reused while its stamp is unchanged
+type OutdatedCache = Mutex<HashMap<PathBuf, (DepsStamp, bool)>>;
+
+static OUTDATED_CACHE: OnceLock<OutdatedCache> = OnceLock::new();
+
+fn deps_stamp(project_path: &Path) -> DepsStamp {
+    ["Chart.yaml", "Chart.lock", "charts"].map(|name| {
+        fs::metadata(project_path.join(name))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    })
+}
+
 /// Check if Chart.yaml dependencies are outdated compared to Chart.lock
+/// The result is memoized for the process until one of the files changes
 fn chart_dependencies_outdated(project_path: &str, verbose: bool) -> Result<bool> {
+    cached_outdated(
+        OUTDATED_CACHE.get_or_init(Default::default),
+        project_path,
+        || parse_chart_dependencies_outdated(project_path, verbose),
+    )
+}
+
+/// Return the cached result for `project_path` if its stamp is unchanged, otherwise run `check`
+fn cached_outdated<F>(cache: &OutdatedCache, project_path: &str, check: F) -> Result<bool>
+where
+    F: FnOnce() -> Result<bool>,
+{
+    let key = fs::canonicalize(project_path).unwrap_or_else(|_| PathBuf::from(project_path));
+    let stamp = deps_stamp(&key);
+
+    if let Some((cached_stamp, outdated)) = cache.lock().unwrap().get(&key) {
+        if *cached_stamp == stamp {
+            return Ok(*outdated);
+        }
+    }
+
+    let outdated = check()?;
+    cache.lock().unwrap().insert(key, (stamp, outdated));
+    Ok(outdated)
+}
+
+/// Parse Chart.yaml and Chart.lock and compare their dependencies
+fn parse_chart_dependencies_outdated(project_path: &str, verbose: bool) -> Result<bool> {
     use yaml_rust2::YamlLoader;
 
     let chart_yaml_path = Path::new(project_path).join("Chart.yaml");
@@ -135,9 +223,8 @@ fn chart_dependencies_outdated(project_path: &str, verbose: bool) -> Result<bool
 
         if !lock_match {
             if verbose {
-                eprintln!(
-                    "{} Dependency mismatch: {} requires version {} but Chart.lock has different/missing version",
-                    "INFO:".cyan(),
+                log::info!(
+                    "Dependency mismatch: {} requires version {} but Chart.lock has different/missing version",
                     chart_name,
                     chart_version
                 );
@@ -149,11 +236,7 @@ fn chart_dependencies_outdated(project_path: &str, verbose: bool) -> Result<bool
         let chart_file = charts_dir.join(format!("{}-{}.tgz", chart_name, chart_version));
         if !chart_file.exists() {
             if verbose {
-                eprintln!(
-                    "{} Chart file missing: {}",
-                    "INFO:".cyan(),
-                    chart_file.display()
-                );
+                log::info!("Chart file missing: {}", chart_file.display());
             }
             return Ok(true);
         }
@@ -162,77 +245,222 @@ fn chart_dependencies_outdated(project_path: &str, verbose: bool) -> Result<bool
     Ok(false)
 }
 
+/// Absolute, shell-quoted paths of `files` resolved against the project directory
+fn resolve_values_files(project_path: &str, files: &[String]) -> Result<Vec<String>> {
+    files
+        .iter()
+        .map(|file| {
+            let path = Path::new(project_path).join(file);
+            let path = fs::canonicalize(&path).map_err(|_| {
+                anyhow::anyhow!("Values file {} not found in {}", file, project_path)
+            })?;
+            Ok(shell_quote(&path.to_string_lossy()))
+        })
+        .collect()
+}
+
+/// Whether `action` runs the dependency update check first
+fn checks_deps(action: &Action, skip_deps: bool) -> bool {
+    !skip_deps
+        && !matches!(
+            action,
+            Action::Deps | Action::Duplicate { .. } | Action::Show
+        )
+}
+
 pub fn get_command(
     action: &Action,
     project_path: &str,
     environment: &str,
     options: &[String],
-    verbose: bool,
     silent: bool,
+    action_options: &ActionOptions,
 ) -> Result<Option<String>> {
+    // Fail on a missing `--values` file before updating dependencies
+    let values = resolve_values_files(project_path, &action_options.values_files)?;
+
     // Auto-update helm dependencies if needed (except for Deps action which handles it explicitly)
-    if !matches!(action, Action::Deps | Action::Duplicate { .. }) {
-        helm_deps_update(project_path, environment, verbose, false, silent)?;
+    if checks_deps(action, action_options.skip_deps) {
+        helm_deps_update(project_path, environment, false, silent, action_options)?;
     }
 
+    match action {
+        Action::Deps => {
+            helm_deps_update(project_path, environment, true, false, action_options)?;
+            Ok(None)
+        }
+        Action::Duplicate { target_env } => {
+            // Perform the duplication using native Rust
+            duplicate_helm_env(project_path, environment, target_env)?;
+            Ok(None)
+        }
+        _ => {
+            let helm_config = Config::load_merged(project_path)?.helm;
+            let cmd = match helm_config.mode {
+                HelmMode::Helmfile => {
+                    build_helmfile_command(action, &helm_config, environment, &values, options)?
+                }
+                HelmMode::Helm => {
+                    let release = chart_name(project_path)?;
+                    build_helm_cli_command(
+                        action,
+                        &helm_config,
+                        &release,
+                        environment,
+                        &values,
+                        options,
+                    )?
+                }
+            };
+            Ok(Some(cmd))
+        }
+    }
+}
+
+/// Chart name from Chart.yaml, used as the release name in `helm` mode
+fn chart_name(project_path: &str) -> Result<String> {
+    use yaml_rust2::YamlLoader;
+
+    let chart_yaml_path = Path::new(project_path).join("Chart.yaml");
+    let content = fs::read_to_string(&chart_yaml_path)?;
+    let docs = YamlLoader::load_from_str(&content)?;
+
+    docs.first()
+        .and_then(|doc| doc["name"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("No chart name in {}", chart_yaml_path.display()))
+}
+
+/// Build the helm CLI command for an action on the plain chart in the project directory
+/// `extra_values` files are passed after the environment values so they take precedence
+fn build_helm_cli_command(
+    action: &Action,
+    helm_config: &HelmConfig,
+    release: &str,
+    environment: &str,
+    extra_values: &[String],
+    options: &[String],
+) -> Result<String> {
     let options_str = options.join(" ");
     let opts = if options_str.is_empty() {
         String::new()
     } else {
         format!(" {}", options_str)
     };
+    let namespace = &helm_config.namespace;
+    // Same layout the environments are discovered from: values/<env>/values.yaml
+    let mut values = format!(
+        "-f {}/{}/values.yaml",
+        env::env_dir_name(Technology::Helm),
+        environment
+    );
+    for file in extra_values {
+        values.push_str(&format!(" -f {}", file));
+    }
 
     let cmd = match action {
-        Action::Apply => {
-            format!("helmfile sync -e {} --skip-deps{}", environment, opts)
-        }
-        Action::Check | Action::Diff => {
-            format!("helmfile diff -e {} --skip-deps{}", environment, opts)
-        }
-        Action::Template => {
-            format!("helmfile template -e {} --skip-deps{}", environment, opts)
-        }
+        Action::Apply => format!(
+            "helm upgrade --install {} . {} -n {}{}",
+            release, values, namespace, opts
+        ),
+        Action::Check => format!("helm lint . {}{}", values, opts),
+        Action::Diff => format!(
+            "helm diff upgrade {} . {} -n {}{}",
+            release, values, namespace, opts
+        ),
+        Action::Template => format!(
+            "helm template {} . {} -n {}{}",
+            release, values, namespace, opts
+        ),
         Action::Delete | Action::Destroy | Action::Uninstall => {
-            format!("helmfile destroy -e {} --skip-deps{}", environment, opts)
-        }
-        Action::Deps => {
-            helm_deps_update(project_path, environment, verbose, true, false)?;
-            return Ok(None);
+            format!("helm uninstall {} -n {}{}", release, namespace, opts)
         }
-        Action::Duplicate { target_env } => {
-            // Perform the duplication using native Rust
-            duplicate_helm_env(project_path, environment, target_env)?;
-            return Ok(None);
+        Action::Show => format!("helm status {} -n {}{}", release, namespace, opts),
+        _ => {
+            anyhow::bail!("Action {:?} not implemented for helm", action);
         }
+    };
+
+    Ok(cmd)
+}
+
+/// Build the helmfile command for an action using the configured binary and env flag
+/// `extra_values` files are passed as `--state-values-file`, merged over the environment values
+fn build_helmfile_command(
+    action: &Action,
+    helm_config: &HelmConfig,
+    environment: &str,
+    extra_values: &[String],
+    options: &[String],
+) -> Result<String> {
+    let options_str = options.join(" ");
+    let opts = if options_str.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", options_str)
+    };
+
+    // Deployed state only, nothing to render or update
+    if matches!(action, Action::Show) {
+        return Ok(format!(
+            "{}{}",
+            helmfile_command(helm_config, "status", environment),
+            opts
+        ));
+    }
+
+    let subcommand = match action {
+        Action::Apply => "sync",
+        Action::Check | Action::Diff => "diff",
+        Action::Template => "template",
+        Action::Delete | Action::Destroy | Action::Uninstall => "destroy",
         _ => {
             anyhow::bail!("Action {:?} not implemented for helm", action);
         }
     };
 
-    Ok(Some(cmd))
+    let state_values: String = extra_values
+        .iter()
+        .map(|file| format!(" --state-values-file {}", file))
+        .collect();
+
+    Ok(format!(
+        "{}{} --skip-deps{}",
+        helmfile_command(helm_config, subcommand, environment),
+        state_values,
+        opts
+    ))
+}
+
+/// `<binary> <subcommand> <env_flag> <environment>`, e.g. `helmfile diff -e prod`
+fn helmfile_command(helm_config: &HelmConfig, subcommand: &str, environment: &str) -> String {
+    format!(
+        "{} {} {} {}",
+        helm_config.binary, subcommand, helm_config.env_flag, environment
+    )
 }
 
 /// Extract helm registries from Chart.yaml dependencies
-fn extract_helm_registries(project_path: &str) -> Result<HashSet<String>> {
+fn extract_helm_registries(project_path: &str) -> Result<BTreeSet<String>> {
     use yaml_rust2::YamlLoader;
 
     let chart_yaml_path = Path::new(project_path).join("Chart.yaml");
     let content = fs::read_to_string(chart_yaml_path)?;
 
     let docs = YamlLoader::load_from_str(&content)?;
-    let mut registries = HashSet::new();
+    let mut registries = BTreeSet::new();
 
     if let Some(doc) = docs.first() {
         if let Some(dependencies) = doc["dependencies"].as_vec() {
             for dep in dependencies {
-                if let Some(repo) = dep["repository"].as_str() {
-                    if repo.contains("docker.pkg.dev") {
-                        // Extract registry from oci://registry/path format
-                        if let Some(registry) = repo.strip_prefix("oci://") {
-                            if let Some(slash_pos) = registry.find('/') {
-                                registries.insert(registry[..slash_pos].to_string());
-                            }
-                        }
+                // Extract registry from oci://registry/path format
+                if let Some(registry) = dep["repository"]
+                    .as_str()
+                    .and_then(|repo| repo.strip_prefix("oci://"))
+                {
+                    let host = registry.split('/').next().unwrap_or(registry);
+                    if !host.is_empty() {
+                        registries.insert(host.to_string());
                     }
                 }
             }
@@ -242,6 +470,41 @@ fn extract_helm_registries(project_path: &str) -> Result<HashSet<String>> {
     Ok(registries)
 }
 
+/// Login commands used when no `[helm.registries]` entry matches, keyed by host pattern
+const DEFAULT_REGISTRY_LOGINS: &[(&str, &str)] = &[(
+    "*docker.pkg.dev",
+    "gcloud auth print-access-token | helm registry login -u oauth2accesstoken --password-stdin https://{registry}",
+)];
+
+/// Login command for `registry`, with `{registry}` substituted
+/// Configured entries win over the defaults, an exact host over a glob and a longer glob over a shorter one
+fn registry_login_command(
+    configured: &HashMap<String, HelmRegistry>,
+    registry: &str,
+) -> Option<String> {
+    let matches = |pattern: &str| {
+        pattern == registry
+            || globset::Glob::new(pattern)
+                .map(|glob| glob.compile_matcher().is_match(registry))
+                .unwrap_or(false)
+    };
+    let best = |candidates: Vec<(&str, &str)>| {
+        candidates
+            .into_iter()
+            .filter(|(pattern, _)| matches(pattern))
+            .max_by_key(|(pattern, _)| (*pattern == registry, pattern.len()))
+            .map(|(_, command)| command.replace("{registry}", registry))
+    };
+
+    best(
+        configured
+            .iter()
+            .map(|(pattern, auth)| (pattern.as_str(), auth.login_command.as_str()))
+            .collect(),
+    )
+    .or_else(|| best(DEFAULT_REGISTRY_LOGINS.to_vec()))
+}
+
 /// Duplicate helm environment configuration
 fn duplicate_helm_env(project_path: &str, source_env: &str, target_env: &str) -> Result<()> {
     let values_dir = Path::new(project_path).join("values");
@@ -276,3 +539,355 @@ fn duplicate_helm_env(project_path: &str, source_env: &str, target_env: &str) ->
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cached_outdated_parses_once_until_files_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+        fs::write(temp_dir.path().join("Chart.yaml"), "name: app\n").unwrap();
+        fs::write(temp_dir.path().join("Chart.lock"), "dependencies: []\n").unwrap();
+
+        let cache = OutdatedCache::default();
+        let parses = std::cell::Cell::new(0);
+        let check = || {
+            cached_outdated(&cache, project_path, || {
+                parses.set(parses.get() + 1);
+                Ok(true)
+            })
+            .unwrap()
+        };
+
+        assert!(check());
+        assert!(check());
+        assert_eq!(parses.get(), 1);
+
+        // A new Chart.lock mtime invalidates the cached result
+        let lock = fs::File::options()
+            .write(true)
+            .open(temp_dir.path().join("Chart.lock"))
+            .unwrap();
+        lock.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        assert!(check());
+        assert_eq!(parses.get(), 2);
+    }
+
+    #[test]
+    fn test_build_helm_cli_command() {
+        let helm_config = HelmConfig {
+            mode: HelmMode::Helm,
+            namespace: "web".to_string(),
+            ..HelmConfig::default()
+        };
+        let build = |action: &Action, options: &[String]| {
+            build_helm_cli_command(action, &helm_config, "my-chart", "prod", &[], options).unwrap()
+        };
+
+        assert_eq!(
+            build(&Action::Apply, &[]),
+            "helm upgrade --install my-chart . -f values/prod/values.yaml -n web"
+        );
+        assert_eq!(
+            build(&Action::Diff, &["--suppress-secrets".to_string()]),
+            "helm diff upgrade my-chart . -f values/prod/values.yaml -n web --suppress-secrets"
+        );
+        assert_eq!(
+            build(&Action::Check, &["--strict".to_string()]),
+            "helm lint . -f values/prod/values.yaml --strict"
+        );
+        assert_eq!(
+            build(&Action::Template, &[]),
+            "helm template my-chart . -f values/prod/values.yaml -n web"
+        );
+        assert_eq!(
+            build(&Action::Uninstall, &[]),
+            "helm uninstall my-chart -n web"
+        );
+        assert!(
+            build_helm_cli_command(&Action::List, &helm_config, "my-chart", "prod", &[], &[])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_helm_mode_get_command_uses_chart_name() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Chart.yaml"),
+            "apiVersion: v2\nname: api\nversion: 1.0.0\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join(".mk.toml"),
+            "[helm]\nmode = \"helm\"\n",
+        )
+        .unwrap();
+        // Chart.lock and charts/ present with no dependencies, so no update runs
+        fs::write(temp_dir.path().join("Chart.lock"), "dependencies: []\n").unwrap();
+        fs::create_dir(temp_dir.path().join("charts")).unwrap();
+
+        let cmd = get_command(
+            &Action::Apply,
+            temp_dir.path().to_str().unwrap(),
+            "dev",
+            &[],
+            true,
+            &ActionOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            cmd.as_deref(),
+            Some("helm upgrade --install api . -f values/dev/values.yaml -n default")
+        );
+    }
+
+    #[test]
+    fn test_checks_deps() {
+        assert!(checks_deps(&Action::Diff, false));
+        assert!(checks_deps(&Action::Apply, false));
+        assert!(!checks_deps(&Action::Diff, true));
+        assert!(!checks_deps(&Action::Template, true));
+        // Deps updates explicitly, Duplicate never needs charts
+        assert!(!checks_deps(&Action::Deps, false));
+        assert!(!checks_deps(
+            &Action::Duplicate {
+                target_env: "prod".to_string()
+            },
+            false
+        ));
+    }
+
+    #[test]
+    fn test_deps_command_bounds_downloads() {
+        let mut helm_config = HelmConfig::default();
+        assert_eq!(
+            deps_command(&helm_config, "dev"),
+            ("helmfile".to_string(), "helmfile deps -e dev".to_string())
+        );
+
+        helm_config.deps_jobs = Some(4);
+        assert_eq!(
+            deps_command(&helm_config, "dev").1,
+            "helmfile deps -e dev --concurrency 4"
+        );
+
+        helm_config.mode = HelmMode::Helm;
+        assert_eq!(
+            deps_command(&helm_config, "dev").1,
+            "helm dependency update"
+        );
+    }
+
+    #[test]
+    fn test_build_helmfile_command_defaults() {
+        let helm_config = HelmConfig::default();
+
+        assert_eq!(
+            build_helmfile_command(&Action::Apply, &helm_config, "prod", &[], &[]).unwrap(),
+            "helmfile sync -e prod --skip-deps"
+        );
+        assert_eq!(
+            build_helmfile_command(
+                &Action::Diff,
+                &helm_config,
+                "dev",
+                &[],
+                &["--context".to_string(), "3".to_string()]
+            )
+            .unwrap(),
+            "helmfile diff -e dev --skip-deps --context 3"
+        );
+        assert!(build_helmfile_command(&Action::List, &helm_config, "dev", &[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_check_is_lint_only_in_helm_mode() {
+        let helmfile_config = HelmConfig::default();
+        assert_eq!(
+            build_helmfile_command(&Action::Check, &helmfile_config, "dev", &[], &[]).unwrap(),
+            "helmfile diff -e dev --skip-deps"
+        );
+
+        let helm_config = HelmConfig {
+            mode: HelmMode::Helm,
+            ..HelmConfig::default()
+        };
+        assert_eq!(
+            build_helm_cli_command(&Action::Check, &helm_config, "app", "dev", &[], &[]).unwrap(),
+            "helm lint . -f values/dev/values.yaml"
+        );
+    }
+
+    #[test]
+    fn test_build_helmfile_command_configured_binary_and_flag() {
+        let helm_config = HelmConfig {
+            binary: "./bin/helmfile-wrapper".to_string(),
+            env_flag: "--environment".to_string(),
+            ..HelmConfig::default()
+        };
+
+        assert_eq!(
+            build_helmfile_command(&Action::Template, &helm_config, "staging", &[], &[]).unwrap(),
+            "./bin/helmfile-wrapper template --environment staging --skip-deps"
+        );
+        assert_eq!(
+            build_helmfile_command(&Action::Destroy, &helm_config, "staging", &[], &[]).unwrap(),
+            "./bin/helmfile-wrapper destroy --environment staging --skip-deps"
+        );
+        assert_eq!(
+            helmfile_command(&helm_config, "deps", "staging"),
+            "./bin/helmfile-wrapper deps --environment staging"
+        );
+    }
+
+    #[test]
+    fn test_show_builds_status_command() {
+        assert_eq!(
+            build_helmfile_command(&Action::Show, &HelmConfig::default(), "prod", &[], &[])
+                .unwrap(),
+            "helmfile status -e prod"
+        );
+
+        let helm_config = HelmConfig {
+            mode: HelmMode::Helm,
+            namespace: "web".to_string(),
+            ..HelmConfig::default()
+        };
+        assert_eq!(
+            build_helm_cli_command(&Action::Show, &helm_config, "my-chart", "prod", &[], &[])
+                .unwrap(),
+            "helm status my-chart -n web"
+        );
+        assert!(!checks_deps(&Action::Show, false));
+    }
+
+    #[test]
+    fn test_extra_values_follow_environment_values() {
+        let extra = ["/charts/api/overrides.yaml".to_string()];
+
+        assert_eq!(
+            build_helmfile_command(&Action::Apply, &HelmConfig::default(), "dev", &extra, &[])
+                .unwrap(),
+            "helmfile sync -e dev --state-values-file /charts/api/overrides.yaml --skip-deps"
+        );
+
+        let helm_config = HelmConfig {
+            mode: HelmMode::Helm,
+            ..HelmConfig::default()
+        };
+        assert_eq!(
+            build_helm_cli_command(&Action::Diff, &helm_config, "api", "dev", &extra, &[]).unwrap(),
+            "helm diff upgrade api . -f values/dev/values.yaml -f /charts/api/overrides.yaml -n default"
+        );
+    }
+
+    #[test]
+    fn test_resolve_values_files_relative_to_project() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("local.yaml"), "replicaCount: 2\n").unwrap();
+        let project_path = temp_dir.path().to_str().unwrap();
+
+        let resolved = resolve_values_files(project_path, &["local.yaml".to_string()]).unwrap();
+        let expected = fs::canonicalize(temp_dir.path().join("local.yaml")).unwrap();
+        assert_eq!(resolved, vec![expected.to_string_lossy().to_string()]);
+
+        let err = resolve_values_files(project_path, &["missing.yaml".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Values file missing.yaml not found in {}", project_path)
+        );
+    }
+
+    #[test]
+    fn test_extract_helm_registries_all_oci_hosts() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("Chart.yaml"),
+            r#"apiVersion: v2
+name: app
+version: 1.0.0
+dependencies:
+  - name: common
+    version: 1.0.0
+    repository: oci://europe-west1-docker.pkg.dev/project/charts
+  - name: api
+    version: 2.0.0
+    repository: oci://123456789.dkr.ecr.us-east-1.amazonaws.com/charts
+  - name: web
+    version: 3.0.0
+    repository: oci://ghcr.io/acme/charts
+  - name: other
+    version: 1.2.0
+    repository: oci://ghcr.io/acme/other
+  - name: redis
+    version: 18.0.0
+    repository: https://charts.bitnami.com/bitnami
+"#,
+        )
+        .unwrap();
+
+        let registries = extract_helm_registries(temp_dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(
+            registries.into_iter().collect::<Vec<_>>(),
+            vec![
+                "123456789.dkr.ecr.us-east-1.amazonaws.com",
+                "europe-west1-docker.pkg.dev",
+                "ghcr.io",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_registry_login_command_selection() {
+        let login = |command: &str| HelmRegistry {
+            login_command: command.to_string(),
+        };
+        let configured = HashMap::from([
+            (
+                "*.azurecr.io".to_string(),
+                login("az acr login --name {registry}"),
+            ),
+            ("*".to_string(), login("generic-login {registry}")),
+            ("ghcr.io".to_string(), login("gh-login {registry}")),
+        ]);
+
+        // Exact host wins over a glob, a longer glob over a shorter one
+        assert_eq!(
+            registry_login_command(&configured, "ghcr.io").as_deref(),
+            Some("gh-login ghcr.io")
+        );
+        assert_eq!(
+            registry_login_command(&configured, "acme.azurecr.io").as_deref(),
+            Some("az acr login --name acme.azurecr.io")
+        );
+        assert_eq!(
+            registry_login_command(&configured, "quay.io").as_deref(),
+            Some("generic-login quay.io")
+        );
+    }
+
+    #[test]
+    fn test_registry_login_command_defaults() {
+        let command =
+            registry_login_command(&HashMap::new(), "europe-west1-docker.pkg.dev").unwrap();
+        assert!(command.starts_with("gcloud auth print-access-token"));
+        assert!(command.ends_with("https://europe-west1-docker.pkg.dev"));
+        assert_eq!(registry_login_command(&HashMap::new(), "ghcr.io"), None);
+
+        // Configured entries override the GKE default
+        let configured = HashMap::from([(
+            "europe-west1-docker.pkg.dev".to_string(),
+            HelmRegistry {
+                login_command: "custom {registry}".to_string(),
+            },
+        )]);
+        assert_eq!(
+            registry_login_command(&configured, "europe-west1-docker.pkg.dev").as_deref(),
+            Some("custom europe-west1-docker.pkg.dev")
+        );
+    }
+}
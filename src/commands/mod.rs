@@ -3,8 +3,9 @@ pub mod helm;
 pub mod kustomize;
 pub mod terraform;
 
-use crate::techno::Technology;
+use crate::techno::{self, Technology};
 use anyhow::Result;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum Action {
@@ -17,11 +18,184 @@ pub enum Action {
     Uninstall,
     Deps,
     Template,
-    Output { key: Option<String> },
+    /// `jobs` queries run at once with `--all --parallel`
+    Output {
+        key: Option<String>,
+        jobs: Option<usize>,
+    },
     List,
-    Duplicate { target_env: String },
-    Unlock { lock_id: String },
+    Duplicate {
+        target_env: String,
+    },
+    Unlock {
+        lock_id: String,
+    },
     Show,
+    Import {
+        address: String,
+        id: String,
+    },
+    Replace {
+        address: String,
+        plan: bool,
+    },
+}
+
+impl Action {
+    /// One action of each kind, with empty arguments
+    pub fn all() -> Vec<Action> {
+        vec![
+            Action::Apply,
+            Action::Check,
+            Action::Diff,
+            Action::Plan,
+            Action::Delete,
+            Action::Destroy,
+            Action::Uninstall,
+            Action::Deps,
+            Action::Template,
+            Action::Output {
+                key: None,
+                jobs: None,
+            },
+            Action::List,
+            Action::Duplicate {
+                target_env: String::new(),
+            },
+            Action::Unlock {
+                lock_id: String::new(),
+            },
+            Action::Show,
+            Action::Import {
+                address: String::new(),
+                id: String::new(),
+            },
+            Action::Replace {
+                address: String::new(),
+                plan: false,
+            },
+        ]
+    }
+
+    /// Subcommand name of the action
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Apply => "apply",
+            Action::Check => "check",
+            Action::Diff => "diff",
+            Action::Plan => "plan",
+            Action::Delete => "delete",
+            Action::Destroy => "destroy",
+            Action::Uninstall => "uninstall",
+            Action::Deps => "deps",
+            Action::Template => "template",
+            Action::Output { .. } => "output",
+            Action::List => "list",
+            Action::Duplicate { .. } => "duplicate",
+            Action::Unlock { .. } => "unlock",
+            Action::Show => "show",
+            Action::Import { .. } => "import",
+            Action::Replace { .. } => "replace",
+        }
+    }
+}
+
+/// Global and subcommand flags shaping how an action runs
+#[derive(Debug, Clone, Default)]
+pub struct ActionOptions {
+    pub verbose: bool,
+    pub no_ignore: bool,
+    /// Print a `mk: <action> <technology> <environment> exit=<code>` line once done
+    pub report_exit: bool,
+    /// Receives the stdout of the action's last command instead of the terminal
+    pub output_file: Option<PathBuf>,
+    /// Also show the output written to `output_file` on the terminal
+    pub output_tee: bool,
+    /// Directory the action's commands run in instead of the detected project path
+    pub workdir: Option<String>,
+    /// Echo the resolved commands as one shell line before running them
+    pub print_command: bool,
+    /// Keep the technology detection quiet, for scripts that already know it
+    pub no_detect_log: bool,
+    /// Require the technology directly at the project path, never in its children
+    pub strict_path: bool,
+    /// Run deploying actions without the per-environment lock
+    pub no_lock: bool,
+    /// Don't check the required binaries are on PATH before running commands
+    pub no_preflight: bool,
+    /// kubectl context switched to instead of validating against the mappings
+    pub context: Option<String>,
+    /// Run terraform apply and destroy without the confirmation prompt
+    pub auto_approve: bool,
+    /// Plan with `-refresh-only`, to only compare the state with the infrastructure
+    pub refresh_only: bool,
+    /// Skip `tfswitch` and `terraform init` when `.terraform` matches the environment
+    pub no_init: bool,
+    /// Don't update the helm dependencies before helmfile commands
+    pub skip_deps: bool,
+    /// Extra helm values files, relative to the project, layered after the environment values
+    pub values_files: Vec<String>,
+    /// `--limit`, `--tags` and `--ansible-verbose` of ansible playbooks
+    pub playbook_filters: ansible::PlaybookFilters,
+}
+
+impl ActionOptions {
+    /// Working directory for action commands: `workdir` or the detected path
+    pub fn working_dir<'a>(&'a self, detected_path: &'a str) -> &'a str {
+        self.workdir.as_deref().unwrap_or(detected_path)
+    }
+
+    /// Flags of the technology detection
+    pub fn detect_flags(&self) -> techno::DetectFlags {
+        techno::DetectFlags {
+            no_detect_log: self.no_detect_log,
+            strict_path: self.strict_path,
+        }
+    }
+
+    /// `techno::detect_technology` of `project_path` with the detection flags
+    pub fn detect(
+        &self,
+        project_path: &str,
+        action: Option<&Action>,
+    ) -> Result<(Technology, String)> {
+        techno::detect_technology(project_path, action, false, self.detect_flags())
+    }
+}
+
+/// Shell builtins that can start a part of a generated command
+const SHELL_BUILTINS: [&str; 7] = ["cd", "test", "[", "true", "false", "echo", "export"];
+
+/// Binaries the generated `commands` run, checked on PATH before executing
+/// The first word of each part separated by `&&`, `||`, `|` or `;`, skipping variable
+/// assignments and shell builtins
+pub fn required_binaries(commands: &[String]) -> Vec<String> {
+    let mut binaries: Vec<String> = Vec::new();
+    for command in commands {
+        let mut at_start = true;
+        for word in command.split_whitespace() {
+            if matches!(word, "&&" | "||" | "|" | ";") {
+                at_start = true;
+                continue;
+            }
+            if !at_start || is_assignment(word) {
+                continue;
+            }
+            at_start = false;
+            if !SHELL_BUILTINS.contains(&word) && !binaries.iter().any(|b| b == word) {
+                binaries.push(word.to_string());
+            }
+        }
+    }
+    binaries
+}
+
+/// Whether `word` is a `NAME=value` variable assignment
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
 }
 
 /// Get the command(s) to execute based on the action, technology, and parameters
@@ -33,14 +207,22 @@ pub fn get_command(
     environment: &str,
     techno: Technology,
     options: &[String],
-    verbose: bool,
     silent: bool,
+    action_options: &ActionOptions,
 ) -> Result<Vec<String>> {
     match techno {
-        Technology::Terraform => terraform::get_command(action, project_path, environment, options),
+        Technology::Terraform => {
+            terraform::get_command(action, project_path, environment, options, action_options)
+        }
         Technology::Helm => {
-            let cmd =
-                helm::get_command(action, project_path, environment, options, verbose, silent)?;
+            let cmd = helm::get_command(
+                action,
+                project_path,
+                environment,
+                options,
+                silent,
+                action_options,
+            )?;
             Ok(cmd.map(|c| vec![c]).unwrap_or_else(Vec::new))
         }
         Technology::Kustomize => {
@@ -48,8 +230,52 @@ pub fn get_command(
             Ok(vec![cmd])
         }
         Technology::Ansible => {
-            let cmd = ansible::get_command(action, project_path, environment, options)?;
+            let cmd = ansible::get_command(
+                action,
+                project_path,
+                environment,
+                options,
+                &action_options.playbook_filters,
+            )?;
             Ok(vec![cmd])
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(commands: &[&str]) -> Vec<String> {
+        commands.iter().map(|cmd| cmd.to_string()).collect()
+    }
+
+    #[test]
+    fn test_working_dir_prefers_workdir() {
+        let mut action_options = ActionOptions::default();
+        assert_eq!(action_options.working_dir("infra/app"), "infra/app");
+
+        action_options.workdir = Some("/tmp/run".to_string());
+        assert_eq!(action_options.working_dir("infra/app"), "/tmp/run");
+    }
+
+    #[test]
+    fn test_required_binaries_from_commands() {
+        let terraform = commands(&[
+            "tfswitch",
+            "terraform init -backend-config=backend-vars/dev.tfvars",
+            "terraform plan -var-file=tfvars/dev.tfvars",
+        ]);
+        assert_eq!(required_binaries(&terraform), ["tfswitch", "terraform"]);
+
+        // tfswitch dropped (already satisfied, or --no-init) is not required
+        assert_eq!(required_binaries(&terraform[1..]), ["terraform"]);
+
+        let kustomize =
+            commands(&["kustomize build overlays/dev | kubectl diff -f - || test $? -eq 1"]);
+        assert_eq!(required_binaries(&kustomize), ["kustomize", "kubectl"]);
+
+        let helm = commands(&["cd charts && HELM_DEBUG=1 ./bin/helmfile -e dev apply"]);
+        assert_eq!(required_binaries(&helm), ["./bin/helmfile"]);
+    }
+}
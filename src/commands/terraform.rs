@@ -1,23 +1,108 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::{Map, Value};
 use std::fs;
+use std::io::BufRead;
 use std::path::Path;
 
-use super::Action;
+use super::{Action, ActionOptions};
+use crate::config::Config;
+use crate::env;
+use crate::executor::{execute_command_output, shell_quote};
+use crate::log;
+
+/// `options` plus `-auto-approve` for apply/destroy when `auto_approve` is set,
+/// unless the options already have it
+fn with_auto_approve(action: &Action, options: &[String], auto_approve: bool) -> Vec<String> {
+    let mut options = options.to_vec();
+    let prompts = matches!(
+        action,
+        Action::Apply
+            | Action::Delete
+            | Action::Destroy
+            | Action::Uninstall
+            | Action::Replace { plan: false, .. }
+    );
+    if auto_approve && prompts && !options.iter().any(|opt| opt == "-auto-approve") {
+        options.push("-auto-approve".to_string());
+    }
+    options
+}
+
+/// `options` plus `-refresh-only` for plan when `refresh_only` is set, unless already there
+/// It composes with `-detailed-exitcode`: exit 2 then means the state drifted
+fn with_refresh_only(action: &Action, options: &[String], refresh_only: bool) -> Vec<String> {
+    let mut options = options.to_vec();
+    let plans = matches!(action, Action::Check | Action::Plan | Action::Diff);
+    if refresh_only && plans && !options.iter().any(|opt| opt == "-refresh-only") {
+        options.insert(0, "-refresh-only".to_string());
+    }
+    options
+}
+
+/// Whether the commands for `environment` start with `tfswitch` and `terraform init`
+/// With `no_init` (`--no-init`), init still runs unless `.terraform` was initialized
+/// with the environment's backend vars
+fn needs_init(project_path: &str, backend_dir: &str, environment: &str, no_init: bool) -> bool {
+    if !no_init {
+        return true;
+    }
+    let backend_file = format!("{}/{}.tfvars", backend_dir, environment);
+    match backend_mismatch(Path::new(project_path), &backend_file) {
+        Some(reason) => {
+            log::warning!("--no-init ignored: {}", reason);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Why the backend recorded in `.terraform/terraform.tfstate` is not the one configured
+/// by `backend_file` (relative to `project`), `None` when they match
+fn backend_mismatch(project: &Path, backend_file: &str) -> Option<String> {
+    let Ok(state) = fs::read_to_string(project.join(".terraform/terraform.tfstate")) else {
+        return Some("the directory was never initialized".to_string());
+    };
+    let state: Value = serde_json::from_str(&state).unwrap_or_default();
+    let config = &state["backend"]["config"];
+
+    let vars = fs::read_to_string(project.join(backend_file)).ok()?;
+    let assignment = Regex::new(r#"^\s*([A-Za-z0-9_]+)\s*=\s*(.*?)\s*$"#).unwrap();
+    for caps in vars.lines().filter_map(|line| assignment.captures(line)) {
+        let (key, expected) = (&caps[1], caps[2].trim_matches('"'));
+        let initialized = match &config[key] {
+            Value::String(value) => value.clone(),
+            Value::Null => String::new(),
+            value => value.to_string(),
+        };
+        if initialized != expected {
+            return Some(format!(
+                "{} is {:?} in .terraform but {:?} in {}",
+                key, initialized, expected, backend_file
+            ));
+        }
+    }
+    None
+}
+
+/// Split `mk output --all` commands into the serial setup and the independent
+/// `terraform output <key>` queries following it
+pub fn split_output_queries(commands: &[String]) -> (&[String], &[String]) {
+    let first_query = commands
+        .iter()
+        .position(|cmd| cmd.starts_with("terraform output "))
+        .unwrap_or(commands.len());
+    commands.split_at(first_query)
+}
 
 pub fn get_command(
     action: &Action,
     project_path: &str,
     environment: &str,
     options: &[String],
+    action_options: &ActionOptions,
 ) -> Result<Vec<String>> {
-    let path = Path::new(project_path);
-
-    // Determine backend directory name (backend-vars or backend_vars)
-    let backend_dir = if path.join("backend-vars").exists() {
-        "backend-vars"
-    } else {
-        "backend_vars"
-    };
+    let backend_dir = backend_dir(project_path);
 
     match action {
         Action::Duplicate { target_env } => {
@@ -25,48 +110,337 @@ pub fn get_command(
             duplicate_terraform_env(project_path, environment, target_env)?;
             Ok(vec![]) // No commands to execute
         }
-        Action::Output { key: None } => {
+        Action::Output { key: None, .. } => {
             // For --all flag, get all output keys and create individual commands
             let output_keys = get_output_keys(project_path)?;
-            let mut commands = vec![
-                "tfswitch".to_string(),
-                format!(
-                    "terraform init -reconfigure -backend-config={}/{}.tfvars",
-                    backend_dir, environment
-                ),
-            ];
+            let init = needs_init(
+                project_path,
+                backend_dir,
+                environment,
+                action_options.no_init,
+            );
+            let mut commands = init_commands(backend_dir, environment, init);
 
             // Add a terraform output command for each key
             for key in output_keys {
                 commands.push(format!("terraform output {}", key));
             }
 
-            Ok(commands)
+            Ok(skip_satisfied_tfswitch(commands, project_path))
         }
-        _ => Ok(build_terraform_commands(
-            action,
-            backend_dir,
-            environment,
-            options,
+        _ => Ok(skip_satisfied_tfswitch(
+            build_terraform_commands(
+                action,
+                backend_dir,
+                environment,
+                &var_files(
+                    project_path,
+                    environment,
+                    &Config::load_merged(project_path)?.terraform.common_var_file,
+                ),
+                &with_refresh_only(
+                    action,
+                    &with_auto_approve(action, options, action_options.auto_approve),
+                    action_options.refresh_only,
+                ),
+                needs_init(
+                    project_path,
+                    backend_dir,
+                    environment,
+                    action_options.no_init,
+                ),
+            ),
+            project_path,
         )),
     }
 }
 
-/// Build the sequence of terraform commands for a given action
-fn build_terraform_commands(
-    action: &Action,
-    backend_dir: &str,
+/// Plan file given to `terraform plan` with `-out=<file>` or `-out <file>`
+pub fn plan_out_file(options: &[String]) -> Option<&str> {
+    options.iter().enumerate().find_map(|(i, opt)| {
+        if opt == "-out" {
+            options.get(i + 1).map(String::as_str)
+        } else {
+            opt.strip_prefix("-out=")
+        }
+    })
+}
+
+/// Check `lock_id` looks like a state lock ID: a UUID (S3, azurerm, ...) or a number (GCS generation)
+pub fn validate_lock_id(lock_id: &str) -> Result<()> {
+    let is_uuid = {
+        let groups: Vec<&str> = lock_id.split('-').collect();
+        groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+            && groups
+                .iter()
+                .all(|g| g.chars().all(|c| c.is_ascii_hexdigit()))
+    };
+    let is_number = !lock_id.is_empty() && lock_id.chars().all(|c| c.is_ascii_digit());
+
+    if !is_uuid && !is_number {
+        anyhow::bail!(
+            "Invalid lock ID {}: expected a UUID (e.g. 2b6a6738-5dd5-6d1a-8f29-d5e8cbc2f2f4) or a numeric ID",
+            lock_id
+        );
+    }
+    Ok(())
+}
+
+/// Check `address` is a resource address such as `aws_instance.web` or
+/// `module.app.aws_instance.web["blue"]`
+pub fn validate_resource_address(address: &str) -> Result<()> {
+    let re = Regex::new(
+        r"^(module\.[A-Za-z_][\w-]*(\[[^\]]+\])?\.)*(data\.)?(?P<type>[A-Za-z_][\w-]*)\.[A-Za-z_][\w-]*(\[[^\]]+\])?$",
+    )
+    .unwrap();
+    // `module.app` alone is a module, not a resource
+    let is_resource = re
+        .captures(address)
+        .is_some_and(|caps| &caps["type"] != "module");
+    if !is_resource {
+        anyhow::bail!(
+            "Invalid resource address {}: expected e.g. aws_instance.web or module.app.aws_instance.web[0]",
+            address
+        );
+    }
+    Ok(())
+}
+
+/// Ask before force-unlocking, reading the answer from `input`
+pub fn confirm_unlock(
+    lock_id: &str,
     environment: &str,
-    options: &[String],
-) -> Vec<String> {
-    // Common setup commands that all terraform operations need
-    let mut commands = vec![
+    input: &mut impl BufRead,
+    is_terminal: bool,
+) -> Result<()> {
+    let prompt = format!("Force-unlock state lock {} for {}?", lock_id, environment);
+    if !log::confirm(
+        &format!("force-unlock {}", lock_id),
+        &prompt,
+        input,
+        is_terminal,
+    )? {
+        anyhow::bail!("Unlock aborted");
+    }
+    Ok(())
+}
+
+/// Backend vars directory name: `backend-vars` if present, otherwise `backend_vars`
+fn backend_dir(project_path: &str) -> &'static str {
+    if Path::new(project_path).join("backend-vars").exists() {
+        "backend-vars"
+    } else {
+        "backend_vars"
+    }
+}
+
+/// Setup commands every terraform operation needs, none when `init` is false
+fn init_commands(backend_dir: &str, environment: &str, init: bool) -> Vec<String> {
+    if !init {
+        return vec![];
+    }
+    vec![
         "tfswitch".to_string(),
         format!(
             "terraform init -reconfigure -backend-config={}/{}.tfvars",
             backend_dir, environment
         ),
-    ];
+    ]
+}
+
+/// Terraform version the project asks for: the content of `.terraform-version`, else the
+/// first `required_version` constraint of its .tf files
+pub fn required_terraform_version(project_path: &str) -> Option<String> {
+    let project = Path::new(project_path);
+    if let Ok(content) = fs::read_to_string(project.join(".terraform-version")) {
+        let version = content.trim();
+        if !version.is_empty() {
+            return Some(version.to_string());
+        }
+    }
+
+    let required_version = Regex::new(r#"(?m)^\s*required_version\s*=\s*"([^"]+)""#).unwrap();
+    let mut tf_files: Vec<_> = fs::read_dir(project)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "tf"))
+        .collect();
+    tf_files.sort();
+    tf_files.iter().find_map(|path| {
+        let content = fs::read_to_string(path).ok()?;
+        let captures = required_version.captures(&content)?;
+        Some(captures[1].trim().to_string())
+    })
+}
+
+/// Whether `version` satisfies a terraform version constraint such as `1.5.7`,
+/// `>= 1.3, < 2.0` or `~> 1.5`
+/// Anything else (`latest`, pre-releases, ...) is never satisfied
+pub fn version_satisfies(version: &semver::Version, constraint: &str) -> bool {
+    use semver::Version;
+
+    constraint.split(',').all(|part| {
+        let part = part.trim();
+        let (op, bound) = ["~>", ">=", "<=", "!=", ">", "<", "="]
+            .iter()
+            .find_map(|op| part.strip_prefix(op).map(|rest| (*op, rest.trim())))
+            .unwrap_or(("=", part));
+        let Some(segments) = bound
+            .split('.')
+            .map(|segment| segment.parse::<u64>().ok())
+            .collect::<Option<Vec<u64>>>()
+            .filter(|segments| (1..=3).contains(&segments.len()))
+        else {
+            return false;
+        };
+        let bound = Version::new(
+            segments[0],
+            segments.get(1).copied().unwrap_or(0),
+            segments.get(2).copied().unwrap_or(0),
+        );
+
+        match op {
+            "~>" => {
+                // Only the rightmost given segment may grow: ~> 1.5 is < 2.0, ~> 1.5.2 is < 1.6
+                let upper = if segments.len() == 3 {
+                    Version::new(bound.major, bound.minor + 1, 0)
+                } else {
+                    Version::new(bound.major + 1, 0, 0)
+                };
+                *version >= bound && *version < upper
+            }
+            ">=" => *version >= bound,
+            "<=" => *version <= bound,
+            "!=" => *version != bound,
+            ">" => *version > bound,
+            "<" => *version < bound,
+            _ => *version == bound,
+        }
+    })
+}
+
+/// Whether the `tfswitch` step is needed: unless the active terraform is known to satisfy
+/// the project's required version
+pub fn tfswitch_needed(required: Option<&str>, active: Option<&semver::Version>) -> bool {
+    match (required, active) {
+        (Some(required), Some(active)) => !version_satisfies(active, required),
+        _ => true,
+    }
+}
+
+/// Version of the terraform on PATH, from `terraform version -json`
+fn active_terraform_version(project_path: &str) -> Option<semver::Version> {
+    let output = execute_command_output("terraform version -json", project_path, false).ok()?;
+    let version: Value = serde_json::from_str(&output).ok()?;
+    semver::Version::parse(version.get("terraform_version")?.as_str()?).ok()
+}
+
+/// `commands` without the leading `tfswitch` step when the active terraform already
+/// satisfies the project's required version
+fn skip_satisfied_tfswitch(mut commands: Vec<String>, project_path: &str) -> Vec<String> {
+    if commands.first().is_none_or(|cmd| cmd != "tfswitch") {
+        return commands;
+    }
+    // Only ask terraform for its version when there is something to compare it with
+    let Some(required) = required_terraform_version(project_path) else {
+        return commands;
+    };
+    let active = active_terraform_version(project_path);
+    if !tfswitch_needed(Some(&required), active.as_ref()) {
+        if let Some(active) = active {
+            log::info!(
+                "terraform {} already satisfies {}, skipping tfswitch",
+                active,
+                required
+            );
+        }
+        commands.remove(0);
+    }
+    commands
+}
+
+/// Commands printing every output of the environment as one JSON object
+/// `no_init` as with `--no-init`
+pub fn output_json_commands(project_path: &str, environment: &str, no_init: bool) -> Vec<String> {
+    let backend_dir = backend_dir(project_path);
+    let init = needs_init(project_path, backend_dir, environment, no_init);
+    let mut commands = init_commands(backend_dir, environment, init);
+    commands.push("terraform output -json".to_string());
+    skip_satisfied_tfswitch(commands, project_path)
+}
+
+/// Output values from `terraform output -json`, keyed by name
+/// Only `key` is kept when given, and it must exist
+pub fn parse_output_values(json: &str, key: Option<&str>) -> Result<Map<String, Value>> {
+    let outputs: Map<String, Value> =
+        serde_json::from_str(json).context("Invalid `terraform output -json` output")?;
+    let values = outputs
+        .into_iter()
+        .filter(|(name, _)| key.is_none_or(|key| key == name))
+        .map(|(name, output)| (name, output.get("value").cloned().unwrap_or(Value::Null)))
+        .collect::<Map<String, Value>>();
+
+    if let Some(key) = key {
+        if values.is_empty() {
+            anyhow::bail!("Output {} not found", key);
+        }
+    }
+    Ok(values)
+}
+
+/// Var files of an environment, relative to the project: `tfvars/<common_var_file>` when
+/// present, then the environment's own files
+pub fn var_files(project_path: &str, environment: &str, common_var_file: &str) -> Vec<String> {
+    let common = format!("tfvars/{}", common_var_file);
+    let mut files = Vec::new();
+    if Path::new(project_path).join(&common).is_file() {
+        files.push(common);
+    }
+    files.extend(env_var_files(project_path, environment));
+    files
+}
+
+/// Own var files of an environment: `tfvars/<env>.tfvars`, or when it is absent and an
+/// `env/<env>/` directory exists, every `*.tfvars` in that directory, sorted
+fn env_var_files(project_path: &str, environment: &str) -> Vec<String> {
+    let default = vec![format!("tfvars/{}.tfvars", environment)];
+    let project = Path::new(project_path);
+    if project.join(&default[0]).exists() {
+        return default;
+    }
+
+    let files: Vec<String> = env::tfvars_files(&project.join("env").join(environment))
+        .into_iter()
+        .map(|name| format!("env/{}/{}", environment, name))
+        .collect();
+
+    if files.is_empty() {
+        default
+    } else {
+        files
+    }
+}
+
+/// Build the sequence of terraform commands for a given action, passing each of `var_files`
+/// as a `-var-file` argument
+/// Without `init`, only the operation itself is returned
+fn build_terraform_commands(
+    action: &Action,
+    backend_dir: &str,
+    environment: &str,
+    var_files: &[String],
+    options: &[String],
+    init: bool,
+) -> Vec<String> {
+    // Common setup commands that all terraform operations need, unless `--no-init`
+    let mut commands = init_commands(backend_dir, environment, init);
+
+    let var_file_args = var_files
+        .iter()
+        .map(|file| format!("-var-file={}", shell_quote(file)))
+        .collect::<Vec<_>>()
+        .join(" ");
 
     // Build options string
     let opts = if options.is_empty() {
@@ -78,18 +452,17 @@ fn build_terraform_commands(
     // Build the terraform operation command based on action
     let operation = match action {
         Action::Apply => format!(
-            "terraform apply -lock-timeout=60s -var-file=tfvars/{}.tfvars{}",
-            environment, opts
-        ),
-        Action::Check | Action::Plan | Action::Diff => format!(
-            "terraform plan -lock-timeout=60s -var-file=tfvars/{}.tfvars{}",
-            environment, opts
+            "terraform apply -lock-timeout=60s {}{}",
+            var_file_args, opts
         ),
+        Action::Check | Action::Plan | Action::Diff => {
+            format!("terraform plan -lock-timeout=60s {}{}", var_file_args, opts)
+        }
         Action::Delete | Action::Destroy | Action::Uninstall => format!(
-            "terraform destroy -lock-timeout=60s -var-file=tfvars/{}.tfvars{}",
-            environment, opts
+            "terraform destroy -lock-timeout=60s {}{}",
+            var_file_args, opts
         ),
-        Action::Output { key } => {
+        Action::Output { key, .. } => {
             // When key is provided, output that specific key
             // When key is None (--all flag), we'll handle it separately
             // to call terraform output for each key individually
@@ -102,6 +475,20 @@ fn build_terraform_commands(
         }
         Action::Unlock { lock_id } => format!("terraform force-unlock -force {}", lock_id),
         Action::Show => "terraform show".to_string(),
+        Action::Replace { address, plan } => format!(
+            "terraform {} -lock-timeout=60s {} -replace={}{}",
+            if *plan { "plan" } else { "apply" },
+            var_file_args,
+            shell_quote(address),
+            opts
+        ),
+        Action::Import { address, id } => format!(
+            "terraform import {}{} {} {}",
+            var_file_args,
+            opts,
+            shell_quote(address),
+            shell_quote(id)
+        ),
         _ => {
             // For unsupported actions, return just the init commands
             return commands;
@@ -115,10 +502,8 @@ fn build_terraform_commands(
 /// Get all output keys from terraform files in the project
 fn get_output_keys(project_path: &str) -> Result<Vec<String>> {
     use ignore::WalkBuilder;
-    use regex::Regex;
     use std::collections::HashSet;
 
-    let output_regex = Regex::new(r#"^output\s+"([^"]+)"\s+\{"#)?;
     let mut output_keys = HashSet::new();
 
     // Walk through the project directory looking for .tf files
@@ -131,13 +516,7 @@ fn get_output_keys(project_path: &str) -> Result<Vec<String>> {
             if let Some(ext) = entry.path().extension() {
                 if ext == "tf" {
                     if let Ok(content) = fs::read_to_string(entry.path()) {
-                        for line in content.lines() {
-                            if let Some(captures) = output_regex.captures(line) {
-                                if let Some(key) = captures.get(1) {
-                                    output_keys.insert(key.as_str().to_string());
-                                }
-                            }
-                        }
+                        output_keys.extend(parse_output_keys(&content));
                     }
                 }
             }
@@ -150,6 +529,30 @@ fn get_output_keys(project_path: &str) -> Result<Vec<String>> {
     Ok(keys)
 }
 
+/// Names of the `output "<name>" {` blocks declared in a .tf file
+/// The brace may follow on the next line, outputs in `#`, `//` or `/* */` comments are ignored
+pub fn parse_output_keys(content: &str) -> Vec<String> {
+    use regex::Regex;
+
+    // Strings are matched first so a `/*`, `#` or `//` inside them starts no comment
+    let string_or_comment =
+        Regex::new(r#"(?s)"(?:[^"\\\n]|\\.)*"|/\*.*?\*/|#[^\n]*|//[^\n]*"#).unwrap();
+    // Anchored at the line start, so `output` inside an expression never matches
+    let output_block = Regex::new(r#"(?m)^[ \t]*output[ \t]*"([^"]+)"\s*\{"#).unwrap();
+
+    let content = string_or_comment.replace_all(content, |caps: &regex::Captures| {
+        if caps[0].starts_with('"') {
+            caps[0].to_string()
+        } else {
+            String::new()
+        }
+    });
+    output_block
+        .captures_iter(&content)
+        .map(|captures| captures[1].to_string())
+        .collect()
+}
+
 /// Duplicate terraform environment configuration
 fn duplicate_terraform_env(project_path: &str, source_env: &str, target_env: &str) -> Result<()> {
     let path = Path::new(project_path);
@@ -185,6 +588,16 @@ fn duplicate_terraform_env(project_path: &str, source_env: &str, target_env: &st
         fs::write(&tfvars_target, updated_content)?;
     }
 
+    // Copy the var files of an env/<env>/ environment
+    let env_source = path.join("env").join(source_env);
+    let env_target = path.join("env").join(target_env);
+    for file_name in env::tfvars_files(&env_source) {
+        fs::create_dir_all(&env_target)?;
+        let content = fs::read_to_string(env_source.join(&file_name))?;
+        let updated_content = content.replace(source_env, target_env);
+        fs::write(env_target.join(&file_name), updated_content)?;
+    }
+
     // Walk through project directory and update any .tfvars files with target_env name
     for entry in ignore::WalkBuilder::new(path)
         .build()
@@ -208,13 +621,251 @@ fn duplicate_terraform_env(project_path: &str, source_env: &str, target_env: &st
 mod tests {
     use super::*;
 
+    fn tfvars(environment: &str) -> Vec<String> {
+        vec![format!("tfvars/{}.tfvars", environment)]
+    }
+
+    #[test]
+    fn test_parse_output_keys_formatting_variations() {
+        let content = r#"
+output "vpc_id" {
+  value = aws_vpc.main.id
+}
+
+output "subnet_ids"{
+  value = aws_subnet.private[*].id
+}
+
+output "cluster_name"
+{
+  value = var.cluster_name
+}
+
+  output   "indented" {
+  value = 1
+}
+"#;
+        assert_eq!(
+            parse_output_keys(content),
+            ["vpc_id", "subnet_ids", "cluster_name", "indented"]
+        );
+    }
+
+    #[test]
+    fn test_parse_output_keys_ignores_commented_outputs() {
+        let content = r#"
+# output "hash_comment" {
+#   value = 1
+# }
+
+// output "slash_comment" {
+// }
+
+/*
+output "block_comment" {
+  value = 1
+}
+*/
+
+output "kept" {
+  value = 1 /* inline */
+}
+"#;
+        assert_eq!(parse_output_keys(content), ["kept"]);
+    }
+
+    #[test]
+    fn test_parse_output_keys_comment_markers_in_strings() {
+        let content = r#"
+output "glob" {
+  value = "logs/*"
+}
+
+output "after_glob" {
+  value = "*/ # // not comments"
+}
+
+output "url" {
+  value = "https://example.com/*"
+}
+"#;
+        assert_eq!(parse_output_keys(content), ["glob", "after_glob", "url"]);
+    }
+
+    #[test]
+    fn test_backend_mismatch() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = temp_dir.path();
+        fs::create_dir(project.join("backend-vars")).unwrap();
+        fs::write(
+            project.join("backend-vars/dev.tfvars"),
+            "bucket = \"states\"\nkey    = \"dev.tfstate\"\nencrypt = true\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            backend_mismatch(project, "backend-vars/dev.tfvars").as_deref(),
+            Some("the directory was never initialized")
+        );
+
+        fs::create_dir(project.join(".terraform")).unwrap();
+        let state = |config: &str| {
+            fs::write(
+                project.join(".terraform/terraform.tfstate"),
+                format!(r#"{{"backend": {{"type": "s3", "config": {}}}}}"#, config),
+            )
+            .unwrap();
+        };
+        state(r#"{"bucket": "states", "key": "dev.tfstate", "encrypt": true, "region": null}"#);
+        assert_eq!(backend_mismatch(project, "backend-vars/dev.tfvars"), None);
+
+        state(r#"{"bucket": "states", "key": "prod.tfstate", "encrypt": true}"#);
+        assert_eq!(
+            backend_mismatch(project, "backend-vars/dev.tfvars").as_deref(),
+            Some(
+                r#"key is "prod.tfstate" in .terraform but "dev.tfstate" in backend-vars/dev.tfvars"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_validate_lock_id() {
+        assert!(validate_lock_id("2b6a6738-5dd5-6d1a-8f29-d5e8cbc2f2f4").is_ok());
+        assert!(validate_lock_id("2B6A6738-5DD5-6D1A-8F29-D5E8CBC2F2F4").is_ok());
+        assert!(validate_lock_id("196787809097").is_ok());
+
+        for malformed in [
+            "",
+            "test-lock-id",
+            "2b6a6738-5dd5-6d1a-8f29",
+            "2b6a6738-5dd5-6d1a-8f29-d5e8cbc2f2f4-00",
+            "2b6a6738-5dd5-6d1a-8f29-d5e8cbc2f2fz",
+            "1967 87809097",
+        ] {
+            let err = validate_lock_id(malformed).unwrap_err();
+            assert!(
+                err.to_string().starts_with("Invalid lock ID"),
+                "{}",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn test_confirm_unlock_refused_without_terminal() {
+        let err = confirm_unlock("196787809097", "dev", &mut "y\n".as_bytes(), false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Refusing to force-unlock 196787809097 without confirmation: stdin is not a terminal (use --force)"
+        );
+    }
+
+    #[test]
+    fn test_confirm_unlock_answers() {
+        assert!(confirm_unlock("196787809097", "dev", &mut "yes\n".as_bytes(), true).is_ok());
+
+        for answer in ["\n", "n\n", "maybe\n", ""] {
+            let err =
+                confirm_unlock("196787809097", "dev", &mut answer.as_bytes(), true).unwrap_err();
+            assert_eq!(err.to_string(), "Unlock aborted");
+        }
+    }
+
+    #[test]
+    fn test_parse_output_values() {
+        let json = r#"{
+            "bucket": {"sensitive": false, "type": "string", "value": "logs-dev"},
+            "replicas": {"sensitive": false, "type": "number", "value": 3}
+        }"#;
+
+        let values = parse_output_values(json, None).unwrap();
+        assert_eq!(values["bucket"], "logs-dev");
+        assert_eq!(values["replicas"], 3);
+
+        let values = parse_output_values(json, Some("replicas")).unwrap();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["replicas"], 3);
+
+        let err = parse_output_values(json, Some("missing")).unwrap_err();
+        assert_eq!(err.to_string(), "Output missing not found");
+    }
+
+    #[test]
+    fn test_with_auto_approve_apply_and_destroy() {
+        let options = ["-compact-warnings".to_string()];
+        assert_eq!(
+            with_auto_approve(&Action::Apply, &options, true),
+            ["-compact-warnings", "-auto-approve"]
+        );
+        assert_eq!(
+            with_auto_approve(&Action::Destroy, &[], true),
+            ["-auto-approve"]
+        );
+        assert_eq!(with_auto_approve(&Action::Apply, &options, false), options);
+        // Plan never prompts
+        assert!(with_auto_approve(&Action::Plan, &[], true).is_empty());
+    }
+
+    #[test]
+    fn test_with_refresh_only_plan() {
+        let options = ["-detailed-exitcode".to_string(), "-input=false".to_string()];
+        let options = with_refresh_only(&Action::Plan, &options, true);
+        assert_eq!(
+            options,
+            ["-refresh-only", "-detailed-exitcode", "-input=false"]
+        );
+
+        let commands = build_terraform_commands(
+            &Action::Plan,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &options,
+            true,
+        );
+        assert_eq!(
+            commands[2],
+            "terraform plan -lock-timeout=60s -var-file=tfvars/dev.tfvars -refresh-only -detailed-exitcode -input=false"
+        );
+
+        // Not added twice, and only to plans
+        assert_eq!(
+            with_refresh_only(&Action::Check, &["-refresh-only".to_string()], true),
+            ["-refresh-only"]
+        );
+        assert!(with_refresh_only(&Action::Apply, &[], true).is_empty());
+        assert!(with_refresh_only(&Action::Plan, &[], false).is_empty());
+    }
+
+    #[test]
+    fn test_with_auto_approve_not_appended_twice() {
+        let options = ["-auto-approve".to_string(), "-parallelism=4".to_string()];
+        let options = with_auto_approve(&Action::Apply, &options, true);
+        assert_eq!(options, ["-auto-approve", "-parallelism=4"]);
+
+        let commands = build_terraform_commands(
+            &Action::Apply,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &options,
+            true,
+        );
+        assert_eq!(
+            commands[2],
+            "terraform apply -lock-timeout=60s -var-file=tfvars/dev.tfvars -auto-approve -parallelism=4"
+        );
+    }
+
     #[test]
     fn test_build_terraform_commands_apply() {
         let commands = build_terraform_commands(
             &Action::Apply,
             "backend-vars",
             "dev",
+            &tfvars("dev"),
             &["-auto-approve".to_string()],
+            true,
         );
 
         assert_eq!(commands.len(), 3);
@@ -231,7 +882,14 @@ mod tests {
 
     #[test]
     fn test_build_terraform_commands_plan() {
-        let commands = build_terraform_commands(&Action::Plan, "backend_vars", "prod", &[]);
+        let commands = build_terraform_commands(
+            &Action::Plan,
+            "backend_vars",
+            "prod",
+            &tfvars("prod"),
+            &[],
+            true,
+        );
 
         assert_eq!(commands.len(), 3);
         assert_eq!(commands[0], "tfswitch");
@@ -247,7 +905,14 @@ mod tests {
 
     #[test]
     fn test_build_terraform_commands_destroy() {
-        let commands = build_terraform_commands(&Action::Destroy, "backend-vars", "staging", &[]);
+        let commands = build_terraform_commands(
+            &Action::Destroy,
+            "backend-vars",
+            "staging",
+            &tfvars("staging"),
+            &[],
+            true,
+        );
 
         assert_eq!(commands.len(), 3);
         assert_eq!(commands[0], "tfswitch");
@@ -266,10 +931,13 @@ mod tests {
         let commands = build_terraform_commands(
             &Action::Output {
                 key: Some("vpc_id".to_string()),
+                jobs: None,
             },
             "backend-vars",
             "dev",
+            &tfvars("dev"),
             &[],
+            true,
         );
 
         assert_eq!(commands.len(), 3);
@@ -287,7 +955,9 @@ mod tests {
             &Action::Apply,
             "backend-vars",
             "dev",
+            &tfvars("dev"),
             &["-auto-approve".to_string(), "-compact-warnings".to_string()],
+            true,
         );
 
         assert_eq!(commands.len(), 3);
@@ -299,23 +969,58 @@ mod tests {
 
     #[test]
     fn test_build_terraform_commands_check_same_as_plan() {
-        let commands_check = build_terraform_commands(&Action::Check, "backend-vars", "dev", &[]);
-        let commands_plan = build_terraform_commands(&Action::Plan, "backend-vars", "dev", &[]);
+        let commands_check = build_terraform_commands(
+            &Action::Check,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &[],
+            true,
+        );
+        let commands_plan = build_terraform_commands(
+            &Action::Plan,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &[],
+            true,
+        );
 
         assert_eq!(commands_check, commands_plan);
     }
 
     #[test]
     fn test_build_terraform_commands_diff_same_as_plan() {
-        let commands_diff = build_terraform_commands(&Action::Diff, "backend-vars", "dev", &[]);
-        let commands_plan = build_terraform_commands(&Action::Plan, "backend-vars", "dev", &[]);
+        let commands_diff = build_terraform_commands(
+            &Action::Diff,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &[],
+            true,
+        );
+        let commands_plan = build_terraform_commands(
+            &Action::Plan,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &[],
+            true,
+        );
 
         assert_eq!(commands_diff, commands_plan);
     }
 
     #[test]
     fn test_build_terraform_commands_show() {
-        let commands = build_terraform_commands(&Action::Show, "backend-vars", "dev", &[]);
+        let commands = build_terraform_commands(
+            &Action::Show,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &[],
+            true,
+        );
 
         assert_eq!(commands.len(), 3);
         assert_eq!(commands[0], "tfswitch");
@@ -325,4 +1030,289 @@ mod tests {
         );
         assert_eq!(commands[2], "terraform show");
     }
+
+    #[test]
+    fn test_build_terraform_commands_replace_on_apply() {
+        let action = Action::Replace {
+            address: "module.app.aws_instance.web[0]".to_string(),
+            plan: false,
+        };
+        let options = with_auto_approve(&action, &["-parallelism=5".to_string()], true);
+        let commands = build_terraform_commands(
+            &action,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &options,
+            true,
+        );
+
+        assert_eq!(commands.len(), 3);
+        assert_eq!(
+            commands[2],
+            "terraform apply -lock-timeout=60s -var-file=tfvars/dev.tfvars \
+             -replace='module.app.aws_instance.web[0]' -parallelism=5 -auto-approve"
+        );
+
+        let action = Action::Replace {
+            address: "aws_instance.web".to_string(),
+            plan: true,
+        };
+        let options = with_auto_approve(&action, &[], true);
+        let commands = build_terraform_commands(
+            &action,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &options,
+            true,
+        );
+        assert_eq!(
+            commands[2],
+            "terraform plan -lock-timeout=60s -var-file=tfvars/dev.tfvars -replace=aws_instance.web"
+        );
+    }
+
+    #[test]
+    fn test_validate_resource_address() {
+        for address in [
+            "aws_instance.web",
+            "data.aws_ami.ubuntu",
+            "module.app.aws_instance.web[0]",
+            "module.net[\"eu\"].google_compute_network.main",
+            "aws_instance.web[\"blue\"]",
+        ] {
+            assert!(validate_resource_address(address).is_ok(), "{}", address);
+        }
+
+        for address in [
+            "aws_instance",
+            "aws_instance.web; rm -rf /",
+            "module.app",
+            "",
+        ] {
+            assert!(validate_resource_address(address).is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn test_build_terraform_commands_import() {
+        let action = Action::Import {
+            address: "aws_s3_bucket.logs".to_string(),
+            id: "my-logs-bucket".to_string(),
+        };
+        let commands =
+            build_terraform_commands(&action, "backend-vars", "dev", &tfvars("dev"), &[], true);
+
+        assert_eq!(
+            commands,
+            vec![
+                "tfswitch",
+                "terraform init -reconfigure -backend-config=backend-vars/dev.tfvars",
+                "terraform import -var-file=tfvars/dev.tfvars aws_s3_bucket.logs my-logs-bucket",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_terraform_commands_import_quotes_address() {
+        let action = Action::Import {
+            address: "aws_instance.web[\"blue\"]".to_string(),
+            id: "i-0abc 123".to_string(),
+        };
+        let commands = build_terraform_commands(
+            &action,
+            "backend-vars",
+            "prod",
+            &tfvars("prod"),
+            &["-lock-timeout=60s".to_string()],
+            true,
+        );
+
+        assert_eq!(
+            commands[2],
+            "terraform import -var-file=tfvars/prod.tfvars -lock-timeout=60s 'aws_instance.web[\"blue\"]' 'i-0abc 123'"
+        );
+    }
+
+    #[test]
+    fn test_var_files_from_env_directory() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let env_dir = temp_dir.path().join("env").join("dev");
+        fs::create_dir_all(&env_dir).unwrap();
+        fs::write(env_dir.join("network.tfvars"), "").unwrap();
+        fs::write(env_dir.join("common.tfvars"), "").unwrap();
+        fs::write(env_dir.join("README.md"), "").unwrap();
+        let project = temp_dir.path().to_str().unwrap();
+
+        assert_eq!(
+            var_files(project, "dev", "common.tfvars"),
+            vec!["env/dev/common.tfvars", "env/dev/network.tfvars"]
+        );
+        // An unknown environment keeps the conventional file
+        assert_eq!(
+            var_files(project, "prod", "common.tfvars"),
+            vec!["tfvars/prod.tfvars"]
+        );
+
+        // tfvars/<env>.tfvars wins over env/<env>/
+        fs::create_dir(temp_dir.path().join("tfvars")).unwrap();
+        fs::write(temp_dir.path().join("tfvars/dev.tfvars"), "").unwrap();
+        assert_eq!(
+            var_files(project, "dev", "common.tfvars"),
+            vec!["tfvars/dev.tfvars"]
+        );
+    }
+
+    #[test]
+    fn test_build_terraform_commands_multiple_var_files() {
+        let var_files = vec![
+            "env/dev/common.tfvars".to_string(),
+            "env/dev/network.tfvars".to_string(),
+        ];
+        let commands =
+            build_terraform_commands(&Action::Plan, "backend-vars", "dev", &var_files, &[], true);
+
+        assert_eq!(
+            commands[2],
+            "terraform plan -lock-timeout=60s -var-file=env/dev/common.tfvars -var-file=env/dev/network.tfvars"
+        );
+    }
+
+    #[test]
+    fn test_split_output_queries() {
+        let commands = vec![
+            "tfswitch".to_string(),
+            "terraform init -reconfigure -backend-config=backend-vars/dev.tfvars".to_string(),
+            "terraform output subnet_ids".to_string(),
+            "terraform output vpc_id".to_string(),
+        ];
+
+        let (setup, queries) = split_output_queries(&commands);
+        assert_eq!(setup, &commands[..2]);
+        assert_eq!(queries, &commands[2..]);
+    }
+
+    #[test]
+    fn test_build_terraform_commands_without_init() {
+        let commands = build_terraform_commands(
+            &Action::Plan,
+            "backend-vars",
+            "dev",
+            &tfvars("dev"),
+            &[],
+            false,
+        );
+        assert_eq!(
+            commands,
+            vec!["terraform plan -lock-timeout=60s -var-file=tfvars/dev.tfvars"]
+        );
+
+        let commands = build_terraform_commands(
+            &Action::Apply,
+            "backend-vars",
+            "prod",
+            &tfvars("prod"),
+            &["-auto-approve".to_string()],
+            false,
+        );
+        assert_eq!(
+            commands,
+            vec!["terraform apply -lock-timeout=60s -var-file=tfvars/prod.tfvars -auto-approve"]
+        );
+    }
+
+    #[test]
+    fn test_required_terraform_version() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = temp_dir.path().to_str().unwrap();
+        assert_eq!(required_terraform_version(project), None);
+
+        fs::write(
+            temp_dir.path().join("versions.tf"),
+            "terraform {\n  required_version = \">= 1.3, < 2.0\"\n}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            required_terraform_version(project).as_deref(),
+            Some(">= 1.3, < 2.0")
+        );
+
+        // .terraform-version wins over required_version
+        fs::write(temp_dir.path().join(".terraform-version"), "1.5.7\n").unwrap();
+        assert_eq!(
+            required_terraform_version(project).as_deref(),
+            Some("1.5.7")
+        );
+    }
+
+    #[rstest::rstest]
+    #[case("1.5.7", "1.5.7", true)]
+    #[case("1.5.7", "= 1.5.7", true)]
+    #[case("1.5.7", "1.6.0", false)]
+    #[case("1.5.7", ">= 1.3, < 2.0", true)]
+    #[case("2.0.0", ">= 1.3, < 2.0", false)]
+    #[case("1.9.0", "~> 1.5", true)]
+    #[case("2.0.0", "~> 1.5", false)]
+    #[case("1.5.9", "~> 1.5.2", true)]
+    #[case("1.6.0", "~> 1.5.2", false)]
+    #[case("1.5.7", "!= 1.5.7", false)]
+    #[case("1.5.7", "latest", false)]
+    fn test_version_satisfies(#[case] version: &str, #[case] constraint: &str, #[case] ok: bool) {
+        let version = semver::Version::parse(version).unwrap();
+        assert_eq!(version_satisfies(&version, constraint), ok);
+    }
+
+    #[test]
+    fn test_tfswitch_skipped_when_already_satisfied() {
+        let active = semver::Version::new(1, 5, 7);
+        assert!(!tfswitch_needed(Some("1.5.7"), Some(&active)));
+        assert!(!tfswitch_needed(Some("~> 1.5"), Some(&active)));
+    }
+
+    #[test]
+    fn test_tfswitch_kept_on_mismatch() {
+        let active = semver::Version::new(1, 5, 7);
+        assert!(tfswitch_needed(Some("1.6.0"), Some(&active)));
+        assert!(tfswitch_needed(Some(">= 1.8"), Some(&active)));
+        // Nothing to compare: keep the step
+        assert!(tfswitch_needed(None, Some(&active)));
+        assert!(tfswitch_needed(Some("1.5.7"), None));
+    }
+
+    #[test]
+    fn test_plan_out_file() {
+        let options = |opts: &[&str]| opts.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+        assert_eq!(plan_out_file(&options(&["-out=tfplan"])), Some("tfplan"));
+        assert_eq!(
+            plan_out_file(&options(&["-input=false", "-out", "plans/dev.tfplan"])),
+            Some("plans/dev.tfplan")
+        );
+        assert_eq!(plan_out_file(&options(&["-input=false"])), None);
+    }
+
+    #[test]
+    fn test_var_files_layer_common_before_environment() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let tfvars = temp_dir.path().join("tfvars");
+        fs::create_dir(&tfvars).unwrap();
+        fs::write(tfvars.join("common.tfvars"), "").unwrap();
+        fs::write(tfvars.join("dev.tfvars"), "").unwrap();
+        let project = temp_dir.path().to_str().unwrap();
+
+        let files = var_files(project, "dev", "common.tfvars");
+        assert_eq!(files, vec!["tfvars/common.tfvars", "tfvars/dev.tfvars"]);
+        assert_eq!(
+            build_terraform_commands(&Action::Plan, "backend-vars", "dev", &files, &[], false),
+            vec![
+                "terraform plan -lock-timeout=60s -var-file=tfvars/common.tfvars -var-file=tfvars/dev.tfvars"
+            ]
+        );
+
+        // A custom name, absent here, adds nothing
+        assert_eq!(
+            var_files(project, "dev", "shared.tfvars"),
+            vec!["tfvars/dev.tfvars"]
+        );
+    }
 }
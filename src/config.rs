@@ -1,12 +1,27 @@
+use crate::commands::Action;
+use crate::log;
 use crate::techno::Technology;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use etcetera::BaseStrategy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Name of the project-local config file discovered from the project path upwards
+pub const PROJECT_CONFIG_FILE: &str = ".mk.toml";
+
+/// Project config path given with `--project-config`, overriding discovery
+static PROJECT_CONFIG_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use an explicit project config file instead of discovering `.mk.toml`
+pub fn set_project_config_override(path: PathBuf) {
+    let _ = PROJECT_CONFIG_OVERRIDE.set(path);
+}
 
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(default)]
     pub technology_priority: Vec<String>,
@@ -14,17 +29,46 @@ pub struct Config {
     pub bump: BumpConfig,
     #[serde(default)]
     pub context: crate::context::ContextConfig,
+    #[serde(default)]
+    pub helm: HelmConfig,
+    #[serde(default)]
+    pub ansible: AnsibleConfig,
+    #[serde(default)]
+    pub terraform: TerraformConfig,
+    #[serde(default)]
+    pub env: EnvConfig,
+    /// Extra options per action, keyed by `<technology>.<action>` (e.g. `terraform.plan`)
+    #[serde(default)]
+    pub args: HashMap<String, Vec<String>>,
+    /// Fail on `${VAR}` references to unset variables instead of expanding them to ""
+    #[serde(default)]
+    pub strict_env: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BumpConfig {
     #[serde(default = "default_max_depth")]
     pub max_depth: usize,
     #[serde(default)]
     pub oci_registries: HashMap<String, OciRegistryAuth>,
+    /// Extra CA certificate (PEM) trusted for registry requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    /// Repository/source globs whose pre-release versions are always considered
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prerelease_repositories: Vec<String>,
+    /// Terraform module sources or Helm chart names never offered for update
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Skip a major line with only prereleases for the newest stable release of a lower one,
+    /// unless already on `0.x`
+    #[serde(default)]
+    pub prefer_stable_major: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct OciRegistryAuth {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token: Option<String>,
@@ -32,43 +76,219 @@ pub struct OciRegistryAuth {
     pub command: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HelmConfig {
+    /// Deploy with helmfile or directly with the helm CLI
+    #[serde(default)]
+    pub mode: HelmMode,
+    /// Release namespace in `helm` mode
+    #[serde(default = "default_helm_namespace")]
+    pub namespace: String,
+    /// helmfile binary or wrapper used for helm projects
+    #[serde(default = "default_helm_binary")]
+    pub binary: String,
+    /// Flag selecting the helmfile environment
+    #[serde(default = "default_helm_env_flag")]
+    pub env_flag: String,
+    /// Extra attempts for the registry login before `helmfile deps`
+    #[serde(default = "default_auth_retries")]
+    pub auth_retries: u32,
+    /// Dependency downloads `helmfile deps` runs at the same time, helmfile's own default if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deps_jobs: Option<usize>,
+    /// Registry login per OCI host pattern (exact host or glob like `*.azurecr.io`)
+    #[serde(default)]
+    pub registries: HashMap<String, HelmRegistry>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnsibleConfig {
+    /// Playbook to run, relative to the project (discovered when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playbook: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TerraformConfig {
+    /// Extra attempts for a failing `terraform init`, with backoff (0 disables retries)
+    #[serde(default)]
+    pub init_retries: u32,
+    /// Var file in `tfvars/` shared by every environment, passed before the environment's own
+    #[serde(default = "default_common_var_file")]
+    pub common_var_file: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvConfig {
+    /// Use the environment differing only by case (e.g. `DEV` for `dev`) with a warning
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Group name to the environments it stands for in drift `--env` filters
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub groups: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HelmMode {
+    /// `helmfile sync/diff/template -e <env>`
+    #[default]
+    Helmfile,
+    /// `helm upgrade --install` on the plain chart with `values/<env>/values.yaml`
+    Helm,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HelmRegistry {
+    /// Shell command logging helm in, `{registry}` is replaced by the host
+    pub login_command: String,
+}
+
 fn default_max_depth() -> usize {
     5
 }
 
+fn default_helm_namespace() -> String {
+    "default".to_string()
+}
+
+fn default_helm_binary() -> String {
+    "helmfile".to_string()
+}
+
+fn default_helm_env_flag() -> String {
+    "-e".to_string()
+}
+
+fn default_auth_retries() -> u32 {
+    2
+}
+
+fn default_common_var_file() -> String {
+    "common.tfvars".to_string()
+}
+
+impl Default for TerraformConfig {
+    fn default() -> Self {
+        Self {
+            init_retries: 0,
+            common_var_file: default_common_var_file(),
+        }
+    }
+}
+
+impl Default for HelmConfig {
+    fn default() -> Self {
+        Self {
+            mode: HelmMode::default(),
+            namespace: default_helm_namespace(),
+            binary: default_helm_binary(),
+            env_flag: default_helm_env_flag(),
+            auth_retries: default_auth_retries(),
+            deps_jobs: None,
+            registries: HashMap::new(),
+        }
+    }
+}
+
 impl Default for BumpConfig {
     fn default() -> Self {
         Self {
             max_depth: default_max_depth(),
             oci_registries: HashMap::new(),
+            ca_bundle: None,
+            prerelease_repositories: Vec::new(),
+            ignore: Vec::new(),
+            prefer_stable_major: false,
         }
     }
 }
 
+impl BumpConfig {
+    /// Whether one of `keys` (a module source or chart name) is listed in `ignore`
+    pub fn is_ignored(&self, keys: &[&str]) -> bool {
+        self.ignore
+            .iter()
+            .any(|ignored| keys.contains(&ignored.as_str()))
+    }
+}
+
 impl Config {
     /// Load configuration from ~/.config/mk/config.toml
     pub fn load() -> Result<Self> {
-        let config_path = Self::get_config_path()?;
+        Self::load_from(&Self::get_config_path()?)
+    }
+
+    /// Load and validate a single config file
+    /// A missing file yields the default config (empty priority list)
+    pub fn load_from(path: &Path) -> Result<Self> {
+        Ok(toml::Value::Table(read_toml_table(path)?).try_into()?)
+    }
 
-        if !config_path.exists() {
-            // No config file, return default (empty priority list)
-            return Ok(Self::default());
+    /// Load the effective configuration for `project_path`
+    ///
+    /// The project config is the `--project-config` file if given, otherwise the
+    /// nearest `.mk.toml` found walking up from `project_path`. It is merged over
+    /// the user config:
+    /// - scalars from the project config override user values
+    /// - tables (`context.mappings`, `bump.oci_registries`, ...) are deep-merged,
+    ///   project keys winning on conflict
+    /// - lists such as `technology_priority` are replaced, not appended
+    pub fn load_merged(project_path: &str) -> Result<Self> {
+        let project_config = match PROJECT_CONFIG_OVERRIDE.get() {
+            Some(path) => Some(path.clone()),
+            None => find_project_config(Path::new(project_path)),
+        };
+
+        Self::load_layered(&Self::get_config_path()?, project_config.as_deref())
+    }
+
+    /// Load the user config file and overlay the project config file on top of it
+    fn load_layered(user_config_path: &Path, project_config_path: Option<&Path>) -> Result<Self> {
+        let mut merged = read_toml_table(user_config_path)?;
+
+        if let Some(project_config_path) = project_config_path {
+            if !project_config_path.exists() {
+                anyhow::bail!(
+                    "Project config file not found: {}",
+                    project_config_path.display()
+                );
+            }
+            merge_tables(&mut merged, read_toml_table(project_config_path)?);
         }
 
-        let content = fs::read_to_string(&config_path)?;
-        let config: Config = toml::from_str(&content)?;
+        Ok(toml::Value::Table(merged).try_into()?)
+    }
 
-        Ok(config)
+    /// `value` of a registry token or path setting with `${VAR}` taken from the environment
+    ///
+    /// Expanded where the setting is used, so only the commands needing it fail
+    /// on an unset variable with `strict_env`.
+    pub fn expand_env(&self, value: &str) -> Result<String> {
+        expand_env_vars(value, self.strict_env, |name| std::env::var(name).ok())
     }
 
-    /// Get the path to the config file
-    fn get_config_path() -> Result<PathBuf> {
+    /// Get the path to the user config file
+    pub fn get_config_path() -> Result<PathBuf> {
         let strategy = etcetera::base_strategy::choose_base_strategy()?;
         let config_dir = strategy.config_dir().join("mk");
 
         Ok(config_dir.join("config.toml"))
     }
 
+    /// Configured extra options for `action` on `techno`, empty if none
+    pub fn action_args(&self, techno: Technology, action: &Action) -> Vec<String> {
+        self.args
+            .get(&format!("{}.{}", techno.as_str(), action.name()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Get the priority order for technologies
     /// Returns None if no priority is configured (should use interactive selection)
     pub fn get_technology_priority(&self) -> Option<Vec<Technology>> {
@@ -136,11 +356,36 @@ impl Config {
 # Uncomment and customize the line below:
 # technology_priority = []
 
+# Registry tokens, ca_bundle and ansible.playbook may reference environment
+# variables as ${VAR}, e.g. token = "${GITHUB_TOKEN}" (token commands are left
+# to the shell)
+# An unset variable expands to an empty value with a warning, or fails when
+# strict_env is true
+# strict_env = false
+
 # Bump command configuration
 [bump]
 # Maximum directory depth for recursive scanning (default: 5)
 max_depth = 5
 
+# Extra CA certificate (PEM) to trust for private registries
+# Proxies are taken from the HTTP_PROXY, HTTPS_PROXY and NO_PROXY environment variables
+# ca_bundle = "/path/to/ca.pem"
+
+# Always consider pre-release versions for matching Helm repositories and
+# Terraform modules (namespace/name/provider), even without --include-prereleases
+# prerelease_repositories = ["oci://ghcr.io/acme/*"]
+
+# Dependencies to keep pinned, by Terraform module source or Helm chart name
+# A single occurrence can also be pinned by ending its version line with a
+# mk:ignore comment, e.g. version = "~> 9.0"  # mk:ignore
+# ignore = ["terraform-google-modules/network/google", "postgresql"]
+
+# With prereleases included, never propose a major line that only has prereleases
+# (e.g. 1.0.0-rc.1) when a lower one has a stable release (e.g. 0.12.0), unless the
+# dependency is already on 0.x
+# prefer_stable_major = true
+
 # OCI registry authentication for Helm charts
 # Configure authentication tokens or commands for OCI registries
 #
@@ -154,7 +399,75 @@ max_depth = 5
 # command = "aws ecr get-login-password --region us-east-1"
 #
 # [bump.oci_registries."registry.gitlab.com"]
-# token = "glpat-your_gitlab_token"
+# token = "${GITLAB_TOKEN}"
+
+# Helm dependency update configuration
+[helm]
+# Deploy with "helmfile" (default) or "helm" for plain charts, which runs
+# `helm upgrade --install <chart name> . -f values/<env>/values.yaml -n <namespace>`
+# mode = "helmfile"
+# namespace = "default"
+
+# helmfile binary or wrapper, and the flag selecting the environment
+# (defaults: "helmfile" and "-e")
+# binary = "helmfile"
+# env_flag = "-e"
+
+# Retries for the registry login run before `helmfile deps`, with a doubling
+# delay between attempts (default: 2)
+# auth_retries = 2
+
+# Dependency downloads run at the same time by `helmfile deps`, passed as
+# --concurrency (default: unset, helmfile decides)
+# Registry logins always run one after the other
+# deps_jobs = 4
+
+# Registry login per OCI host before `helmfile deps`, keyed by exact host or glob
+# ({registry} is substituted). Artifact Registry (*docker.pkg.dev) uses gcloud
+# unless overridden here.
+#
+# Examples:
+# [helm.registries."123456789.dkr.ecr.us-east-1.amazonaws.com"]
+# login_command = "aws ecr get-login-password --region us-east-1 | helm registry login -u AWS --password-stdin {registry}"
+#
+# [helm.registries."*.azurecr.io"]
+# login_command = "az acr login --name {registry}"
+#
+# [helm.registries."ghcr.io"]
+# login_command = "echo $GITHUB_TOKEN | helm registry login -u $GITHUB_USER --password-stdin {registry}"
+
+# Ansible configuration
+[ansible]
+# Playbook to run, relative to the project. When unset, mk uses site.yml,
+# playbook.yml or main.yml, else the only *.yml file at the project root
+# playbook = "deploy.yml"
+
+# Terraform configuration
+[terraform]
+# Retry a failing `terraform init` this many times, waiting 2s, 4s, 8s, ...
+# between attempts. Plan and apply are never retried (default: 0)
+# init_retries = 3
+
+# Var file of tfvars/ shared by every environment: when present, it is passed
+# with -var-file before the environment's own file and is not an environment
+# itself (default: common.tfvars)
+# common_var_file = "shared.tfvars"
+
+# Environment name matching
+[env]
+# Accept an environment differing only by case (e.g. DEV for dev) with a
+# warning instead of failing (default: false)
+# case_insensitive = true
+
+# Environment groups usable in place of an environment with `mk drift --env`
+# [env.groups]
+# nonprod = ["dev", "staging"]
+
+# Extra options appended to an action, keyed by "<technology>.<action>"
+# Options passed on the command line come after these and can override them
+[args]
+# "terraform.plan" = ["-compact-warnings"]
+# "helm.diff" = ["--suppress-secrets"]
 
 # Kubernetes context validation (Helm/Kustomize only)
 [context]
@@ -163,6 +476,18 @@ max_depth = 5
 # before applying/diffing Helm or Kustomize changes
 disable_context_check = false
 
+# Also validate the context for Ansible apply/diff, for playbooks using
+# kubernetes.core modules (default: false)
+# validate_ansible = true
+
+# Git remote identifying the repository when there is no `origin` remote
+# (otherwise the first remote is used)
+# remote = "upstream"
+
+# Command generating the kubeconfig entry when the expected context is missing,
+# run before re-checking ({context} and {environment} are substituted)
+# login_command = "gcloud container clusters get-credentials {context}"
+
 # Context mappings: repository -> environment -> kubectl context
 # These mappings are automatically created when you run commands
 # You can also define them manually here
@@ -172,6 +497,10 @@ disable_context_check = false
 # prod = "gke_project_cluster-prod"
 # staging = "gke_project_cluster-staging"
 #
+# Branch-specific mappings take precedence on the matching branch:
+# [context.branch_mappings."github.com/user/infra".develop]
+# staging = "gke_project_cluster-staging-develop"
+#
 # Note: You can also create a .mk/contexts.toml file in your git repository
 # to share context mappings with your team
 "#;
@@ -182,10 +511,256 @@ disable_context_check = false
     }
 }
 
+/// Project config file a setting is saved to: the `--project-config` file, the nearest
+/// `.mk.toml` above `project_path`, or a new `.mk.toml` in `project_path`
+pub fn project_config_path(project_path: &str) -> PathBuf {
+    PROJECT_CONFIG_OVERRIDE
+        .get()
+        .cloned()
+        .or_else(|| find_project_config(Path::new(project_path)))
+        .unwrap_or_else(|| Path::new(project_path).join(PROJECT_CONFIG_FILE))
+}
+
+/// `technology_priority` with `selected` first, followed by the rest of `current`
+pub fn priority_with_selection(selected: Technology, current: &[String]) -> Vec<String> {
+    std::iter::once(selected.as_str().to_string())
+        .chain(
+            current
+                .iter()
+                .filter(|tech| !tech.eq_ignore_ascii_case(selected.as_str()))
+                .cloned(),
+        )
+        .collect()
+}
+
+/// Set `technology_priority` in the config file at `path`, keeping the rest of the file
+pub fn save_technology_priority(path: &Path, priority: &[String]) -> Result<()> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    let mut doc = content
+        .parse::<toml_edit::DocumentMut>()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    doc["technology_priority"] = toml_edit::value(
+        priority
+            .iter()
+            .map(String::as_str)
+            .collect::<toml_edit::Array>(),
+    );
+    fs::write(path, doc.to_string()).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Find the nearest project config file, starting at `start` and walking up
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().ok()?;
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Unset variables already warned about, so each is reported once per run
+static WARNED_ENV_VARS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Replace each `${NAME}` in `value` with the variable given by `lookup`
+///
+/// An unset variable is an error when `strict`, otherwise it expands to an
+/// empty string with a warning. Anything else in `${...}` (e.g. shell defaults
+/// like `${REGION:-eu}`) and an unterminated `${` are kept as is.
+fn expand_env_vars(
+    value: &str,
+    strict: bool,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        let end = start + 3 + len;
+        if !is_env_var_name(name) {
+            expanded.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        expanded.push_str(&rest[..start]);
+        match lookup(name) {
+            Some(resolved) => expanded.push_str(&resolved),
+            None if strict => {
+                anyhow::bail!("Environment variable {} used in config is not set", name)
+            }
+            None => {
+                if WARNED_ENV_VARS.lock().unwrap().insert(name.to_string()) {
+                    log::warning!(
+                        "Environment variable {} used in config is not set, using an empty value",
+                        name
+                    );
+                }
+            }
+        }
+        rest = &rest[end..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Whether `name` is a plain variable name (`[A-Za-z_][A-Za-z0-9_]*`)
+fn is_env_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Deep-merge `overlay` into `base`: nested tables are merged key by key,
+/// any other value (scalar or array) from `overlay` replaces the one in `base`
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Read a config file as a table, treating a missing file as empty
+///
+/// The file is validated on its own before merging so syntax errors and
+/// unknown keys are reported with the file name and line.
+fn read_toml_table(path: &Path) -> Result<toml::Table> {
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    toml::from_str::<Config>(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid config file {}: {}", path.display(), e))?;
+
+    Ok(toml::from_str(&content)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_priority_with_selection() {
+        let current = [
+            "Terraform".to_string(),
+            "helm".to_string(),
+            "ansible".to_string(),
+        ];
+        assert_eq!(
+            priority_with_selection(Technology::Helm, &current),
+            ["helm", "Terraform", "ansible"]
+        );
+        assert_eq!(
+            priority_with_selection(Technology::Terraform, &current),
+            ["terraform", "helm", "ansible"]
+        );
+        assert_eq!(
+            priority_with_selection(Technology::Kustomize, &[]),
+            ["kustomize"]
+        );
+    }
+
+    #[test]
+    fn test_save_technology_priority_keeps_other_settings() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(
+            &path,
+            "# team settings\ntechnology_priority = [\"ansible\"]\n\n[helm]\nnamespace = \"platform\"\n",
+        )
+        .unwrap();
+
+        let priority = priority_with_selection(Technology::Helm, &["ansible".to_string()]);
+        save_technology_priority(&path, &priority).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# team settings\n"));
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(
+            config.get_technology_priority().unwrap(),
+            [Technology::Helm, Technology::Ansible]
+        );
+        assert_eq!(config.helm.namespace, "platform");
+
+        // A missing file is created
+        let new_path = temp_dir.path().join("nested.toml");
+        save_technology_priority(&new_path, &priority).unwrap();
+        assert_eq!(
+            Config::load_from(&new_path).unwrap().technology_priority,
+            ["helm", "ansible"]
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        let lookup = |name: &str| (name == "GITHUB_TOKEN").then(|| "ghp_secret".to_string());
+        assert_eq!(
+            expand_env_vars("Bearer ${GITHUB_TOKEN}!", false, lookup).unwrap(),
+            "Bearer ghp_secret!"
+        );
+        assert_eq!(
+            expand_env_vars("${MISSING}-${GITHUB_TOKEN}", false, lookup).unwrap(),
+            "-ghp_secret"
+        );
+        assert_eq!(
+            expand_env_vars("no ${closing", false, lookup).unwrap(),
+            "no ${closing"
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars_keeps_shell_syntax() {
+        let lookup = |name: &str| (name == "REGION").then(|| "eu".to_string());
+        assert_eq!(
+            expand_env_vars("${AWS_REGION:-us-east-1}/${REGION}", true, lookup).unwrap(),
+            "${AWS_REGION:-us-east-1}/eu"
+        );
+        assert_eq!(
+            expand_env_vars("${} ${1X} ${A B}", true, lookup).unwrap(),
+            "${} ${1X} ${A B}"
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars_strict_missing() {
+        let error = expand_env_vars("${MISSING}", true, |_| None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Environment variable MISSING used in config is not set"
+        );
+    }
+
+    #[test]
+    fn test_config_strict_env_fails_only_where_used() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        fs::write(
+            &path,
+            "strict_env = true\n[bump.oci_registries.\"ghcr.io\"]\ntoken = \"${MK_TEST_UNSET_TOKEN}\"\n",
+        )
+        .unwrap();
+
+        // Loading succeeds, so actions not using the token are unaffected
+        let config = Config::load_from(&path).unwrap();
+        let token = config.bump.oci_registries["ghcr.io"].token.as_deref();
+        assert_eq!(token, Some("${MK_TEST_UNSET_TOKEN}"));
+
+        let error = config.expand_env(token.unwrap()).unwrap_err();
+        assert!(error.to_string().contains("MK_TEST_UNSET_TOKEN"));
+    }
+
     #[test]
     fn test_config_default() {
         let config = Config::default();
@@ -199,6 +774,12 @@ mod tests {
             technology_priority: vec!["terraform".to_string(), "ansible".to_string()],
             bump: BumpConfig::default(),
             context: crate::context::ContextConfig::default(),
+            helm: HelmConfig::default(),
+            ansible: AnsibleConfig::default(),
+            terraform: TerraformConfig::default(),
+            env: EnvConfig::default(),
+            args: HashMap::new(),
+            strict_env: false,
         };
 
         let priorities = config.get_technology_priority().unwrap();
@@ -213,6 +794,12 @@ mod tests {
             technology_priority: vec!["Terraform".to_string(), "ANSIBLE".to_string()],
             bump: BumpConfig::default(),
             context: crate::context::ContextConfig::default(),
+            helm: HelmConfig::default(),
+            ansible: AnsibleConfig::default(),
+            terraform: TerraformConfig::default(),
+            env: EnvConfig::default(),
+            args: HashMap::new(),
+            strict_env: false,
         };
 
         let priorities = config.get_technology_priority().unwrap();
@@ -231,6 +818,12 @@ mod tests {
             ],
             bump: BumpConfig::default(),
             context: crate::context::ContextConfig::default(),
+            helm: HelmConfig::default(),
+            ansible: AnsibleConfig::default(),
+            terraform: TerraformConfig::default(),
+            env: EnvConfig::default(),
+            args: HashMap::new(),
+            strict_env: false,
         };
 
         let priorities = config.get_technology_priority().unwrap();
@@ -239,6 +832,200 @@ mod tests {
         assert_eq!(priorities[1], Technology::Ansible);
     }
 
+    #[test]
+    fn test_find_project_config_in_project_dir() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_config = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&project_config, "technology_priority = [\"helm\"]\n").unwrap();
+
+        let found = find_project_config(temp_dir.path()).unwrap();
+        assert_eq!(found, project_config.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_find_project_config_in_parent_dir() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let project_dir = temp_dir.path().join("infra").join("terraform");
+        fs::create_dir_all(&project_dir).unwrap();
+        let project_config = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&project_config, "technology_priority = [\"helm\"]\n").unwrap();
+
+        let found = find_project_config(&project_dir).unwrap();
+        assert_eq!(found, project_config.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_load_layered_project_overrides_user() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let user_config = temp_dir.path().join("config.toml");
+        fs::write(
+            &user_config,
+            "technology_priority = [\"terraform\"]\n\n[bump]\nmax_depth = 3\n",
+        )
+        .unwrap();
+        let project_config = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&project_config, "technology_priority = [\"helm\"]\n").unwrap();
+
+        let config = Config::load_layered(&user_config, Some(&project_config)).unwrap();
+        assert_eq!(config.technology_priority, vec!["helm"]);
+        assert_eq!(config.bump.max_depth, 3);
+    }
+
+    fn write_layers(temp_dir: &tempfile::TempDir, user: &str, project: &str) -> Config {
+        let user_config = temp_dir.path().join("config.toml");
+        let project_config = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&user_config, user).unwrap();
+        fs::write(&project_config, project).unwrap();
+        Config::load_layered(&user_config, Some(&project_config)).unwrap()
+    }
+
+    #[test]
+    fn test_merge_scalar_override() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = write_layers(
+            &temp_dir,
+            "[bump]\nmax_depth = 3\n\n[context]\ndisable_context_check = false\n",
+            "[context]\ndisable_context_check = true\n",
+        );
+
+        assert!(config.context.disable_context_check);
+        assert_eq!(config.bump.max_depth, 3);
+    }
+
+    #[test]
+    fn test_merge_maps_deep_merged() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = write_layers(
+            &temp_dir,
+            r#"[bump.oci_registries."ghcr.io"]
+token = "user-token"
+
+[bump.oci_registries."registry.gitlab.com"]
+token = "gitlab-token"
+
+[context.mappings."github.com/user/infra"]
+prod = "user-prod"
+staging = "user-staging"
+"#,
+            r#"[bump.oci_registries."ghcr.io"]
+token = "project-token"
+
+[context.mappings."github.com/user/infra"]
+prod = "project-prod"
+
+[context.mappings."github.com/user/apps"]
+dev = "apps-dev"
+"#,
+        );
+
+        let registries = &config.bump.oci_registries;
+        assert_eq!(registries.len(), 2);
+        assert_eq!(
+            registries["ghcr.io"].token.as_deref(),
+            Some("project-token")
+        );
+        assert_eq!(
+            registries["registry.gitlab.com"].token.as_deref(),
+            Some("gitlab-token")
+        );
+
+        let context = &config.context;
+        assert_eq!(
+            context.get_mapping("github.com/user/infra", "prod"),
+            Some("project-prod".to_string())
+        );
+        assert_eq!(
+            context.get_mapping("github.com/user/infra", "staging"),
+            Some("user-staging".to_string())
+        );
+        assert_eq!(
+            context.get_mapping("github.com/user/apps", "dev"),
+            Some("apps-dev".to_string())
+        );
+    }
+
+    #[test]
+    fn test_merge_list_replaced() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = write_layers(
+            &temp_dir,
+            "technology_priority = [\"terraform\", \"ansible\"]\n",
+            "technology_priority = [\"helm\"]\n",
+        );
+
+        assert_eq!(config.technology_priority, vec!["helm"]);
+    }
+
+    #[test]
+    fn test_action_args_merged_per_key() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let config = write_layers(
+            &temp_dir,
+            "[args]\n\"terraform.plan\" = [\"-compact-warnings\"]\n\"helm.diff\" = [\"--suppress-secrets\"]\n",
+            "[args]\n\"terraform.plan\" = [\"-parallelism=4\"]\n",
+        );
+
+        assert_eq!(
+            config.action_args(Technology::Terraform, &Action::Plan),
+            vec!["-parallelism=4"]
+        );
+        assert_eq!(
+            config.action_args(Technology::Helm, &Action::Diff),
+            vec!["--suppress-secrets"]
+        );
+        assert!(config
+            .action_args(Technology::Terraform, &Action::Apply)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_load_layered_reports_syntax_error_line() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let user_config = temp_dir.path().join("config.toml");
+        fs::write(&user_config, "[bump]\nmax_depth = 3\nca_bundle = \n").unwrap();
+
+        let err = Config::load_layered(&user_config, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&user_config.display().to_string()));
+        assert!(err.contains("line 3"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_layered_reports_unknown_key() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let user_config = temp_dir.path().join("config.toml");
+        let project_config = temp_dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&user_config, "[bump]\nmax_depth = 3\n").unwrap();
+        fs::write(&project_config, "[bump]\nmax_dept = 2\n").unwrap();
+
+        let err = Config::load_layered(&user_config, Some(&project_config))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&project_config.display().to_string()));
+        assert!(err.contains("line 2"), "{}", err);
+        assert!(err.contains("unknown field `max_dept`"), "{}", err);
+    }
+
+    #[test]
+    fn test_load_layered_missing_explicit_project_config() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let user_config = temp_dir.path().join("config.toml");
+        let missing = temp_dir.path().join("missing.toml");
+
+        let result = Config::load_layered(&user_config, Some(&missing));
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("not found"));
+    }
+
     #[test]
     fn test_init_config_creates_file() {
         use std::fs;
@@ -1,3 +1,7 @@
+use crate::error::MkError;
+use crate::log;
+use crate::techno::Technology;
+use crate::Action;
 use anyhow::{Context, Result};
 use colored::*;
 use etcetera::BaseStrategy;
@@ -10,11 +14,28 @@ use std::process::Command;
 use toml_edit::{DocumentMut, Item, Table};
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ContextConfig {
     #[serde(default)]
     pub disable_context_check: bool,
     #[serde(default)]
     pub mappings: HashMap<String, HashMap<String, String>>,
+    /// Branch-specific mappings: repo -> branch -> environment -> context
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub branch_mappings: HashMap<String, HashMap<String, HashMap<String, String>>>,
+    /// Command creating the kubeconfig entry when the expected context is missing
+    /// (`{context}` and `{environment}` are substituted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub login_command: Option<String>,
+    /// Per-repository login commands, overriding `login_command`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub login_commands: HashMap<String, String>,
+    /// Also validate the context for Ansible apply/diff (kubernetes.core plays)
+    #[serde(default)]
+    pub validate_ansible: bool,
+    /// Git remote used to identify the repository when `origin` is missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
 }
 
 impl ContextConfig {
@@ -26,6 +47,41 @@ impl ContextConfig {
             .cloned()
     }
 
+    /// Get the expected context for a given repo, branch and environment
+    pub fn get_branch_mapping(
+        &self,
+        repo_id: &str,
+        branch: &str,
+        environment: &str,
+    ) -> Option<String> {
+        self.branch_mappings
+            .get(repo_id)
+            .and_then(|branches| branches.get(branch))
+            .and_then(|envs| envs.get(environment))
+            .cloned()
+    }
+
+    /// Resolve the expected context, preferring a mapping for the current branch
+    /// over the branch-agnostic one
+    pub fn resolve_mapping(
+        &self,
+        repo_id: &str,
+        branch: Option<&str>,
+        environment: &str,
+    ) -> Option<String> {
+        branch
+            .and_then(|branch| self.get_branch_mapping(repo_id, branch, environment))
+            .or_else(|| self.get_mapping(repo_id, environment))
+    }
+
+    /// Get the login command template for a repo
+    pub fn login_command_for(&self, repo_id: &str) -> Option<&str> {
+        self.login_commands
+            .get(repo_id)
+            .or(self.login_command.as_ref())
+            .map(String::as_str)
+    }
+
     /// Set a context mapping for a repo and environment
     pub fn set_mapping(&mut self, repo_id: &str, environment: &str, context: &str) {
         self.mappings
@@ -35,57 +91,113 @@ impl ContextConfig {
     }
 }
 
+/// Whether running `action` on a `techno` project targets a cluster and needs context validation
+pub fn requires_validation(techno: Technology, action: &Action, validate_ansible: bool) -> bool {
+    match techno {
+        Technology::Helm | Technology::Kustomize => matches!(
+            action,
+            Action::Apply
+                | Action::Diff
+                | Action::Check
+                | Action::Delete
+                | Action::Destroy
+                | Action::Uninstall
+        ),
+        Technology::Ansible => validate_ansible && matches!(action, Action::Apply | Action::Diff),
+        _ => false,
+    }
+}
+
 /// Main entry point for context validation
-pub fn validate_context(project_path: &str, environment: &str, verbose: bool) -> Result<()> {
-    // Check if feature is disabled in user config
-    if is_context_check_disabled()? {
+/// `context_override` (`--context`) is switched to instead of validating against the mappings
+pub fn validate_context(
+    project_path: &str,
+    environment: &str,
+    context_override: Option<&str>,
+    verbose: bool,
+) -> Result<()> {
+    // An explicit --context wins over the mappings
+    if let Some(context) = context_override {
+        use_kube_context(context)
+            .with_context(|| format!("Failed to switch to kubectl context '{}'", context))?;
+        log::info!(
+            "Using kubectl context {} from --context, skipping mapping validation",
+            context.cyan()
+        );
+        return Ok(());
+    }
+
+    // Check if feature is disabled in user or project config
+    let config = crate::config::Config::load_merged(project_path)?;
+    if config.context.disable_context_check {
         if verbose {
-            eprintln!("{} Context validation disabled in config", "INFO:".cyan());
+            log::info!("Context validation disabled in config");
         }
         return Ok(());
     }
 
     // Get git repo identifier
-    let repo_id = match get_git_repo_identifier(project_path) {
+    let repo_id = match get_git_repo_identifier(project_path, config.context.remote.as_deref()) {
         Ok(id) => id,
         Err(_) => {
             if verbose {
-                eprintln!(
-                    "{} Not a git repository, skipping context validation",
-                    "INFO:".cyan()
-                );
+                log::info!("Not a git repository, skipping context validation");
             }
             return Ok(());
         }
     };
 
     // Get current kubectl context
-    let current_context = get_current_kube_context()
+    let mut current_context = get_current_kube_context()
         .context("Failed to get current kubectl context. Is kubectl installed and configured?")?;
 
     // Find and load context config (repo config takes precedence)
     let (config_path, mut context_config) = load_context_mappings(project_path)?;
 
+    // Branch-specific mappings take precedence when on a known branch
+    let branch = get_current_branch(project_path);
+
     // Check if mapping exists
-    match context_config.get_mapping(&repo_id, environment) {
+    match context_config.resolve_mapping(&repo_id, branch.as_deref(), environment) {
         Some(expected_context) => {
             // Mapping exists - validate
             if current_context != expected_context {
-                anyhow::bail!(
-                    "Kubernetes context mismatch!\n\
-                     Repository: {}\n\
-                     Environment: {}\n\
-                     Expected context: {}\n\
-                     Current context: {}\n\n\
-                     Please switch to the correct context with:\n\
-                     kubectl config use-context {}\n\n\
-                     Or update the mapping if the context has changed.",
-                    repo_id,
+                // Generate the kubeconfig entry first if it is missing and a login command is set
+                let template = context_config
+                    .login_command_for(&repo_id)
+                    .or_else(|| config.context.login_command_for(&repo_id));
+                let available_contexts = list_kube_contexts().unwrap_or_default();
+                if let Some(login_command) = login_command_to_run(
+                    template,
+                    &expected_context,
                     environment,
-                    expected_context.cyan(),
-                    current_context.red(),
-                    expected_context.cyan()
-                );
+                    &available_contexts,
+                ) {
+                    log::info!(
+                        "Context {} not found in kubeconfig, running login command",
+                        expected_context.cyan()
+                    );
+                    match crate::executor::execute_command(&login_command, project_path, verbose) {
+                        Ok(()) => {
+                            if let Ok(context) = get_current_kube_context() {
+                                current_context = context;
+                            }
+                        }
+                        Err(e) => {
+                            log::warning!("Login command failed: {}", e);
+                        }
+                    }
+                }
+            }
+
+            if current_context != expected_context {
+                return Err(MkError::ContextMismatch {
+                    repository: repo_id.to_string(),
+                    environment: environment.to_string(),
+                    expected: expected_context.to_string(),
+                    current: current_context,
+                }
+                .into());
             }
             if verbose {
                 eprintln!(
@@ -110,63 +222,237 @@ pub fn validate_context(project_path: &str, environment: &str, verbose: bool) ->
     Ok(())
 }
 
-/// Check if context validation is disabled in user config
-fn is_context_check_disabled() -> Result<bool> {
-    let user_config = crate::config::Config::load()?;
-    Ok(user_config.context.disable_context_check)
+/// Print how context validation resolves for a project and environment
+/// Read-only: never prompts, saves mappings or fails on a mismatch
+pub fn doctor(project_path: &str, environment: &str) -> Result<()> {
+    let config = crate::config::Config::load_merged(project_path)?;
+
+    if config.context.disable_context_check {
+        println!(
+            "{} Context validation is disabled in config",
+            "WARNING:".yellow()
+        );
+    }
+
+    let repo_id = match get_git_repo_identifier(project_path, config.context.remote.as_deref()) {
+        Ok(id) => id,
+        Err(e) => {
+            println!("Repository:       {} ({})", "unknown".red(), e);
+            println!("Validation is skipped outside git repositories with a remote");
+            return Ok(());
+        }
+    };
+    let branch = get_current_branch(project_path);
+
+    println!("Repository:       {}", repo_id);
+    println!(
+        "Branch:           {}",
+        branch.as_deref().unwrap_or("(detached HEAD)")
+    );
+    println!("Environment:      {}", environment);
+
+    let (config_path, context_config) = load_context_mappings(project_path)?;
+    let expected_context = context_config.resolve_mapping(&repo_id, branch.as_deref(), environment);
+    let is_branch_mapping = branch.as_deref().is_some_and(|branch| {
+        context_config
+            .get_branch_mapping(&repo_id, branch, environment)
+            .is_some()
+    });
+
+    match &expected_context {
+        Some(expected) => println!(
+            "Expected context: {} (from {}{})",
+            expected.cyan(),
+            config_path.display(),
+            if is_branch_mapping {
+                ", branch mapping"
+            } else {
+                ""
+            }
+        ),
+        None => println!(
+            "Expected context: {} (checked {})",
+            "not configured".yellow(),
+            config_path.display()
+        ),
+    }
+
+    let current_context = get_current_kube_context();
+    match &current_context {
+        Ok(current) => println!("Current context:  {}", current.cyan()),
+        Err(e) => println!("Current context:  {} ({})", "unavailable".red(), e),
+    }
+
+    match (expected_context, current_context) {
+        (Some(expected), Ok(current)) if expected == current => {
+            println!("Status:           {}", "match".green())
+        }
+        (Some(_), Ok(_)) => println!("Status:           {}", "mismatch".red()),
+        (None, Ok(_)) => println!(
+            "Status:           {}",
+            "no mapping, you will be prompted to save the current context".yellow()
+        ),
+        (_, Err(_)) => println!("Status:           {}", "unknown".yellow()),
+    }
+
+    Ok(())
 }
 
 /// Get git repository identifier (normalized remote URL)
-fn get_git_repo_identifier(project_path: &str) -> Result<String> {
+fn get_git_repo_identifier(project_path: &str, configured_remote: Option<&str>) -> Result<String> {
+    let output = Command::new("git")
+        .arg("remote")
+        .current_dir(project_path)
+        .output()
+        .context("Failed to list git remotes")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Not a git repository");
+    }
+
+    let remotes: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let remote = select_remote(&remotes, configured_remote)
+        .ok_or_else(|| anyhow::anyhow!("No git remote configured"))?;
+
     let output = Command::new("git")
-        .args(["remote", "get-url", "origin"])
+        .args(["remote", "get-url", remote])
         .current_dir(project_path)
         .output()
         .context("Failed to get git remote URL")?;
 
     if !output.status.success() {
-        anyhow::bail!("Not a git repository or no 'origin' remote configured");
+        anyhow::bail!("Failed to get URL of git remote '{}'", remote);
     }
 
     let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
     normalize_git_url(&url)
 }
 
+/// Get the current git branch, `None` on a detached HEAD or outside a repository
+fn get_current_branch(project_path: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if branch.is_empty() || branch == "HEAD" {
+        None
+    } else {
+        Some(branch)
+    }
+}
+
+/// Pick the remote identifying the repository:
+/// `origin`, then the configured remote, then the first remote
+fn select_remote<'a>(remotes: &'a [String], configured_remote: Option<&str>) -> Option<&'a str> {
+    let find = |name: &str| remotes.iter().find(|r| r.as_str() == name);
+
+    find("origin")
+        .or_else(|| configured_remote.and_then(find))
+        .or_else(|| remotes.first())
+        .map(String::as_str)
+}
+
 /// Normalize git URL to a consistent format
 /// Examples:
 ///   git@github.com:user/repo.git -> github.com/user/repo
 ///   https://github.com/user/repo.git -> github.com/user/repo
-///   ssh://git@gitlab.com/user/repo.git -> gitlab.com/user/repo
+///   ssh://git@gitlab.com:2222/group/subgroup/repo.git -> gitlab.com/group/subgroup/repo
 fn normalize_git_url(url: &str) -> Result<String> {
     let url = url.trim();
 
-    // Handle SSH format: git@github.com:user/repo.git
-    if let Some(ssh_part) = url.strip_prefix("git@") {
-        let normalized = ssh_part.replace(':', "/");
-        let normalized = normalized.strip_suffix(".git").unwrap_or(&normalized);
-        return Ok(normalized.to_string());
+    // Handle URL format: https://, http://, ssh://, git:// (user info and port are dropped)
+    if url.contains("://") {
+        if let Ok(parsed) = url::Url::parse(url) {
+            if let Some(host) = parsed.host_str() {
+                return Ok(join_host_path(host, parsed.path()));
+            }
+        }
+        return Ok(url.to_string());
     }
 
-    // Handle HTTPS format: https://github.com/user/repo.git
-    if let Some(https_part) = url.strip_prefix("https://") {
-        let normalized = https_part.strip_suffix(".git").unwrap_or(https_part);
-        return Ok(normalized.to_string());
+    // Handle scp-like SSH format: git@github.com:user/repo.git
+    // Only the first colon separates host and path, the path may contain more
+    if let Some((host, path)) = url.split_once(':') {
+        if !host.contains('/') {
+            let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+            return Ok(join_host_path(host, path));
+        }
     }
 
-    // Handle SSH URL format: ssh://git@gitlab.com/user/repo.git
-    if let Some(ssh_url) = url.strip_prefix("ssh://git@") {
-        let normalized = ssh_url.strip_suffix(".git").unwrap_or(ssh_url);
-        return Ok(normalized.to_string());
+    // If no recognized format, return as-is
+    Ok(url.to_string())
+}
+
+/// Join host and repository path, dropping surrounding slashes and the `.git` suffix
+fn join_host_path(host: &str, path: &str) -> String {
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    format!("{}/{}", host, path)
+}
+
+/// Render the login command to run, if the expected context is missing from kubeconfig
+fn login_command_to_run(
+    template: Option<&str>,
+    expected_context: &str,
+    environment: &str,
+    available_contexts: &[String],
+) -> Option<String> {
+    let template = template?;
+    if available_contexts.iter().any(|c| c == expected_context) {
+        return None;
+    }
+
+    Some(
+        template
+            .replace("{context}", expected_context)
+            .replace("{environment}", environment),
+    )
+}
+
+/// List the context names defined in kubeconfig
+fn list_kube_contexts() -> Result<Vec<String>> {
+    let output = Command::new("kubectl")
+        .args(["config", "get-contexts", "-o", "name"])
+        .output()
+        .context("Failed to execute kubectl command")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("kubectl command failed: {}", stderr);
     }
 
-    // Handle http format: http://github.com/user/repo.git
-    if let Some(http_part) = url.strip_prefix("http://") {
-        let normalized = http_part.strip_suffix(".git").unwrap_or(http_part);
-        return Ok(normalized.to_string());
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+/// Switch the current kubectl context
+fn use_kube_context(context: &str) -> Result<()> {
+    let output = Command::new("kubectl")
+        .args(["config", "use-context", context])
+        .output()
+        .context("Failed to execute kubectl command")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("kubectl command failed: {}", stderr.trim());
     }
 
-    // If no recognized format, return as-is
-    Ok(url.to_string())
+    Ok(())
 }
 
 /// Get current kubectl context
@@ -184,12 +470,27 @@ fn get_current_kube_context() -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
+/// Nearest `.mk/contexts.toml` from `start` upward, not looking past the git repository root
+fn find_repo_context_config(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().ok()?;
+    for dir in start.ancestors() {
+        let candidate = dir.join(".mk").join("contexts.toml");
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        // `.git` is a directory, or a file in worktrees and submodules
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    None
+}
+
 /// Find context config file (repo config takes precedence over user config)
 /// Returns the path to use for reading/writing and the loaded config
 fn load_context_mappings(project_path: &str) -> Result<(PathBuf, ContextConfig)> {
-    // Check for repo config first
-    let repo_config_path = Path::new(project_path).join(".mk").join("contexts.toml");
-    if repo_config_path.exists() {
+    // Check for repo config first, from the project up to the repository root
+    if let Some(repo_config_path) = find_repo_context_config(Path::new(project_path)) {
         let content =
             fs::read_to_string(&repo_config_path).context("Failed to read repo context config")?;
         let config: ContextConfig =
@@ -197,11 +498,12 @@ fn load_context_mappings(project_path: &str) -> Result<(PathBuf, ContextConfig)>
         return Ok((repo_config_path, config));
     }
 
-    // Fall back to user config
-    let user_config = crate::config::Config::load()?;
+    // Fall back to the user config with the project `.mk.toml` merged over it
+    // New mappings are still saved to the user config
+    let merged_config = crate::config::Config::load_merged(project_path)?;
     let user_config_path = get_user_config_path()?;
 
-    Ok((user_config_path, user_config.context.clone()))
+    Ok((user_config_path, merged_config.context))
 }
 
 /// Get the path to user config file
@@ -219,10 +521,7 @@ fn prompt_save_context(
     config_path: &Path,
     context_config: &mut ContextConfig,
 ) -> Result<()> {
-    eprintln!(
-        "\n{} No Kubernetes context configured for:",
-        "WARNING:".yellow()
-    );
+    log::warning!("\nNo Kubernetes context configured for:");
     eprintln!("  Repository: {}", repo_id.cyan());
     eprintln!("  Environment: {}", environment.cyan());
     eprintln!("\n  Current kubectl context: {}", current_context.cyan());
@@ -234,17 +533,18 @@ fn prompt_save_context(
 
     let response = response.trim().to_lowercase();
     if response.is_empty() || response == "y" || response == "yes" {
+        // Mappings read from a project `.mk.toml` stay out of the user config
+        if config_path == get_user_config_path()? {
+            *context_config = crate::config::Config::load()?.context;
+        }
+
         // Update context config
         context_config.set_mapping(repo_id, environment, current_context);
 
         // Save to appropriate config file
         save_context_config(config_path, context_config)?;
 
-        eprintln!(
-            "{} Context mapping saved to {}",
-            "SUCCESS:".green(),
-            config_path.display()
-        );
+        log::success!("Context mapping saved to {}", config_path.display());
         Ok(())
     } else {
         anyhow::bail!(
@@ -314,6 +614,28 @@ fn save_context_config(config_path: &Path, context_config: &ContextConfig) -> Re
         // Add disable_context_check
         doc["disable_context_check"] = toml_edit::value(context_config.disable_context_check);
 
+        // Keep the Ansible opt-in
+        if context_config.validate_ansible {
+            doc["validate_ansible"] = toml_edit::value(true);
+        }
+
+        // Keep the login commands
+        if let Some(login_command) = &context_config.login_command {
+            doc["login_command"] = toml_edit::value(login_command.as_str());
+        }
+        if !context_config.login_commands.is_empty() {
+            let mut login_commands_table = Table::new();
+            for (repo_id, login_command) in &context_config.login_commands {
+                login_commands_table.insert(repo_id, toml_edit::value(login_command.as_str()));
+            }
+            doc["login_commands"] = Item::Table(login_commands_table);
+        }
+
+        // Keep the configured remote
+        if let Some(remote) = &context_config.remote {
+            doc["remote"] = toml_edit::value(remote.as_str());
+        }
+
         // Add mappings table
         let mut mappings_table = Table::new();
         for (repo_id, environments) in &context_config.mappings {
@@ -325,6 +647,24 @@ fn save_context_config(config_path: &Path, context_config: &ContextConfig) -> Re
         }
         doc["mappings"] = Item::Table(mappings_table);
 
+        // Keep branch-specific mappings
+        if !context_config.branch_mappings.is_empty() {
+            let mut branch_mappings_table = Table::new();
+            for (repo_id, branches) in &context_config.branch_mappings {
+                let mut repo_table = Table::new();
+                repo_table.set_implicit(true);
+                for (branch, environments) in branches {
+                    let mut branch_table = Table::new();
+                    for (env, context) in environments {
+                        branch_table.insert(env, toml_edit::value(context.as_str()));
+                    }
+                    repo_table.insert(branch, Item::Table(branch_table));
+                }
+                branch_mappings_table.insert(repo_id, Item::Table(repo_table));
+            }
+            doc["branch_mappings"] = Item::Table(branch_mappings_table);
+        }
+
         fs::write(config_path, doc.to_string()).context("Failed to write context config file")?;
     }
 
@@ -335,6 +675,73 @@ fn save_context_config(config_path: &Path, context_config: &ContextConfig) -> Re
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_repo_context_config_found_from_nested_project() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        let project = repo.join("infra").join("apps").join("helm");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join(".mk")).unwrap();
+        fs::create_dir_all(&project).unwrap();
+        fs::write(
+            repo.join(".mk").join("contexts.toml"),
+            "[mappings.\"github.com/team/infra\"]\nprod = \"gke_prod\"\n",
+        )
+        .unwrap();
+
+        let (path, config) = load_context_mappings(project.to_str().unwrap()).unwrap();
+        assert_eq!(
+            path,
+            repo.join(".mk")
+                .join("contexts.toml")
+                .canonicalize()
+                .unwrap()
+        );
+        assert_eq!(
+            config.get_mapping("github.com/team/infra", "prod"),
+            Some("gke_prod".to_string())
+        );
+    }
+
+    #[test]
+    fn test_context_mappings_from_project_config() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        let project = repo.join("helm");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(&project).unwrap();
+        fs::write(
+            repo.join(".mk.toml"),
+            "[context.mappings.\"github.com/team/project-only\"]\nprod = \"gke_prod\"\n\n\
+             [context.branch_mappings.\"github.com/team/project-only\".release]\nprod = \"gke_release\"\n",
+        )
+        .unwrap();
+
+        let (path, config) = load_context_mappings(project.to_str().unwrap()).unwrap();
+        assert_eq!(path, get_user_config_path().unwrap());
+        assert_eq!(
+            config.resolve_mapping("github.com/team/project-only", None, "prod"),
+            Some("gke_prod".to_string())
+        );
+        assert_eq!(
+            config.resolve_mapping("github.com/team/project-only", Some("release"), "prod"),
+            Some("gke_release".to_string())
+        );
+    }
+
+    #[test]
+    fn test_repo_context_config_search_stops_at_repo_root() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        let project = repo.join("helm");
+        fs::create_dir_all(temp_dir.path().join(".mk")).unwrap();
+        fs::write(temp_dir.path().join(".mk").join("contexts.toml"), "").unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(&project).unwrap();
+
+        assert_eq!(find_repo_context_config(&project), None);
+    }
+
     #[test]
     fn test_normalize_git_url_ssh() {
         let url = "git@github.com:user/repo.git";
@@ -370,6 +777,66 @@ mod tests {
         assert_eq!(result, "github.com/user/repo");
     }
 
+    #[test]
+    fn test_normalize_git_url_ssh_with_port() {
+        let url = "ssh://git@gitlab.com:2222/group/repo.git";
+        let result = normalize_git_url(url).unwrap();
+        assert_eq!(result, "gitlab.com/group/repo");
+    }
+
+    #[test]
+    fn test_normalize_git_url_gitlab_subgroups() {
+        let url = "ssh://git@gitlab.com:2222/group/subgroup/repo.git";
+        let result = normalize_git_url(url).unwrap();
+        assert_eq!(result, "gitlab.com/group/subgroup/repo");
+
+        let url = "git@gitlab.com:group/subgroup/nested/repo.git";
+        let result = normalize_git_url(url).unwrap();
+        assert_eq!(result, "gitlab.com/group/subgroup/nested/repo");
+    }
+
+    #[test]
+    fn test_normalize_git_url_azure_devops() {
+        let url = "git@ssh.dev.azure.com:v3/org/project/repo";
+        let result = normalize_git_url(url).unwrap();
+        assert_eq!(result, "ssh.dev.azure.com/v3/org/project/repo");
+    }
+
+    #[test]
+    fn test_normalize_git_url_colon_in_path() {
+        let url = "git@example.com:team/repo:v2.git";
+        let result = normalize_git_url(url).unwrap();
+        assert_eq!(result, "example.com/team/repo:v2");
+    }
+
+    fn remotes(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_select_remote_prefers_origin() {
+        let remotes = remotes(&["upstream", "origin"]);
+        assert_eq!(select_remote(&remotes, Some("upstream")), Some("origin"));
+    }
+
+    #[test]
+    fn test_select_remote_uses_configured_without_origin() {
+        let remotes = remotes(&["fork", "upstream"]);
+        assert_eq!(select_remote(&remotes, Some("upstream")), Some("upstream"));
+    }
+
+    #[test]
+    fn test_select_remote_falls_back_to_first() {
+        let remotes = remotes(&["fork", "upstream"]);
+        assert_eq!(select_remote(&remotes, None), Some("fork"));
+        assert_eq!(select_remote(&remotes, Some("missing")), Some("fork"));
+    }
+
+    #[test]
+    fn test_select_remote_none_without_remotes() {
+        assert_eq!(select_remote(&[], Some("upstream")), None);
+    }
+
     #[test]
     fn test_context_config_get_mapping() {
         let mut config = ContextConfig::default();
@@ -383,6 +850,174 @@ mod tests {
         assert_eq!(config.get_mapping("other/repo", "prod"), None);
     }
 
+    fn branch_config() -> ContextConfig {
+        let mut config = ContextConfig::default();
+        config.set_mapping("github.com/user/repo", "staging", "gke-staging");
+        config.set_mapping("github.com/user/repo", "prod", "gke-prod");
+        config.branch_mappings.insert(
+            "github.com/user/repo".to_string(),
+            HashMap::from([(
+                "develop".to_string(),
+                HashMap::from([("staging".to_string(), "gke-develop".to_string())]),
+            )]),
+        );
+        config
+    }
+
+    #[test]
+    fn test_context_config_resolve_branch_mapping() {
+        let config = branch_config();
+
+        assert_eq!(
+            config.resolve_mapping("github.com/user/repo", Some("develop"), "staging"),
+            Some("gke-develop".to_string())
+        );
+    }
+
+    #[test]
+    fn test_context_config_resolve_falls_back_to_branch_agnostic() {
+        let config = branch_config();
+
+        // No mapping for this environment on the branch
+        assert_eq!(
+            config.resolve_mapping("github.com/user/repo", Some("develop"), "prod"),
+            Some("gke-prod".to_string())
+        );
+        // Other branch
+        assert_eq!(
+            config.resolve_mapping("github.com/user/repo", Some("main"), "staging"),
+            Some("gke-staging".to_string())
+        );
+        // Detached HEAD
+        assert_eq!(
+            config.resolve_mapping("github.com/user/repo", None, "staging"),
+            Some("gke-staging".to_string())
+        );
+    }
+
+    #[test]
+    fn test_context_config_branch_mappings_from_toml() {
+        let config: ContextConfig = toml::from_str(
+            r#"
+[mappings."github.com/user/repo"]
+prod = "gke-prod"
+
+[branch_mappings."github.com/user/repo".main]
+prod = "gke-prod-main"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.resolve_mapping("github.com/user/repo", Some("main"), "prod"),
+            Some("gke-prod-main".to_string())
+        );
+        assert_eq!(
+            config.get_mapping("github.com/user/repo", "prod"),
+            Some("gke-prod".to_string())
+        );
+    }
+
+    #[test]
+    fn test_login_command_runs_when_context_missing() {
+        let available = vec!["gke-staging".to_string()];
+        let command = login_command_to_run(
+            Some("gcloud container clusters get-credentials {context} --project {environment}"),
+            "gke-prod",
+            "prod",
+            &available,
+        );
+
+        assert_eq!(
+            command,
+            Some("gcloud container clusters get-credentials gke-prod --project prod".to_string())
+        );
+    }
+
+    #[test]
+    fn test_login_command_skipped_when_context_exists() {
+        let available = vec!["gke-staging".to_string(), "gke-prod".to_string()];
+        let command = login_command_to_run(
+            Some("aws eks update-kubeconfig --name {context}"),
+            "gke-prod",
+            "prod",
+            &available,
+        );
+
+        assert_eq!(command, None);
+    }
+
+    #[test]
+    fn test_login_command_skipped_without_template() {
+        assert_eq!(login_command_to_run(None, "gke-prod", "prod", &[]), None);
+    }
+
+    #[test]
+    fn test_context_config_login_command_for_repo() {
+        let mut config = ContextConfig {
+            login_command: Some("default {context}".to_string()),
+            ..Default::default()
+        };
+        config.login_commands.insert(
+            "github.com/user/eks".to_string(),
+            "aws {context}".to_string(),
+        );
+
+        assert_eq!(
+            config.login_command_for("github.com/user/eks"),
+            Some("aws {context}")
+        );
+        assert_eq!(
+            config.login_command_for("github.com/user/other"),
+            Some("default {context}")
+        );
+    }
+
+    #[test]
+    fn test_requires_validation_helm_and_kustomize() {
+        assert!(requires_validation(Technology::Helm, &Action::Apply, false));
+        assert!(requires_validation(
+            Technology::Kustomize,
+            &Action::Delete,
+            false
+        ));
+        assert!(!requires_validation(Technology::Helm, &Action::List, false));
+        assert!(!requires_validation(
+            Technology::Terraform,
+            &Action::Apply,
+            true
+        ));
+    }
+
+    #[test]
+    fn test_requires_validation_ansible_opt_in() {
+        assert!(!requires_validation(
+            Technology::Ansible,
+            &Action::Apply,
+            false
+        ));
+        assert!(requires_validation(
+            Technology::Ansible,
+            &Action::Apply,
+            true
+        ));
+        assert!(requires_validation(
+            Technology::Ansible,
+            &Action::Diff,
+            true
+        ));
+        assert!(!requires_validation(
+            Technology::Ansible,
+            &Action::Check,
+            true
+        ));
+        assert!(!requires_validation(
+            Technology::Ansible,
+            &Action::List,
+            true
+        ));
+    }
+
     #[test]
     fn test_context_config_set_mapping() {
         let mut config = ContextConfig::default();
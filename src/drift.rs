@@ -2,12 +2,19 @@ use anyhow::{Context, Result};
 use colored::*;
 use ignore::WalkBuilder;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::HashMap;
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
+use crate::cli::DriftTech;
+use crate::commands::ActionOptions;
+use crate::config::Config;
 use crate::env;
+use crate::log;
+use crate::output::{self, OutputFormat};
+use crate::path_filter::PathFilter;
 use crate::techno::{self, Technology};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -24,6 +31,8 @@ pub struct DriftResult {
     pub technology: Technology,
     pub status: DriftStatus,
     pub output: Option<String>,
+    /// Wall-clock time of the check
+    pub duration: Duration,
 }
 
 #[derive(Debug)]
@@ -35,59 +44,260 @@ pub struct DriftSummary {
     pub error_items: Vec<(String, String)>,
 }
 
-/// Main entry point for drift detection
-pub fn run_drift(
-    base_path: &str,
-    verbose: bool,
-    tech_filter: Option<String>,
-    env_filter: Vec<String>,
-    capture: bool,
-    max_depth: usize,
-    no_ignore: bool,
-) -> Result<()> {
-    eprintln!(
-        "{} Scanning: {} (max depth: {})",
-        "INFO:".cyan(),
-        base_path,
-        max_depth
+/// Options for `mk drift`
+pub struct DriftOptions {
+    pub verbose: bool,
+    pub tech_filter: Option<DriftTech>,
+    pub env_filter: Vec<String>,
+    /// Also check the environments matching this regex
+    pub env_regex: Option<Regex>,
+    pub capture: bool,
+    /// Capture output for every status, not only results with output
+    pub capture_all: bool,
+    /// Stop at the first drift or error
+    pub fail_fast: bool,
+    pub max_depth: usize,
+    pub no_ignore: bool,
+    /// Globs restricting the scan to matching project paths
+    pub path_filters: Vec<String>,
+    /// Only check projects with files changed since this git ref
+    pub changed_since: Option<String>,
+    /// Fail instead of warning when no project is found
+    pub require_projects: bool,
+    /// Show absolute project paths instead of paths relative to the base
+    pub absolute_paths: bool,
+    /// Text summary, or one JSON object with every result on stdout
+    pub format: OutputFormat,
+    /// Flags of the plan and diff run for each check
+    pub action_options: ActionOptions,
+}
+
+/// Environment names listed one per line, skipping blank lines and `#` comments
+fn parse_env_file(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `--env` values followed by the environments of `--env-file`, without duplicates
+pub fn env_filter(environments: Vec<String>, env_file: Option<&str>) -> Result<Vec<String>> {
+    let mut filter = environments;
+    if let Some(env_file) = env_file {
+        let content = fs::read_to_string(env_file)
+            .with_context(|| format!("Failed to read env file {}", env_file))?;
+        for environment in parse_env_file(&content) {
+            if !filter.contains(&environment) {
+                filter.push(environment);
+            }
+        }
+    }
+    Ok(filter)
+}
+
+/// Compiled `--env-regex` pattern
+pub fn env_regex(pattern: Option<&str>) -> Result<Option<Regex>> {
+    pattern
+        .map(|pattern| {
+            Regex::new(pattern).with_context(|| format!("Invalid --env-regex pattern {}", pattern))
+        })
+        .transpose()
+}
+
+/// Whether drift checks `environment`: every one without filters, otherwise those listed in
+/// `env_filter` or matching `env_regex`
+fn selects_environment(
+    environment: &str,
+    env_filter: &[String],
+    env_regex: Option<&Regex>,
+) -> bool {
+    if env_filter.is_empty() && env_regex.is_none() {
+        return true;
+    }
+    env_filter.iter().any(|e| e == environment)
+        || env_regex.is_some_and(|regex| regex.is_match(environment))
+}
+
+/// Source of files changed since a git ref
+trait ChangedFiles {
+    /// Absolute paths of the files under `base` changed since `git_ref`
+    fn changed_files(&self, base: &Path, git_ref: &str) -> Result<Vec<PathBuf>>;
+}
+
+/// Changed files reported by `git diff --name-only`
+struct GitChangedFiles;
+
+impl ChangedFiles for GitChangedFiles {
+    fn changed_files(&self, base: &Path, git_ref: &str) -> Result<Vec<PathBuf>> {
+        let git = |args: &[&str]| -> Result<String> {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .current_dir(base)
+                .output()
+                .context("Failed to execute git")?;
+            if !output.status.success() {
+                anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        };
+
+        // Names are relative to the repository root
+        let toplevel = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim());
+        let toplevel = toplevel.canonicalize().unwrap_or(toplevel);
+        let names = git(&["diff", "--name-only", git_ref, "--", "."])?;
+
+        Ok(names
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| toplevel.join(line))
+            .collect())
+    }
+}
+
+/// Keep the projects containing files changed since `git_ref`
+/// Falls back to every project with a warning when git cannot answer
+fn select_changed_projects(
+    projects: Vec<(String, Technology)>,
+    base: &Path,
+    git_ref: &str,
+    source: &dyn ChangedFiles,
+) -> Vec<(String, Technology)> {
+    let changed = match source.changed_files(base, git_ref) {
+        Ok(changed) => changed,
+        Err(e) => {
+            log::warning!(
+                "Cannot list changes since {} ({}), checking all projects",
+                git_ref,
+                e
+            );
+            return projects;
+        }
+    };
+
+    let total = projects.len();
+    let selected: Vec<_> = projects
+        .into_iter()
+        .filter(|(project_path, _)| {
+            changed
+                .iter()
+                .any(|file| file.starts_with(Path::new(project_path)))
+        })
+        .collect();
+
+    log::info!(
+        "{} of {} project(s) changed since {}",
+        selected.len(),
+        total,
+        git_ref
     );
 
-    // Find all IaC projects
-    let projects = scan_for_projects(base_path, max_depth, tech_filter.as_deref(), no_ignore)?;
+    selected
+}
+
+/// Main entry point for drift detection
+pub fn run_drift(base_path: &str, options: DriftOptions) -> Result<()> {
+    let DriftOptions {
+        verbose,
+        tech_filter,
+        env_filter,
+        env_regex,
+        capture,
+        capture_all,
+        fail_fast,
+        max_depth,
+        no_ignore,
+        path_filters,
+        changed_since,
+        require_projects,
+        absolute_paths,
+        format,
+        action_options,
+    } = options;
+    let capture = capture || capture_all;
+    let path_filter = PathFilter::new(&path_filters)?;
+
+    // Groups from `[env] groups` stand for their member environments
+    let env_groups = Config::load_merged(base_path)?.env.groups;
+    let requested_envs = env_filter;
+    let mut env_filter = env::expand_env_groups(&requested_envs, &env_groups);
+
+    // A single environment of a project itself needs no scan, unless --path-filter must
+    // decide whether the project is checked
+    let single = if env_regex.is_none() && path_filters.is_empty() {
+        single_project(base_path, &env_filter, tech_filter)
+    } else {
+        None
+    };
+    let projects = match single {
+        Some(techno) => {
+            env_filter[0] = env::check_environment(base_path, &env_filter[0], techno, no_ignore)?;
+            log::info!(
+                "Checking {} ({}) directly, no scan needed",
+                base_path,
+                env_filter[0]
+            );
+            // Absolute like scanned projects, to match --changed-since paths
+            let project_path = Path::new(base_path).canonicalize()?;
+            vec![(project_path.to_string_lossy().to_string(), techno)]
+        }
+        None => {
+            log::info!("Scanning: {} (max depth: {})", base_path, max_depth);
+
+            // Find all IaC projects
+            scan_for_projects(base_path, max_depth, tech_filter, &path_filter, no_ignore)?
+        }
+    };
+
+    // A scan finding nothing is an error, unlike --changed-since selecting nothing
+    if projects.is_empty() && require_projects {
+        anyhow::bail!("No IaC projects found in {}", base_path);
+    }
+
+    // Skip projects untouched since --changed-since
+    let projects = match &changed_since {
+        Some(git_ref) => select_changed_projects(
+            projects,
+            &Path::new(base_path).canonicalize()?,
+            git_ref,
+            &GitChangedFiles,
+        ),
+        None => projects,
+    };
 
     if projects.is_empty() {
-        eprintln!("{} No IaC projects found", "WARNING:".yellow());
+        log::warning!("No IaC projects found");
         return Ok(());
     }
 
     // Count total checks to perform
     let mut total_checks = 0;
     let mut project_env_map: HashMap<String, Vec<String>> = HashMap::new();
+    let mut known_envs = BTreeSet::new();
 
     for (project_path, techno) in &projects {
         if let Ok(environments) = env::get_environments(project_path, *techno, no_ignore) {
-            let filtered_envs: Vec<String> = if env_filter.is_empty() {
-                environments
-            } else {
-                environments
-                    .into_iter()
-                    .filter(|e| env_filter.contains(e))
-                    .collect()
-            };
+            known_envs.extend(environments.iter().cloned());
+            let filtered_envs: Vec<String> = environments
+                .into_iter()
+                .filter(|e| selects_environment(e, &env_filter, env_regex.as_ref()))
+                .collect();
             total_checks += filtered_envs.len();
             project_env_map.insert(project_path.clone(), filtered_envs);
         }
     }
 
-    eprintln!(
-        "{} Found {} project(s), {} total check(s) to perform\n",
-        "INFO:".cyan(),
+    env::validate_env_groups(&requested_envs, &env_groups, &known_envs)?;
+
+    log::info!(
+        "Found {} project(s), {} total check(s) to perform\n",
         projects.len(),
         total_checks
     );
 
     if total_checks == 0 {
-        eprintln!("{} No environments to check", "WARNING:".yellow());
+        log::warning!("No environments to check");
         return Ok(());
     }
 
@@ -100,39 +310,85 @@ pub fn run_drift(
             .progress_chars("█▓▒░"),
     );
 
+    // Projects are shown relative to the base unless --absolute-paths
+    let display_base = if absolute_paths {
+        None
+    } else {
+        Path::new(base_path).canonicalize().ok()
+    };
+    let display = |path: &str| display_path(path, display_base.as_deref());
+
     // Perform drift checks
+    let settings = CheckSettings {
+        verbose,
+        capture,
+        capture_all,
+        action_options: &action_options,
+    };
+    let started = Instant::now();
     let mut results = Vec::new();
     let log_dir = if capture {
-        Some(create_log_dir()?)
+        Some(CaptureLogDir::create(Path::new(LOG_ROOT))?)
     } else {
         None
     };
 
-    for (project_path, techno) in &projects {
+    'checks: for (project_path, techno) in &projects {
         if let Some(environments) = project_env_map.get(project_path) {
             for env in environments {
-                pb.set_message(format!("{} ({})", project_path, env));
+                pb.set_message(format!("{} ({})", display(project_path), env));
 
                 let result = check_drift(
                     project_path,
                     env,
                     *techno,
-                    verbose,
-                    capture,
-                    log_dir.as_deref(),
+                    settings,
+                    log_dir.as_ref().map(CaptureLogDir::path),
                 )?;
 
+                if verbose {
+                    pb.suspend(|| {
+                        log::info!(
+                            "{} ({}): {} in {:.1}s",
+                            display(&result.path),
+                            result.environment,
+                            status_name(&result.status),
+                            result.duration.as_secs_f64()
+                        )
+                    });
+                }
+
+                let failed = result.status != DriftStatus::Ok;
                 results.push(result);
                 pb.inc(1);
+
+                if fail_fast && failed {
+                    break 'checks;
+                }
             }
         }
     }
 
     pb.finish_and_clear();
 
+    if results.len() < total_checks {
+        log::warning!(
+            "Stopped at the first failure (--fail-fast), skipped {} check(s)",
+            total_checks - results.len()
+        );
+    }
+
+    if let Some(log_dir) = log_dir {
+        log_dir.complete()?;
+    }
+
     // Print summary
-    let summary = generate_summary(&results);
-    print_summary(&summary);
+    let elapsed = started.elapsed();
+    let summary = generate_summary(&results, display_base.as_deref());
+    match format {
+        OutputFormat::Text => print_summary(&summary, elapsed),
+        OutputFormat::Json => output::print_json("drift", drift_json(&results, elapsed)),
+    }
 
     // Exit with appropriate code
     if summary.drift_count > 0 {
@@ -144,11 +400,34 @@ pub fn run_drift(
     Ok(())
 }
 
+/// Whether drift checks `techno` projects: terraform and helm only, narrowed by `--tech`
+fn is_drift_candidate(techno: Technology, tech_filter: Option<DriftTech>) -> bool {
+    match tech_filter {
+        Some(DriftTech::Terraform) => techno == Technology::Terraform,
+        Some(DriftTech::Helm) => techno == Technology::Helm,
+        None => matches!(techno, Technology::Terraform | Technology::Helm),
+    }
+}
+
+/// Technology of `base_path` when drift targets one environment of that project directly
+fn single_project(
+    base_path: &str,
+    env_filter: &[String],
+    tech_filter: Option<DriftTech>,
+) -> Option<Technology> {
+    if env_filter.len() != 1 {
+        return None;
+    }
+    techno::detect_technology_direct(base_path)
+        .filter(|techno| is_drift_candidate(*techno, tech_filter))
+}
+
 /// Scan directory recursively for IaC projects
 fn scan_for_projects(
     base_path: &str,
     max_depth: usize,
-    tech_filter: Option<&str>,
+    tech_filter: Option<DriftTech>,
+    path_filter: &PathFilter,
     no_ignore: bool,
 ) -> Result<Vec<(String, Technology)>> {
     let mut projects = Vec::new();
@@ -169,24 +448,15 @@ fn scan_for_projects(
             continue;
         }
 
+        // Skip projects outside the --path-filter globs
+        if !path_filter.matches(&base, path) {
+            continue;
+        }
+
         // Try to detect technology directly (no child scanning)
         // This ensures we only detect at the leaf level (actual chart/project directories)
         if let Some(techno) = techno::detect_technology_direct(path.to_str().unwrap()) {
-            // Apply technology filter
-            if let Some(filter) = tech_filter {
-                let filter_lower = filter.to_lowercase();
-                let matches = match techno {
-                    Technology::Terraform => filter_lower == "terraform",
-                    Technology::Helm => filter_lower == "helm",
-                    _ => false,
-                };
-                if !matches {
-                    continue;
-                }
-            }
-
-            // Only include terraform and helm
-            if matches!(techno, Technology::Terraform | Technology::Helm) {
+            if is_drift_candidate(techno, tech_filter) {
                 let project_path = path.to_string_lossy().to_string();
                 // Avoid duplicates
                 if !projects.iter().any(|(p, _)| p == &project_path) {
@@ -199,18 +469,27 @@ fn scan_for_projects(
     Ok(projects)
 }
 
+/// Flags applying to every project/environment check of a drift run
+#[derive(Debug, Clone, Copy)]
+struct CheckSettings<'a> {
+    verbose: bool,
+    capture: bool,
+    capture_all: bool,
+    action_options: &'a ActionOptions,
+}
+
 /// Check for drift in a single project/environment
 fn check_drift(
     project_path: &str,
     environment: &str,
     techno: Technology,
-    verbose: bool,
-    capture: bool,
+    settings: CheckSettings<'_>,
     log_dir: Option<&Path>,
 ) -> Result<DriftResult> {
-    let result = match techno {
-        Technology::Terraform => check_terraform_drift(project_path, environment, verbose)?,
-        Technology::Helm => check_helm_drift(project_path, environment, verbose)?,
+    let started = Instant::now();
+    let mut result = match techno {
+        Technology::Terraform => check_terraform_drift(project_path, environment, settings)?,
+        Technology::Helm => check_helm_drift(project_path, environment, settings)?,
         _ => {
             return Ok(DriftResult {
                 path: project_path.to_string(),
@@ -218,12 +497,14 @@ fn check_drift(
                 technology: techno,
                 status: DriftStatus::Error("Unsupported technology".to_string()),
                 output: None,
+                duration: started.elapsed(),
             });
         }
     };
+    result.duration = started.elapsed();
 
     // Save output if capture is enabled
-    if capture && result.output.is_some() {
+    if settings.capture && should_save_output(&result, settings.capture_all) {
         if let Some(log_dir) = log_dir {
             save_output(log_dir, &result)?;
         }
@@ -232,21 +513,33 @@ fn check_drift(
     Ok(result)
 }
 
+/// Extra options for the drift command of `techno`
+/// Captured output goes to log files, so ANSI colors are turned off
+fn drift_options(techno: Technology, capture: bool) -> Vec<String> {
+    let mut options = match techno {
+        Technology::Terraform => vec!["-detailed-exitcode".to_string(), "-input=false".to_string()],
+        _ => Vec::new(),
+    };
+    if capture {
+        match techno {
+            Technology::Terraform => options.push("-no-color".to_string()),
+            Technology::Helm => options.push("--no-color".to_string()),
+            _ => {}
+        }
+    }
+    options
+}
+
 /// Check terraform drift using terraform plan with -detailed-exitcode
 fn check_terraform_drift(
     project_path: &str,
     environment: &str,
-    verbose: bool,
+    settings: CheckSettings<'_>,
 ) -> Result<DriftResult> {
     use crate::Action;
 
-    if verbose {
-        eprintln!(
-            "\n{} Checking drift for {} ({})",
-            "INFO:".cyan(),
-            project_path,
-            environment
-        );
+    if settings.verbose {
+        log::info!("\nChecking drift for {} ({})", project_path, environment);
     }
 
     // Use the unified execute_action_internal in drift mode
@@ -254,10 +547,9 @@ fn check_terraform_drift(
         Action::Plan,
         project_path,
         environment,
-        &["-detailed-exitcode".to_string(), "-input=false".to_string()],
-        verbose,
-        true,  // drift_mode = true
-        false, // no_ignore = false (drift doesn't need this for env check)
+        &drift_options(Technology::Terraform, settings.capture),
+        true, // drift_mode = true
+        settings.action_options,
     ) {
         Ok(result) => result,
         Err(e) => {
@@ -267,6 +559,7 @@ fn check_terraform_drift(
                 technology: Technology::Terraform,
                 status: DriftStatus::Error(format!("Execution failed: {}", e)),
                 output: None,
+                duration: Duration::ZERO,
             });
         }
     };
@@ -284,20 +577,20 @@ fn check_terraform_drift(
         technology: Technology::Terraform,
         status,
         output,
+        duration: Duration::ZERO,
     })
 }
 
 /// Check helm drift using helm diff
-fn check_helm_drift(project_path: &str, environment: &str, verbose: bool) -> Result<DriftResult> {
+fn check_helm_drift(
+    project_path: &str,
+    environment: &str,
+    settings: CheckSettings<'_>,
+) -> Result<DriftResult> {
     use crate::Action;
 
-    if verbose {
-        eprintln!(
-            "\n{} Checking drift for {} ({})",
-            "INFO:".cyan(),
-            project_path,
-            environment
-        );
+    if settings.verbose {
+        log::info!("\nChecking drift for {} ({})", project_path, environment);
     }
 
     // Use the unified execute_action_internal in drift mode
@@ -305,10 +598,9 @@ fn check_helm_drift(project_path: &str, environment: &str, verbose: bool) -> Res
         Action::Diff,
         project_path,
         environment,
-        &[],
-        verbose,
-        true,  // drift_mode = true
-        false, // no_ignore = false (drift doesn't need this for env check)
+        &drift_options(Technology::Helm, settings.capture),
+        true, // drift_mode = true
+        settings.action_options,
     ) {
         Ok(result) => result,
         Err(e) => {
@@ -318,6 +610,7 @@ fn check_helm_drift(project_path: &str, environment: &str, verbose: bool) -> Res
                 technology: Technology::Helm,
                 status: DriftStatus::Error(format!("Execution failed: {}", e)),
                 output: None,
+                duration: Duration::ZERO,
             });
         }
     };
@@ -335,11 +628,21 @@ fn check_helm_drift(project_path: &str, environment: &str, verbose: bool) -> Res
         technology: Technology::Helm,
         status,
         output,
+        duration: Duration::ZERO,
     })
 }
 
-/// Generate summary statistics
-fn generate_summary(results: &[DriftResult]) -> DriftSummary {
+/// `path` relative to `base` when it is inside it (`.` for the base itself), otherwise unchanged
+fn display_path(path: &str, base: Option<&Path>) -> String {
+    match base.map(|base| Path::new(path).strip_prefix(base)) {
+        Some(Ok(relative)) if relative.as_os_str().is_empty() => ".".to_string(),
+        Some(Ok(relative)) => relative.to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// Generate summary statistics, listing project paths relative to `base` when given
+fn generate_summary(results: &[DriftResult], base: Option<&Path>) -> DriftSummary {
     let mut ok_count = 0;
     let mut drift_count = 0;
     let mut error_count = 0;
@@ -351,12 +654,20 @@ fn generate_summary(results: &[DriftResult]) -> DriftSummary {
             DriftStatus::Ok => ok_count += 1,
             DriftStatus::Drift => {
                 drift_count += 1;
-                drift_items.push(format!("{} ({})", result.path, result.environment));
+                drift_items.push(format!(
+                    "{} ({})",
+                    display_path(&result.path, base),
+                    result.environment
+                ));
             }
             DriftStatus::Error(msg) => {
                 error_count += 1;
                 error_items.push((
-                    format!("{} ({})", result.path, result.environment),
+                    format!(
+                        "{} ({})",
+                        display_path(&result.path, base),
+                        result.environment
+                    ),
                     msg.clone(),
                 ));
             }
@@ -372,12 +683,47 @@ fn generate_summary(results: &[DriftResult]) -> DriftSummary {
     }
 }
 
+/// Lowercase name of a status, as shown in verbose and JSON output
+fn status_name(status: &DriftStatus) -> &'static str {
+    match status {
+        DriftStatus::Ok => "ok",
+        DriftStatus::Drift => "drift",
+        DriftStatus::Error(_) => "error",
+    }
+}
+
+/// JSON fields of `mk drift --format json`: every result with its duration, and the total time
+fn drift_json(results: &[DriftResult], elapsed: Duration) -> serde_json::Value {
+    let results: Vec<serde_json::Value> = results
+        .iter()
+        .map(|result| {
+            let mut value = serde_json::json!({
+                "path": result.path,
+                "environment": result.environment,
+                "technology": result.technology.as_str(),
+                "status": status_name(&result.status),
+                "duration_secs": result.duration.as_secs_f64(),
+            });
+            if let DriftStatus::Error(message) = &result.status {
+                value["error"] = serde_json::Value::from(message.as_str());
+            }
+            value
+        })
+        .collect();
+
+    serde_json::json!({
+        "results": results,
+        "elapsed_secs": elapsed.as_secs_f64(),
+    })
+}
+
 /// Print summary
-fn print_summary(summary: &DriftSummary) {
+fn print_summary(summary: &DriftSummary, elapsed: Duration) {
     eprintln!("\n{}", "Summary:".bold());
     eprintln!("  {} {} OK", "✓".green(), summary.ok_count);
     eprintln!("  {} {} Drift Detected", "⚠".yellow(), summary.drift_count);
     eprintln!("  {} {} Errors", "✗".red(), summary.error_count);
+    eprintln!("  Elapsed: {:.1}s", elapsed.as_secs_f64());
 
     if !summary.drift_items.is_empty() {
         eprintln!("\n{}", "Drift detected in:".yellow().bold());
@@ -394,40 +740,373 @@ fn print_summary(summary: &DriftSummary) {
     }
 }
 
-/// Create log directory for captured output
-fn create_log_dir() -> Result<PathBuf> {
-    let timestamp = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)?
-        .as_secs();
+/// Root directory for captured drift logs
+const LOG_ROOT: &str = ".drift-logs";
 
-    let log_dir = PathBuf::from(".drift-logs").join(format!("drift-{}", timestamp));
-    fs::create_dir_all(&log_dir).context("Failed to create log directory")?;
+/// Marker file telling whether a capture run finished (`status: complete`)
+/// or was cut short (`status: incomplete`)
+const CAPTURE_STATUS_FILE: &str = "status";
 
-    eprintln!(
-        "{} Capturing output to: {}",
-        "INFO:".cyan(),
-        log_dir.display()
-    );
+/// Log directory for captured output
+/// The status marker stays `incomplete` unless `complete` is called, so runs
+/// interrupted by an error, a panic or a signal can be told apart
+struct CaptureLogDir {
+    path: PathBuf,
+    completed: bool,
+}
+
+impl CaptureLogDir {
+    /// Create a timestamped log directory under `root` marked as incomplete
+    fn create(root: &Path) -> Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs();
 
-    Ok(log_dir)
+        let path = root.join(format!("drift-{}", timestamp));
+        fs::create_dir_all(&path).context("Failed to create log directory")?;
+        write_capture_status(&path, "incomplete")?;
+
+        log::info!("Capturing output to: {}", path.display());
+
+        Ok(Self {
+            path,
+            completed: false,
+        })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Mark the capture run as complete
+    fn complete(mut self) -> Result<()> {
+        write_capture_status(&self.path, "complete")?;
+        self.completed = true;
+        Ok(())
+    }
+}
+
+impl Drop for CaptureLogDir {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+
+        // Prune the directory on early exit if nothing was captured yet
+        let only_marker = fs::read_dir(&self.path)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .all(|e| e.file_name() == CAPTURE_STATUS_FILE)
+            })
+            .unwrap_or(false);
+        if only_marker {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+fn write_capture_status(log_dir: &Path, status: &str) -> Result<()> {
+    fs::write(
+        log_dir.join(CAPTURE_STATUS_FILE),
+        format!("status: {}\n", status),
+    )
+    .context("Failed to write capture status")
+}
+
+/// Whether a result is written to the log directory
+/// `--capture` keeps results with output, `--capture-all` keeps every result
+fn should_save_output(result: &DriftResult, capture_all: bool) -> bool {
+    capture_all || result.output.is_some()
+}
+
+/// Log file name for a result: readable path and environment, plus a short
+/// stable hash of the absolute path and environment so that paths differing
+/// only in separators or dots never overwrite each other
+fn log_file_name(path: &str, environment: &str) -> String {
+    let absolute_path = fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string());
+    let hash = stable_hash(format!("{}\0{}", absolute_path, environment).as_bytes());
+
+    format!(
+        "{}__{}-{:08x}.log",
+        path.trim_start_matches('/').replace(['/', '.'], "_"),
+        environment,
+        hash as u32
+    )
+}
+
+/// FNV-1a hash, identical across runs and toolchains
+fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
 }
 
 /// Save output to log file
 fn save_output(log_dir: &Path, result: &DriftResult) -> Result<()> {
-    if let Some(output) = &result.output {
-        let filename = format!(
-            "{}_{}.log",
-            result.path.replace(['/', '.'], "_"),
-            result.environment
+    let log_file = log_dir.join(log_file_name(&result.path, &result.environment));
+    let output = result.output.as_deref().unwrap_or("(no output)");
+
+    let content = format!(
+        "Project: {}\nEnvironment: {}\nTechnology: {:?}\nStatus: {:?}\n\n{}",
+        result.path, result.environment, result.technology, result.status, output
+    );
+
+    fs::write(log_file, content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture_status(log_dir: &Path) -> String {
+        fs::read_to_string(log_dir.join(CAPTURE_STATUS_FILE)).unwrap()
+    }
+
+    fn ok_result(path: &str, environment: &str, output: Option<&str>) -> DriftResult {
+        DriftResult {
+            path: path.to_string(),
+            environment: environment.to_string(),
+            technology: Technology::Terraform,
+            status: DriftStatus::Ok,
+            output: output.map(String::from),
+            duration: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_parse_env_file_skips_blank_lines_and_comments() {
+        let content = "# curated environments\n\ndev\n  prod  # production\n\t\nstaging\n";
+        assert_eq!(parse_env_file(content), ["dev", "prod", "staging"]);
+    }
+
+    #[test]
+    fn test_env_filter_unions_env_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let env_file = temp_dir.path().join("envs");
+        fs::write(&env_file, "prod\nstaging\n").unwrap();
+
+        let filter = env_filter(
+            vec!["dev".to_string(), "prod".to_string()],
+            Some(env_file.to_str().unwrap()),
+        )
+        .unwrap();
+        assert_eq!(filter, ["dev", "prod", "staging"]);
+
+        let err = env_filter(Vec::new(), Some("/nonexistent/envs")).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to read env file /nonexistent/envs");
+    }
+
+    #[test]
+    fn test_display_path_relative_to_base() {
+        let base = Path::new("/work/infra");
+        assert_eq!(
+            display_path("/work/infra/network/terraform", Some(base)),
+            "network/terraform"
         );
-        let log_file = log_dir.join(filename);
+        assert_eq!(display_path("/work/infra", Some(base)), ".");
+        assert_eq!(display_path("/elsewhere/app", Some(base)), "/elsewhere/app");
+        assert_eq!(
+            display_path("/work/infra/network/terraform", None),
+            "/work/infra/network/terraform"
+        );
+    }
+
+    #[test]
+    fn test_env_regex_selects_matching_subset() {
+        let regex = env_regex(Some("-prod$")).unwrap();
+        let environments = ["eu-prod", "us-prod", "eu-staging", "prod-copy", "dev"];
+
+        let selected: Vec<&str> = environments
+            .into_iter()
+            .filter(|e| selects_environment(e, &["dev".to_string()], regex.as_ref()))
+            .collect();
+        // Unioned with the explicit --env values
+        assert_eq!(selected, ["eu-prod", "us-prod", "dev"]);
+
+        assert!(env_regex(None).unwrap().is_none());
+        assert!(selects_environment("anything", &[], None));
+    }
+
+    #[test]
+    fn test_env_regex_invalid_pattern() {
+        let err = env_regex(Some("*-prod")).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid --env-regex pattern *-prod");
+    }
 
-        let content = format!(
-            "Project: {}\nEnvironment: {}\nTechnology: {:?}\nStatus: {:?}\n\n{}",
-            result.path, result.environment, result.technology, result.status, output
+    #[test]
+    fn test_drift_options_disable_color_when_captured() {
+        assert_eq!(
+            drift_options(Technology::Terraform, false),
+            ["-detailed-exitcode", "-input=false"]
+        );
+        assert_eq!(
+            drift_options(Technology::Terraform, true),
+            ["-detailed-exitcode", "-input=false", "-no-color"]
         );
+        assert!(drift_options(Technology::Helm, false).is_empty());
+        assert_eq!(drift_options(Technology::Helm, true), ["--no-color"]);
+    }
+
+    #[test]
+    fn test_capture_all_saves_ok_results() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let result = ok_result("/infra/terraform", "dev", None);
 
-        fs::write(log_file, content)?;
+        assert!(!should_save_output(&result, false));
+        assert!(should_save_output(&result, true));
+
+        save_output(temp_dir.path(), &result).unwrap();
+        let log_file = temp_dir
+            .path()
+            .join(log_file_name("/infra/terraform", "dev"));
+        let content = fs::read_to_string(log_file).unwrap();
+        assert!(content.contains("Status: Ok"));
+        assert!(content.contains("Environment: dev"));
+    }
+
+    #[test]
+    fn test_capture_keeps_results_with_output() {
+        let result = ok_result("/infra/terraform", "dev", Some("No changes."));
+        assert!(should_save_output(&result, false));
+    }
+
+    #[test]
+    fn test_log_file_name_disambiguates_similar_paths() {
+        let names = [
+            log_file_name("/infra/a/b", "dev"),
+            log_file_name("/infra/a_b", "dev"),
+            log_file_name("/infra/a.b", "dev"),
+            log_file_name("/infra/a/b", "prod"),
+        ];
+
+        for (i, a) in names.iter().enumerate() {
+            for b in &names[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        assert!(names[0].starts_with("infra_a_b__dev-"));
+    }
+
+    #[test]
+    fn test_save_output_keeps_previously_colliding_paths() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // Both normalized to "infra_app_terraform_dev.log" with the old scheme
+        let nested = ok_result("/infra/app/terraform", "dev", Some("nested"));
+        let dotted = ok_result("/infra/app.terraform", "dev", Some("dotted"));
+
+        save_output(temp_dir.path(), &nested).unwrap();
+        save_output(temp_dir.path(), &dotted).unwrap();
+
+        let nested_log = temp_dir
+            .path()
+            .join(log_file_name("/infra/app/terraform", "dev"));
+        let dotted_log = temp_dir
+            .path()
+            .join(log_file_name("/infra/app.terraform", "dev"));
+        assert_ne!(nested_log, dotted_log);
+        assert!(fs::read_to_string(nested_log).unwrap().ends_with("nested"));
+        assert!(fs::read_to_string(dotted_log).unwrap().ends_with("dotted"));
+    }
+
+    #[test]
+    fn test_log_file_name_is_stable() {
+        assert_eq!(
+            log_file_name("/infra/app/terraform", "dev"),
+            log_file_name("/infra/app/terraform", "dev")
+        );
+        assert_eq!(stable_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    /// Reports a fixed list of changed files, or fails like git outside a repository
+    struct FakeChangedFiles(Option<Vec<&'static str>>);
+
+    impl ChangedFiles for FakeChangedFiles {
+        fn changed_files(&self, _base: &Path, _git_ref: &str) -> Result<Vec<PathBuf>> {
+            match &self.0 {
+                Some(files) => Ok(files.iter().map(PathBuf::from).collect()),
+                None => anyhow::bail!("not a git repository"),
+            }
+        }
+    }
+
+    fn drift_projects() -> Vec<(String, Technology)> {
+        vec![
+            ("/repo/network/terraform".to_string(), Technology::Terraform),
+            (
+                "/repo/network/terraform-dns".to_string(),
+                Technology::Terraform,
+            ),
+            ("/repo/charts/app".to_string(), Technology::Helm),
+        ]
+    }
+
+    #[test]
+    fn test_select_changed_projects_keeps_affected_projects() {
+        let source = FakeChangedFiles(Some(vec![
+            "/repo/network/terraform/tfvars/dev.tfvars",
+            "/repo/README.md",
+        ]));
+
+        let selected =
+            select_changed_projects(drift_projects(), Path::new("/repo"), "main", &source);
+
+        assert_eq!(
+            selected,
+            vec![("/repo/network/terraform".to_string(), Technology::Terraform)]
+        );
+    }
+
+    #[test]
+    fn test_select_changed_projects_without_git_keeps_all() {
+        let source = FakeChangedFiles(None);
+
+        let selected =
+            select_changed_projects(drift_projects(), Path::new("/repo"), "main", &source);
+
+        assert_eq!(selected, drift_projects());
+    }
+
+    #[test]
+    fn test_capture_log_dir_marked_complete() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let log_dir = CaptureLogDir::create(temp_dir.path()).unwrap();
+        let path = log_dir.path().to_path_buf();
+        assert_eq!(capture_status(&path), "status: incomplete\n");
+
+        log_dir.complete().unwrap();
+        assert_eq!(capture_status(&path), "status: complete\n");
+    }
+
+    #[test]
+    fn test_capture_log_dir_stays_incomplete_on_early_return() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let mut path = PathBuf::new();
+        let mut run = || -> Result<()> {
+            let log_dir = CaptureLogDir::create(temp_dir.path())?;
+            path = log_dir.path().to_path_buf();
+            fs::write(path.join("terraform_dev.log"), "partial output")?;
+            // Simulate a failing check before the run completes
+            anyhow::bail!("check failed");
+        };
+
+        assert!(run().is_err());
+        assert_eq!(capture_status(&path), "status: incomplete\n");
+        assert!(path.join("terraform_dev.log").exists());
+    }
+
+    #[test]
+    fn test_capture_log_dir_pruned_when_empty() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let log_dir = CaptureLogDir::create(temp_dir.path()).unwrap();
+        let path = log_dir.path().to_path_buf();
+
+        drop(log_dir);
+        assert!(!path.exists());
     }
-    Ok(())
 }
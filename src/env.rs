@@ -1,9 +1,43 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
+use etcetera::BaseStrategy;
 use ignore::WalkBuilder;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-use crate::techno::Technology;
+use crate::commands::ActionOptions;
+use crate::config::Config;
+use crate::error::MkError;
+use crate::log;
+use crate::techno::{self, Technology};
+
+/// How long a cached completion listing stays valid
+const COMPLETION_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Cached environment listing used by shell completion
+#[derive(Debug, Serialize, Deserialize)]
+struct CompletionCacheEntry {
+    /// Directories listed to find the environments, with their mtime (None when missing)
+    watched: Vec<(PathBuf, Option<u128>)>,
+    created: u64,
+    environments: Vec<String>,
+}
+
+/// Directory (relative to the project) that holds the environment definitions
+pub fn env_dir_name(techno: Technology) -> &'static str {
+    match techno {
+        Technology::Terraform => "tfvars",
+        Technology::Helm => "values",
+        Technology::Kustomize => "overlays",
+        Technology::Ansible => "inventories",
+    }
+}
 
 /// Get list of available environments for a given technology and project path
 pub fn get_environments(
@@ -16,6 +50,10 @@ pub fn get_environments(
 
     match techno {
         Technology::Terraform => {
+            // The shared var file is layered under every environment, not one itself
+            let common_var_file = Config::load_merged(project_path)?.terraform.common_var_file;
+            let common_env = Path::new(&common_var_file).file_stem();
+
             // Look for tfvars files in tfvars/ directory
             let tfvars_dir = path.join("tfvars");
             if tfvars_dir.exists() {
@@ -27,13 +65,36 @@ pub fn get_environments(
                     .build()
                     .filter_map(|e| e.ok())
                 {
-                    if entry.file_type().is_some_and(|ft| ft.is_file()) {
+                    if entry.file_type().is_some_and(|ft| ft.is_file())
+                        && entry.path().file_stem() != common_env
+                    {
                         if let Some(stem) = entry.path().file_stem().and_then(|s| s.to_str()) {
                             envs.push(stem.to_string());
                         }
                     }
                 }
             }
+            // Environments may also keep several var files in env/<env>/
+            let env_dir = path.join("env");
+            if env_dir.exists() {
+                for entry in WalkBuilder::new(&env_dir)
+                    .max_depth(Some(1))
+                    .git_ignore(!no_ignore)
+                    .git_exclude(!no_ignore)
+                    .git_global(!no_ignore)
+                    .build()
+                    .filter_map(|e| e.ok())
+                {
+                    if entry.depth() > 0
+                        && entry.file_type().is_some_and(|ft| ft.is_dir())
+                        && !tfvars_files(entry.path()).is_empty()
+                    {
+                        if let Some(name) = entry.file_name().to_str() {
+                            envs.push(name.to_string());
+                        }
+                    }
+                }
+            }
         }
         Technology::Helm => {
             // Look for directories in values/ directory
@@ -78,7 +139,7 @@ pub fn get_environments(
             }
         }
         Technology::Ansible => {
-            // Look for inventory files in inventories/ directory
+            // Look for inventory files and inventory directories in inventories/ directory
             let inventories_dir = path.join("inventories");
             if inventories_dir.exists() {
                 for entry in WalkBuilder::new(&inventories_dir)
@@ -89,7 +150,12 @@ pub fn get_environments(
                     .build()
                     .filter_map(|e| e.ok())
                 {
-                    if entry.file_type().is_some_and(|ft| ft.is_file()) {
+                    if entry.depth() > 0 && entry.file_type().is_some_and(|ft| ft.is_dir()) {
+                        // Directory inventory (e.g., "prod/hosts")
+                        if let Some(name) = entry.file_name().to_str() {
+                            envs.push(name.to_string());
+                        }
+                    } else if entry.file_type().is_some_and(|ft| ft.is_file()) {
                         if let Some(name) = entry.path().file_name().and_then(|s| s.to_str()) {
                             // Remove all extensions (e.g., "demo.yml" -> "demo")
                             let env_name = name.split('.').next().unwrap_or(name);
@@ -108,13 +174,171 @@ pub fn get_environments(
     Ok(envs)
 }
 
+/// Names of the `*.tfvars` files directly in `dir`, sorted
+pub fn tfvars_files(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_file()))
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .filter(|name| name.ends_with(".tfvars"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Whether a terraform project keeps its environments in env/<env>/ rather than tfvars/
+fn uses_env_directories(project_path: &Path) -> bool {
+    project_path.join("env").is_dir() && !project_path.join("tfvars").is_dir()
+}
+
+/// Directories whose changes can add or remove environments of the project
+fn watched_dirs(project_path: &Path, techno: Technology) -> Vec<PathBuf> {
+    let mut dirs = vec![project_path.join(env_dir_name(techno))];
+    if techno == Technology::Terraform {
+        let env_dir = project_path.join("env");
+        if let Ok(entries) = fs::read_dir(&env_dir) {
+            let mut env_dirs: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect();
+            env_dirs.sort();
+            dirs.extend(env_dirs);
+        }
+        dirs.push(env_dir);
+    }
+    dirs
+}
+
+/// Get the directory where shell completion results are cached
+pub fn completion_cache_dir() -> Result<PathBuf> {
+    let strategy = etcetera::base_strategy::choose_base_strategy()?;
+    Ok(strategy.cache_dir().join("mk").join("complete-env"))
+}
+
+/// Get environments for shell completion, reusing a cached listing while the
+/// project's environment directories are unchanged
+pub fn get_environments_cached(project_path: &str, cache_dir: &Path) -> Result<Vec<String>> {
+    let project = Path::new(project_path)
+        .canonicalize()
+        .context("Failed to resolve project path")?;
+
+    let mut hasher = DefaultHasher::new();
+    project.hash(&mut hasher);
+    let cache_file = cache_dir.join(format!("{:016x}.json", hasher.finish()));
+
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+
+    if let Some(entry) = fs::read_to_string(&cache_file)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CompletionCacheEntry>(&content).ok())
+    {
+        let fresh = now.as_secs().saturating_sub(entry.created) < COMPLETION_CACHE_TTL.as_secs();
+        if fresh
+            && entry
+                .watched
+                .iter()
+                .all(|(dir, mtime)| dir_mtime(dir) == *mtime)
+        {
+            return Ok(entry.environments);
+        }
+    }
+
+    // Always use no_ignore=true for completion to show all available environments
+    let (techno, actual_path) =
+        techno::detect_technology(project_path, None, true, techno::DetectFlags::default())?;
+    let environments = get_environments(&actual_path, techno, true)?;
+
+    let entry = CompletionCacheEntry {
+        watched: watched_dirs(Path::new(&actual_path), techno)
+            .into_iter()
+            .map(|dir| {
+                let mtime = dir_mtime(&dir);
+                (dir, mtime)
+            })
+            .collect(),
+        created: now.as_secs(),
+        environments: environments.clone(),
+    };
+    // Caching is best-effort: completion must keep working on read-only homes
+    if fs::create_dir_all(cache_dir).is_ok() {
+        let _ = fs::write(&cache_file, serde_json::to_string(&entry)?);
+    }
+
+    Ok(environments)
+}
+
+/// Modification time of a directory in nanoseconds since the epoch
+fn dir_mtime(path: &Path) -> Option<u128> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+}
+
+/// Replace each `[env] groups` name in `names` by its members, dropping duplicates
+pub fn expand_env_groups(names: &[String], groups: &HashMap<String, Vec<String>>) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::new();
+    for name in names {
+        let members = groups
+            .get(name)
+            .map_or(std::slice::from_ref(name), Vec::as_slice);
+        for member in members {
+            if !expanded.contains(member) {
+                expanded.push(member.clone());
+            }
+        }
+    }
+    expanded
+}
+
+/// Fail when a group used in `names` lists an environment missing from `known`
+pub fn validate_env_groups(
+    names: &[String],
+    groups: &HashMap<String, Vec<String>>,
+    known: &BTreeSet<String>,
+) -> Result<()> {
+    for name in names {
+        let Some(members) = groups.get(name) else {
+            continue;
+        };
+        if let Some(missing) = members.iter().find(|member| !known.contains(*member)) {
+            anyhow::bail!(
+                "Environment group {} lists {}, which no project has. Valid options are: [{}]",
+                name,
+                missing,
+                known.iter().cloned().collect::<Vec<_>>().join("|")
+            );
+        }
+    }
+    Ok(())
+}
+
 /// Check if the given environment is valid for the technology and project
+/// Returns the project's name for it, to be used instead of the name as typed
 pub fn check_environment(
     project_path: &str,
     environment: &str,
     techno: Technology,
     no_ignore: bool,
-) -> Result<()> {
+) -> Result<String> {
+    resolve_environment(project_path, environment, techno, no_ignore, false)
+}
+
+/// Name of the project environment matching `environment`, ignoring surrounding whitespace
+/// A name differing only by case is used with a warning when `case_insensitive`, suggested otherwise
+pub fn resolve_environment(
+    project_path: &str,
+    environment: &str,
+    techno: Technology,
+    no_ignore: bool,
+    case_insensitive: bool,
+) -> Result<String> {
+    let environment = environment.trim();
     let envs = get_environments(project_path, techno, no_ignore)?;
 
     if envs.is_empty() {
@@ -126,16 +350,233 @@ pub fn check_environment(
         );
     }
 
-    if envs.contains(&environment.to_string()) {
-        Ok(())
-    } else {
+    if envs.iter().any(|env| env == environment) {
+        return Ok(environment.to_string());
+    }
+
+    match envs
+        .iter()
+        .find(|env| env.eq_ignore_ascii_case(environment))
+    {
+        Some(env) if case_insensitive => {
+            log::warning!("Using environment {} for {}", env, environment);
+            Ok(env.clone())
+        }
+        suggestion => Err(MkError::InvalidEnvironment {
+            provided: environment.to_string(),
+            suggestion: suggestion.cloned(),
+            valid: envs,
+        }
+        .into()),
+    }
+}
+
+/// Reject names that cannot be a single file or directory name
+fn validate_environment_name(environment: &str) -> Result<()> {
+    if environment.is_empty()
+        || environment.starts_with('.')
+        || environment
+            .chars()
+            .any(|c| c == '/' || c == '\\' || c.is_whitespace())
+    {
         anyhow::bail!(
-            "{} Invalid env provided ({}). Valid options are: [{}]",
-            "ERROR:".red(),
+            "Invalid environment name {:?}: use a plain name such as staging",
+            environment
+        );
+    }
+    Ok(())
+}
+
+/// Files `mk env add` creates for `environment`, with their initial content
+fn scaffold_files(
+    project_path: &Path,
+    techno: Technology,
+    environment: &str,
+) -> Vec<(PathBuf, String)> {
+    let env_dir = project_path.join(env_dir_name(techno));
+    match techno {
+        Technology::Terraform => {
+            // Same preference as the generated -backend-config path
+            let backend_dir = if !project_path.join("backend-vars").exists()
+                && project_path.join("backend_vars").exists()
+            {
+                "backend_vars"
+            } else {
+                "backend-vars"
+            };
+            let file_name = format!("{}.tfvars", environment);
+            let var_file = if uses_env_directories(project_path) {
+                project_path
+                    .join("env")
+                    .join(environment)
+                    .join("terraform.tfvars")
+            } else {
+                env_dir.join(&file_name)
+            };
+            vec![
+                (var_file, String::new()),
+                (
+                    project_path.join(backend_dir).join(&file_name),
+                    String::new(),
+                ),
+            ]
+        }
+        Technology::Helm => vec![(env_dir.join(environment).join("values.yaml"), String::new())],
+        Technology::Kustomize => {
+            let resources = if project_path.join("base").is_dir() {
+                "resources:\n  - ../../base\n"
+            } else {
+                "resources: []\n"
+            };
+            vec![(
+                env_dir.join(environment).join("kustomization.yaml"),
+                format!(
+                    "apiVersion: kustomize.config.k8s.io/v1beta1\nkind: Kustomization\n{}",
+                    resources
+                ),
+            )]
+        }
+        Technology::Ansible => vec![(
+            env_dir.join(format!("{}.yml", environment)),
+            "all:\n  hosts: {}\n".to_string(),
+        )],
+    }
+}
+
+/// Existing files and directories defining `environment`
+fn environment_paths(
+    project_path: &Path,
+    techno: Technology,
+    environment: &str,
+) -> Result<Vec<PathBuf>> {
+    let env_dir = project_path.join(env_dir_name(techno));
+    let mut paths = match techno {
+        Technology::Terraform => {
+            let file_name = format!("{}.tfvars", environment);
+            vec![
+                env_dir.join(&file_name),
+                project_path.join("env").join(environment),
+                project_path.join("backend-vars").join(&file_name),
+                project_path.join("backend_vars").join(&file_name),
+            ]
+        }
+        Technology::Helm | Technology::Kustomize => vec![env_dir.join(environment)],
+        Technology::Ansible => {
+            // Directory inventory, or inventory files with any extension
+            let mut paths = vec![env_dir.join(environment)];
+            for entry in fs::read_dir(&env_dir)? {
+                let path = entry?.path();
+                let name = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default();
+                if path.is_file() && name.split('.').next() == Some(environment) {
+                    paths.push(path);
+                }
+            }
+            paths
+        }
+    };
+    paths.retain(|path| path.exists());
+    paths.sort();
+    Ok(paths)
+}
+
+/// Create the files of a new `environment` in an already detected project
+fn scaffold_environment(
+    project_path: &str,
+    techno: Technology,
+    environment: &str,
+    no_ignore: bool,
+) -> Result<Vec<PathBuf>> {
+    validate_environment_name(environment)?;
+    if get_environments(project_path, techno, no_ignore)?.contains(&environment.to_string()) {
+        anyhow::bail!(
+            "Environment {} already exists in {}",
             environment,
-            envs.join("|")
+            project_path
         );
     }
+
+    let mut created = Vec::new();
+    for (path, content) in scaffold_files(Path::new(project_path), techno, environment) {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        created.push(path);
+    }
+    Ok(created)
+}
+
+/// `mk env add`: scaffold a new environment for the detected technology
+pub fn add_environment(
+    project_path: &str,
+    environment: &str,
+    action_options: &ActionOptions,
+) -> Result<()> {
+    let (techno, actual_path) = action_options
+        .detect(project_path, None)
+        .context("Failed to detect technology")?;
+
+    for path in scaffold_environment(&actual_path, techno, environment, action_options.no_ignore)? {
+        log::info!("Created {}", path.display());
+    }
+    log::success!("Environment {} added to {}", environment, actual_path);
+    Ok(())
+}
+
+/// Ask before deleting the files of an environment, reading the answer from `input`
+fn confirm_removal(
+    environment: &str,
+    paths: &[PathBuf],
+    input: &mut impl BufRead,
+    is_terminal: bool,
+) -> Result<()> {
+    let mut prompt = format!("Environment {} is defined by:\n", environment);
+    for path in paths {
+        prompt.push_str(&format!("  {}\n", path.display()));
+    }
+    prompt.push_str("Delete these files?");
+
+    let action = format!("remove environment {}", environment);
+    if !log::confirm(&action, &prompt, input, is_terminal)? {
+        anyhow::bail!("Environment removal aborted");
+    }
+    Ok(())
+}
+
+/// `mk env remove`: delete the files of an environment, after confirmation unless `force`
+pub fn remove_environment(
+    project_path: &str,
+    environment: &str,
+    force: bool,
+    action_options: &ActionOptions,
+) -> Result<()> {
+    let (techno, actual_path) = action_options
+        .detect(project_path, None)
+        .context("Failed to detect technology")?;
+    let environment =
+        &check_environment(&actual_path, environment, techno, action_options.no_ignore)?;
+
+    let paths = environment_paths(Path::new(&actual_path), techno, environment)?;
+    if !force {
+        let stdin = io::stdin();
+        confirm_removal(environment, &paths, &mut stdin.lock(), stdin.is_terminal())?;
+    }
+
+    for path in &paths {
+        if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+        .with_context(|| format!("Failed to remove {}", path.display()))?;
+        log::info!("Removed {}", path.display());
+    }
+    log::success!("Environment {} removed from {}", environment, actual_path);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -145,6 +586,43 @@ mod tests {
     use std::fs;
     use tempfile::TempDir;
 
+    fn groups() -> HashMap<String, Vec<String>> {
+        HashMap::from([(
+            "nonprod".to_string(),
+            vec!["dev".to_string(), "staging".to_string()],
+        )])
+    }
+
+    #[test]
+    fn test_expand_env_groups() {
+        let names = ["nonprod", "prod", "dev"].map(String::from);
+        assert_eq!(
+            expand_env_groups(&names, &groups()),
+            ["dev", "staging", "prod"]
+        );
+        assert_eq!(
+            expand_env_groups(&names[1..], &HashMap::new()),
+            ["prod", "dev"]
+        );
+    }
+
+    #[test]
+    fn test_validate_env_groups_invalid_member() {
+        let names = ["nonprod".to_string()];
+        let known: BTreeSet<String> = ["dev", "prod", "staging"].map(String::from).into();
+        assert!(validate_env_groups(&names, &groups(), &known).is_ok());
+
+        let known: BTreeSet<String> = ["dev", "prod"].map(String::from).into();
+        assert_eq!(
+            validate_env_groups(&names, &groups(), &known)
+                .unwrap_err()
+                .to_string(),
+            "Environment group nonprod lists staging, which no project has. Valid options are: [dev|prod]"
+        );
+        // Plain environments are left to the per-project filter
+        assert!(validate_env_groups(&["qa".to_string()], &groups(), &known).is_ok());
+    }
+
     // Helper function to create a test project structure
     fn create_terraform_project(temp_dir: &TempDir, envs: &[&str]) -> String {
         let project_dir = temp_dir.path().join("terraform");
@@ -254,6 +732,66 @@ mod tests {
         assert_eq!(envs, vec!["dev", "prod"]);
     }
 
+    #[test]
+    fn test_get_ansible_environments_directory_layout() {
+        let temp_dir = TempDir::new().unwrap();
+        let inventories_dir = temp_dir.path().join("inventories");
+        for env in ["prod", "staging"] {
+            fs::create_dir_all(inventories_dir.join(env).join("group_vars")).unwrap();
+            fs::write(inventories_dir.join(env).join("hosts"), "[web]\n").unwrap();
+        }
+
+        let envs = get_environments(
+            temp_dir.path().to_str().unwrap(),
+            Technology::Ansible,
+            false,
+        )
+        .unwrap();
+        assert_eq!(envs, vec!["prod", "staging"]);
+    }
+
+    #[test]
+    fn test_get_ansible_environments_mixed_layout() {
+        let temp_dir = TempDir::new().unwrap();
+        let inventories_dir = temp_dir.path().join("inventories");
+        fs::create_dir_all(inventories_dir.join("prod")).unwrap();
+        fs::write(inventories_dir.join("prod").join("hosts"), "[web]\n").unwrap();
+        fs::write(inventories_dir.join("dev.yml"), "all:\n").unwrap();
+
+        let envs = get_environments(
+            temp_dir.path().to_str().unwrap(),
+            Technology::Ansible,
+            false,
+        )
+        .unwrap();
+        assert_eq!(envs, vec!["dev", "prod"]);
+    }
+
+    #[test]
+    fn test_get_terraform_environments_excludes_common() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = create_terraform_project(&temp_dir, &["common", "dev", "prod"]);
+
+        let envs = get_environments(&project_path, Technology::Terraform, false).unwrap();
+        assert_eq!(envs, vec!["dev", "prod"]);
+    }
+
+    #[test]
+    fn test_get_terraform_environments_env_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = create_terraform_project(&temp_dir, &["dev"]);
+        for env in ["prod", "staging"] {
+            let env_dir = Path::new(&project_path).join("env").join(env);
+            fs::create_dir_all(&env_dir).unwrap();
+            fs::write(env_dir.join("common.tfvars"), "").unwrap();
+        }
+        // Without var files a directory is not an environment
+        fs::create_dir_all(Path::new(&project_path).join("env").join("empty")).unwrap();
+
+        let envs = get_environments(&project_path, Technology::Terraform, false).unwrap();
+        assert_eq!(envs, vec!["dev", "prod", "staging"]);
+    }
+
     #[test]
     fn test_get_environments_empty() {
         let temp_dir = TempDir::new().unwrap();
@@ -296,6 +834,50 @@ mod tests {
         assert!(err_msg.contains("dev|prod"));
     }
 
+    #[test]
+    fn test_resolve_environment_trims_whitespace() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = create_terraform_project(&temp_dir, &["dev", "prod"]);
+
+        let env = resolve_environment(&project_path, " dev\t", Technology::Terraform, false, false);
+        assert_eq!(env.unwrap(), "dev");
+    }
+
+    #[test]
+    fn test_resolve_environment_case_mismatch() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = create_terraform_project(&temp_dir, &["dev", "prod"]);
+
+        let err = resolve_environment(&project_path, "PROD", Technology::Terraform, false, false)
+            .unwrap_err();
+        assert!(err.to_string().contains("did you mean prod?"));
+
+        let env = resolve_environment(&project_path, "PROD", Technology::Terraform, false, true);
+        assert_eq!(env.unwrap(), "prod");
+
+        // Unrelated names still fail when case-insensitive
+        assert!(
+            resolve_environment(&project_path, "qa", Technology::Terraform, false, true).is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_environment_variant() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = create_terraform_project(&temp_dir, &["dev", "prod"]);
+
+        let err =
+            check_environment(&project_path, "Dev", Technology::Terraform, false).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MkError>(),
+            Some(&MkError::InvalidEnvironment {
+                provided: "Dev".to_string(),
+                valid: vec!["dev".to_string(), "prod".to_string()],
+                suggestion: Some("dev".to_string()),
+            })
+        );
+    }
+
     #[test]
     fn test_check_environment_no_environments_found() {
         let temp_dir = TempDir::new().unwrap();
@@ -339,6 +921,123 @@ mod tests {
         assert_eq!(envs, expected);
     }
 
+    #[rstest]
+    #[case(Technology::Terraform)]
+    #[case(Technology::Helm)]
+    #[case(Technology::Kustomize)]
+    #[case(Technology::Ansible)]
+    fn test_scaffold_environment_is_discovered(#[case] tech: Technology) {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = match tech {
+            Technology::Terraform => create_terraform_project(&temp_dir, &["dev"]),
+            Technology::Helm => create_helm_project(&temp_dir, &["dev"]),
+            Technology::Kustomize => create_kustomize_project(&temp_dir, &["dev"]),
+            Technology::Ansible => create_ansible_project(&temp_dir, &["dev"]),
+        };
+
+        let created = scaffold_environment(&project_path, tech, "qa", false).unwrap();
+        assert!(created.iter().all(|path| path.is_file()), "{:?}", created);
+        assert_eq!(
+            get_environments(&project_path, tech, false).unwrap(),
+            ["dev", "qa"]
+        );
+
+        let err = scaffold_environment(&project_path, tech, "qa", false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Environment qa already exists in {}", project_path)
+        );
+
+        // Removal deletes what was scaffolded
+        let paths = environment_paths(Path::new(&project_path), tech, "qa").unwrap();
+        assert!(!paths.is_empty());
+        for path in paths {
+            if path.is_dir() {
+                fs::remove_dir_all(path).unwrap();
+            } else {
+                fs::remove_file(path).unwrap();
+            }
+        }
+        assert_eq!(
+            get_environments(&project_path, tech, false).unwrap(),
+            ["dev"]
+        );
+    }
+
+    #[test]
+    fn test_scaffold_terraform_environment_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = create_terraform_project(&temp_dir, &["dev"]);
+
+        scaffold_environment(&project_path, Technology::Terraform, "qa", false).unwrap();
+        let project = Path::new(&project_path);
+        assert!(project.join("tfvars/qa.tfvars").is_file());
+        assert!(project.join("backend-vars/qa.tfvars").is_file());
+    }
+
+    #[test]
+    fn test_scaffold_and_remove_terraform_env_directory() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("terraform");
+        fs::create_dir_all(project.join("env/dev")).unwrap();
+        fs::write(project.join("env/dev/main.tfvars"), "").unwrap();
+        let project_path = project.to_str().unwrap();
+
+        scaffold_environment(project_path, Technology::Terraform, "qa", false).unwrap();
+        assert!(project.join("env/qa/terraform.tfvars").is_file());
+        assert!(project.join("backend-vars/qa.tfvars").is_file());
+        assert!(!project.join("tfvars").exists());
+
+        let envs = get_environments(project_path, Technology::Terraform, false).unwrap();
+        assert_eq!(envs, vec!["dev", "qa"]);
+        assert_eq!(
+            environment_paths(&project, Technology::Terraform, "qa").unwrap(),
+            vec![
+                project.join("backend-vars/qa.tfvars"),
+                project.join("env/qa")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scaffold_kustomize_overlay_uses_base() {
+        let temp_dir = TempDir::new().unwrap();
+        let project_path = create_kustomize_project(&temp_dir, &["dev"]);
+        fs::create_dir(Path::new(&project_path).join("base")).unwrap();
+
+        scaffold_environment(&project_path, Technology::Kustomize, "qa", false).unwrap();
+        let overlay =
+            fs::read_to_string(Path::new(&project_path).join("overlays/qa/kustomization.yaml"))
+                .unwrap();
+        assert!(
+            overlay.contains("resources:\n  - ../../base\n"),
+            "{}",
+            overlay
+        );
+    }
+
+    #[test]
+    fn test_validate_environment_name() {
+        assert!(validate_environment_name("staging-eu").is_ok());
+        for name in ["", ".hidden", "eu/prod", "my env"] {
+            let err = validate_environment_name(name).unwrap_err();
+            assert!(err.to_string().starts_with("Invalid environment name"));
+        }
+    }
+
+    #[test]
+    fn test_confirm_removal_refused_without_terminal() {
+        let err = confirm_removal("qa", &[], &mut "y\n".as_bytes(), false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Refusing to remove environment qa without confirmation: stdin is not a terminal (use --force)"
+        );
+
+        assert!(confirm_removal("qa", &[], &mut "y\n".as_bytes(), true).is_ok());
+        let err = confirm_removal("qa", &[], &mut "\n".as_bytes(), true).unwrap_err();
+        assert_eq!(err.to_string(), "Environment removal aborted");
+    }
+
     #[test]
     fn test_ansible_inventory_multiple_extensions() {
         let temp_dir = TempDir::new().unwrap();
@@ -358,6 +1057,71 @@ mod tests {
         assert_eq!(envs, vec!["dev", "prod", "staging"]);
     }
 
+    #[test]
+    fn test_get_environments_cached_reuses_listing() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("cache");
+        let project_path = create_terraform_project(&temp_dir, &["dev", "prod"]);
+        let tfvars_dir = Path::new(&project_path).join("tfvars");
+
+        let envs = get_environments_cached(&project_path, &cache_dir).unwrap();
+        assert_eq!(envs, vec!["dev", "prod"]);
+
+        // Add an environment but keep the directory mtime unchanged
+        let mtime = fs::metadata(&tfvars_dir).unwrap().modified().unwrap();
+        fs::write(tfvars_dir.join("staging.tfvars"), "env = \"staging\"").unwrap();
+        fs::File::open(&tfvars_dir)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let envs = get_environments_cached(&project_path, &cache_dir).unwrap();
+        assert_eq!(envs, vec!["dev", "prod"]);
+    }
+
+    #[test]
+    fn test_get_environments_cached_invalidated_by_mtime() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("cache");
+        let project_path = create_terraform_project(&temp_dir, &["dev", "prod"]);
+        let tfvars_dir = Path::new(&project_path).join("tfvars");
+
+        let envs = get_environments_cached(&project_path, &cache_dir).unwrap();
+        assert_eq!(envs, vec!["dev", "prod"]);
+
+        fs::write(tfvars_dir.join("staging.tfvars"), "env = \"staging\"").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(10);
+        fs::File::open(&tfvars_dir)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        let envs = get_environments_cached(&project_path, &cache_dir).unwrap();
+        assert_eq!(envs, vec!["dev", "prod", "staging"]);
+    }
+
+    #[test]
+    fn test_get_environments_cached_watches_env_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join("cache");
+        let project_path = create_terraform_project(&temp_dir, &["dev"]);
+        let qa_dir = Path::new(&project_path).join("env").join("qa");
+        fs::create_dir_all(&qa_dir).unwrap();
+
+        let envs = get_environments_cached(&project_path, &cache_dir).unwrap();
+        assert_eq!(envs, vec!["dev"]);
+
+        fs::write(qa_dir.join("main.tfvars"), "").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(10);
+        fs::File::open(&qa_dir)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        let envs = get_environments_cached(&project_path, &cache_dir).unwrap();
+        assert_eq!(envs, vec!["dev", "qa"]);
+    }
+
     #[test]
     fn test_environments_deduplication() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,178 @@
+use serde_json::{json, Value};
+use thiserror::Error;
+
+/// Failures callers may want to tell apart, carried inside the anyhow chain
+/// Find them with `error.chain().find_map(|cause| cause.downcast_ref::<MkError>())`
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MkError {
+    /// No technology in the project directory or its children
+    #[error("No technology detected in {path}")]
+    NoTechnology { path: String },
+
+    /// The environment is not one of the project environments
+    #[error(
+        "Invalid env provided ({provided}){} Valid options are: [{}]",
+        .suggestion.as_ref().map_or(".".to_string(), |env| format!(", did you mean {}?", env)),
+        .valid.join("|")
+    )]
+    InvalidEnvironment {
+        provided: String,
+        valid: Vec<String>,
+        /// Environment differing only by case
+        suggestion: Option<String>,
+    },
+
+    /// The detected technology has no command for the action
+    #[error(
+        "{action} is not supported for {technology} projects. Supported actions: {}",
+        .supported.join(", ")
+    )]
+    UnsupportedAction {
+        action: String,
+        technology: String,
+        supported: Vec<String>,
+    },
+
+    /// The current kubectl context is not the one mapped to the repository and environment
+    #[error(
+        "Kubernetes context mismatch!\n\
+         Repository: {repository}\n\
+         Environment: {environment}\n\
+         Expected context: {expected}\n\
+         Current context: {current}\n\n\
+         Please switch to the correct context with:\n\
+         kubectl config use-context {expected}\n\n\
+         Or update the mapping if the context has changed."
+    )]
+    ContextMismatch {
+        repository: String,
+        environment: String,
+        expected: String,
+        current: String,
+    },
+
+    /// A tool command exited unsuccessfully, `code` is `None` when terminated by a signal
+    #[error("{}", command_failed_message(.cmd, *.code, *.step))]
+    CommandFailed {
+        cmd: String,
+        code: Option<i32>,
+        /// (step, total) when the command ran as part of a sequence
+        step: Option<(usize, usize)>,
+    },
+}
+
+impl MkError {
+    /// Stable snake_case name of the variant, e.g. `invalid_environment`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MkError::NoTechnology { .. } => "no_technology",
+            MkError::InvalidEnvironment { .. } => "invalid_environment",
+            MkError::UnsupportedAction { .. } => "unsupported_action",
+            MkError::ContextMismatch { .. } => "context_mismatch",
+            MkError::CommandFailed { .. } => "command_failed",
+        }
+    }
+}
+
+/// `{"error", "kind", "caused_by"}` for `--format json`
+/// `kind` is the first `MkError` in the chain, `error` when there is none
+pub fn to_json(error: &anyhow::Error) -> Value {
+    let kind = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<MkError>())
+        .map_or("error", MkError::kind);
+    let caused_by: Vec<String> = error
+        .chain()
+        .skip(1)
+        .map(|cause| cause.to_string())
+        .collect();
+    json!({
+        "error": error.to_string(),
+        "kind": kind,
+        "caused_by": caused_by,
+    })
+}
+
+fn command_failed_message(cmd: &str, code: Option<i32>, step: Option<(usize, usize)>) -> String {
+    let exit = code
+        .map(|c| format!("code: {}", c))
+        .unwrap_or_else(|| "unknown (terminated by signal)".to_string());
+    match step {
+        Some((step, total)) => format!(
+            "Command failed at step {}/{}: {}\nExit {}",
+            step, total, cmd, exit
+        ),
+        None => format!("Command '{}' failed with exit {}", cmd, exit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_to_json() {
+        let err = Err::<(), _>(MkError::NoTechnology {
+            path: "infra".to_string(),
+        })
+        .context("Failed to detect technology")
+        .unwrap_err();
+        assert_eq!(
+            to_json(&err),
+            json!({
+                "error": "Failed to detect technology",
+                "kind": "no_technology",
+                "caused_by": ["No technology detected in infra"],
+            })
+        );
+
+        assert_eq!(to_json(&anyhow::anyhow!("boom"))["kind"], "error");
+    }
+
+    #[test]
+    fn test_command_failed_messages() {
+        let failed = MkError::CommandFailed {
+            cmd: "terraform plan".to_string(),
+            code: Some(2),
+            step: None,
+        };
+        assert_eq!(
+            failed.to_string(),
+            "Command 'terraform plan' failed with exit code: 2"
+        );
+
+        let failed = MkError::CommandFailed {
+            cmd: "terraform init".to_string(),
+            code: None,
+            step: Some((2, 3)),
+        };
+        assert_eq!(
+            failed.to_string(),
+            "Command failed at step 2/3: terraform init\nExit unknown (terminated by signal)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_environment_message_is_plain() {
+        let invalid = MkError::InvalidEnvironment {
+            provided: "Dev".to_string(),
+            valid: vec!["dev".to_string(), "prod".to_string()],
+            suggestion: Some("dev".to_string()),
+        };
+        assert_eq!(
+            invalid.to_string(),
+            "Invalid env provided (Dev), did you mean dev? Valid options are: [dev|prod]"
+        );
+
+        let invalid = MkError::InvalidEnvironment {
+            provided: "qa".to_string(),
+            valid: vec!["dev".to_string(), "prod".to_string()],
+            suggestion: None,
+        };
+        assert_eq!(
+            invalid.to_string(),
+            "Invalid env provided (qa). Valid options are: [dev|prod]"
+        );
+    }
+}
@@ -1,19 +1,65 @@
+use crate::error::MkError;
+use crate::log;
 use anyhow::{Context, Result};
-use colored::*;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// `sh -c cmd`
+fn shell_command(cmd: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(cmd);
+    command
+}
+
+/// Single shell line running `commands` from `working_dir`, stopping at the first failure
+fn one_liner(commands: &[String], working_dir: &str) -> String {
+    let mut parts = vec![format!("cd {}", shell_quote(working_dir))];
+    parts.extend(commands.iter().cloned());
+    parts.join(" && ")
+}
+
+/// Print `commands` as one copy-pasteable line, for `--print-command`
+pub fn print_commands(commands: &[String], working_dir: &str) {
+    println!("{}", one_liner(commands, working_dir));
+}
+
+/// Exit code to report for a failed action: the failing command's code if any, otherwise 1
+pub fn exit_code_of(error: &anyhow::Error) -> i32 {
+    error
+        .chain()
+        .find_map(|cause| match cause.downcast_ref::<MkError>() {
+            Some(MkError::CommandFailed { code, .. }) => Some(*code),
+            _ => None,
+        })
+        .flatten()
+        .unwrap_or(1)
+}
+
+/// Quote `value` for `sh -c` when it contains characters the shell would interpret
+pub fn shell_quote(value: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-.,:/=@%+".contains(c);
+    if !value.is_empty() && value.chars().all(is_safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
 
 /// Execute a shell command and return the result
 pub fn execute_command(cmd: &str, working_dir: &str, verbose: bool) -> Result<()> {
-    eprintln!("{} Running `{}`", "INFO:".cyan(), cmd);
+    log::info!("Running `{}`", cmd);
 
     if verbose {
-        eprintln!("{} Working directory: {}", "DEBUG:".blue(), working_dir);
-        eprintln!("{} Command: {}", "DEBUG:".blue(), cmd);
+        log::debug!("Working directory: {}", working_dir);
+        log::debug!("Command: {}", cmd);
     }
 
-    let status = Command::new("sh")
-        .arg("-c")
-        .arg(cmd)
+    let status = shell_command(cmd)
         .current_dir(working_dir)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
@@ -22,69 +68,281 @@ pub fn execute_command(cmd: &str, working_dir: &str, verbose: bool) -> Result<()
         .context("Failed to execute command")?;
 
     if !status.success() {
-        let exit_code = status
-            .code()
-            .map(|c| format!("code: {}", c))
-            .unwrap_or_else(|| "unknown (terminated by signal)".to_string());
-        anyhow::bail!("Command '{}' failed with exit {}", cmd, exit_code);
+        return Err(MkError::CommandFailed {
+            cmd: cmd.to_string(),
+            code: status.code(),
+            step: None,
+        }
+        .into());
     }
 
     Ok(())
 }
 
+/// Execute a shell command with its stdout written to `output_file`, and to the terminal too
+/// with `tee`
+/// Parent directories are created; stderr still goes to the terminal
+pub fn execute_command_to_file(
+    cmd: &str,
+    working_dir: &str,
+    verbose: bool,
+    output_file: &Path,
+    tee: bool,
+) -> Result<()> {
+    log::info!("Running `{}` > {}", cmd, output_file.display());
+
+    if verbose {
+        log::debug!("Working directory: {}", working_dir);
+    }
+
+    if let Some(parent) = output_file.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut file = fs::File::create(output_file)
+        .with_context(|| format!("Failed to create {}", output_file.display()))?;
+
+    let mut command = shell_command(cmd);
+    command
+        .current_dir(working_dir)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    let status = if tee {
+        let mut child = command
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to execute command")?;
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        let mut buffer = [0; 8192];
+        loop {
+            let read = stdout.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read])
+                .with_context(|| format!("Failed to write {}", output_file.display()))?;
+            io::stdout().write_all(&buffer[..read])?;
+        }
+        child.wait().context("Failed to execute command")?
+    } else {
+        command
+            .stdout(file)
+            .status()
+            .context("Failed to execute command")?
+    };
+
+    if !status.success() {
+        return Err(MkError::CommandFailed {
+            cmd: cmd.to_string(),
+            code: status.code(),
+            step: None,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Delay before the first retry of an init step, doubled after each attempt
+const INIT_RETRY_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Whether `cmd` is an idempotent step worth retrying: `terraform init`
+fn is_retry_eligible(cmd: &str) -> bool {
+    let words: Vec<&str> = cmd.split_whitespace().collect();
+    words
+        .windows(2)
+        .any(|pair| pair[0].rsplit('/').next() == Some("terraform") && pair[1] == "init")
+}
+
+/// Run `attempt` once plus up to `retries` more times, doubling the delay in between
+/// Returns the last error when every attempt fails
+pub fn retry_with_backoff<F>(retries: u32, initial_delay: Duration, mut attempt: F) -> Result<()>
+where
+    F: FnMut() -> Result<()>,
+{
+    let mut delay = initial_delay;
+    let mut remaining = retries;
+    loop {
+        match attempt() {
+            Ok(()) => return Ok(()),
+            Err(e) if remaining == 0 => return Err(e),
+            Err(_) => {
+                std::thread::sleep(delay);
+                delay *= 2;
+                remaining -= 1;
+            }
+        }
+    }
+}
+
+/// Run a step with `run`, retrying retry-eligible steps up to `retries` times
+/// with a delay doubling from `initial_delay`; other steps run once
+fn run_with_retries(
+    cmd: &str,
+    retries: u32,
+    initial_delay: Duration,
+    mut run: impl FnMut() -> Result<()>,
+) -> Result<()> {
+    if !is_retry_eligible(cmd) {
+        return run();
+    }
+
+    let mut attempt = 0;
+    retry_with_backoff(retries, initial_delay, || {
+        attempt += 1;
+        let result = run();
+        if let Err(e) = &result {
+            if attempt <= retries {
+                log::warning!(
+                    "`{}` failed (attempt {}/{}), retrying: {}",
+                    cmd,
+                    attempt,
+                    retries + 1,
+                    e
+                );
+            }
+        }
+        result
+    })
+}
+
 /// Execute multiple commands sequentially in a specific directory
 /// Stops on first failure and provides clear error context
+/// `terraform init` steps are retried up to `init_retries` times with backoff
 pub fn execute_commands_sequential(
     commands: &[String],
     working_dir: &str,
     verbose: bool,
+    init_retries: u32,
 ) -> Result<()> {
     if commands.is_empty() {
         return Ok(());
     }
 
     for (i, cmd) in commands.iter().enumerate() {
-        eprintln!(
-            "{} Step {}/{}: Running `{}`",
-            "INFO:".cyan(),
-            i + 1,
-            commands.len(),
-            cmd
-        );
+        log::info!("Step {}/{}: Running `{}`", i + 1, commands.len(), cmd);
 
         if verbose {
-            eprintln!("{} Working directory: {}", "DEBUG:".blue(), working_dir);
-            eprintln!("{} Command: {}", "DEBUG:".blue(), cmd);
+            log::debug!("Working directory: {}", working_dir);
+            log::debug!("Command: {}", cmd);
         }
 
-        let status = Command::new("sh")
-            .arg("-c")
-            .arg(cmd)
-            .current_dir(working_dir)
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .status()
-            .context(format!("Failed to execute command: {}", cmd))?;
+        run_with_retries(cmd, init_retries, INIT_RETRY_BACKOFF, || {
+            run_step(cmd, working_dir, i + 1, commands.len())
+        })?;
+    }
 
-        if !status.success() {
-            let exit_code = status
-                .code()
-                .map(|c| format!("code: {}", c))
-                .unwrap_or_else(|| "unknown (terminated by signal)".to_string());
-            anyhow::bail!(
-                "Command failed at step {}/{}: {}\nExit {}",
-                i + 1,
-                commands.len(),
-                cmd,
-                exit_code
-            );
+    Ok(())
+}
+
+/// Run `setup` in order, then `commands` with at most `jobs` running at once
+/// Meant for independent read-only commands such as `terraform output <key>`
+/// Each command's stdout is captured and returned in the order of `commands`;
+/// the first failing command in that order fails the run
+pub fn execute_commands_parallel(
+    setup: &[String],
+    commands: &[String],
+    working_dir: &str,
+    verbose: bool,
+    init_retries: u32,
+    jobs: usize,
+) -> Result<Vec<String>> {
+    execute_commands_sequential(setup, working_dir, verbose, init_retries)?;
+
+    let total = setup.len() + commands.len();
+    let steps: Vec<(usize, &String)> = commands
+        .iter()
+        .enumerate()
+        .map(|(i, cmd)| (setup.len() + i + 1, cmd))
+        .collect();
+    run_bounded(&steps, jobs, |(step, cmd)| {
+        log::info!("Step {}/{}: Running `{}`", step, total, cmd);
+        capture_step(cmd, working_dir, *step, total)
+    })
+    .into_iter()
+    .collect()
+}
+
+/// Run `task` for every item with at most `jobs` running at once
+/// A single item (or `jobs <= 1`) runs on the calling thread, in order
+/// Results are returned in the same order as `items`
+pub fn run_bounded<T, R, F>(items: &[T], jobs: usize, task: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = jobs.min(items.len());
+    if workers <= 1 {
+        return items.iter().map(task).collect();
+    }
+
+    let next_item = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next_item.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = task(item);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is run"))
+        .collect()
+}
+
+/// Run step `step` of `total` of a sequence
+fn run_step(cmd: &str, working_dir: &str, step: usize, total: usize) -> Result<()> {
+    let status = shell_command(cmd)
+        .current_dir(working_dir)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .context(format!("Failed to execute command: {}", cmd))?;
+
+    if !status.success() {
+        return Err(MkError::CommandFailed {
+            cmd: cmd.to_string(),
+            code: status.code(),
+            step: Some((step, total)),
         }
+        .into());
     }
 
     Ok(())
 }
 
+/// Run step `step` of `total` of a sequence, returning its stdout
+fn capture_step(cmd: &str, working_dir: &str, step: usize, total: usize) -> Result<String> {
+    let output = shell_command(cmd)
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .context(format!("Failed to execute command: {}", cmd))?;
+
+    if !output.status.success() {
+        return Err(MkError::CommandFailed {
+            cmd: cmd.to_string(),
+            code: output.status.code(),
+            step: Some((step, total)),
+        }
+        .into());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
 /// Execute a command and capture its output, returning exit code and output
 /// In verbose mode, streams output to terminal while still capturing exit code
 pub fn execute_command_with_output(
@@ -93,15 +351,13 @@ pub fn execute_command_with_output(
     verbose: bool,
 ) -> Result<(i32, Option<String>)> {
     if verbose {
-        eprintln!("{} Running `{}`", "INFO:".cyan(), cmd);
-        eprintln!("{} Working directory: {}", "DEBUG:".blue(), working_dir);
+        log::info!("Running `{}`", cmd);
+        log::debug!("Working directory: {}", working_dir);
     }
 
     if verbose {
         // In verbose mode, stream output to terminal and just capture exit code
-        let status = Command::new("sh")
-            .arg("-c")
-            .arg(cmd)
+        let status = shell_command(cmd)
             .current_dir(working_dir)
             .stdin(Stdio::inherit())
             .stdout(Stdio::inherit())
@@ -113,9 +369,7 @@ pub fn execute_command_with_output(
         Ok((exit_code, None))
     } else {
         // In non-verbose mode, capture output silently
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(cmd)
+        let output = shell_command(cmd)
             .current_dir(working_dir)
             .output()
             .context("Failed to execute command")?;
@@ -130,12 +384,10 @@ pub fn execute_command_with_output(
 /// Execute a command and capture its output (legacy version that fails on error)
 pub fn execute_command_output(cmd: &str, working_dir: &str, verbose: bool) -> Result<String> {
     if verbose {
-        eprintln!("{} Running `{}` (capturing output)", "DEBUG:".blue(), cmd);
+        log::debug!("Running `{}` (capturing output)", cmd);
     }
 
-    let output = Command::new("sh")
-        .arg("-c")
-        .arg(cmd)
+    let output = shell_command(cmd)
         .current_dir(working_dir)
         .output()
         .context("Failed to execute command")?;
@@ -175,22 +427,14 @@ pub fn execute_commands_sequential_with_output(
     // Execute all commands except the last one
     for (i, cmd) in commands.iter().take(total.saturating_sub(1)).enumerate() {
         if verbose {
-            eprintln!(
-                "{} Step {}/{}: Running `{}`",
-                "INFO:".cyan(),
-                i + 1,
-                total,
-                cmd
-            );
-            eprintln!("{} Working directory: {}", "DEBUG:".blue(), working_dir);
-            eprintln!("{} Command: {}", "DEBUG:".blue(), cmd);
+            log::info!("Step {}/{}: Running `{}`", i + 1, total, cmd);
+            log::debug!("Working directory: {}", working_dir);
+            log::debug!("Command: {}", cmd);
         }
 
         let status = if verbose {
             // Stream output in verbose mode
-            Command::new("sh")
-                .arg("-c")
-                .arg(cmd)
+            shell_command(cmd)
                 .current_dir(working_dir)
                 .stdin(Stdio::inherit())
                 .stdout(Stdio::inherit())
@@ -199,9 +443,7 @@ pub fn execute_commands_sequential_with_output(
                 .context(format!("Failed to execute command: {}", cmd))?
         } else {
             // Capture and suppress output in non-verbose mode
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(cmd)
+            let output = shell_command(cmd)
                 .current_dir(working_dir)
                 .output()
                 .context(format!("Failed to execute command: {}", cmd))?;
@@ -226,22 +468,14 @@ pub fn execute_commands_sequential_with_output(
     // Execute the last command with optional output capture
     if let Some(last_cmd) = commands.last() {
         if verbose {
-            eprintln!(
-                "{} Step {}/{}: Running `{}`",
-                "INFO:".cyan(),
-                total,
-                total,
-                last_cmd
-            );
-            eprintln!("{} Working directory: {}", "DEBUG:".blue(), working_dir);
-            eprintln!("{} Command: {}", "DEBUG:".blue(), last_cmd);
+            log::info!("Step {}/{}: Running `{}`", total, total, last_cmd);
+            log::debug!("Working directory: {}", working_dir);
+            log::debug!("Command: {}", last_cmd);
         }
 
         if capture_last {
             // Capture output mode
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(last_cmd)
+            let output = shell_command(last_cmd)
                 .current_dir(working_dir)
                 .output()
                 .context(format!("Failed to execute command: {}", last_cmd))?;
@@ -252,9 +486,7 @@ pub fn execute_commands_sequential_with_output(
             Ok((exit_code, Some(output_text)))
         } else {
             // Streaming mode
-            let status = Command::new("sh")
-                .arg("-c")
-                .arg(last_cmd)
+            let status = shell_command(last_cmd)
                 .current_dir(working_dir)
                 .stdin(Stdio::inherit())
                 .stdout(Stdio::inherit())
@@ -274,6 +506,235 @@ pub fn execute_commands_sequential_with_output(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_run_bounded_limits_jobs_and_keeps_order() {
+        let items: Vec<usize> = (0..12).collect();
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let results = run_bounded(&items, 3, |item| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+            item * 2
+        });
+
+        assert_eq!(
+            results,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert!(peak.load(Ordering::SeqCst) > 1);
+    }
+
+    #[test]
+    fn test_run_bounded_single_item_runs_inline() {
+        let caller = std::thread::current().id();
+        let threads = run_bounded(&["ghcr.io"], 4, |_| std::thread::current().id());
+        assert_eq!(threads, [caller]);
+
+        let threads = run_bounded(&["ghcr.io", "quay.io"], 1, |_| std::thread::current().id());
+        assert_eq!(threads, [caller, caller]);
+    }
+
+    #[test]
+    fn test_execute_commands_parallel_keeps_order() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let working_dir = temp_dir.path().to_str().unwrap();
+        let setup = vec!["touch initialized".to_string()];
+        // Earlier commands finish last, and each checks the setup ran first
+        let commands: Vec<String> = (1..=4)
+            .map(|i| format!("test -f initialized && sleep 0.{} && echo key{}", 5 - i, i))
+            .collect();
+
+        let outputs =
+            execute_commands_parallel(&setup, &commands, working_dir, false, 0, 4).unwrap();
+        assert_eq!(outputs, ["key1\n", "key2\n", "key3\n", "key4\n"]);
+    }
+
+    #[test]
+    fn test_execute_commands_parallel_reports_failing_step() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let commands = vec!["echo ok".to_string(), "exit 3".to_string()];
+
+        let error = execute_commands_parallel(
+            &["true".to_string()],
+            &commands,
+            temp_dir.path().to_str().unwrap(),
+            false,
+            0,
+            2,
+        )
+        .unwrap_err();
+        assert_eq!(exit_code_of(&error), 3);
+        assert!(error.to_string().contains("3/3"), "{}", error);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("web,db:prod"), "web,db:prod");
+        assert_eq!(shell_quote("db*"), "'db*'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_one_liner() {
+        assert_eq!(
+            one_liner(
+                &["tfswitch".to_string(), "terraform plan".to_string()],
+                "/tmp/my project"
+            ),
+            "cd '/tmp/my project' && tfswitch && terraform plan"
+        );
+    }
+
+    #[test]
+    fn test_exit_code_of_failed_command() {
+        let err = execute_command("exit 3", ".", false)
+            .context("Failed to execute command")
+            .unwrap_err();
+        assert_eq!(exit_code_of(&err), 3);
+
+        let err =
+            execute_commands_sequential(&["true".to_string(), "exit 4".to_string()], ".", false, 0)
+                .unwrap_err();
+        assert_eq!(exit_code_of(&err), 4);
+
+        assert_eq!(exit_code_of(&anyhow::anyhow!("Invalid environment")), 1);
+    }
+
+    #[test]
+    fn test_command_failure_variant() {
+        let err =
+            execute_commands_sequential(&["true".to_string(), "exit 5".to_string()], ".", false, 0)
+                .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MkError>(),
+            Some(&MkError::CommandFailed {
+                cmd: "exit 5".to_string(),
+                code: Some(5),
+                step: Some((2, 2)),
+            })
+        );
+    }
+
+    #[test]
+    fn test_is_retry_eligible() {
+        assert!(is_retry_eligible(
+            "terraform init -reconfigure -backend-config=backend-vars/dev.tfvars"
+        ));
+        assert!(is_retry_eligible("/usr/local/bin/terraform init"));
+        assert!(!is_retry_eligible(
+            "terraform plan -var-file=tfvars/dev.tfvars"
+        ));
+        assert!(!is_retry_eligible("terraform apply -auto-approve"));
+        assert!(!is_retry_eligible("tfswitch"));
+        assert!(!is_retry_eligible("helmfile -e init diff"));
+    }
+
+    #[test]
+    fn test_run_with_retries_retries_init() {
+        let mut attempts = 0;
+        let result = run_with_retries("terraform init -reconfigure", 3, Duration::ZERO, || {
+            attempts += 1;
+            if attempts < 3 {
+                anyhow::bail!("backend unavailable");
+            }
+            Ok(())
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_run_with_retries_gives_up_after_retries() {
+        let mut attempts = 0;
+        let err = run_with_retries("terraform init", 2, Duration::ZERO, || {
+            attempts += 1;
+            anyhow::bail!("attempt {} failed", attempts)
+        })
+        .unwrap_err();
+
+        assert_eq!(attempts, 3);
+        assert_eq!(err.to_string(), "attempt 3 failed");
+    }
+
+    #[test]
+    fn test_run_with_retries_fails_other_steps_immediately() {
+        let mut attempts = 0;
+        let result = run_with_retries("terraform apply -auto-approve", 3, Duration::ZERO, || {
+            attempts += 1;
+            anyhow::bail!("apply failed")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_retry_with_backoff_succeeds_after_failures() {
+        let mut calls = 0;
+        let result = retry_with_backoff(2, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 {
+                anyhow::bail!("token fetch failed");
+            }
+            Ok(())
+        });
+
+        assert!(result.is_ok());
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_retry_with_backoff_returns_last_error() {
+        let mut calls = 0;
+        let result = retry_with_backoff(1, Duration::ZERO, || {
+            calls += 1;
+            anyhow::bail!("attempt {} failed", calls)
+        });
+
+        assert_eq!(result.unwrap_err().to_string(), "attempt 2 failed");
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_retry_with_backoff_no_retries() {
+        let mut calls = 0;
+        let result = retry_with_backoff(0, Duration::ZERO, || {
+            calls += 1;
+            anyhow::bail!("login failed")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_execute_command_to_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let output_file = temp_dir.path().join("rendered/dev/manifests.yaml");
+
+        execute_command_to_file("echo 'kind: ConfigMap'", ".", false, &output_file, false).unwrap();
+        assert_eq!(
+            fs::read_to_string(&output_file).unwrap(),
+            "kind: ConfigMap\n"
+        );
+
+        let err = execute_command_to_file("exit 5", ".", false, &output_file, false).unwrap_err();
+        assert_eq!(exit_code_of(&err), 5);
+
+        // With tee the file is written the same way
+        execute_command_to_file("echo 'Plan: 1 to add'", ".", false, &output_file, true).unwrap();
+        assert_eq!(
+            fs::read_to_string(&output_file).unwrap(),
+            "Plan: 1 to add\n"
+        );
+    }
+
     #[test]
     fn test_execute_command_output() {
         let result = execute_command_output("echo 'test'", "./", false);
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::commands::Action;
+use crate::log;
+
+/// Advisory lock on `.mk/locks/<env>.lock`, released when dropped
+#[derive(Debug)]
+pub struct EnvLock {
+    // Closing the file releases the lock
+    _file: File,
+}
+
+/// Whether `action` changes deployed state and must hold the environment lock
+pub fn requires_lock(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Apply
+            | Action::Delete
+            | Action::Destroy
+            | Action::Uninstall
+            | Action::Import { .. }
+            | Action::Replace { plan: false, .. }
+    )
+}
+
+/// `<project>/.mk/locks/<env>.lock`
+fn lock_path(project_path: &Path, environment: &str) -> PathBuf {
+    project_path
+        .join(".mk")
+        .join("locks")
+        .join(format!("{}.lock", environment))
+}
+
+/// Who holds a lock: `user@host (pid N) since <time>`
+fn holder() -> String {
+    let user = std::env::var("USER").unwrap_or_else(|_| "unknown".to_string());
+    let host = fs::read_to_string("/etc/hostname")
+        .map(|host| host.trim().to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+    format!(
+        "{}@{} (pid {}) since {}",
+        user,
+        host,
+        std::process::id(),
+        log::rfc3339(SystemTime::now())
+    )
+}
+
+/// Take the lock of `environment` in the project, failing with its holder when already held
+pub fn acquire(project_path: &Path, environment: &str) -> Result<EnvLock> {
+    let path = lock_path(project_path, environment);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create lock directory {}", dir.display()))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+    match file.try_lock() {
+        Ok(()) => {
+            file.set_len(0)?;
+            file.rewind()?;
+            writeln!(file, "{}", holder())?;
+            Ok(EnvLock { _file: file })
+        }
+        Err(fs::TryLockError::WouldBlock) => {
+            let mut held_by = String::new();
+            let _ = file.read_to_string(&mut held_by);
+            let held_by = held_by.trim();
+            anyhow::bail!(
+                "Environment {} is locked by {} ({}): wait for it to finish or use --no-lock",
+                environment,
+                if held_by.is_empty() {
+                    "another mk"
+                } else {
+                    held_by
+                },
+                path.display()
+            );
+        }
+        Err(fs::TryLockError::Error(e)) => {
+            Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+        }
+    }
+}
+
+/// Lock `environment` for the duration of a deploying `action`
+pub fn lock_environment(
+    action: &Action,
+    project_path: &str,
+    environment: &str,
+) -> Result<Option<EnvLock>> {
+    if !requires_lock(action) {
+        return Ok(None);
+    }
+    acquire(Path::new(project_path), environment).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_second_acquisition_fails_while_held() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let lock = acquire(temp_dir.path(), "prod").unwrap();
+        let err = acquire(temp_dir.path(), "prod").unwrap_err().to_string();
+        assert!(err.starts_with("Environment prod is locked by "));
+        assert!(err.contains(&format!("(pid {})", std::process::id())));
+        assert!(err.contains(".mk/locks/prod.lock"));
+
+        // Other environments are independent
+        assert!(acquire(temp_dir.path(), "dev").is_ok());
+
+        drop(lock);
+        assert!(acquire(temp_dir.path(), "prod").is_ok());
+    }
+
+    #[test]
+    fn test_requires_lock() {
+        assert!(requires_lock(&Action::Apply));
+        assert!(requires_lock(&Action::Destroy));
+        assert!(!requires_lock(&Action::Diff));
+        assert!(!requires_lock(&Action::Template));
+    }
+}
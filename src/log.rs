@@ -0,0 +1,212 @@
+use crate::cli::LogFormat;
+use clap::ValueEnum;
+use colored::*;
+use std::io::{self, BufRead, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable holding the log format, set from `--log-format`
+pub const FORMAT_ENV: &str = "MK_LOG_FORMAT";
+
+/// Export `--log-format` so every leveled message of this run uses it
+pub fn set_format(format: LogFormat) {
+    if let Some(value) = format.to_possible_value() {
+        std::env::set_var(FORMAT_ENV, value.get_name());
+    }
+}
+
+/// Format from `MK_LOG_FORMAT`, text when unset or unknown
+pub fn format() -> LogFormat {
+    std::env::var(FORMAT_ENV)
+        .ok()
+        .and_then(|value| LogFormat::from_str(&value, true).ok())
+        .unwrap_or(LogFormat::Text)
+}
+
+/// Whether leveled messages are emitted as JSON lines
+pub fn is_json() -> bool {
+    format() == LogFormat::Json
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Debug,
+    Info,
+    Success,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Success => "success",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+
+    fn prefix(&self) -> ColoredString {
+        match self {
+            Level::Debug => "DEBUG:".blue(),
+            Level::Info => "INFO:".cyan(),
+            Level::Success => "SUCCESS:".green(),
+            Level::Warn => "WARNING:".yellow(),
+            Level::Error => "ERROR:".red(),
+        }
+    }
+}
+
+/// Print a leveled message to stderr in the selected format
+/// Surrounding newlines are kept as spacing in text mode and dropped in JSON mode
+pub fn emit(level: Level, message: &str) {
+    let trimmed = message.trim_start_matches('\n');
+    if is_json() {
+        let trimmed = trimmed.trim_end_matches('\n');
+        eprintln!("{}", json_line(level, trimmed, SystemTime::now()));
+    } else {
+        let spacing = &message[..message.len() - trimmed.len()];
+        eprintln!("{}{} {}", spacing, level.prefix(), trimmed);
+    }
+}
+
+/// One JSON object per message: `{"level", "message", "ts"}`
+fn json_line(level: Level, message: &str, time: SystemTime) -> String {
+    serde_json::json!({
+        "level": level.as_str(),
+        "message": message,
+        "ts": rfc3339(time),
+    })
+    .to_string()
+}
+
+/// UTC timestamp with millisecond precision, e.g. `2024-01-31T12:00:00.000Z`
+pub fn rfc3339(time: SystemTime) -> String {
+    let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = elapsed.as_secs();
+    let (hour, minute, second) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+
+    // Civil date from days since epoch (Howard Hinnant's algorithm)
+    let days = (secs / 86400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        elapsed.subsec_millis()
+    )
+}
+
+/// Ask `prompt` on stderr and read a yes/no answer from `input`
+/// Without a terminal there is nobody to ask, so `action` is refused with an error
+pub fn confirm(
+    action: &str,
+    prompt: &str,
+    input: &mut impl BufRead,
+    is_terminal: bool,
+) -> anyhow::Result<bool> {
+    if !is_terminal {
+        anyhow::bail!(
+            "Refusing to {} without confirmation: stdin is not a terminal (use --force)",
+            action
+        );
+    }
+
+    eprint!("{} [y/N]: ", prompt);
+    io::stderr().flush()?;
+
+    let mut response = String::new();
+    input.read_line(&mut response)?;
+    Ok(matches!(
+        response.trim().to_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log::emit($crate::log::Level::Debug, &format!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::emit($crate::log::Level::Info, &format!($($arg)*))
+    };
+}
+
+macro_rules! success {
+    ($($arg:tt)*) => {
+        $crate::log::emit($crate::log::Level::Success, &format!($($arg)*))
+    };
+}
+
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::log::emit($crate::log::Level::Warn, &format!($($arg)*))
+    };
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log::emit($crate::log::Level::Error, &format!($($arg)*))
+    };
+}
+
+pub(crate) use {debug, error, info, success, warning};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_millis(1_709_210_096_789)),
+            "2024-02-29T12:34:56.789Z"
+        );
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(951_868_800)),
+            "2000-03-01T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn test_format_from_env() {
+        std::env::set_var(FORMAT_ENV, "JSON");
+        assert_eq!(format(), LogFormat::Json);
+        std::env::set_var(FORMAT_ENV, "yaml");
+        assert_eq!(format(), LogFormat::Text);
+        std::env::remove_var(FORMAT_ENV);
+        assert_eq!(format(), LogFormat::Text);
+    }
+
+    #[test]
+    fn test_json_line() {
+        let line = json_line(Level::Warn, "No IaC projects found", UNIX_EPOCH);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "warn");
+        assert_eq!(value["message"], "No IaC projects found");
+        assert_eq!(value["ts"], "1970-01-01T00:00:00.000Z");
+    }
+}
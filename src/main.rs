@@ -5,53 +5,204 @@ mod config;
 mod context;
 mod drift;
 mod env;
+mod error;
 mod executor;
+mod lock;
+mod log;
+mod output;
+mod path_filter;
 mod techno;
+mod tools;
 
 use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser};
 use clap_complete::{generate, Shell as CompletionShell};
-use colored::*;
+use colored::Colorize;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use cli::{Cli, Commands, Shell};
+use cli::{Cli, Commands, ConfigAction, ContextAction, EnvAction, Shell};
 use commands::Action;
+use output::OutputFormat;
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("{} {}", "ERROR:".red(), e);
-        // Print the full error chain
-        let mut source = e.source();
-        while let Some(err) = source {
-            eprintln!("  Caused by: {}", err);
-            source = err.source();
+    let cli = Cli::parse();
+    let format = cli.command.format();
+    if format == OutputFormat::Json {
+        // Keep ANSI escapes out of the JSON error
+        colored::control::set_override(false);
+    }
+
+    if let Err(e) = run(cli) {
+        if format == OutputFormat::Json {
+            eprintln!("{}", error::to_json(&e));
+        } else if log::is_json() {
+            // Keep the full error chain on a single JSON line
+            log::error!("{:#}", e);
+        } else {
+            log::error!("{}", e);
+            // Print the full error chain
+            let mut source = e.source();
+            while let Some(err) = source {
+                eprintln!("  {} {}", "Caused by:".red(), err);
+                source = err.source();
+            }
         }
-        std::process::exit(1);
+        // Mirror the failing tool's exit code so CI can tell e.g. terraform 1 from 2
+        std::process::exit(executor::exit_code_of(&e));
     }
 }
 
-fn run() -> Result<()> {
-    let cli = Cli::parse();
+fn run(cli: Cli) -> Result<()> {
+    if let Some(log_format) = cli.log_format {
+        log::set_format(log_format);
+    }
+    if log::is_json() {
+        // Keep ANSI escapes out of JSON messages
+        colored::control::set_override(false);
+    }
+
+    if let Some(project_config) = &cli.project_config {
+        config::set_project_config_override(project_config.into());
+    }
+
+    if cli.dump_config {
+        return dump_config(cli.command.project_path().unwrap_or("."));
+    }
+
+    if let Some(workdir) = &cli.workdir {
+        if !std::path::Path::new(workdir).is_dir() {
+            anyhow::bail!("Working directory not found: {}", workdir);
+        }
+    }
+
+    // Tools run from the project directory, the context checks from here
+    // Exported before any thread starts, every tool and kubectl check mk spawns inherits it
+    if let Some(kubeconfig) = &cli.kubeconfig {
+        let kubeconfig = std::path::absolute(kubeconfig)
+            .with_context(|| format!("Invalid kubeconfig path {}", kubeconfig))?;
+        std::env::set_var("KUBECONFIG", kubeconfig);
+    }
+
+    let mut action_options = commands::ActionOptions {
+        verbose: cli.verbose,
+        no_ignore: cli.no_ignore,
+        report_exit: cli.report_exit,
+        workdir: cli.workdir.clone(),
+        print_command: cli.print_command,
+        no_detect_log: cli.no_detect_log,
+        strict_path: cli.strict_path,
+        no_lock: cli.no_lock,
+        no_preflight: cli.no_preflight,
+        context: cli.context.clone(),
+        auto_approve: cli.yes,
+        refresh_only: cli.command.refresh_only(),
+        no_init: cli.no_init,
+        skip_deps: cli.command.skip_deps(),
+        values_files: cli.command.values_files(),
+        playbook_filters: cli.command.playbook_filters(),
+        ..Default::default()
+    };
 
     match cli.command {
         Commands::Init { path, force } => init_config(path, force),
+        Commands::Config { action } => match action {
+            ConfigAction::Edit => edit_config(cli.verbose),
+        },
+        Commands::Env { action } => match action {
+            EnvAction::Add {
+                project_path,
+                environment,
+            } => env::add_environment(&project_path, &environment, &action_options),
+            EnvAction::Remove {
+                project_path,
+                environment,
+                force,
+            } => env::remove_environment(
+                &project_path,
+                &environment,
+                force || cli.yes,
+                &action_options,
+            ),
+        },
+        Commands::Detect {
+            project_path,
+            format,
+        } => detect(&project_path, format, &action_options),
+        Commands::Doctor => tools::doctor(),
+        Commands::Technologies { format } => {
+            technologies(format);
+            Ok(())
+        }
+        Commands::Context { action } => match action {
+            ContextAction::Doctor {
+                project_path,
+                environment,
+            } => context::doctor(&project_path, &environment),
+        },
         Commands::Completions { shell } => {
             generate_completions(shell);
             Ok(())
         }
-        Commands::CompleteEnv { project_path } => complete_env(&project_path),
+        Commands::CompleteEnv {
+            project_path,
+            format,
+        } => complete_env(&project_path, format),
         Commands::CompleteOutputKey { project_path } => complete_output_key(&project_path),
         Commands::Unlock {
             project_path,
             environment,
             lock_id,
+            force,
+        } => {
+            commands::terraform::validate_lock_id(&lock_id)?;
+            if !force && !cli.yes {
+                commands::terraform::confirm_unlock(
+                    &lock_id,
+                    &environment,
+                    &mut std::io::stdin().lock(),
+                    std::io::stdin().is_terminal(),
+                )?;
+            }
+            execute_action(
+                Action::Unlock { lock_id },
+                &project_path,
+                &environment,
+                &[],
+                &action_options,
+            )
+        }
+        Commands::Import {
+            project_path,
+            environment,
+            address,
+            id,
+            options,
         } => execute_action(
-            Action::Unlock { lock_id },
+            Action::Import { address, id },
             &project_path,
             &environment,
-            &[],
-            cli.verbose,
-            cli.no_ignore,
+            &options,
+            &action_options,
         ),
+        Commands::Replace {
+            project_path,
+            environment,
+            address,
+            plan,
+            options,
+        } => {
+            commands::terraform::validate_resource_address(&address)?;
+            execute_action(
+                Action::Replace { address, plan },
+                &project_path,
+                &environment,
+                &options,
+                &action_options,
+            )
+        }
         Commands::Show {
             project_path,
             environment,
@@ -60,92 +211,99 @@ fn run() -> Result<()> {
             &project_path,
             &environment,
             &[],
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Apply {
             project_path,
             environment,
             options,
+            ..
         } => execute_action(
             Action::Apply,
             &project_path,
             &environment,
             &options,
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Check {
             project_path,
             environment,
             options,
+            ..
         } => execute_action(
             Action::Check,
             &project_path,
             &environment,
             &options,
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Diff {
             project_path,
             environment,
             options,
+            ..
         } => execute_action(
             Action::Diff,
             &project_path,
             &environment,
             &options,
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Plan {
             project_path,
             environment,
+            save_plan_dir,
             options,
-        } => execute_action(
-            Action::Plan,
-            &project_path,
-            &environment,
-            &options,
-            cli.verbose,
-            cli.no_ignore,
-        ),
+            ..
+        } => {
+            if let Some(save_plan_dir) = save_plan_dir {
+                let plan_dir = plan_artifact_dir(Path::new(&save_plan_dir))?;
+                action_options.output_file = Some(plan_dir.join(PLAN_LOG_FILE));
+                action_options.output_tee = true;
+            }
+            execute_action(
+                Action::Plan,
+                &project_path,
+                &environment,
+                &options,
+                &action_options,
+            )
+        }
         Commands::Delete {
             project_path,
             environment,
             options,
+            ..
         } => execute_action(
             Action::Delete,
             &project_path,
             &environment,
             &options,
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Destroy {
             project_path,
             environment,
             options,
+            ..
         } => execute_action(
             Action::Destroy,
             &project_path,
             &environment,
             &options,
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Uninstall {
             project_path,
             environment,
             options,
+            ..
         } => execute_action(
             Action::Uninstall,
             &project_path,
             &environment,
             &options,
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Deps {
             project_path,
@@ -156,48 +314,64 @@ fn run() -> Result<()> {
             &project_path,
             &environment,
             &options,
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Template {
             project_path,
             environment,
+            output_file,
             options,
-        } => execute_action(
-            Action::Template,
-            &project_path,
-            &environment,
-            &options,
-            cli.verbose,
-            cli.no_ignore,
-        ),
+            ..
+        } => {
+            action_options.output_file = output_file.map(PathBuf::from);
+            execute_action(
+                Action::Template,
+                &project_path,
+                &environment,
+                &options,
+                &action_options,
+            )
+        }
         Commands::Output {
             project_path,
             environment,
             key,
             all,
+            parallel,
+            format,
         } => {
             let output_key = if all { None } else { key };
+            if format == OutputFormat::Json {
+                return output_json(&project_path, &environment, output_key, &action_options);
+            }
             execute_action(
-                Action::Output { key: output_key },
+                Action::Output {
+                    key: output_key,
+                    jobs: parallel,
+                },
                 &project_path,
                 &environment,
                 &[],
-                cli.verbose,
-                cli.no_ignore,
+                &action_options,
             )
         }
         Commands::List {
             project_path,
             environment,
+            format: OutputFormat::Json,
             options,
+        } => list_json(&project_path, &environment, &options, &action_options),
+        Commands::List {
+            project_path,
+            environment,
+            options,
+            ..
         } => execute_action(
             Action::List,
             &project_path,
             &environment,
             &options,
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Duplicate {
             project_path,
@@ -208,35 +382,87 @@ fn run() -> Result<()> {
             &project_path,
             &source_env,
             &[],
-            cli.verbose,
-            cli.no_ignore,
+            &action_options,
         ),
         Commands::Bump {
             project_path,
             include_prereleases,
             recursive,
+            format,
+            sort,
+            fail_on_error,
+            path_filters,
+            changelog_file,
+            check,
+            only_errors,
+            update_lock,
+            resume,
+            fresh,
+            max_major_jump,
+            git_commit,
+            commit_message_template,
         } => bump::run_bump(
             &project_path,
-            cli.verbose,
-            include_prereleases,
-            recursive,
-            cli.no_ignore,
+            bump::BumpOptions {
+                verbose: cli.verbose,
+                include_prereleases,
+                recursive,
+                no_ignore: cli.no_ignore,
+                strict_path: cli.strict_path,
+                format,
+                sort,
+                fail_on_error,
+                path_filters,
+                changelog_file,
+                check,
+                only_errors,
+                update_lock,
+                resume,
+                fresh,
+                max_major_jump,
+                git_commit,
+                commit_message_template,
+            },
         ),
         Commands::Drift {
             base_path,
             verbose,
             tech,
             environments,
+            env_file,
+            env_regex,
             capture,
+            capture_all,
+            fail_fast,
             max_depth,
+            path_filters,
+            changed_since,
+            require_projects,
+            absolute_paths,
+            format,
+            ..
         } => drift::run_drift(
             &base_path,
-            verbose,
-            tech,
-            environments,
-            capture,
-            max_depth,
-            cli.no_ignore,
+            drift::DriftOptions {
+                verbose,
+                tech_filter: tech,
+                env_filter: drift::env_filter(environments, env_file.as_deref())?,
+                env_regex: drift::env_regex(env_regex.as_deref())?,
+                capture,
+                capture_all,
+                fail_fast,
+                max_depth,
+                no_ignore: cli.no_ignore,
+                path_filters,
+                changed_since,
+                require_projects,
+                absolute_paths,
+                format,
+                action_options: commands::ActionOptions {
+                    verbose,
+                    ..action_options
+                },
+            },
         ),
     }
 }
@@ -246,18 +472,41 @@ fn execute_action(
     project_path: &str,
     environment: &str,
     options: &[String],
-    verbose: bool,
-    no_ignore: bool,
+    action_options: &commands::ActionOptions,
 ) -> Result<()> {
-    execute_action_internal(
-        action,
-        project_path,
-        environment,
-        options,
-        verbose,
-        false,
-        no_ignore,
-    )?;
+    let action_name = action.name();
+    let detected = action_options
+        .detect(project_path, Some(&action))
+        .context("Failed to detect technology");
+    let techno_name = detected
+        .as_ref()
+        .map(|(techno, _)| techno.as_str())
+        .unwrap_or("unknown");
+
+    let result = detected.and_then(|detected| {
+        execute_detected_action(
+            action,
+            detected,
+            environment,
+            options,
+            false,
+            action_options,
+        )
+    });
+
+    // Machine-parseable line for wrappers needing the underlying tool's exit code
+    if action_options.report_exit {
+        let exit_code = match &result {
+            Ok(_) => 0,
+            Err(e) => executor::exit_code_of(e),
+        };
+        println!(
+            "mk: {} {} {} exit={}",
+            action_name, techno_name, environment, exit_code
+        );
+    }
+
+    result?;
     Ok(())
 }
 
@@ -268,37 +517,97 @@ pub fn execute_action_internal(
     project_path: &str,
     environment: &str,
     options: &[String],
-    verbose: bool,
     drift_mode: bool,
-    no_ignore: bool,
+    action_options: &commands::ActionOptions,
 ) -> Result<(i32, Option<String>)> {
     // Detect technology and get the actual path where it was found
-    let (techno, actual_path) = techno::detect_technology(project_path, Some(&action), drift_mode)
-        .context("Failed to detect technology")?;
+    let detected = techno::detect_technology(
+        project_path,
+        Some(&action),
+        drift_mode,
+        action_options.detect_flags(),
+    )
+    .context("Failed to detect technology")?;
+
+    execute_detected_action(
+        action,
+        detected,
+        environment,
+        options,
+        drift_mode,
+        action_options,
+    )
+}
+
+/// Execute an action on an already detected (technology, actual path)
+fn execute_detected_action(
+    action: Action,
+    (techno, actual_path): (techno::Technology, String),
+    environment: &str,
+    options: &[String],
+    drift_mode: bool,
+    action_options: &commands::ActionOptions,
+) -> Result<(i32, Option<String>)> {
+    let commands::ActionOptions {
+        verbose, no_ignore, ..
+    } = *action_options;
+    // Fail before any environment or context check when no command exists for the action
+    techno.ensure_supports(&action)?;
+    if techno != techno::Technology::Helm && !action_options.values_files.is_empty() {
+        anyhow::bail!(
+            "--values only applies to helm projects, {} is a {} project",
+            actual_path,
+            techno
+        );
+    }
+    if techno != techno::Technology::Ansible && action_options.playbook_filters.is_set() {
+        anyhow::bail!(
+            "--limit, --tags and --ansible-verbose only apply to ansible projects, {} is a {} project",
+            actual_path,
+            techno
+        );
+    }
+
+    let config = config::Config::load_merged(&actual_path)?;
 
     // Check environment validity (skip for deps action)
     // Use actual_path instead of project_path
-    if !matches!(action, Action::Deps) {
-        env::check_environment(&actual_path, environment, techno, no_ignore)
-            .context("Invalid environment")?;
-    }
+    let environment = if matches!(action, Action::Deps) {
+        environment.trim().to_string()
+    } else {
+        env::resolve_environment(
+            &actual_path,
+            environment,
+            techno,
+            no_ignore,
+            config.env.case_insensitive,
+        )
+        .context("Invalid environment")?
+    };
+    let environment = environment.as_str();
 
-    if matches!(
-        techno,
-        techno::Technology::Helm | techno::Technology::Kustomize
-    ) && matches!(
-        action,
-        Action::Apply
-            | Action::Diff
-            | Action::Check
-            | Action::Delete
-            | Action::Destroy
-            | Action::Uninstall
-    ) {
-        context::validate_context(&actual_path, environment, verbose)
-            .context("Kubernetes context validation failed")?;
+    // Held until the action finishes so concurrent deploys of the environment fail fast
+    let _lock = if action_options.no_lock {
+        None
+    } else {
+        lock::lock_environment(&action, &actual_path, environment)?
+    };
+
+    // Ansible plays only target a cluster when opted in
+    let validate_ansible = techno == techno::Technology::Ansible && config.context.validate_ansible;
+    if context::requires_validation(techno, &action, validate_ansible) {
+        context::validate_context(
+            &actual_path,
+            environment,
+            action_options.context.as_deref(),
+            verbose,
+        )
+        .context("Kubernetes context validation failed")?;
     }
 
+    // Configured [args] first so CLI options can override them
+    let options = [config.action_args(techno, &action), options.to_vec()].concat();
+
     // Get the commands to execute
     // Use actual_path instead of project_path
     let commands = commands::get_command(
@@ -306,9 +615,9 @@ pub fn execute_action_internal(
         &actual_path,
         environment,
         techno,
-        options,
-        verbose,
+        &options,
         drift_mode,
+        action_options,
     )
     .context("Failed to generate commands")?;
 
@@ -318,6 +627,11 @@ pub fn execute_action_internal(
         return Ok((0, None));
     }
 
+    // Fail early with an install hint rather than deep inside `sh -c`
+    if !action_options.no_preflight {
+        tools::preflight(&commands::required_binaries(&commands), &commands)?;
+    }
+
     if drift_mode {
         // In drift mode, capture output and return exit code
         let (exit_code, output) = if commands.len() == 1 {
@@ -332,63 +646,347 @@ pub fn execute_action_internal(
         };
         Ok((exit_code, output))
     } else {
-        // Normal mode - just execute
-        if commands.len() == 1 {
-            executor::execute_command(&commands[0], &actual_path, verbose)
+        // Normal mode - just execute, honoring --workdir
+        let workdir = action_options.working_dir(&actual_path);
+        if action_options.print_command {
+            executor::print_commands(&commands, workdir);
+        }
+        if let Some(output_file) = &action_options.output_file {
+            // Setup steps run as usual, only the rendering step's stdout goes to the file
+            let (render, setup) = commands.split_last().expect("commands are not empty");
+            executor::execute_commands_sequential(
+                setup,
+                workdir,
+                verbose,
+                config.terraform.init_retries,
+            )
+            .context("Failed to execute commands")?;
+            let result = executor::execute_command_to_file(
+                render,
+                workdir,
+                verbose,
+                output_file,
+                action_options.output_tee,
+            );
+            if matches!(action, Action::Plan) {
+                // Also kept when the plan fails, e.g. exit 2 of -detailed-exitcode
+                save_plan_file(&options, workdir, output_file)?;
+            }
+            result.context("Failed to execute command")?;
+            if matches!(action, Action::Plan) {
+                log::success!("Plan saved to {}", output_file.display());
+            } else {
+                log::success!("Rendered manifests written to {}", output_file.display());
+            }
+        } else if let Action::Output {
+            key: None,
+            jobs: Some(jobs),
+        } = action
+        {
+            // Output queries don't touch the state, so only the init has to run first
+            let (setup, queries) = commands::terraform::split_output_queries(&commands);
+            let outputs = executor::execute_commands_parallel(
+                setup,
+                queries,
+                workdir,
+                verbose,
+                config.terraform.init_retries,
+                jobs,
+            )
+            .context("Failed to execute commands")?;
+            for output in outputs {
+                print!("{}", output);
+            }
+        } else if commands.len() == 1 {
+            executor::execute_command(&commands[0], workdir, verbose)
                 .context("Failed to execute command")?;
         } else {
-            executor::execute_commands_sequential(&commands, &actual_path, verbose)
-                .context("Failed to execute commands")?;
+            executor::execute_commands_sequential(
+                &commands,
+                workdir,
+                verbose,
+                config.terraform.init_retries,
+            )
+            .context("Failed to execute commands")?;
         }
         Ok((0, None))
     }
 }
 
+/// Name of the plan output in a `--save-plan-dir` subdirectory
+const PLAN_LOG_FILE: &str = "plan.log";
+
+/// `plan-<timestamp>-<pid>` subdirectory of `save_plan_dir` receiving a plan's artifacts
+/// The process id keeps plans started in the same second apart; the directory is only
+/// created once the plan runs, so a failed detection or validation leaves nothing behind
+fn plan_artifact_dir(save_plan_dir: &Path) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs();
+    Ok(save_plan_dir.join(format!("plan-{}-{}", timestamp, std::process::id())))
+}
+
+/// Copy the `-out` plan file of `options`, when it was written, next to the saved plan output
+fn save_plan_file(options: &[String], workdir: &str, output_file: &Path) -> Result<()> {
+    let Some(out) = commands::terraform::plan_out_file(options) else {
+        return Ok(());
+    };
+    let source = Path::new(workdir).join(out);
+    let (Some(plan_dir), Some(name)) = (output_file.parent(), source.file_name()) else {
+        return Ok(());
+    };
+    if source.is_file() {
+        fs::copy(&source, plan_dir.join(name))
+            .with_context(|| format!("Failed to save plan file {}", source.display()))?;
+    }
+    Ok(())
+}
+
 fn init_config(path: Option<String>, force: bool) -> Result<()> {
     let path_buf = path.map(std::path::PathBuf::from);
 
     match config::Config::init_config(path_buf, force) {
         Ok(config_path) => {
-            eprintln!(
-                "{} Configuration file created at: {}",
-                "SUCCESS:".green(),
-                config_path.display()
-            );
-            eprintln!(
-                "{} Edit the file to customize mk's behavior",
-                "INFO:".cyan()
-            );
+            log::success!("Configuration file created at: {}", config_path.display());
+            log::info!("Edit the file to customize mk's behavior");
             Ok(())
         }
         Err(e) => Err(e),
     }
 }
 
-fn complete_env(project_path: &str) -> Result<()> {
+fn edit_config(verbose: bool) -> Result<()> {
+    let config_path = config::Config::get_config_path()?;
+
+    if !config_path.exists() {
+        config::Config::init_config(Some(config_path.clone()), false)?;
+        log::info!("Configuration file created at: {}", config_path.display());
+    }
+
+    let editor = std::env::var("EDITOR")
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let quoted_path = executor::shell_quote(&config_path.to_string_lossy());
+    let working_dir = config_path
+        .parent()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_else(|| ".".to_string());
+
+    executor::execute_command(
+        &format!("{} {}", editor, quoted_path),
+        &working_dir,
+        verbose,
+    )
+    .context("Editor exited with an error")?;
+
+    config::Config::load_from(&config_path)?;
+    log::success!("Configuration at {} is valid", config_path.display());
+
+    Ok(())
+}
+
+fn detect(
+    project_path: &str,
+    format: OutputFormat,
+    action_options: &commands::ActionOptions,
+) -> Result<()> {
+    let (techno, actual_path) = action_options
+        .detect(project_path, None)
+        .context("Failed to detect technology")?;
+    let environments = env::get_environments(&actual_path, techno, action_options.no_ignore)?;
+
+    match format {
+        OutputFormat::Text => {
+            println!("Technology:   {}", techno);
+            println!("Path:         {}", actual_path);
+            println!(
+                "Environments: {}",
+                if environments.is_empty() {
+                    "(none)".to_string()
+                } else {
+                    environments.join(", ")
+                }
+            );
+        }
+        OutputFormat::Json => output::print_json(
+            "detect",
+            serde_json::json!({
+                "technology": techno.as_str(),
+                "path": actual_path,
+                "environments": environments,
+            }),
+        ),
+    }
+
+    Ok(())
+}
+
+/// `--dump-config`: the merged user and project configuration as one JSON line
+fn dump_config(project_path: &str) -> Result<()> {
+    let config = config::Config::load_merged(project_path)?;
+    output::print_json(
+        "dump-config",
+        serde_json::json!({
+            "path": project_path,
+            "config": serde_json::to_value(&config)?,
+        }),
+    );
+    Ok(())
+}
+
+/// `mk technologies`: the actions of every technology, from `supports_action`
+fn technologies(format: OutputFormat) {
+    match format {
+        OutputFormat::Text => {
+            for techno in techno::Technology::ALL {
+                println!(
+                    "{:<10} {}",
+                    techno.as_str(),
+                    techno.supported_actions().join(", ")
+                );
+            }
+        }
+        OutputFormat::Json => {
+            let technologies: serde_json::Map<String, serde_json::Value> = techno::Technology::ALL
+                .iter()
+                .map(|techno| {
+                    (
+                        techno.as_str().to_string(),
+                        serde_json::json!(techno.supported_actions()),
+                    )
+                })
+                .collect();
+            output::print_json(
+                "technologies",
+                serde_json::json!({ "technologies": technologies }),
+            )
+        }
+    }
+}
+
+fn complete_env(project_path: &str, format: OutputFormat) -> Result<()> {
     // Silently detect technology and get environments
     // This is used by shell completion, so we only output environment names
-    // Pass silent=true to suppress all INFO messages
-    // Always use no_ignore=true for completion to show all available environments
-    if let Ok((techno, actual_path)) = techno::detect_technology(project_path, None, true) {
-        if let Ok(envs) = env::get_environments(&actual_path, techno, true) {
+    // Results are cached per project until its environment directory changes
+    let envs = env::completion_cache_dir()
+        .and_then(|cache_dir| env::get_environments_cached(project_path, &cache_dir))
+        .unwrap_or_default();
+
+    match format {
+        OutputFormat::Text => {
             // Print each environment on a separate line for shell completion
             for env in envs {
                 println!("{}", env);
             }
         }
+        OutputFormat::Json => output::print_json(
+            "complete-env",
+            serde_json::json!({
+                "path": project_path,
+                "environments": envs,
+            }),
+        ),
     }
 
     Ok(())
 }
 
+/// Detect the project for a read-only `action` and check `environment` exists
+/// Returns the technology, the project path and the project's name for the environment
+fn detect_with_environment(
+    action: &Action,
+    project_path: &str,
+    environment: &str,
+    action_options: &commands::ActionOptions,
+) -> Result<(techno::Technology, String, String)> {
+    let (techno, actual_path) = action_options
+        .detect(project_path, Some(action))
+        .context("Failed to detect technology")?;
+    let environment =
+        env::check_environment(&actual_path, environment, techno, action_options.no_ignore)
+            .context("Invalid environment")?;
+    Ok((techno, actual_path, environment))
+}
+
+/// `mk output --format json`: terraform output values in the JSON envelope
+fn output_json(
+    project_path: &str,
+    environment: &str,
+    key: Option<String>,
+    action_options: &commands::ActionOptions,
+) -> Result<()> {
+    let action = Action::Output {
+        key: key.clone(),
+        jobs: None,
+    };
+    let (techno, actual_path, environment) =
+        detect_with_environment(&action, project_path, environment, action_options)?;
+
+    let commands = commands::terraform::output_json_commands(
+        &actual_path,
+        &environment,
+        action_options.no_init,
+    );
+    if !action_options.no_preflight {
+        tools::preflight(&commands::required_binaries(&commands), &commands)?;
+    }
+
+    // Tool output is captured so stdout only carries the envelope
+    let (exit_code, stdout) = executor::execute_commands_sequential_with_output(
+        &commands,
+        action_options.working_dir(&actual_path),
+        false,
+        true,
+    )
+    .context("Failed to execute commands")?;
+    if exit_code != 0 {
+        anyhow::bail!("terraform output -json failed with exit code {}", exit_code);
+    }
+    let outputs =
+        commands::terraform::parse_output_values(&stdout.unwrap_or_default(), key.as_deref())?;
+
+    output::print_json(
+        "output",
+        serde_json::json!({
+            "technology": techno.as_str(),
+            "path": actual_path,
+            "environment": environment,
+            "outputs": outputs,
+        }),
+    );
+    Ok(())
+}
+
+/// `mk list --format json`: the ansible inventory in the JSON envelope
+fn list_json(
+    project_path: &str,
+    environment: &str,
+    options: &[String],
+    action_options: &commands::ActionOptions,
+) -> Result<()> {
+    let (techno, actual_path, environment) =
+        detect_with_environment(&Action::List, project_path, environment, action_options)?;
+    let inventory = commands::ansible::inventory(&actual_path, &environment, options)?;
+
+    output::print_json(
+        "list",
+        serde_json::json!({
+            "technology": techno.as_str(),
+            "path": actual_path,
+            "environment": environment,
+            "inventory": inventory,
+        }),
+    );
+    Ok(())
+}
+
 fn complete_output_key(project_path: &str) -> Result<()> {
     use ignore::WalkBuilder;
-    use regex::Regex;
     use std::collections::HashSet;
     use std::fs;
 
     // Scan for .tf files and extract output keys
-    let output_regex = Regex::new(r#"^output\s+"([^"]+)"\s+\{"#).unwrap();
     let mut output_keys = HashSet::new();
 
     // Walk through the project directory looking for .tf files
@@ -405,13 +1003,7 @@ fn complete_output_key(project_path: &str) -> Result<()> {
             if let Some(ext) = entry.path().extension() {
                 if ext == "tf" {
                     if let Ok(content) = fs::read_to_string(entry.path()) {
-                        for line in content.lines() {
-                            if let Some(captures) = output_regex.captures(line) {
-                                if let Some(key) = captures.get(1) {
-                                    output_keys.insert(key.as_str().to_string());
-                                }
-                            }
-                        }
+                        output_keys.extend(commands::terraform::parse_output_keys(&content));
                     }
                 }
             }
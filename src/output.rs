@@ -0,0 +1,44 @@
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+/// Format of the results printed by read-only commands
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable lines
+    Text,
+    /// A single JSON object: {"command", ...fields}
+    Json,
+}
+
+/// JSON envelope shared by read-only commands: the command's fields plus its `command` name
+pub fn envelope(command: &str, fields: Value) -> Value {
+    let mut object = Map::new();
+    object.insert("command".to_string(), Value::from(command));
+    if let Value::Object(fields) = fields {
+        object.extend(fields);
+    }
+    Value::Object(object)
+}
+
+/// Print the JSON envelope of `command` as one line on stdout
+pub fn print_json(command: &str, fields: Value) {
+    println!("{}", envelope(command, fields));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_envelope_adds_command_name() {
+        let value = envelope(
+            "detect",
+            json!({"technology": "helm", "path": "charts/api", "environments": ["dev"]}),
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{"command":"detect","environments":["dev"],"path":"charts/api","technology":"helm"}"#
+        );
+    }
+}
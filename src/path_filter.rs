@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Project path filter built from `--path-filter` globs
+/// Patterns are matched against the project path relative to the scan base
+/// and multiple patterns are combined as a union.
+pub struct PathFilter {
+    globs: Option<GlobSet>,
+}
+
+impl PathFilter {
+    /// Build a filter, an empty pattern list matches every path
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        if patterns.is_empty() {
+            return Ok(Self { globs: None });
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern.trim_end_matches('/'))
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid path filter '{}'", pattern))?;
+            builder.add(glob);
+        }
+
+        Ok(Self {
+            globs: Some(builder.build()?),
+        })
+    }
+
+    /// Check whether `path` (below `base`) is kept by the filter
+    pub fn matches(&self, base: &Path, path: &Path) -> bool {
+        let Some(globs) = &self.globs else {
+            return true;
+        };
+
+        let relative = path.strip_prefix(base).unwrap_or(path);
+        globs.is_match(relative)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(patterns: &[&str]) -> PathFilter {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        PathFilter::new(&patterns).unwrap()
+    }
+
+    #[test]
+    fn test_empty_filter_matches_everything() {
+        let filter = filter(&[]);
+        assert!(filter.matches(Path::new("/repo"), Path::new("/repo/anything/terraform")));
+    }
+
+    #[test]
+    fn test_filter_matches_relative_to_base() {
+        let filter = filter(&["services/*/terraform"]);
+        let base = Path::new("/repo");
+
+        assert!(filter.matches(base, Path::new("/repo/services/api/terraform")));
+        assert!(!filter.matches(base, Path::new("/repo/services/api/nested/terraform")));
+        assert!(!filter.matches(base, Path::new("/repo/platform/terraform")));
+    }
+
+    #[test]
+    fn test_multiple_filters_union() {
+        let filter = filter(&["services/*/terraform", "charts/**"]);
+        let base = Path::new("/repo");
+
+        assert!(filter.matches(base, Path::new("/repo/services/api/terraform")));
+        assert!(filter.matches(base, Path::new("/repo/charts/team/app")));
+        assert!(!filter.matches(base, Path::new("/repo/platform/terraform")));
+    }
+
+    #[test]
+    fn test_invalid_filter_is_rejected() {
+        assert!(PathFilter::new(&["services/[".to_string()]).is_err());
+    }
+}
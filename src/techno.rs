@@ -1,9 +1,14 @@
 use anyhow::Result;
 use colored::*;
 use dialoguer::Select;
-use std::path::Path;
-
-use crate::config::Config;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::config::{self, Config};
+use crate::error::MkError;
+use crate::log;
 use crate::Action;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,6 +20,14 @@ pub enum Technology {
 }
 
 impl Technology {
+    /// Every technology, in detection priority order
+    pub const ALL: [Technology; 4] = [
+        Technology::Terraform,
+        Technology::Helm,
+        Technology::Kustomize,
+        Technology::Ansible,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Technology::Terraform => "terraform",
@@ -30,13 +43,17 @@ impl Technology {
             // Terraform actions
             (Technology::Terraform, Action::Plan) => true,
             (Technology::Terraform, Action::Check) => true,
+            (Technology::Terraform, Action::Diff) => true,
             (Technology::Terraform, Action::Apply) => true,
             (Technology::Terraform, Action::Destroy) => true,
             (Technology::Terraform, Action::Delete) => true,
+            (Technology::Terraform, Action::Uninstall) => true,
             (Technology::Terraform, Action::Output { .. }) => true,
             (Technology::Terraform, Action::Duplicate { .. }) => true,
             (Technology::Terraform, Action::Unlock { .. }) => true,
             (Technology::Terraform, Action::Show) => true,
+            (Technology::Terraform, Action::Import { .. }) => true,
+            (Technology::Terraform, Action::Replace { .. }) => true,
 
             // Helm actions
             (Technology::Helm, Action::Apply) => true,
@@ -48,9 +65,11 @@ impl Technology {
             (Technology::Helm, Action::Diff) => true,
             (Technology::Helm, Action::Check) => true,
             (Technology::Helm, Action::Duplicate { .. }) => true,
+            (Technology::Helm, Action::Show) => true,
 
             // Ansible actions
             (Technology::Ansible, Action::Diff) => true,
+            (Technology::Ansible, Action::Check) => true,
             (Technology::Ansible, Action::Apply) => true,
             (Technology::Ansible, Action::List) => true,
             (Technology::Ansible, Action::Deps) => true,
@@ -58,10 +77,62 @@ impl Technology {
             // Kustomize actions
             (Technology::Kustomize, Action::Apply) => true,
             (Technology::Kustomize, Action::Diff) => true,
+            (Technology::Kustomize, Action::Check) => true,
+            (Technology::Kustomize, Action::Template) => true,
+            (Technology::Kustomize, Action::Delete) => true,
+            (Technology::Kustomize, Action::Destroy) => true,
+            (Technology::Kustomize, Action::Uninstall) => true,
 
             _ => false,
         }
     }
+
+    /// Whether a child directory of this technology is picked for `action` when a parent
+    /// holds several technologies
+    ///
+    /// Actions only supported as aliases of another (terraform `diff` for `plan`, kustomize
+    /// `template`, ...) don't count, so such parents keep selecting the native technology.
+    fn selected_for_action(&self, action: &Action) -> bool {
+        let alias = matches!(
+            (self, action),
+            (Technology::Terraform, Action::Diff | Action::Uninstall)
+                | (Technology::Ansible, Action::Check)
+                | (
+                    Technology::Kustomize,
+                    Action::Check
+                        | Action::Template
+                        | Action::Delete
+                        | Action::Destroy
+                        | Action::Uninstall
+                )
+        );
+        !alias && self.supports_action(action)
+    }
+
+    /// Names of the actions this technology supports, in subcommand order
+    pub fn supported_actions(&self) -> Vec<&'static str> {
+        Action::all()
+            .iter()
+            .filter(|action| self.supports_action(action))
+            .map(Action::name)
+            .collect()
+    }
+
+    /// Fail with the supported actions when this technology can't run `action`
+    pub fn ensure_supports(&self, action: &Action) -> Result<()> {
+        if self.supports_action(action) {
+            return Ok(());
+        }
+        Err(MkError::UnsupportedAction {
+            action: action.name().to_string(),
+            technology: self.to_string(),
+            supported: self
+                .supported_actions()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+        })?
+    }
 }
 
 impl std::fmt::Display for Technology {
@@ -70,6 +141,15 @@ impl std::fmt::Display for Technology {
     }
 }
 
+/// Command-line flags changing how `detect_technology` behaves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DetectFlags {
+    /// `--no-detect-log`: keep detection but drop its INFO messages
+    pub no_detect_log: bool,
+    /// `--strict-path`: detect only at the given path, never in its children
+    pub strict_path: bool,
+}
+
 /// Try to detect technology in the given path without fallback
 fn try_detect_technology_direct(path: &Path) -> Option<Technology> {
     // Check based on directory name first (ansible or terraform)
@@ -144,20 +224,70 @@ fn scan_child_technologies(path: &Path) -> Result<Vec<(String, Technology)>> {
 /// 4. If multiple technologies found, filters by action support if action is provided
 /// 5. Falls back to priority-based or interactive selection if needed
 ///
+/// With `--strict-path`, only step 1 runs and its failure is an error.
+///
 /// Returns a tuple of (Technology, actual_path) where actual_path is the directory
 /// where the technology was found. This is important when technology is detected
 /// in a child directory, as subsequent operations need to use the correct path.
 ///
+/// Detections are memoized for the process, so the "Detected" message is only logged once per path.
+///
 /// # Parameters
 /// * `project_path` - The path to analyze for technology detection
 /// * `action` - Optional action to filter technologies by support
 /// * `silent` - If true, suppresses all informational output (useful for shell completion)
+/// * `flags` - Flags given on the command line
 pub fn detect_technology(
     project_path: &str,
     action: Option<&Action>,
     silent: bool,
+    flags: DetectFlags,
+) -> Result<(Technology, String)> {
+    cached_detection(
+        DETECTION_CACHE.get_or_init(Default::default),
+        project_path,
+        action,
+        || detect_technology_uncached(project_path, action, silent, flags),
+    )
+}
+
+/// Detected (technology, actual path) per canonical project path and action
+type DetectionCache = Mutex<HashMap<(PathBuf, Option<&'static str>), (Technology, String)>>;
+
+static DETECTION_CACHE: OnceLock<DetectionCache> = OnceLock::new();
+
+/// Return the cached detection of `project_path` for `action`, otherwise run `detect`
+/// The action is part of the key since it filters technologies found in child directories
+fn cached_detection<F>(
+    cache: &DetectionCache,
+    project_path: &str,
+    action: Option<&Action>,
+    detect: F,
+) -> Result<(Technology, String)>
+where
+    F: FnOnce() -> Result<(Technology, String)>,
+{
+    let key = (
+        std::fs::canonicalize(project_path).unwrap_or_else(|_| PathBuf::from(project_path)),
+        action.map(Action::name),
+    );
+    if let Some(detected) = cache.lock().unwrap().get(&key) {
+        return Ok(detected.clone());
+    }
+
+    let detected = detect()?;
+    cache.lock().unwrap().insert(key, detected.clone());
+    Ok(detected)
+}
+
+fn detect_technology_uncached(
+    project_path: &str,
+    action: Option<&Action>,
+    silent: bool,
+    flags: DetectFlags,
 ) -> Result<(Technology, String)> {
     let path = Path::new(project_path);
+    let log_detection = !silent && !flags.no_detect_log;
 
     if !path.exists() {
         anyhow::bail!("Path does not exist: {}", project_path);
@@ -169,33 +299,34 @@ pub fn detect_technology(
 
     // Try direct detection first (maintains backward compatibility and precedence)
     if let Some(tech) = try_detect_technology_direct(path) {
-        if !silent {
-            eprintln!(
-                "{} Detected {} in {}",
-                "INFO:".cyan(),
-                tech.to_string().bold(),
-                project_path
-            );
+        if log_detection {
+            log::info!("Detected {} in {}", tech.to_string().bold(), project_path);
         }
         return Ok((tech, project_path.to_string()));
     }
 
+    if flags.strict_path {
+        return Err(anyhow::Error::from(MkError::NoTechnology {
+            path: project_path.to_string(),
+        })
+        .context("Child directories are not scanned with --strict-path"));
+    }
+
     // If direct detection failed, try scanning child directories
     let child_technologies = scan_child_technologies(path)?;
 
     match child_technologies.len() {
-        0 => {
-            anyhow::bail!("No technology detected in {}", project_path);
-        }
+        0 => Err(MkError::NoTechnology {
+            path: project_path.to_string(),
+        })?,
         1 => {
             // Exactly one technology found in children, use it automatically
             let (dir_name, tech) = &child_technologies[0];
             let full_path = path.join(dir_name);
             let full_path_str = full_path.to_string_lossy().to_string();
-            if !silent {
-                eprintln!(
-                    "{} Detected {} in {} (discovered from parent directory)",
-                    "INFO:".cyan(),
+            if log_detection {
+                log::info!(
+                    "Detected {} in {} (discovered from parent directory)",
                     tech.to_string().bold(),
                     full_path.display()
                 );
@@ -209,7 +340,7 @@ pub fn detect_technology(
             let filtered_technologies = if let Some(action) = action {
                 let filtered: Vec<_> = child_technologies
                     .iter()
-                    .filter(|(_, tech)| tech.supports_action(action))
+                    .filter(|(_, tech)| tech.selected_for_action(action))
                     .cloned()
                     .collect();
 
@@ -218,12 +349,13 @@ pub fn detect_technology(
                     let (dir_name, tech) = &filtered[0];
                     let full_path = path.join(dir_name);
                     let full_path_str = full_path.to_string_lossy().to_string();
-                    eprintln!(
-                        "{} Multiple technologies detected. Auto-selected {} for action (from {})",
-                        "INFO:".cyan(),
-                        tech.to_string().bold(),
-                        full_path.display()
-                    );
+                    if log_detection {
+                        log::info!(
+                            "Multiple technologies detected. Auto-selected {} for action (from {})",
+                            tech.to_string().bold(),
+                            full_path.display()
+                        );
+                    }
                     return Ok((*tech, full_path_str));
                 }
 
@@ -244,8 +376,8 @@ pub fn detect_technology(
                 child_technologies.clone()
             };
 
-            // Try to load config
-            let config = Config::load().unwrap_or_default();
+            // Load user and project config
+            let config = Config::load_merged(project_path)?;
 
             // Check if user has configured priority order
             if let Some(priority_list) = config.get_technology_priority() {
@@ -257,12 +389,13 @@ pub fn detect_technology(
                     {
                         let full_path = path.join(dir_name);
                         let full_path_str = full_path.to_string_lossy().to_string();
-                        eprintln!(
-                            "{} Multiple technologies detected. Using {} based on configured priority (from {})",
-                            "INFO:".cyan(),
-                            priority_tech.to_string().bold(),
-                            full_path.display()
-                        );
+                        if log_detection {
+                            log::info!(
+                                "Multiple technologies detected. Using {} based on configured priority (from {})",
+                                priority_tech.to_string().bold(),
+                                full_path.display()
+                            );
+                        }
                         return Ok((priority_tech, full_path_str));
                     }
                 }
@@ -270,11 +403,7 @@ pub fn detect_technology(
 
             // No priority configured or priority didn't match
             // Log info message
-            eprintln!(
-                "{} Multiple technologies detected in {}",
-                "INFO:".cyan(),
-                project_path
-            );
+            log::info!("Multiple technologies detected in {}", project_path);
 
             // Try interactive selection only if not in silent mode
             if !silent {
@@ -294,12 +423,12 @@ pub fn detect_technology(
                         let (dir_name, tech) = &filtered_technologies[idx];
                         let full_path = path.join(dir_name);
                         let full_path_str = full_path.to_string_lossy().to_string();
-                        eprintln!(
-                            "{} Selected {} in {}",
-                            "INFO:".cyan(),
+                        log::info!(
+                            "Selected {} in {}",
                             tech.to_string().bold(),
                             full_path.display()
                         );
+                        prompt_save_priority(project_path, *tech, &config.technology_priority)?;
                         return Ok((*tech, full_path_str));
                     }
                     Err(_) => {
@@ -314,6 +443,37 @@ pub fn detect_technology(
     }
 }
 
+/// Offer to save an interactive selection as the project's `technology_priority`
+fn prompt_save_priority(
+    project_path: &str,
+    selected: Technology,
+    current: &[String],
+) -> Result<()> {
+    let config_path = config::project_config_path(project_path);
+    eprint!(
+        "Save {} as the preferred technology in {}? [Y/n]: ",
+        selected.to_string().bold(),
+        config_path.display()
+    );
+    io::stderr().flush()?;
+
+    let mut response = String::new();
+    io::stdin().read_line(&mut response)?;
+    let response = response.trim().to_lowercase();
+    if !(response.is_empty() || response == "y" || response == "yes") {
+        return Ok(());
+    }
+
+    let priority = config::priority_with_selection(selected, current);
+    config::save_technology_priority(&config_path, &priority)?;
+    log::success!(
+        "Saved technology_priority = [{}] to {}",
+        priority.join(", "),
+        config_path.display()
+    );
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -329,6 +489,77 @@ mod tests {
         assert_eq!(Technology::Ansible.to_string(), "ansible");
     }
 
+    #[test]
+    fn test_supported_actions() {
+        assert_eq!(
+            Technology::Kustomize.supported_actions(),
+            [
+                "apply",
+                "check",
+                "diff",
+                "delete",
+                "destroy",
+                "uninstall",
+                "template"
+            ]
+        );
+        assert!(Technology::Helm.supported_actions().contains(&"show"));
+        assert!(!Technology::Helm.supported_actions().contains(&"plan"));
+    }
+
+    #[test]
+    fn test_ensure_supports_lists_supported_actions() {
+        assert!(Technology::Helm.ensure_supports(&Action::Diff).is_ok());
+
+        let err = Technology::Helm.ensure_supports(&Action::Plan).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MkError>().map(MkError::kind),
+            Some("unsupported_action")
+        );
+        assert_eq!(
+            err.to_string(),
+            "plan is not supported for helm projects. Supported actions: \
+             apply, check, diff, delete, destroy, uninstall, deps, template, duplicate, show"
+        );
+    }
+
+    #[test]
+    fn test_cached_detection_runs_once_per_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let terraform_dir = temp_dir.path().join("terraform");
+        let helm_dir = temp_dir.path().join("chart");
+        fs::create_dir(&terraform_dir).unwrap();
+        fs::create_dir(&helm_dir).unwrap();
+        fs::write(helm_dir.join("Chart.yaml"), "name: chart\n").unwrap();
+
+        let cache = DetectionCache::default();
+        let detections = std::cell::Cell::new(0);
+        let detect = |path: &Path| {
+            let path = path.to_str().unwrap().to_string();
+            cached_detection(&cache, &path, Some(&Action::Apply), || {
+                // Stands for the detection logging "Detected ..."
+                detections.set(detections.get() + 1);
+                detect_technology_uncached(
+                    &path,
+                    Some(&Action::Apply),
+                    true,
+                    DetectFlags::default(),
+                )
+            })
+            .unwrap()
+        };
+
+        let first = detect(&terraform_dir);
+        // Same directory through another spelling of the path
+        let second = detect(&terraform_dir.join("..").join("terraform"));
+        assert_eq!(first, second);
+        assert_eq!(detections.get(), 1);
+
+        // Other paths are detected on their own
+        assert_eq!(detect(&helm_dir).0, Technology::Helm);
+        assert_eq!(detections.get(), 2);
+    }
+
     #[test]
     fn test_technology_as_str() {
         assert_eq!(Technology::Terraform.as_str(), "terraform");
@@ -343,8 +574,13 @@ mod tests {
         let terraform_dir = temp_dir.path().join("terraform");
         fs::create_dir(&terraform_dir).unwrap();
 
-        let (tech, _path) =
-            detect_technology(terraform_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            terraform_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Terraform);
     }
 
@@ -354,7 +590,13 @@ mod tests {
         let ansible_dir = temp_dir.path().join("ansible");
         fs::create_dir(&ansible_dir).unwrap();
 
-        let (tech, _path) = detect_technology(ansible_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            ansible_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Ansible);
     }
 
@@ -371,7 +613,13 @@ mod tests {
         .unwrap();
         fs::write(helm_dir.join("values.yaml"), "replicaCount: 3").unwrap();
 
-        let (tech, _path) = detect_technology(helm_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            helm_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Helm);
     }
 
@@ -382,14 +630,24 @@ mod tests {
         fs::create_dir(&kustomize_dir).unwrap();
         fs::create_dir(kustomize_dir.join("overlays")).unwrap();
 
-        let (tech, _path) =
-            detect_technology(kustomize_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            kustomize_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Kustomize);
     }
 
     #[test]
     fn test_detect_technology_nonexistent_path() {
-        let result = detect_technology("/nonexistent/path/12345", None, false);
+        let result = detect_technology(
+            "/nonexistent/path/12345",
+            None,
+            false,
+            DetectFlags::default(),
+        );
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("does not exist"));
     }
@@ -400,7 +658,12 @@ mod tests {
         let file_path = temp_dir.path().join("test.txt");
         fs::write(&file_path, "content").unwrap();
 
-        let result = detect_technology(file_path.to_str().unwrap(), None, false);
+        let result = detect_technology(
+            file_path.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        );
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("not a directory"));
     }
@@ -411,7 +674,12 @@ mod tests {
         let unknown_dir = temp_dir.path().join("unknown");
         fs::create_dir(&unknown_dir).unwrap();
 
-        let result = detect_technology(unknown_dir.to_str().unwrap(), None, false);
+        let result = detect_technology(
+            unknown_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        );
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
@@ -438,8 +706,13 @@ mod tests {
         fs::create_dir(&terraform_dir).unwrap();
         fs::write(terraform_dir.join("values.yaml"), "content").unwrap();
 
-        let (tech, _path) =
-            detect_technology(terraform_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            terraform_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Terraform);
     }
 
@@ -452,7 +725,13 @@ mod tests {
         fs::create_dir(&ansible_dir).unwrap();
         fs::write(ansible_dir.join("values.yaml"), "content").unwrap();
 
-        let (tech, _path) = detect_technology(ansible_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            ansible_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Ansible);
     }
 
@@ -470,7 +749,13 @@ mod tests {
         fs::write(project_dir.join("values.yaml"), "content").unwrap();
         fs::create_dir(project_dir.join("overlays")).unwrap();
 
-        let (tech, _path) = detect_technology(project_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            project_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Helm);
     }
 
@@ -486,7 +771,13 @@ mod tests {
         let terraform_dir = parent_dir.join("terraform");
         fs::create_dir(&terraform_dir).unwrap();
 
-        let (tech, _path) = detect_technology(parent_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Terraform);
     }
 
@@ -500,7 +791,13 @@ mod tests {
         let ansible_dir = parent_dir.join("ansible");
         fs::create_dir(&ansible_dir).unwrap();
 
-        let (tech, _path) = detect_technology(parent_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Ansible);
     }
 
@@ -520,7 +817,13 @@ mod tests {
         .unwrap();
         fs::write(helm_dir.join("values.yaml"), "replicaCount: 3").unwrap();
 
-        let (tech, _path) = detect_technology(parent_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Helm);
     }
 
@@ -536,8 +839,13 @@ mod tests {
         fs::create_dir(&ansible_child).unwrap();
 
         // Should detect terraform (parent directory name), not ansible (child)
-        let (tech, _path) =
-            detect_technology(terraform_parent.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            terraform_parent.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Terraform);
     }
 
@@ -556,7 +864,13 @@ mod tests {
         let terraform_dir = parent_dir.join("terraform");
         fs::create_dir(&terraform_dir).unwrap();
 
-        let (tech, _path) = detect_technology(parent_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Terraform);
     }
 
@@ -577,7 +891,13 @@ mod tests {
         let terraform_dir = parent_dir.join("terraform");
         fs::create_dir(&terraform_dir).unwrap();
 
-        let (tech, _path) = detect_technology(parent_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Terraform);
     }
 
@@ -599,7 +919,13 @@ mod tests {
         fs::create_dir(&terraform_child).unwrap();
 
         // Should detect Helm (parent), not Terraform (child)
-        let (tech, _path) = detect_technology(helm_dir.to_str().unwrap(), None, false).unwrap();
+        let (tech, _path) = detect_technology(
+            helm_dir.to_str().unwrap(),
+            None,
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Helm);
     }
 
@@ -647,12 +973,45 @@ mod tests {
         fs::create_dir(parent_dir.join("ansible")).unwrap();
 
         // Plan action should auto-select Terraform (only Terraform supports Plan)
-        let (tech, path) =
-            detect_technology(parent_dir.to_str().unwrap(), Some(&Action::Plan), false).unwrap();
+        let (tech, path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            Some(&Action::Plan),
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Terraform);
         assert!(path.contains("terraform"));
     }
 
+    #[rstest::rstest]
+    #[case::diff(Action::Diff)]
+    #[case::uninstall(Action::Uninstall)]
+    fn test_action_filtering_prefers_helm_over_terraform_alias(#[case] action: Action) {
+        // Terraform runs diff and uninstall as aliases, Helm natively
+        let temp_dir = TempDir::new().unwrap();
+        let parent_dir = temp_dir.path().join("project");
+        fs::create_dir(&parent_dir).unwrap();
+        fs::create_dir(parent_dir.join("terraform")).unwrap();
+        let helm_dir = parent_dir.join("my-chart");
+        fs::create_dir(&helm_dir).unwrap();
+        fs::write(
+            helm_dir.join("Chart.yaml"),
+            "name: my-chart\nversion: 1.0.0",
+        )
+        .unwrap();
+
+        let (tech, path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            Some(&action),
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
+        assert_eq!(tech, Technology::Helm);
+        assert!(path.contains("my-chart"));
+    }
+
     #[test]
     fn test_action_filtering_list_action() {
         // List action is only supported by Ansible
@@ -667,8 +1026,13 @@ mod tests {
         fs::create_dir(parent_dir.join("ansible")).unwrap();
 
         // List action should auto-select Ansible
-        let (tech, path) =
-            detect_technology(parent_dir.to_str().unwrap(), Some(&Action::List), false).unwrap();
+        let (tech, path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            Some(&Action::List),
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Ansible);
         assert!(path.contains("ansible"));
     }
@@ -694,9 +1058,13 @@ mod tests {
         fs::write(helm_dir.join("values.yaml"), "content").unwrap();
 
         // Template action should auto-select Helm
-        let (tech, path) =
-            detect_technology(parent_dir.to_str().unwrap(), Some(&Action::Template), false)
-                .unwrap();
+        let (tech, path) = detect_technology(
+            parent_dir.to_str().unwrap(),
+            Some(&Action::Template),
+            false,
+            DetectFlags::default(),
+        )
+        .unwrap();
         assert_eq!(tech, Technology::Helm);
         assert!(path.contains("my-chart"));
     }
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use colored::*;
+use regex::Regex;
+use semver::Version;
+use std::ffi::OsStr;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+
+use crate::log;
+
+/// An external binary mk drives, with how to ask its version
+struct Tool {
+    name: &'static str,
+    version_args: &'static [&'static str],
+    min_version: Version,
+}
+
+/// Tools probed by `mk doctor`, in display order
+fn tools() -> Vec<Tool> {
+    let tool = |name, version_args, min_version| Tool {
+        name,
+        version_args,
+        min_version: Version::parse(min_version).unwrap(),
+    };
+    vec![
+        tool("terraform", &["--version"], "1.0.0"),
+        tool("helm", &["version", "--short"], "3.0.0"),
+        tool("helmfile", &["--version"], "0.150.0"),
+        tool("kustomize", &["version"], "4.0.0"),
+        tool("ansible", &["--version"], "2.9.0"),
+        tool("kubectl", &["version", "--client"], "1.20.0"),
+    ]
+}
+
+/// Where to get a tool mk may need
+fn install_hint(name: &str) -> Option<&'static str> {
+    match name {
+        "terraform" => Some("https://developer.hashicorp.com/terraform/install"),
+        "tfswitch" => Some("https://tfswitch.warrensbox.com"),
+        "helm" => Some("https://helm.sh/docs/intro/install"),
+        "helmfile" => Some("https://helmfile.readthedocs.io"),
+        "kustomize" => Some("https://kubectl.docs.kubernetes.io/installation/kustomize"),
+        "kubectl" => Some("https://kubernetes.io/docs/tasks/tools"),
+        "ansible" | "ansible-playbook" | "ansible-galaxy" | "ansible-inventory" => {
+            Some("https://docs.ansible.com/ansible/latest/installation_guide")
+        }
+        _ => None,
+    }
+}
+
+/// Whether `name` is an executable file in one of the `path` directories
+fn is_on_path(name: &str, path: &OsStr) -> bool {
+    std::env::split_paths(path).any(|dir| {
+        fs::metadata(dir.join(name))
+            .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    })
+}
+
+/// Fail with an install hint if one of `binaries` is not on `PATH`
+/// The error names the first of `commands` using the missing binary
+/// Names containing a `/` are explicit paths and are left to the shell
+pub fn preflight(binaries: &[String], commands: &[String]) -> Result<()> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    for binary in binaries {
+        if binary.contains('/') || is_on_path(binary, &path) {
+            continue;
+        }
+        let mut message = format!("command not found: {}", binary);
+        if let Some(hint) = install_hint(binary) {
+            message.push_str(&format!(" (install from {})", hint));
+        }
+        if let Some(cmd) = commands
+            .iter()
+            .find(|cmd| cmd.split_whitespace().any(|word| word == binary))
+        {
+            message.push_str(&format!("\nNeeded to run `{}`", cmd));
+        }
+        anyhow::bail!(message);
+    }
+
+    Ok(())
+}
+
+/// Run `name args` and return the first non-empty output line
+/// Returns `None` when the binary cannot be started
+pub fn probe_tool(name: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(name).args(args).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    Some(
+        stdout
+            .lines()
+            .chain(stderr.lines())
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default()
+            .to_string(),
+    )
+}
+
+/// First `X.Y[.Z]` version in a version line, e.g. `Terraform v1.5.7` or `ansible [core 2.15.0]`
+fn parse_version(line: &str) -> Option<Version> {
+    let re = Regex::new(r"(\d+)\.(\d+)(?:\.(\d+))?").unwrap();
+    let caps = re.captures(line)?;
+    let part = |i: usize| caps.get(i).map_or(Ok(0), |m| m.as_str().parse());
+    Some(Version::new(part(1).ok()?, part(2).ok()?, part(3).ok()?))
+}
+
+/// Print found/missing status and version of each tool, warning on unsupported versions
+pub fn doctor() -> Result<()> {
+    println!("{:<12}{:<10}Version", "Tool", "Status");
+
+    for tool in tools() {
+        match probe_tool(tool.name, tool.version_args) {
+            Some(line) => {
+                let version = parse_version(&line);
+                println!(
+                    "{:<12}{:<10}{}",
+                    tool.name,
+                    "found".green(),
+                    version
+                        .as_ref()
+                        .map_or_else(|| "unknown".to_string(), Version::to_string)
+                );
+                match version {
+                    Some(version) if version < tool.min_version => log::warning!(
+                        "{} {} is unsupported, {} or later is required",
+                        tool.name,
+                        version,
+                        tool.min_version
+                    ),
+                    None => {
+                        log::warning!("Could not read the {} version from: {}", tool.name, line)
+                    }
+                    _ => {}
+                }
+            }
+            None => match install_hint(tool.name) {
+                Some(hint) => println!(
+                    "{:<12}{:<10}install from {}",
+                    tool.name,
+                    "missing".red(),
+                    hint
+                ),
+                None => println!("{:<12}{}", tool.name, "missing".red()),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_preflight_reports_missing_binary() {
+        let err = preflight(
+            &["mk-test-missing-tool".to_string()],
+            &[
+                "tfswitch".to_string(),
+                "mk-test-missing-tool plan -var-file=tfvars/dev.tfvars".to_string(),
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "command not found: mk-test-missing-tool\n\
+             Needed to run `mk-test-missing-tool plan -var-file=tfvars/dev.tfvars`"
+        );
+
+        // Explicit paths are left to the shell
+        assert!(preflight(&["./bin/helmfile-wrapper".to_string()], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_is_on_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let tool = temp_dir.path().join("helmfile");
+        fs::write(&tool, "#!/bin/sh\n").unwrap();
+        let path =
+            std::env::join_paths(["/nonexistent", temp_dir.path().to_str().unwrap()]).unwrap();
+
+        // Present but not executable
+        assert!(!is_on_path("helmfile", &path));
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(is_on_path("helmfile", &path));
+        assert!(!is_on_path("tfswitch", &path));
+    }
+
+    #[test]
+    fn test_probe_tool_fake_binary() {
+        let temp_dir = TempDir::new().unwrap();
+        let fake = temp_dir.path().join("terraform");
+        fs::write(
+            &fake,
+            "#!/bin/sh\necho\necho \"Terraform v1.5.7\"\necho \"on linux_amd64\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&fake, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let line = probe_tool(fake.to_str().unwrap(), &["--version"]).unwrap();
+        assert_eq!(line, "Terraform v1.5.7");
+        assert_eq!(parse_version(&line), Some(Version::new(1, 5, 7)));
+    }
+
+    #[test]
+    fn test_probe_tool_missing_binary() {
+        assert_eq!(probe_tool("/nonexistent/terraform", &["--version"]), None);
+    }
+
+    #[test]
+    fn test_parse_version_lines() {
+        assert_eq!(
+            parse_version("v3.14.2+g35c7aac"),
+            Some(Version::new(3, 14, 2))
+        );
+        assert_eq!(
+            parse_version("ansible [core 2.15.0]"),
+            Some(Version::new(2, 15, 0))
+        );
+        assert_eq!(
+            parse_version("Client Version: v1.29"),
+            Some(Version::new(1, 29, 0))
+        );
+        assert_eq!(parse_version("helmfile version unknown"), None);
+    }
+}
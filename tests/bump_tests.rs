@@ -1,7 +1,111 @@
 use assert_cmd::Command;
 use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
 use tempfile::TempDir;
 
+/// Serve `index.yaml` as a Helm repository on localhost, returning its URL
+fn serve_helm_index(index: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            // Drain the request headers
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                line.clear();
+            }
+            let mut stream = &stream;
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                index.len(),
+                index
+            );
+        }
+    });
+    url
+}
+
+/// A chart depending on `redis` at `version` from the repository at `url`
+fn write_chart(dir: &std::path::Path, url: &str, version: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(
+        dir.join("Chart.yaml"),
+        format!(
+            "apiVersion: v2\nname: app\nversion: 1.0.0\ndependencies:\n  - name: redis\n    version: \"{}\"\n    repository: \"{}\"\n",
+            version, url
+        ),
+    )
+    .unwrap();
+}
+
+const REDIS_INDEX: &str = "apiVersion: v1\nentries:\n  redis:\n    - version: 18.1.0\n      appVersion: 7.2.0\n    - version: 17.0.0\n      appVersion: 7.0.0\n";
+
+/// `mk bump` with proxies unset so requests reach the local repository
+fn bump_command() -> Command {
+    let mut cmd = Command::cargo_bin("mk").unwrap();
+    for var in [
+        "HTTP_PROXY",
+        "HTTPS_PROXY",
+        "ALL_PROXY",
+        "http_proxy",
+        "https_proxy",
+        "all_proxy",
+    ] {
+        cmd.env_remove(var);
+    }
+    cmd.arg("bump");
+    cmd
+}
+
+#[test]
+fn test_bump_check_exits_non_zero_on_stale_dependency() {
+    let temp_dir = TempDir::new().unwrap();
+    let url = serve_helm_index(REDIS_INDEX);
+    let chart_dir = temp_dir.path().join("chart");
+    write_chart(&chart_dir, &url, "17.0.0");
+
+    bump_command()
+        .arg(chart_dir.to_str().unwrap())
+        .arg("--check")
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("17.0.0"))
+        .stderr(predicates::str::contains("18.1.0"))
+        .stderr(predicates::str::contains("1 dependencies are out of date"));
+
+    // No changes are made
+    let chart = fs::read_to_string(chart_dir.join("Chart.yaml")).unwrap();
+    assert!(chart.contains("version: \"17.0.0\""));
+}
+
+#[test]
+fn test_bump_check_recursive() {
+    let temp_dir = TempDir::new().unwrap();
+    let url = serve_helm_index(REDIS_INDEX);
+    write_chart(&temp_dir.path().join("charts/current"), &url, "18.1.0");
+    write_chart(&temp_dir.path().join("charts/stale"), &url, "17.0.0");
+
+    bump_command()
+        .arg(temp_dir.path().to_str().unwrap())
+        .args(["--recursive", "--check"])
+        .assert()
+        .code(3);
+
+    // Up-to-date projects pass
+    fs::remove_dir_all(temp_dir.path().join("charts/stale")).unwrap();
+    bump_command()
+        .arg(temp_dir.path().to_str().unwrap())
+        .args(["--recursive", "--check"])
+        .assert()
+        .success()
+        .stderr(predicates::str::contains(
+            "All dependencies are up to date!",
+        ));
+}
+
 #[test]
 fn test_bump_terraform_module_parsing() {
     let temp_dir = TempDir::new().unwrap();
@@ -287,3 +391,77 @@ module "vpc" {
         );
     }
 }
+
+#[test]
+fn test_bump_only_errors_lists_unverified_dependencies() {
+    let temp_dir = TempDir::new().unwrap();
+    let url = serve_helm_index(REDIS_INDEX);
+    write_chart(&temp_dir.path().join("charts/stale"), &url, "17.0.0");
+    // Nothing listens on port 1, the version fetch fails
+    write_chart(
+        &temp_dir.path().join("charts/unreachable"),
+        "http://127.0.0.1:1",
+        "17.0.0",
+    );
+
+    let output = bump_command()
+        .arg(temp_dir.path().to_str().unwrap())
+        .args(["--recursive", "--only-errors"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stdout);
+    assert!(
+        lines[0].starts_with("redis (") && lines[0].contains("charts/unreachable/Chart.yaml"),
+        "{}",
+        lines[0]
+    );
+    assert!(!stdout.contains("18.1.0"));
+
+    // Nothing to report once every version can be fetched
+    fs::remove_dir_all(temp_dir.path().join("charts/unreachable")).unwrap();
+    bump_command()
+        .arg(temp_dir.path().to_str().unwrap())
+        .args(["--recursive", "--only-errors"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_bump_terraform_registry_failure_is_an_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_dir = temp_dir.path().join("terraform");
+    fs::create_dir(&project_dir).unwrap();
+    fs::write(
+        project_dir.join("main.tf"),
+        "module \"vpc\" {\n  source  = \"terraform-google-modules/network/google\"\n  version = \"~> 7.0\"\n}\n",
+    )
+    .unwrap();
+
+    // Nothing listens on port 1, the registry can't be reached through the proxy
+    let output = bump_command()
+        .env("HTTPS_PROXY", "http://127.0.0.1:1")
+        .arg(project_dir.to_str().unwrap())
+        .arg("--fail-on-error")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = bump_command()
+        .env("HTTPS_PROXY", "http://127.0.0.1:1")
+        .arg(project_dir.to_str().unwrap())
+        .arg("--only-errors")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("vpc (") && stdout.contains("main.tf"),
+        "{}",
+        stdout
+    );
+}
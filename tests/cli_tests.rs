@@ -363,3 +363,1187 @@ fn test_invalid_subcommand() {
         .failure()
         .stderr(predicate::str::contains("error"));
 }
+
+#[test]
+fn test_project_config_override_sets_technology_priority() {
+    let temp_dir = TempDir::new().unwrap();
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir(&home_dir).unwrap();
+
+    // Parent directory with both terraform and ansible projects (both support apply)
+    let parent_dir = temp_dir.path().join("stack");
+    fs::create_dir(&parent_dir).unwrap();
+    fs::create_dir(parent_dir.join("terraform")).unwrap();
+    fs::create_dir(parent_dir.join("ansible")).unwrap();
+
+    let project_config = temp_dir.path().join("team.toml");
+    fs::write(&project_config, "technology_priority = [\"ansible\"]\n").unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("HOME", &home_dir)
+        .env("XDG_CONFIG_HOME", home_dir.join(".config"))
+        .args([
+            "--project-config",
+            project_config.to_str().unwrap(),
+            "apply",
+            parent_dir.to_str().unwrap(),
+            "dev",
+        ])
+        .assert()
+        .stderr(predicate::str::contains(
+            "Using ansible based on configured priority",
+        ));
+}
+
+#[test]
+fn test_project_config_discovered_from_parent() {
+    let temp_dir = TempDir::new().unwrap();
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir(&home_dir).unwrap();
+
+    let parent_dir = temp_dir.path().join("stack");
+    fs::create_dir(&parent_dir).unwrap();
+    fs::create_dir(parent_dir.join("terraform")).unwrap();
+    fs::create_dir(parent_dir.join("ansible")).unwrap();
+
+    fs::write(
+        temp_dir.path().join(".mk.toml"),
+        "technology_priority = [\"terraform\"]\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("HOME", &home_dir)
+        .env("XDG_CONFIG_HOME", home_dir.join(".config"))
+        .args(["apply", parent_dir.to_str().unwrap(), "dev"])
+        .assert()
+        .stderr(predicate::str::contains(
+            "Using terraform based on configured priority",
+        ));
+}
+
+#[test]
+fn test_config_invalid_toml_reports_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let home_dir = temp_dir.path().join("home");
+    let config_dir = home_dir.join(".config").join("mk");
+    fs::create_dir_all(&config_dir).unwrap();
+    fs::write(
+        config_dir.join("config.toml"),
+        "technology_priority = [\"terraform\"\n",
+    )
+    .unwrap();
+
+    let parent_dir = temp_dir.path().join("stack");
+    fs::create_dir(&parent_dir).unwrap();
+    fs::create_dir(parent_dir.join("terraform")).unwrap();
+    fs::create_dir(parent_dir.join("ansible")).unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("HOME", &home_dir)
+        .env("XDG_CONFIG_HOME", home_dir.join(".config"))
+        .args(["apply", parent_dir.to_str().unwrap(), "dev"])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("Invalid config file"))
+        .stderr(predicate::str::contains("line 1"))
+        .stderr(predicate::str::contains("panicked").not());
+}
+
+/// Helper to create an executable editor script that appends `content` to the edited file
+fn create_fake_editor(temp_dir: &TempDir, content: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let editor = temp_dir.path().join("fake-editor.sh");
+    fs::write(
+        &editor,
+        format!("#!/bin/sh\nprintf '%s\\n' '{}' >> \"$1\"\n", content),
+    )
+    .unwrap();
+    fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+    editor
+}
+
+#[test]
+fn test_config_edit_creates_and_validates_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir(&home_dir).unwrap();
+    let editor = create_fake_editor(&temp_dir, "[context.mappings.\"github.com/acme/infra\"]");
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("HOME", &home_dir)
+        .env("XDG_CONFIG_HOME", home_dir.join(".config"))
+        .env("EDITOR", &editor)
+        .args(["config", "edit"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Configuration file created at"))
+        .stderr(predicate::str::contains("is valid"));
+
+    let content = fs::read_to_string(home_dir.join(".config/mk/config.toml")).unwrap();
+    assert!(content.contains("# mk configuration file"));
+    assert!(content.ends_with("[context.mappings.\"github.com/acme/infra\"]\n"));
+}
+
+#[test]
+fn test_config_edit_reports_invalid_config() {
+    let temp_dir = TempDir::new().unwrap();
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir(&home_dir).unwrap();
+    let editor = create_fake_editor(&temp_dir, "bogus_key = 1");
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("HOME", &home_dir)
+        .env("XDG_CONFIG_HOME", home_dir.join(".config"))
+        .env("EDITOR", &editor)
+        .args(["config", "edit"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid config file"))
+        .stderr(predicate::str::contains("unknown field `bogus_key`"));
+
+    // The edit is kept so it can be fixed on the next run
+    let content = fs::read_to_string(home_dir.join(".config/mk/config.toml")).unwrap();
+    assert!(content.ends_with("bogus_key = 1\n"));
+}
+
+#[test]
+fn test_context_doctor_reports_repo_and_contexts() {
+    let temp_dir = TempDir::new().unwrap();
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir(&home_dir).unwrap();
+
+    let project_dir = temp_dir.path().join("chart");
+    fs::create_dir(&project_dir).unwrap();
+    for args in [
+        vec!["init", "-q"],
+        vec!["remote", "add", "origin", "git@github.com:acme/infra.git"],
+    ] {
+        std::process::Command::new("git")
+            .args(&args)
+            .current_dir(&project_dir)
+            .status()
+            .unwrap();
+    }
+
+    fs::create_dir(project_dir.join(".mk")).unwrap();
+    fs::write(
+        project_dir.join(".mk/contexts.toml"),
+        "[mappings.\"github.com/acme/infra\"]\nprod = \"gke-prod\"\n",
+    )
+    .unwrap();
+
+    let kubeconfig = temp_dir.path().join("kubeconfig");
+    fs::write(
+        &kubeconfig,
+        "apiVersion: v1\nkind: Config\ncurrent-context: gke-staging\n\
+         contexts:\n- name: gke-staging\n  context:\n    cluster: staging\n    user: admin\n\
+         clusters: []\nusers: []\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("HOME", &home_dir)
+        .env("XDG_CONFIG_HOME", home_dir.join(".config"))
+        .env("KUBECONFIG", &kubeconfig)
+        .args(["context", "doctor", project_dir.to_str().unwrap(), "prod"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("github.com/acme/infra"))
+        .stdout(predicate::str::contains("gke-prod"))
+        .stdout(predicate::str::contains("contexts.toml"))
+        .stdout(predicate::str::contains("gke-staging"))
+        .stdout(predicate::str::contains("mismatch"));
+}
+
+#[test]
+fn test_detect_terraform_project() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["detect", &project_path])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Technology:   terraform"))
+        .stdout(predicate::str::contains(format!(
+            "Path:         {}",
+            project_path
+        )))
+        .stdout(predicate::str::contains("dev"))
+        .stdout(predicate::str::contains("prod"));
+}
+
+#[test]
+fn test_detect_parent_with_single_child_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "detect",
+            temp_dir.path().to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let detected: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(detected["technology"], "terraform");
+    assert_eq!(detected["path"], project_path.as_str());
+    let environments: Vec<&str> = detected["environments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|env| env.as_str().unwrap())
+        .collect();
+    assert!(environments.contains(&"dev"));
+    assert!(environments.contains(&"prod"));
+}
+
+#[test]
+fn test_strict_path_rejects_parent_with_child() {
+    let temp_dir = TempDir::new().unwrap();
+    let _project_path = create_terraform_test_project(&temp_dir);
+    let parent = temp_dir.path().to_str().unwrap();
+
+    for args in [vec!["detect", parent], vec!["plan", parent, "dev"]] {
+        Command::cargo_bin("mk")
+            .unwrap()
+            .arg("--strict-path")
+            .args(&args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "Child directories are not scanned with --strict-path",
+            ))
+            .stderr(predicate::str::contains(format!(
+                "No technology detected in {}",
+                parent
+            )));
+    }
+}
+
+#[test]
+fn test_strict_path_detects_technology_at_path() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["detect", &project_path, "--strict-path"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("dev"));
+}
+
+#[test]
+fn test_report_exit_prints_final_line() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    // Invalid environment fails before running terraform
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .args(["--report-exit", "plan", &project_path, "nonexistent"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().last(),
+        Some("mk: plan terraform nonexistent exit=1")
+    );
+}
+
+#[test]
+fn test_report_exit_unknown_technology() {
+    let temp_dir = TempDir::new().unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "plan",
+            temp_dir.path().to_str().unwrap(),
+            "dev",
+            "--report-exit",
+        ])
+        .assert()
+        .failure()
+        .stdout(predicate::str::ends_with("mk: plan unknown dev exit=1\n"));
+}
+
+/// Helper to install executable fake tools and return a PATH that finds them first
+fn fake_tools_path(temp_dir: &TempDir, tools: &[(&str, &str)]) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin_dir = temp_dir.path().join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    for (name, script) in tools {
+        let tool = bin_dir.join(name);
+        fs::write(&tool, script).unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    )
+}
+
+#[test]
+fn test_no_init_checks_initialized_backend() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let dot_terraform = std::path::Path::new(&project_path).join(".terraform");
+    fs::create_dir(&dot_terraform).unwrap();
+    fs::write(
+        dot_terraform.join("terraform.tfstate"),
+        r#"{"backend": {"type": "s3", "config": {"key": "terraform-dev.tfstate"}}}"#,
+    )
+    .unwrap();
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\nexit 0\n"),
+            ("terraform", "#!/bin/sh\nexit 0\n"),
+        ],
+    );
+
+    // Initialized for dev: init is skipped
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &path)
+        .args(["--no-init", "plan", &project_path, "dev"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("terraform init").not())
+        .stderr(predicate::str::contains("--no-init ignored").not());
+
+    // prod uses another state key: init runs anyway
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &path)
+        .args(["--no-init", "plan", &project_path, "prod"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "--no-init ignored: key is \"terraform-dev.tfstate\" in .terraform but \"terraform-prod.tfstate\" in backend-vars/prod.tfvars",
+        ))
+        .stderr(predicate::str::contains("terraform init -reconfigure"));
+}
+
+#[test]
+fn test_no_init_does_not_warn_outside_terraform() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_helm_test_project(&temp_dir);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["--no-init", "template", &project_path, "dev"])
+        .assert()
+        .stderr(predicate::str::contains("--no-init").not());
+}
+
+#[test]
+fn test_tool_exit_code_is_propagated() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    // Fake tools: tfswitch and terraform init succeed, terraform plan exits 2
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\nexit 0\n"),
+            (
+                "terraform",
+                "#!/bin/sh\n[ \"$1\" = plan ] && exit 2\nexit 0\n",
+            ),
+        ],
+    );
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .args(["plan", &project_path, "dev"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("ERROR:"));
+}
+
+#[test]
+fn test_print_command_terraform_check() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\nexit 0\n"),
+            ("terraform", "#!/bin/sh\nexit 0\n"),
+        ],
+    );
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .args(["check", &project_path, "dev", "--print-command"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().next().unwrap(),
+        format!(
+            "cd {} && tfswitch && terraform init -reconfigure -backend-config=backend-vars/dev.tfvars && terraform plan -lock-timeout=60s -var-file=tfvars/dev.tfvars",
+            project_path
+        )
+    );
+}
+
+#[test]
+fn test_yes_auto_approves_terraform_apply() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\nexit 0\n"),
+            ("terraform", "#!/bin/sh\nexit 0\n"),
+        ],
+    );
+
+    for args in [
+        vec!["--yes", "apply", &project_path, "dev"],
+        vec![
+            "--auto-approve",
+            "apply",
+            &project_path,
+            "dev",
+            "-auto-approve",
+        ],
+    ] {
+        let output = Command::cargo_bin("mk")
+            .unwrap()
+            .env("PATH", &path)
+            .arg("--print-command")
+            .args(&args)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let line = stdout.lines().next().unwrap();
+        assert!(
+            line.ends_with(
+                "terraform apply -lock-timeout=60s -var-file=tfvars/dev.tfvars -auto-approve"
+            ),
+            "{}",
+            line
+        );
+    }
+}
+
+#[test]
+fn test_workdir_overrides_command_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let workdir = temp_dir.path().join("checkout");
+    fs::create_dir(&workdir).unwrap();
+
+    // Fake tools record the directory they run in
+    let record = "#!/bin/sh\npwd >> \"$MK_TEST_PWD_LOG\"\n";
+    let path = fake_tools_path(&temp_dir, &[("tfswitch", record), ("terraform", record)]);
+    let pwd_log = temp_dir.path().join("pwd.log");
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .env("MK_TEST_PWD_LOG", &pwd_log)
+        .args([
+            "plan",
+            &project_path,
+            "dev",
+            "--workdir",
+            workdir.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let workdir = workdir.canonicalize().unwrap();
+    let recorded = fs::read_to_string(&pwd_log).unwrap();
+    assert_eq!(recorded.lines().count(), 3);
+    for line in recorded.lines() {
+        assert_eq!(std::path::Path::new(line), workdir);
+    }
+}
+
+#[test]
+fn test_workdir_must_exist() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "plan",
+            &project_path,
+            "dev",
+            "--workdir",
+            "/nonexistent/dir",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Working directory not found"));
+}
+
+#[test]
+fn test_log_format_json_emits_json_lines() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .args(["--log-format", "json", "plan", &project_path, "nonexistent"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    let info = lines
+        .iter()
+        .find(|line| line["level"] == "info")
+        .expect("an info line");
+    assert_eq!(
+        info["message"],
+        format!("Detected terraform in {}", project_path).as_str()
+    );
+    assert!(info["ts"].as_str().unwrap().ends_with('Z'));
+    assert!(lines.iter().any(|line| line["level"] == "error"));
+}
+
+#[test]
+fn test_log_format_from_environment() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .env("MK_LOG_FORMAT", "json")
+        .args(["plan", &project_path, "nonexistent"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr
+        .lines()
+        .all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok()));
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("MK_LOG_FORMAT", "json")
+        .args(["--log-format", "text", "plan", &project_path, "nonexistent"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("ERROR:"));
+}
+
+#[test]
+fn test_doctor_reports_tool_versions() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("terraform", "#!/bin/sh\necho \"Terraform v0.12.31\"\n"),
+            ("kubectl", "#!/bin/sh\necho \"Client Version: v1.29.2\"\n"),
+        ],
+    );
+    // Only the fake tools are reachable
+    let bin_dir = path.split(':').next().unwrap().to_string();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", bin_dir)
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(r"terraform\s+found\s+0\.12\.31").unwrap())
+        .stdout(predicate::str::is_match(r"kubectl\s+found\s+1\.29\.2").unwrap())
+        .stdout(predicate::str::is_match(r"helmfile\s+missing").unwrap())
+        .stderr(predicate::str::contains(
+            "terraform 0.12.31 is unsupported, 1.0.0 or later is required",
+        ));
+}
+
+#[test]
+fn test_ansible_flags_rejected_for_terraform() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    for flags in [
+        &["--limit", "web"][..],
+        &["--tags", "nginx"],
+        &["--ansible-verbose"],
+    ] {
+        Command::cargo_bin("mk")
+            .unwrap()
+            .arg("apply")
+            .args(flags)
+            .args([&project_path, "dev"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "--limit, --tags and --ansible-verbose only apply to ansible projects",
+            ))
+            .stderr(predicate::str::contains("Running").not());
+    }
+}
+
+#[test]
+fn test_preflight_reports_missing_binary() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let empty_bin = temp_dir.path().join("empty-bin");
+    fs::create_dir(&empty_bin).unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &empty_bin)
+        .args(["plan", &project_path, "dev"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "command not found: tfswitch (install from https://tfswitch.warrensbox.com)",
+        ))
+        .stderr(predicate::str::contains("Running").not());
+
+    // The escape hatch goes straight to execution
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &empty_bin)
+        .args(["--no-preflight", "plan", &project_path, "dev"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("command not found").not())
+        .stderr(predicate::str::contains("Running `tfswitch`"));
+}
+
+#[test]
+fn test_configured_action_args_before_cli_options() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    fs::write(
+        std::path::Path::new(&project_path).join(".mk.toml"),
+        "[args]\n\"terraform.plan\" = [\"-compact-warnings\", \"-lock=true\"]\n",
+    )
+    .unwrap();
+
+    let record = "#!/bin/sh\necho \"$@\" >> \"$MK_TEST_ARGS_LOG\"\n";
+    let path = fake_tools_path(&temp_dir, &[("tfswitch", record), ("terraform", record)]);
+    let args_log = temp_dir.path().join("args.log");
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .env("MK_TEST_ARGS_LOG", &args_log)
+        .args(["plan", &project_path, "dev", "--", "-lock=false"])
+        .assert()
+        .success();
+
+    // The CLI option comes last, so terraform uses -lock=false
+    let recorded = fs::read_to_string(&args_log).unwrap();
+    assert_eq!(
+        recorded.lines().last(),
+        Some("plan -lock-timeout=60s -var-file=tfvars/dev.tfvars -compact-warnings -lock=true -lock=false")
+    );
+}
+
+#[test]
+fn test_environment_trimmed_and_case_insensitive() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let record = "#!/bin/sh\necho \"$@\" >> \"$MK_TEST_ARGS_LOG\"\n";
+    let path = fake_tools_path(&temp_dir, &[("tfswitch", record), ("terraform", record)]);
+    let args_log = temp_dir.path().join("args.log");
+
+    // Surrounding whitespace is ignored
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &path)
+        .env("MK_TEST_ARGS_LOG", &args_log)
+        .args(["plan", &project_path, " dev "])
+        .assert()
+        .success();
+    let recorded = fs::read_to_string(&args_log).unwrap();
+    assert!(recorded.ends_with("-var-file=tfvars/dev.tfvars\n"));
+
+    // A case mismatch is only suggested by default
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &path)
+        .env("MK_TEST_ARGS_LOG", &args_log)
+        .args(["plan", &project_path, "DEV"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("did you mean dev?"));
+
+    fs::write(
+        std::path::Path::new(&project_path).join(".mk.toml"),
+        "[env]\ncase_insensitive = true\n",
+    )
+    .unwrap();
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &path)
+        .env("MK_TEST_ARGS_LOG", &args_log)
+        .args(["plan", &project_path, "DEV"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Using environment dev for DEV"));
+    let recorded = fs::read_to_string(&args_log).unwrap();
+    assert!(recorded.ends_with("-var-file=tfvars/dev.tfvars\n"));
+}
+
+#[test]
+fn test_format_json_reports_errors_as_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .args(["output", &project_path, "qa", "--format", "json"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    // The error is the last stderr line, after the leveled messages
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(error["error"], "Invalid environment");
+    assert_eq!(error["kind"], "invalid_environment");
+    assert_eq!(
+        error["caused_by"][0],
+        "Invalid env provided (qa). Valid options are: [dev|prod]"
+    );
+}
+
+#[test]
+fn test_zsh_completions_offer_enum_values() {
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["completions", "zsh"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "--tech=[Filter to only check specific technology (terraform or helm)]:TECH:((terraform\\:\"Only terraform projects\"",
+        ))
+        .stdout(predicate::str::contains("helm\\:\"Only helm charts\""))
+        .stdout(predicate::str::contains(":shell -- Shell type:(bash zsh fish)"));
+}
+
+#[test]
+fn test_bash_completions_offer_enum_values() {
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["completions", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "--tech)\n                    COMPREPLY=($(compgen -W \"terraform helm\" -- \"${cur}\"))",
+        ))
+        .stdout(predicate::str::contains("--log-format)"));
+}
+
+/// Run mk with `args` and parse its stdout as one JSON envelope
+fn json_envelope(path: Option<&str>, args: &[&str]) -> serde_json::Value {
+    let mut cmd = Command::cargo_bin("mk").unwrap();
+    if let Some(path) = path {
+        cmd.env("PATH", path);
+    }
+    let output = cmd.args(args).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_json_envelope_shape_across_commands() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let cache_dir = TempDir::new().unwrap();
+
+    let detected = json_envelope(None, &["detect", &project_path, "--format", "json"]);
+    assert_eq!(detected["command"], "detect");
+    assert_eq!(detected["technology"], "terraform");
+    assert_eq!(detected["path"], project_path.as_str());
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .env("XDG_CACHE_HOME", cache_dir.path())
+        .args(["complete-env", &project_path, "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let completed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(completed["command"], "complete-env");
+    assert_eq!(completed["path"], project_path.as_str());
+    assert_eq!(
+        completed["environments"],
+        serde_json::json!(["dev", "prod"])
+    );
+
+    // Every envelope is a flat object naming its command, with environments as an array
+    for envelope in [&detected, &completed] {
+        assert!(envelope.is_object());
+        assert!(envelope["command"].is_string());
+        assert!(envelope["environments"].is_array());
+    }
+}
+
+#[test]
+fn test_relative_kubeconfig_is_exported_absolute() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_helm_test_project(&temp_dir);
+    let print_kubeconfig = "#!/bin/sh\necho \"KUBECONFIG=$KUBECONFIG\"\n";
+    let path = fake_tools_path(
+        &temp_dir,
+        &[("helmfile", print_kubeconfig), ("helm", print_kubeconfig)],
+    );
+
+    // Relative to where mk runs, not to the project directory the tools run in
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .current_dir(temp_dir.path())
+        .args([
+            "--kubeconfig",
+            "kube.yaml",
+            "template",
+            &project_path,
+            "dev",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "KUBECONFIG={}",
+            temp_dir.path().join("kube.yaml").display()
+        )));
+}
+
+#[test]
+fn test_output_format_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\necho switched\n"),
+            (
+                "terraform",
+                "#!/bin/sh\n\
+                 if [ \"$1\" = output ]; then\n\
+                 echo '{\"bucket\": {\"sensitive\": false, \"type\": \"string\", \"value\": \"logs-dev\"}, \"replicas\": {\"sensitive\": false, \"type\": \"number\", \"value\": 3}}'\n\
+                 else echo initialized; fi\n",
+            ),
+        ],
+    );
+
+    let all = json_envelope(
+        Some(&path),
+        &["output", &project_path, "dev", "--all", "--format", "json"],
+    );
+    assert_eq!(all["command"], "output");
+    assert_eq!(all["technology"], "terraform");
+    assert_eq!(all["path"], project_path.as_str());
+    assert_eq!(all["environment"], "dev");
+    assert_eq!(
+        all["outputs"],
+        serde_json::json!({"bucket": "logs-dev", "replicas": 3})
+    );
+
+    let one = json_envelope(
+        Some(&path),
+        &[
+            "output",
+            &project_path,
+            "dev",
+            "replicas",
+            "--format",
+            "json",
+        ],
+    );
+    assert_eq!(one["outputs"], serde_json::json!({"replicas": 3}));
+}
+
+#[test]
+fn test_output_all_parallel_prints_in_key_order() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    fs::write(
+        std::path::Path::new(&project_path).join("outputs.tf"),
+        "output \"alpha\" {}\noutput \"beta\" {}\noutput \"gamma\" {}\n",
+    )
+    .unwrap();
+
+    // alpha is the slowest query, so it only comes first when results are reordered
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\nexit 0\n"),
+            (
+                "terraform",
+                "#!/bin/sh\n\
+                 case \"$1 $2\" in\n\
+                 \"output alpha\") sleep 0.4; echo value-alpha ;;\n\
+                 \"output beta\") sleep 0.2; echo value-beta ;;\n\
+                 \"output gamma\") echo value-gamma ;;\n\
+                 esac\n",
+            ),
+        ],
+    );
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["output", &project_path, "dev", "--all", "--parallel", "3"])
+        .env("PATH", path)
+        .assert()
+        .success()
+        .stdout("value-alpha\nvalue-beta\nvalue-gamma\n");
+}
+
+#[test]
+fn test_plan_save_plan_dir_keeps_output_and_plan_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let save_dir = temp_dir.path().join("plans");
+
+    // Stubbed plan printing a summary and writing the -out file
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\nexit 0\n"),
+            (
+                "terraform",
+                "#!/bin/sh\n\
+                 [ \"$1\" = plan ] || exit 0\n\
+                 echo 'Plan: 1 to add, 0 to change, 0 to destroy.'\n\
+                 for arg; do case $arg in -out=*) echo binary > \"${arg#-out=}\" ;; esac; done\n",
+            ),
+        ],
+    );
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "plan",
+            &project_path,
+            "dev",
+            "--save-plan-dir",
+            save_dir.to_str().unwrap(),
+            "-out=tfplan",
+        ])
+        .env("PATH", path)
+        .assert()
+        .success()
+        // The plan is still shown
+        .stdout(predicate::str::contains("Plan: 1 to add"));
+
+    let plan_dirs: Vec<_> = fs::read_dir(&save_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(plan_dirs.len(), 1);
+    let plan_dir = &plan_dirs[0];
+    assert!(plan_dir
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .starts_with("plan-"));
+    assert_eq!(
+        fs::read_to_string(plan_dir.join("plan.log")).unwrap(),
+        "Plan: 1 to add, 0 to change, 0 to destroy.\n"
+    );
+    assert_eq!(
+        fs::read_to_string(plan_dir.join("tfplan")).unwrap(),
+        "binary\n"
+    );
+}
+
+#[test]
+fn test_plan_save_plan_dir_not_created_on_invalid_environment() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let save_dir = temp_dir.path().join("plans");
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "plan",
+            &project_path,
+            "missing",
+            "--save-plan-dir",
+            save_dir.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+
+    assert!(!save_dir.exists());
+}
+
+#[test]
+fn test_env_add_and_remove_terraform() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let project = std::path::Path::new(&project_path);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["env", "add", &project_path, "qa"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Environment qa added"));
+    assert!(project.join("tfvars/qa.tfvars").is_file());
+    assert!(project.join("backend-vars/qa.tfvars").is_file());
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["complete-env", &project_path])
+        .env("XDG_CACHE_HOME", temp_dir.path().join("cache"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("qa"));
+
+    // No terminal to confirm on
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["env", "remove", &project_path, "qa"])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("use --force"));
+    assert!(project.join("tfvars/qa.tfvars").exists());
+
+    // Surrounding whitespace resolves to the project's name
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["env", "remove", &project_path, " qa", "--force"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Environment qa removed"));
+    assert!(!project.join("tfvars/qa.tfvars").exists());
+    assert!(!project.join("backend-vars/qa.tfvars").exists());
+    assert!(project.join("tfvars/dev.tfvars").exists());
+}
+
+#[test]
+fn test_technologies_lists_supported_actions() {
+    let listed = json_envelope(None, &["technologies", "--format", "json"]);
+    assert_eq!(listed["command"], "technologies");
+    let actions = |techno: &str| -> Vec<String> {
+        serde_json::from_value(listed["technologies"][techno].clone()).unwrap()
+    };
+    assert!(actions("terraform").contains(&"plan".to_string()));
+    assert!(actions("helm").contains(&"template".to_string()));
+    assert!(!actions("helm").contains(&"plan".to_string()));
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .arg("actions")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "terraform  apply, check, diff, plan",
+        ));
+}
+
+#[test]
+fn test_no_detect_log_hides_detection_message() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+    let path = fake_tools_path(
+        &temp_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\nexit 0\n"),
+            ("terraform", "#!/bin/sh\necho \"terraform $*\"\n"),
+        ],
+    );
+
+    let run = |extra: &[&str]| {
+        Command::cargo_bin("mk")
+            .unwrap()
+            .env("PATH", &path)
+            .args(["plan", &project_path, "dev"])
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    let logged = run(&[]);
+    assert!(String::from_utf8_lossy(&logged.stderr).contains("Detected terraform in"));
+
+    let quiet = run(&["--no-detect-log"]);
+    assert!(quiet.status.success());
+    assert!(!String::from_utf8_lossy(&quiet.stderr).contains("Detected"));
+    assert!(String::from_utf8_lossy(&quiet.stdout).contains("terraform plan"));
+}
+
+#[test]
+fn test_dump_config_shows_cli_overridden_value() {
+    let temp_dir = TempDir::new().unwrap();
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir(&home_dir).unwrap();
+    let project_path = create_terraform_test_project(&temp_dir);
+
+    // Discovered project config, replaced by the one given on the command line
+    fs::write(
+        temp_dir.path().join(".mk.toml"),
+        "[helm]\nnamespace = \"discovered\"\n",
+    )
+    .unwrap();
+    let project_config = temp_dir.path().join("team.toml");
+    fs::write(&project_config, "[helm]\nnamespace = \"platform\"\n").unwrap();
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .env("HOME", &home_dir)
+        .env("XDG_CONFIG_HOME", home_dir.join(".config"))
+        .args([
+            "plan",
+            &project_path,
+            "dev",
+            "--dump-config",
+            "--project-config",
+            project_config.to_str().unwrap(),
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let dumped: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(dumped["command"], "dump-config");
+    assert_eq!(dumped["config"]["helm"]["namespace"], "platform");
+    // Exits before detecting or running anything
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Detected"));
+}
@@ -112,6 +112,23 @@ releases:
     project_dir.to_str().unwrap().to_string()
 }
 
+/// Helper to install executable fake tools in `bin_dir` and return a PATH that finds them first
+/// `bin_dir` is kept out of the scanned directory
+fn fake_tools_path(bin_dir: &TempDir, tools: &[(&str, &str)]) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    for (name, script) in tools {
+        let tool = bin_dir.path().join(name);
+        fs::write(&tool, script).unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    format!(
+        "{}:{}",
+        bin_dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    )
+}
+
 #[test]
 fn test_drift_command_requires_base_path() {
     Command::cargo_bin("mk")
@@ -149,6 +166,105 @@ fn test_drift_empty_directory() {
     );
 }
 
+#[test]
+fn test_drift_empty_directory_with_require_projects() {
+    let temp_dir = TempDir::new().unwrap();
+    let base_path = temp_dir.path().to_str().unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["drift", base_path, "--require-projects"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "No IaC projects found in {}",
+            base_path
+        )));
+}
+
+#[test]
+fn test_drift_require_projects_allows_no_changed_project() {
+    let temp_dir = TempDir::new().unwrap();
+    create_terraform_drift_project(&temp_dir, &["dev"]);
+    let base_path = temp_dir.path().to_str().unwrap();
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=mk", "-c", "user.email=mk@example.com"])
+            .args(args)
+            .current_dir(base_path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "init"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "drift",
+            base_path,
+            "--require-projects",
+            "--changed-since",
+            "HEAD",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("No IaC projects found"));
+}
+
+#[test]
+fn test_drift_single_project_changed_since() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_drift_project(&temp_dir, &["dev"]);
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=mk", "-c", "user.email=mk@example.com"])
+            .args(args)
+            .current_dir(temp_dir.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    };
+    git(&["init", "-q"]);
+    git(&["add", "-A"]);
+    git(&["commit", "-q", "-m", "init"]);
+    fs::write(
+        std::path::Path::new(&project_path).join("main.tf"),
+        "# changed\n",
+    )
+    .unwrap();
+
+    let bin_dir = TempDir::new().unwrap();
+    let path = fake_tools_path(
+        &bin_dir,
+        &[("tfswitch", "#!/bin/sh\n"), ("terraform", "#!/bin/sh\n")],
+    );
+
+    // Relative project path, as typed from the repository root
+    Command::cargo_bin("mk")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("PATH", path)
+        .args([
+            "drift",
+            "terraform",
+            "--env",
+            "dev",
+            "--changed-since",
+            "HEAD",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "1 of 1 project(s) changed since HEAD",
+        ))
+        .stderr(predicate::str::contains("No IaC projects found").not());
+}
+
 #[test]
 fn test_drift_detects_terraform_project() {
     let temp_dir = TempDir::new().unwrap();
@@ -365,6 +481,269 @@ fn test_drift_capture_flag_creates_log_dir() {
     );
 }
 
+#[test]
+fn test_drift_capture_disables_terraform_color() {
+    let temp_dir = TempDir::new().unwrap();
+    let _project_path = create_terraform_drift_project(&temp_dir, &["dev"]);
+
+    // Fake tools echo their arguments so the captured log shows the plan command
+    let bin_dir = TempDir::new().unwrap();
+    let path = fake_tools_path(
+        &bin_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\necho \"$0 $*\"\n"),
+            ("terraform", "#!/bin/sh\necho \"$0 $*\"\n"),
+        ],
+    );
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("PATH", path)
+        .args(["drift", ".", "--capture-all"])
+        .assert()
+        .success();
+
+    let run_dir = fs::read_dir(temp_dir.path().join(".drift-logs"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let logs: String = fs::read_dir(&run_dir)
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect();
+    let plan = logs
+        .lines()
+        .find(|line| line.contains("terraform plan"))
+        .unwrap_or_else(|| panic!("no plan command in captured logs:\n{}", logs));
+    assert!(plan.contains("-no-color"), "{}", plan);
+}
+
+#[test]
+fn test_drift_single_project_environment() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_drift_project(&temp_dir, &["dev", "prod"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["drift", &project_path, "--env", "dev"])
+        .assert()
+        .stderr(predicate::str::contains("directly, no scan needed"))
+        .stderr(predicate::str::contains("Scanning").not())
+        .stderr(predicate::str::contains(
+            "Found 1 project(s), 1 total check(s) to perform",
+        ));
+}
+
+#[test]
+fn test_drift_single_project_unknown_environment() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_drift_project(&temp_dir, &["dev"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["drift", &project_path, "--env", "staging"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("staging"));
+}
+
+#[test]
+fn test_drift_fail_fast_stops_at_first_drift() {
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["network", "storage"] {
+        let project_dir = temp_dir.path().join(name).join("terraform");
+        fs::create_dir_all(project_dir.join("tfvars")).unwrap();
+        fs::create_dir_all(project_dir.join("backend-vars")).unwrap();
+        fs::write(project_dir.join("tfvars/dev.tfvars"), "env = \"dev\"\n").unwrap();
+        fs::write(
+            project_dir.join("backend-vars/dev.tfvars"),
+            "key = \"dev\"\n",
+        )
+        .unwrap();
+        fs::write(project_dir.join("main.tf"), "").unwrap();
+    }
+
+    // Fake terraform whose plan always reports changes
+    let bin_dir = TempDir::new().unwrap();
+    let path = fake_tools_path(
+        &bin_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\n"),
+            (
+                "terraform",
+                "#!/bin/sh\n[ \"$1\" = plan ] && exit 2\nexit 0\n",
+            ),
+        ],
+    );
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .args(["drift", temp_dir.path().to_str().unwrap(), "--fail-fast"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("2 total check(s)"))
+        .stderr(predicate::str::contains("skipped 1 check(s)"))
+        .stderr(predicate::str::contains("1 Drift Detected"));
+}
+
+#[test]
+fn test_drift_summary_paths_relative_to_base() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_dir = temp_dir.path().join("network").join("terraform");
+    fs::create_dir_all(project_dir.join("tfvars")).unwrap();
+    fs::create_dir_all(project_dir.join("backend-vars")).unwrap();
+    fs::write(project_dir.join("tfvars/dev.tfvars"), "env = \"dev\"\n").unwrap();
+    fs::write(
+        project_dir.join("backend-vars/dev.tfvars"),
+        "key = \"dev\"\n",
+    )
+    .unwrap();
+    fs::write(project_dir.join("main.tf"), "").unwrap();
+
+    // Fake terraform whose plan always reports changes
+    let bin_dir = TempDir::new().unwrap();
+    let path = fake_tools_path(
+        &bin_dir,
+        &[
+            ("tfswitch", "#!/bin/sh\n"),
+            (
+                "terraform",
+                "#!/bin/sh\n[ \"$1\" = plan ] && exit 2\nexit 0\n",
+            ),
+        ],
+    );
+    let absolute = project_dir.canonicalize().unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &path)
+        .args(["drift", temp_dir.path().to_str().unwrap()])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("  - network/terraform (dev)"))
+        .stderr(predicate::str::contains(absolute.to_str().unwrap()).not());
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", &path)
+        .args([
+            "drift",
+            temp_dir.path().to_str().unwrap(),
+            "--absolute-paths",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(format!(
+            "  - {} (dev)",
+            absolute.display()
+        )));
+}
+
+#[test]
+fn test_drift_with_env_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let _project_path = create_terraform_drift_project(&temp_dir, &["dev", "staging", "prod"]);
+    let env_file = temp_dir.path().join("drift-envs");
+    fs::write(&env_file, "# nightly drift\nstaging\n\nprod\n").unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "drift",
+            temp_dir.path().to_str().unwrap(),
+            "--env-file",
+            env_file.to_str().unwrap(),
+        ])
+        .assert()
+        .stderr(predicate::str::contains(
+            "Found 1 project(s), 2 total check(s) to perform",
+        ))
+        .stderr(predicate::str::contains("(dev)").not());
+}
+
+#[test]
+fn test_drift_with_env_regex() {
+    let temp_dir = TempDir::new().unwrap();
+    let _project_path =
+        create_terraform_drift_project(&temp_dir, &["dev", "eu-prod", "us-prod", "staging"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "drift",
+            temp_dir.path().to_str().unwrap(),
+            "--env-regex",
+            "-prod$",
+            "--env",
+            "dev",
+        ])
+        .assert()
+        .stderr(predicate::str::contains(
+            "Found 1 project(s), 3 total check(s) to perform",
+        ))
+        .stderr(predicate::str::contains("(staging)").not());
+}
+
+#[test]
+fn test_drift_invalid_env_regex() {
+    let temp_dir = TempDir::new().unwrap();
+    let _project_path = create_terraform_drift_project(&temp_dir, &["dev"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "drift",
+            temp_dir.path().to_str().unwrap(),
+            "--env-regex",
+            "(prod",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Invalid --env-regex pattern (prod",
+        ));
+}
+
+#[test]
+fn test_drift_json_reports_durations() {
+    let temp_dir = TempDir::new().unwrap();
+    let _project_path = create_terraform_drift_project(&temp_dir, &["dev", "prod"]);
+
+    // Fake terraform whose plan finds no changes
+    let bin_dir = TempDir::new().unwrap();
+    let path = fake_tools_path(
+        &bin_dir,
+        &[("tfswitch", "#!/bin/sh\n"), ("terraform", "#!/bin/sh\n")],
+    );
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .args([
+            "drift",
+            temp_dir.path().to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "drift");
+    assert!(json["elapsed_secs"].is_f64());
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert_eq!(result["status"], "ok");
+        assert!(result["duration_secs"].is_f64(), "{}", result);
+    }
+}
+
 #[test]
 fn test_drift_multiple_projects() {
     let temp_dir = TempDir::new().unwrap();
@@ -398,3 +777,129 @@ fn test_drift_multiple_projects() {
         "Should detect multiple projects"
     );
 }
+
+#[test]
+fn test_drift_with_path_filter() {
+    let temp_dir = TempDir::new().unwrap();
+    let _tf_project = create_terraform_drift_project(&temp_dir, &["dev"]);
+    let _helm_project = create_helm_drift_project(&temp_dir, &["dev"]);
+
+    // Skip the actual checks, only project discovery matters here
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "drift",
+            temp_dir.path().to_str().unwrap(),
+            "--env",
+            "none",
+            "--path-filter",
+            "my-*",
+        ])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Found 1 project(s)"),
+        "Should only keep projects matching my-*. stderr: {}",
+        stderr
+    );
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "drift",
+            temp_dir.path().to_str().unwrap(),
+            "--env",
+            "none",
+            "--path-filter",
+            "my-*",
+            "--path-filter",
+            "terraform",
+        ])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Found 2 project(s)"),
+        "Filters should union. stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_drift_single_project_honors_path_filter() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_drift_project(&temp_dir, &["dev"]);
+
+    // The project is not checked directly when the filter excludes it
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "drift",
+            &project_path,
+            "--env",
+            "dev",
+            "--path-filter",
+            "services/*",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("no scan needed").not())
+        .stderr(predicate::str::contains("No IaC projects found"));
+}
+
+#[test]
+fn test_drift_no_ignore_checks_gitignored_environment() {
+    let temp_dir = TempDir::new().unwrap();
+    let _project_path = create_terraform_drift_project(&temp_dir, &["dev", "sandbox"]);
+    std::process::Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(temp_dir.path())
+        .status()
+        .unwrap();
+    fs::write(
+        temp_dir.path().join(".gitignore"),
+        "terraform/tfvars/sandbox.tfvars\n",
+    )
+    .unwrap();
+
+    // Fake terraform whose plan finds no changes
+    let bin_dir = TempDir::new().unwrap();
+    let path = fake_tools_path(
+        &bin_dir,
+        &[("tfswitch", "#!/bin/sh\n"), ("terraform", "#!/bin/sh\n")],
+    );
+
+    let drift_results = |extra: &[&str]| {
+        let output = Command::cargo_bin("mk")
+            .unwrap()
+            .env("PATH", &path)
+            .args([
+                "drift",
+                temp_dir.path().to_str().unwrap(),
+                "--format",
+                "json",
+            ])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| {
+                assert_eq!(result["status"], "ok", "{}", result);
+                result["environment"].as_str().unwrap().to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(drift_results(&[]), ["dev"]);
+    let mut environments = drift_results(&["--no-ignore"]);
+    environments.sort();
+    assert_eq!(environments, ["dev", "sandbox"]);
+}
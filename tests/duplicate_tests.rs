@@ -89,6 +89,37 @@ fn test_terraform_duplicate_creates_files() {
     );
 }
 
+#[test]
+#[serial]
+fn test_terraform_duplicate_env_directory() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_dir = temp_dir.path().join("terraform");
+    let env_dir = project_dir.join("env").join("dev");
+    fs::create_dir_all(&env_dir).unwrap();
+    fs::write(env_dir.join("main.tfvars"), "env = \"dev\"\n").unwrap();
+    fs::write(env_dir.join("sizes.tfvars"), "nodes = 1\n").unwrap();
+    fs::create_dir(project_dir.join("backend-vars")).unwrap();
+    fs::write(
+        project_dir.join("backend-vars").join("dev.tfvars"),
+        "key = \"terraform-dev.tfstate\"\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["duplicate", project_dir.to_str().unwrap(), "dev", "staging"])
+        .assert()
+        .success();
+
+    let staging_dir = project_dir.join("env").join("staging");
+    assert_eq!(
+        fs::read_to_string(staging_dir.join("main.tfvars")).unwrap(),
+        "env = \"staging\"\n"
+    );
+    assert!(staging_dir.join("sizes.tfvars").is_file());
+    assert!(project_dir.join("backend-vars/staging.tfvars").is_file());
+}
+
 #[test]
 #[serial]
 fn test_terraform_duplicate_replaces_content() {
@@ -288,3 +288,130 @@ fn test_helm_with_namespace() {
         "Should generate helmfile command with namespace"
     );
 }
+
+/// Helper to put a fake helmfile recording its arguments first on PATH
+/// Returns (PATH value, log file)
+fn fake_helmfile(temp_dir: &TempDir) -> (String, std::path::PathBuf) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin_dir = temp_dir.path().join("bin");
+    fs::create_dir(&bin_dir).unwrap();
+    let log = temp_dir.path().join("helmfile.log");
+    let helmfile = bin_dir.join("helmfile");
+    fs::write(
+        &helmfile,
+        format!("#!/bin/sh\necho \"$@\" >> '{}'\n", log.display()),
+    )
+    .unwrap();
+    fs::set_permissions(&helmfile, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    (path, log)
+}
+
+#[test]
+fn test_helm_diff_updates_missing_deps() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_helm_project(&temp_dir, &["dev"]);
+    let (path, log) = fake_helmfile(&temp_dir);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .args(["diff", &project_path, "dev"])
+        .assert()
+        .success();
+
+    let calls = fs::read_to_string(log).unwrap();
+    assert_eq!(calls, "deps -e dev\ndiff -e dev --skip-deps\n");
+}
+
+#[test]
+fn test_helm_diff_skip_deps_flag() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_helm_project(&temp_dir, &["dev"]);
+    let (path, log) = fake_helmfile(&temp_dir);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .args(["diff", "--skip-deps", &project_path, "dev"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Helm dependencies need updating").not());
+
+    // Only the diff runs, no `helmfile deps`
+    let calls = fs::read_to_string(log).unwrap();
+    assert_eq!(calls, "diff -e dev --skip-deps\n");
+}
+
+#[test]
+fn test_helm_values_override_appended() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_helm_project(&temp_dir, &["dev"]);
+    fs::write(
+        std::path::Path::new(&project_path).join("local.yaml"),
+        "replicaCount: 3\n",
+    )
+    .unwrap();
+    let (path, log) = fake_helmfile(&temp_dir);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .args([
+            "template",
+            "--skip-deps",
+            "--values",
+            "local.yaml",
+            &project_path,
+            "dev",
+        ])
+        .assert()
+        .success();
+
+    // Resolved against the project directory and placed after the environment
+    let local = fs::canonicalize(std::path::Path::new(&project_path).join("local.yaml")).unwrap();
+    let calls = fs::read_to_string(log).unwrap();
+    assert_eq!(
+        calls,
+        format!(
+            "template -e dev --state-values-file {} --skip-deps\n",
+            local.display()
+        )
+    );
+}
+
+#[test]
+fn test_helm_values_override_missing_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_helm_project(&temp_dir, &["dev"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["apply", "--values", "missing.yaml", &project_path, "dev"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Values file missing.yaml not found",
+        ));
+}
+
+#[test]
+fn test_helm_unsupported_action_lists_supported_actions() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_helm_project(&temp_dir, &["dev"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["plan", &project_path, "dev"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "plan is not supported for helm projects. Supported actions: apply, check, diff",
+        ));
+}
@@ -63,6 +63,22 @@ spec:
     kustomize_dir.to_str().unwrap().to_string()
 }
 
+/// Helper to install executable fake tools in `bin_dir` and return a PATH that finds them first
+fn fake_tools_path(bin_dir: &TempDir, tools: &[(&str, &str)]) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    for (name, script) in tools {
+        let tool = bin_dir.path().join(name);
+        fs::write(&tool, script).unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    format!(
+        "{}:{}",
+        bin_dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    )
+}
+
 #[test]
 fn test_kustomize_apply_command() {
     let temp_dir = TempDir::new().unwrap();
@@ -149,6 +165,21 @@ fn test_kustomize_invalid_environment() {
         .stderr(predicate::str::contains("Invalid env"));
 }
 
+#[test]
+fn test_kustomize_rejects_values_override() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_kustomize_project(&temp_dir, &["dev"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["apply", "--values", "local.yaml", &project_path, "dev"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--values only applies to helm projects",
+        ));
+}
+
 #[test]
 fn test_kustomize_multiple_environments() {
     let temp_dir = TempDir::new().unwrap();
@@ -204,3 +235,116 @@ fn test_kustomize_detection() {
         "Should detect kustomize technology"
     );
 }
+
+#[test]
+fn test_kustomize_context_override_bypasses_mapping() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_kustomize_project(&temp_dir, &["prod"]);
+    let home_dir = temp_dir.path().join("home");
+    fs::create_dir(&home_dir).unwrap();
+
+    // Git repository whose mapping expects a different context than --context
+    for args in [
+        vec!["init", "-q"],
+        vec!["remote", "add", "origin", "git@github.com:acme/k8s.git"],
+    ] {
+        std::process::Command::new("git")
+            .args(&args)
+            .current_dir(&project_path)
+            .status()
+            .unwrap();
+    }
+    fs::create_dir(std::path::Path::new(&project_path).join(".mk")).unwrap();
+    fs::write(
+        std::path::Path::new(&project_path).join(".mk/contexts.toml"),
+        "[mappings.\"github.com/acme/k8s\"]\nprod = \"ctx-mapped\"\n",
+    )
+    .unwrap();
+
+    let kubeconfig = temp_dir.path().join("kubeconfig");
+    fs::write(
+        &kubeconfig,
+        "apiVersion: v1\nkind: Config\ncurrent-context: ctx-mapped\n\
+         contexts:\n\
+         - name: ctx-mapped\n  context:\n    cluster: a\n    user: admin\n\
+         - name: ctx-override\n  context:\n    cluster: b\n    user: admin\n\
+         clusters: []\nusers: []\n",
+    )
+    .unwrap();
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .env("HOME", &home_dir)
+        .env("XDG_CONFIG_HOME", home_dir.join(".config"))
+        .env("KUBECONFIG", &kubeconfig)
+        .args(["--context", "ctx-override", "diff", &project_path, "prod"])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("skipping mapping validation"),
+        "Should bypass mapping validation: {}",
+        stderr
+    );
+    assert!(!stderr.contains("context mismatch"));
+    assert!(fs::read_to_string(&kubeconfig)
+        .unwrap()
+        .contains("current-context: ctx-override"));
+}
+
+#[test]
+fn test_kustomize_context_override_fails_on_unknown_context() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_kustomize_project(&temp_dir, &["prod"]);
+
+    let kubeconfig = temp_dir.path().join("kubeconfig");
+    fs::write(
+        &kubeconfig,
+        "apiVersion: v1\nkind: Config\ncontexts: []\nclusters: []\nusers: []\n",
+    )
+    .unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("KUBECONFIG", &kubeconfig)
+        .args(["--context", "missing", "diff", &project_path, "prod"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to switch to kubectl context 'missing'",
+        ));
+}
+
+#[test]
+fn test_kustomize_template_output_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_kustomize_project(&temp_dir, &["dev"]);
+
+    // Fake kustomize printing the overlay it builds
+    let bin_dir = TempDir::new().unwrap();
+    let path = fake_tools_path(
+        &bin_dir,
+        &[("kustomize", "#!/bin/sh\ncat \"$2/kustomization.yaml\"\n")],
+    );
+
+    let output_file = temp_dir.path().join("rendered/dev/manifests.yaml");
+    Command::cargo_bin("mk")
+        .unwrap()
+        .env("PATH", path)
+        .args([
+            "template",
+            &project_path,
+            "dev",
+            "--output-file",
+            output_file.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("namespace: dev").not())
+        .stderr(predicate::str::contains("Rendered manifests written to"));
+
+    let manifests = fs::read_to_string(&output_file).unwrap();
+    assert!(!manifests.is_empty());
+    assert!(manifests.contains("namespace: dev"), "{}", manifests);
+}
@@ -2,6 +2,7 @@
 //! These tests verify the bump --recursive command
 
 use assert_cmd::Command;
+use predicates::prelude::*;
 use std::fs;
 use tempfile::TempDir;
 
@@ -328,3 +329,74 @@ fn test_recursive_bump_reports_up_to_date() {
         "Should report dependency status"
     );
 }
+
+#[test]
+fn test_bump_path_filter_requires_recursive() {
+    let temp_dir = TempDir::new().unwrap();
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "bump",
+            temp_dir.path().to_str().unwrap(),
+            "--path-filter",
+            "services/*",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--recursive"));
+}
+
+#[test]
+fn test_recursive_bump_path_filter_keeps_matching_projects() {
+    let temp_dir = TempDir::new().unwrap();
+    let services = temp_dir.path().join("services");
+    fs::create_dir_all(services.join("api")).unwrap();
+    fs::create_dir_all(services.join("web")).unwrap();
+    fs::create_dir(temp_dir.path().join("platform")).unwrap();
+    create_terraform_project_with_modules(&services.join("api"), "terraform");
+    create_terraform_project_with_modules(&services.join("web"), "terraform");
+    create_terraform_project_with_modules(&temp_dir.path().join("platform"), "terraform");
+    create_helm_project_with_deps(temp_dir.path(), "my-chart");
+
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "bump",
+            temp_dir.path().to_str().unwrap(),
+            "--recursive",
+            "--path-filter",
+            "services/*/terraform",
+        ])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Found 2 Terraform project(s), 0 Helm project(s)"),
+        "Should only keep services/*/terraform. stderr: {}",
+        stderr
+    );
+
+    // Multiple filters union
+    let output = Command::cargo_bin("mk")
+        .unwrap()
+        .args([
+            "bump",
+            temp_dir.path().to_str().unwrap(),
+            "--recursive",
+            "--path-filter",
+            "services/api/terraform",
+            "--path-filter",
+            "my-chart",
+        ])
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Found 1 Terraform project(s), 1 Helm project(s)"),
+        "Filters should union. stderr: {}",
+        stderr
+    );
+}
@@ -145,7 +145,13 @@ fn test_terraform_unlock_command() {
 
     let output = Command::cargo_bin("mk")
         .unwrap()
-        .args(["unlock", &project_path, "dev", "test-lock-id"])
+        .args([
+            "unlock",
+            &project_path,
+            "dev",
+            "2b6a6738-5dd5-6d1a-8f29-d5e8cbc2f2f4",
+            "--force",
+        ])
         .output()
         .unwrap();
 
@@ -218,3 +224,30 @@ fn test_terraform_multiple_environments() {
         "Prod environment should be detected"
     );
 }
+
+#[test]
+fn test_terraform_unlock_rejects_malformed_lock_id() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_project(&temp_dir, &["dev"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["unlock", &project_path, "dev", "not-a-lock", "--force"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Invalid lock ID not-a-lock"));
+}
+
+#[test]
+fn test_terraform_unlock_requires_confirmation_without_terminal() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = create_terraform_project(&temp_dir, &["dev"]);
+
+    Command::cargo_bin("mk")
+        .unwrap()
+        .args(["unlock", &project_path, "dev", "196787809097"])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("use --force"));
+}